@@ -259,6 +259,11 @@ impl Set<RowId> for OptionalIndex {
 
 impl OptionalIndex {
     pub fn for_test(num_rows: RowId, row_ids: &[RowId]) -> OptionalIndex {
+        Self::from_row_ids(num_rows, row_ids)
+    }
+
+    /// Builds an in-memory optional index given the sorted list of non-null row ids.
+    pub fn from_row_ids(num_rows: RowId, row_ids: &[RowId]) -> OptionalIndex {
         assert!(
             row_ids
                 .last()
@@ -309,6 +309,22 @@ impl CompactSpaceU64Accessor {
     pub fn compact_to_u128(&self, compact: u32) -> u128 {
         self.0.compact_to_u128(compact)
     }
+
+    /// Convert a u128 value to the compact space.
+    ///
+    /// If the value is not part of the compact space, the smallest compact value mapping to a
+    /// larger u128 is returned instead, so that the order of values is preserved. This makes it
+    /// possible to convert the bounds of a u128 range into a compact space range.
+    pub fn u128_to_next_compact(&self, value: u128) -> u64 {
+        let compact_space = &self.0.params.compact_space;
+        match compact_space.u128_to_compact(value) {
+            Ok(compact) => compact as u64,
+            Err(pos) if pos < compact_space.ranges_mapping.len() => {
+                compact_space.get_range_mapping(pos).compact_start as u64
+            }
+            Err(_) => compact_space.amplitude_compact_space() as u64 + 1,
+        }
+    }
 }
 
 impl ColumnValues<u64> for CompactSpaceU64Accessor {
@@ -545,6 +561,31 @@ mod tests {
         assert_eq!(amplitude, 2);
     }
 
+    #[test]
+    fn u128_to_next_compact_test() {
+        let vals = &[100u128, 1_000_000_000_000, 2_000_000_000_000];
+        let mut data = test_aux_vals(vals);
+        let _header = U128Header::deserialize(&mut data);
+        let accessor = CompactSpaceU64Accessor::open(data).unwrap();
+        for &val in vals {
+            let compact = accessor.u128_to_next_compact(val);
+            assert_eq!(accessor.compact_to_u128(compact as u32), val);
+        }
+        // Values outside of the compact space map to the next value in it.
+        assert_eq!(
+            accessor.u128_to_next_compact(0),
+            accessor.u128_to_next_compact(100)
+        );
+        assert_eq!(
+            accessor.u128_to_next_compact(1_500_000_000_000),
+            accessor.u128_to_next_compact(2_000_000_000_000)
+        );
+        assert!(
+            accessor.u128_to_next_compact(u128::MAX)
+                > accessor.u128_to_next_compact(2_000_000_000_000)
+        );
+    }
+
     fn test_all(mut data: OwnedBytes, expected: &[u128]) {
         let _header = U128Header::deserialize(&mut data);
         let decompressor = CompactSpaceDecompressor::open(data).unwrap();
@@ -82,7 +82,11 @@ impl FileHandle for WrapFile {
 
         Ok(OwnedBytes::new(buffer))
     }
-    // todo implement async
+
+    /// Positional reads do not share any state, so the read is simply done inline.
+    async fn read_bytes_async(&self, byte_range: Range<usize>) -> io::Result<OwnedBytes> {
+        self.read_bytes(byte_range)
+    }
 }
 impl HasLen for WrapFile {
     fn len(&self) -> usize {
@@ -755,9 +755,35 @@ fn negate(expr: UserInputAst) -> UserInputAst {
     expr.unary(Occur::MustNot)
 }
 
+/// Parses the minimum should match suffix of a group, e.g. `@2`, `@-1`, `@75%` or `@3<90%`.
+fn minimum_should_match(inp: &str) -> JResult<&str, Option<String>> {
+    opt_i(map(
+        preceded(char('@'), recognize(many1(one_of("0123456789%<-")))),
+        str::to_string,
+    ))(inp)
+}
+
+fn with_minimum_should_match(
+    ast: UserInputAst,
+    minimum_should_match: Option<String>,
+) -> UserInputAst {
+    match minimum_should_match {
+        Some(minimum_should_match) => {
+            UserInputAst::MinimumShouldMatch(Box::new(ast), minimum_should_match)
+        }
+        None => ast,
+    }
+}
+
 fn leaf(inp: &str) -> IResult<&str, UserInputAst> {
     alt((
-        delimited(char('('), ast, char(')')),
+        map(
+            tuple((
+                delimited(char('('), ast, char(')')),
+                fallible(minimum_should_match),
+            )),
+            |(ast, minimum_should_match)| with_minimum_should_match(ast, minimum_should_match),
+        ),
         map(
             terminated(
                 char('*'),
@@ -780,12 +806,20 @@ fn leaf_infallible(inp: &str) -> JResult<&str, Option<UserInputAst>> {
             (
                 value((), char('(')),
                 map(
-                    delimited_infallible(
-                        nothing,
-                        ast_infallible,
-                        opt_i_err(char(')'), "expected ')'"),
-                    ),
-                    |(ast, errs)| (Some(ast), errs),
+                    tuple_infallible((
+                        delimited_infallible(
+                            nothing,
+                            ast_infallible,
+                            opt_i_err(char(')'), "expected ')'"),
+                        ),
+                        minimum_should_match,
+                    )),
+                    |((ast, minimum_should_match), errs)| {
+                        (
+                            Some(with_minimum_should_match(ast, minimum_should_match)),
+                            errs,
+                        )
+                    },
                 ),
             ),
             (
@@ -1249,6 +1283,16 @@ mod test {
         test_parse_query_to_ast_helper("NOT a", "(-a)");
     }
 
+    #[test]
+    fn test_minimum_should_match() {
+        test_parse_query_to_ast_helper("(a b c)@2", "((*a *b *c))@2");
+        test_parse_query_to_ast_helper("(a b c)@75%", "((*a *b *c))@75%");
+        test_parse_query_to_ast_helper("(a b c d)@-1", "((*a *b *c *d))@-1");
+        test_parse_query_to_ast_helper("(a b c d)@2<-25%", "((*a *b *c *d))@2<-25%");
+        test_parse_query_to_ast_helper("+x (a b c)@2^3", "(+x *(((*a *b *c))@2)^3)");
+        test_parse_query_to_ast_helper("a@2", "a@2");
+    }
+
     #[test]
     fn test_boosting() {
         test_is_parse_err("a^2^3", "(a)^2");
@@ -209,6 +209,10 @@ impl UserInputBound {
 pub enum UserInputAst {
     Clause(Vec<(Option<Occur>, UserInputAst)>),
     Boost(Box<UserInputAst>, ordered_float::OrderedFloat<f64>),
+    /// A group with a minimum number of should clauses to match, as in `(a b c)@2`.
+    ///
+    /// The specification is kept as written, e.g. `2`, `-1`, `75%` or `3<90%`.
+    MinimumShouldMatch(Box<UserInputAst>, String),
     Leaf(Box<UserInputLeaf>),
 }
 
@@ -222,6 +226,10 @@ enum UserInputAstSerde {
         underlying: Box<UserInputAst>,
         boost: f64,
     },
+    MinimumShouldMatch {
+        underlying: Box<UserInputAst>,
+        minimum_should_match: String,
+    },
     #[serde(untagged)]
     Leaf(Box<UserInputLeaf>),
 }
@@ -234,6 +242,12 @@ impl From<UserInputAst> for UserInputAstSerde {
                 underlying,
                 boost: boost.into_inner(),
             },
+            UserInputAst::MinimumShouldMatch(underlying, minimum_should_match) => {
+                UserInputAstSerde::MinimumShouldMatch {
+                    underlying,
+                    minimum_should_match,
+                }
+            }
             UserInputAst::Leaf(leaf) => UserInputAstSerde::Leaf(leaf),
         }
     }
@@ -278,6 +292,7 @@ impl UserInputAst {
                 .for_each(|(_, ast)| ast.set_default_field(field.clone())),
             UserInputAst::Leaf(leaf) => leaf.set_default_field(field),
             UserInputAst::Boost(ast, _) => ast.set_default_field(field),
+            UserInputAst::MinimumShouldMatch(ast, _) => ast.set_default_field(field),
         }
     }
 }
@@ -327,6 +342,9 @@ impl fmt::Debug for UserInputAst {
             }
             UserInputAst::Leaf(ref subquery) => write!(formatter, "{subquery:?}"),
             UserInputAst::Boost(ref leaf, boost) => write!(formatter, "({leaf:?})^{boost}"),
+            UserInputAst::MinimumShouldMatch(ref ast, ref minimum_should_match) => {
+                write!(formatter, "({ast:?})@{minimum_should_match}")
+            }
         }
     }
 }
@@ -428,6 +446,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_minimum_should_match_serialization() {
+        let ast = UserInputAst::MinimumShouldMatch(
+            Box::new(UserInputAst::Leaf(Box::new(UserInputLeaf::All))),
+            "75%".to_string(),
+        );
+        let json = serde_json::to_string(&ast).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"minimum_should_match","underlying":{"type":"all"},"minimum_should_match":"75%"}"#
+        );
+    }
+
     #[test]
     fn test_clause_serialization() {
         let clause = UserInputAst::Clause(vec![
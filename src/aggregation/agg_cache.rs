@@ -0,0 +1,462 @@
+//! Caching of the per-segment results of an aggregation.
+//!
+//! Segments are immutable, so the intermediate result of an aggregation on a segment only
+//! depends on the segment, its deletes, the query and the aggregation request.
+//! An [`AggregationCache`] keeps these results across searcher generations, so that refreshing
+//! an aggregation only requires to collect the new or modified segments.
+//!
+//! The cache is a [`Warmer`]: once registered on the
+//! [`IndexReaderBuilder`](crate::IndexReaderBuilder), the entries of the segments that are not
+//! part of any live searcher generation anymore are evicted.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use lru::LruCache;
+
+use super::agg_req::{AggregationVariants, Aggregations};
+use super::intermediate_agg_result::IntermediateAggregationResults;
+use crate::index::SegmentId;
+use crate::query::Query;
+use crate::store::CacheStats;
+use crate::{DateTime, Opstamp, Searcher, SearcherGeneration, SegmentReader, Warmer};
+
+/// Default memory budget of an [`AggregationCache`]: 64MB.
+pub const DEFAULT_AGGREGATION_CACHE_BUDGET_NUM_BYTES: usize = 64_000_000;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct AggregationCacheKey {
+    segment_id: SegmentId,
+    delete_opstamp: Option<Opstamp>,
+    query_fingerprint: u128,
+    aggregation: Arc<str>,
+    /// The instant `now` refers to, for the requests with bounds relative to it.
+    now: Option<DateTime>,
+    /// Runtime fields may be redefined under the same name.
+    runtime_fields_generation: u64,
+}
+
+struct CachedResult {
+    res: IntermediateAggregationResults,
+    num_bytes: usize,
+}
+
+struct AggregationCacheInner {
+    entries: LruCache<AggregationCacheKey, CachedResult>,
+    num_bytes: usize,
+}
+
+/// Cache of the per-segment [`IntermediateAggregationResults`] of aggregations.
+///
+/// Entries are keyed by segment id, delete opstamp, query and aggregation request. For requests
+/// with date math relative to `now`, the instant `now` refers to is part of the key as well.
+/// Registering or unregistering a runtime field invalidates all of the entries.
+/// Enable it on a collector with `AggregationCollector::with_cache`.
+///
+/// The size of an entry is estimated as the size of its serialized form. The least recently
+/// used entries are evicted once the memory budget is exceeded.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use tantivy::aggregation::AggregationCache;
+/// use tantivy::schema::Schema;
+/// use tantivy::{Index, Warmer};
+///
+/// let index = Index::create_in_ram(Schema::builder().build());
+/// let cache = Arc::new(AggregationCache::default());
+/// let warmer: Arc<dyn Warmer> = cache.clone();
+/// let reader = index
+///     .reader_builder()
+///     .warmers(vec![Arc::downgrade(&warmer)])
+///     .try_into()
+///     .unwrap();
+/// # drop(reader);
+/// ```
+pub struct AggregationCache {
+    inner: Mutex<AggregationCacheInner>,
+    budget_num_bytes: usize,
+    cache_hits: AtomicUsize,
+    cache_misses: AtomicUsize,
+}
+
+impl Default for AggregationCache {
+    fn default() -> Self {
+        AggregationCache::new(DEFAULT_AGGREGATION_CACHE_BUDGET_NUM_BYTES)
+    }
+}
+
+impl AggregationCache {
+    /// Creates an aggregation cache with the given memory budget.
+    pub fn new(budget_num_bytes: usize) -> AggregationCache {
+        AggregationCache {
+            inner: Mutex::new(AggregationCacheInner {
+                entries: LruCache::unbounded(),
+                num_bytes: 0,
+            }),
+            budget_num_bytes,
+            cache_hits: AtomicUsize::new(0),
+            cache_misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of entries, hits and misses of the cache.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            num_entries: self.inner.lock().unwrap().entries.len(),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the estimated memory used by the cached results.
+    pub fn num_bytes(&self) -> usize {
+        self.inner.lock().unwrap().num_bytes
+    }
+
+    /// Removes all of the entries of the cache.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.num_bytes = 0;
+    }
+
+    fn get(&self, key: &AggregationCacheKey) -> Option<IntermediateAggregationResults> {
+        let cached_res = self
+            .inner
+            .lock()
+            .unwrap()
+            .entries
+            .get(key)
+            .map(|cached| cached.res.clone());
+        if cached_res.is_some() {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+        cached_res
+    }
+
+    fn insert(&self, key: AggregationCacheKey, res: IntermediateAggregationResults) {
+        // Results that cannot be sized are not cached.
+        let Ok(res_num_bytes) = postcard::experimental::serialized_size(&res) else {
+            return;
+        };
+        let num_bytes = res_num_bytes + key.aggregation.len();
+        if num_bytes > self.budget_num_bytes {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(previous) = inner.entries.put(key, CachedResult { res, num_bytes }) {
+            inner.num_bytes -= previous.num_bytes;
+        }
+        inner.num_bytes += num_bytes;
+        while inner.num_bytes > self.budget_num_bytes {
+            let Some((_, evicted)) = inner.entries.pop_lru() else {
+                break;
+            };
+            inner.num_bytes -= evicted.num_bytes;
+        }
+    }
+}
+
+impl Warmer for AggregationCache {
+    fn warm(&self, _searcher: &Searcher) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn garbage_collect(&self, live_generations: &[&SearcherGeneration]) {
+        let mut inner = self.inner.lock().unwrap();
+        let is_live = |key: &AggregationCacheKey| {
+            live_generations.iter().any(|generation| {
+                generation.segments().get(&key.segment_id) == Some(&key.delete_opstamp)
+            })
+        };
+        let dead_keys: Vec<AggregationCacheKey> = inner
+            .entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| !is_live(key))
+            .cloned()
+            .collect();
+        for key in dead_keys {
+            if let Some(evicted) = inner.entries.pop(&key) {
+                inner.num_bytes -= evicted.num_bytes;
+            }
+        }
+    }
+}
+
+/// Returns true if the result of the aggregations depends on the instant `now` refers to.
+fn depends_on_now(aggs: &Aggregations) -> bool {
+    aggs.values().any(|agg| {
+        let is_relative = match &agg.agg {
+            AggregationVariants::DateRange(date_range) => date_range.is_relative_to_now(),
+            _ => false,
+        };
+        is_relative || depends_on_now(&agg.sub_aggregation)
+    })
+}
+
+/// The cache of an aggregation collector, along with the query and request part of the keys.
+pub(crate) struct AggregationCacheContext {
+    cache: Arc<AggregationCache>,
+    query_fingerprint: u128,
+    aggregation: Arc<str>,
+    now: Option<DateTime>,
+}
+
+impl AggregationCacheContext {
+    /// Returns `None` if the request cannot be used as a cache key, in which case the cache is
+    /// bypassed.
+    pub(crate) fn new(
+        cache: Arc<AggregationCache>,
+        query: &dyn Query,
+        aggs: &Aggregations,
+        now: Option<DateTime>,
+    ) -> Option<AggregationCacheContext> {
+        // Going through `serde_json::Value` sorts the keys of the request.
+        let aggregation = serde_json::to_value(aggs).ok()?.to_string();
+        Some(AggregationCacheContext {
+            cache,
+            query_fingerprint: query.fingerprint(),
+            aggregation: aggregation.into(),
+            now: now.filter(|_| depends_on_now(aggs)),
+        })
+    }
+
+    fn key(&self, reader: &SegmentReader) -> AggregationCacheKey {
+        AggregationCacheKey {
+            segment_id: reader.segment_id(),
+            delete_opstamp: reader.delete_opstamp(),
+            query_fingerprint: self.query_fingerprint,
+            aggregation: self.aggregation.clone(),
+            now: self.now,
+            runtime_fields_generation: reader.fast_fields().runtime_fields_generation(),
+        }
+    }
+
+    /// Returns the cached result for the segment, or computes and caches it with `collect`.
+    pub(crate) fn get_or_collect(
+        &self,
+        reader: &SegmentReader,
+        collect: impl FnOnce() -> crate::Result<crate::Result<IntermediateAggregationResults>>,
+    ) -> crate::Result<crate::Result<IntermediateAggregationResults>> {
+        let key = self.key(reader);
+        if let Some(cached_res) = self.cache.get(&key) {
+            return Ok(Ok(cached_res));
+        }
+        let res = collect()?;
+        // The result of an interrupted search is partial.
+        if let (Ok(intermediate_res), false) = (&res, reader.search_context().timed_out()) {
+            self.cache.insert(key, intermediate_res.clone());
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::AggregationCache;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::agg_result::AggregationResults;
+    use crate::aggregation::AggContextParams;
+    use crate::aggregation::AggregationCollector;
+    use crate::fastfield::{RuntimeField, RuntimeFieldType};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, STRING};
+    use crate::{DateTime, Index, IndexWriter, ReloadPolicy, Term, Warmer};
+
+    #[test]
+    fn test_aggregation_cache() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let category = schema_builder.add_text_field("category", STRING);
+        let score = schema_builder.add_u64_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let cache = Arc::new(AggregationCache::default());
+        let warmer: Arc<dyn Warmer> = cache.clone();
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .warmers(vec![Arc::downgrade(&warmer)])
+            .try_into()?;
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(category => "a", score => 1u64))?;
+        index_writer.add_document(doc!(category => "b", score => 2u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(category => "a", score => 3u64))?;
+        index_writer.commit()?;
+        reader.reload()?;
+
+        let agg_req: Aggregations =
+            serde_json::from_value(json!({ "sum_score": { "sum": { "field": "score" } } }))
+                .unwrap();
+        let sum_score = |query: &dyn crate::query::Query| -> crate::Result<f64> {
+            let collector = AggregationCollector::from_aggs(agg_req.clone(), Default::default())
+                .with_cache(cache.clone(), query);
+            let agg_res: AggregationResults = reader.searcher().search(query, &collector)?;
+            Ok(serde_json::to_value(agg_res)?["sum_score"]["value"]
+                .as_f64()
+                .unwrap())
+        };
+
+        assert_eq!(sum_score(&AllQuery)?, 6.0);
+        assert_eq!(cache.stats().cache_misses, 2);
+        assert_eq!(sum_score(&AllQuery)?, 6.0);
+        assert_eq!(cache.stats().cache_hits, 2);
+
+        // A different query does not share entries.
+        let term_query = TermQuery::new(
+            Term::from_field_text(category, "a"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(sum_score(&term_query)?, 4.0);
+        assert_eq!(cache.stats().cache_misses, 4);
+
+        // Only the new segment is collected.
+        index_writer.add_document(doc!(category => "a", score => 4u64))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(sum_score(&AllQuery)?, 10.0);
+        let stats = cache.stats();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (4, 5));
+
+        // Deletes change the key of the segment.
+        index_writer.delete_term(Term::from_field_text(category, "b"));
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(sum_score(&AllQuery)?, 8.0);
+
+        let searcher = reader.searcher();
+        warmer.garbage_collect(&[searcher.generation()]);
+        // The entries of the segment with the deleted document are evicted.
+        assert_eq!(cache.stats().num_entries, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_cache_budget() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let score = schema_builder.add_u64_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for segment_score in 0..3u64 {
+            index_writer.add_document(doc!(score => segment_score))?;
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let agg_req: Aggregations =
+            serde_json::from_value(json!({ "sum_score": { "sum": { "field": "score" } } }))
+                .unwrap();
+        let run = |cache: &Arc<AggregationCache>| -> crate::Result<()> {
+            let collector = AggregationCollector::from_aggs(agg_req.clone(), Default::default())
+                .with_cache(cache.clone(), &AllQuery);
+            searcher.search(&AllQuery, &collector)?;
+            Ok(())
+        };
+
+        let cache = Arc::new(AggregationCache::default());
+        run(&cache)?;
+        assert_eq!(cache.stats().num_entries, 3);
+        let entry_num_bytes = cache.num_bytes() / 3;
+
+        // Only two entries fit in the budget, the least recently used one is evicted.
+        let cache = Arc::new(AggregationCache::new(entry_num_bytes * 2));
+        run(&cache)?;
+        assert_eq!(cache.stats().num_entries, 2);
+        assert!(cache.num_bytes() <= entry_num_bytes * 2);
+
+        // Entries larger than the budget are not cached.
+        let cache = Arc::new(AggregationCache::new(entry_num_bytes - 1));
+        run(&cache)?;
+        assert_eq!(cache.stats().num_entries, 0);
+        assert_eq!(cache.num_bytes(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_cache_date_math() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let date = schema_builder.add_date_field("date", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for day in [1, 5, 9] {
+            index_writer.add_document(doc!(date => DateTime::from_timestamp_secs(day * 86_400)))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let cache = Arc::new(AggregationCache::default());
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "recent": {
+                "date_range": {
+                    "field": "date",
+                    "keyed": true,
+                    "ranges": [{ "key": "recent", "from": "now-3d/d" }]
+                }
+            }
+        }))
+        .unwrap();
+        let num_recent = |now_day: i64| -> crate::Result<u64> {
+            let context = AggContextParams::new(Default::default(), index.tokenizers().clone())
+                .with_now(DateTime::from_timestamp_secs(now_day * 86_400));
+            let collector = AggregationCollector::from_aggs(agg_req.clone(), context)
+                .with_cache(cache.clone(), &AllQuery);
+            let agg_res: AggregationResults = searcher.search(&AllQuery, &collector)?;
+            Ok(
+                serde_json::to_value(agg_res)?["recent"]["buckets"]["recent"]["doc_count"]
+                    .as_u64()
+                    .unwrap(),
+            )
+        };
+
+        assert_eq!(num_recent(10)?, 1);
+        assert_eq!(num_recent(10)?, 1);
+        assert_eq!(cache.stats().cache_hits, 1);
+        // Another instant does not reuse the buckets resolved for the previous one.
+        assert_eq!(num_recent(6)?, 2);
+        assert_eq!(cache.stats().cache_hits, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_cache_runtime_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let score = schema_builder.add_u64_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(score => 3u64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let cache = Arc::new(AggregationCache::default());
+        let agg_req: Aggregations =
+            serde_json::from_value(json!({ "sum_boosted": { "sum": { "field": "boosted" } } }))
+                .unwrap();
+        let sum_boosted = || -> crate::Result<f64> {
+            let collector = AggregationCollector::from_aggs(agg_req.clone(), Default::default())
+                .with_cache(cache.clone(), &AllQuery);
+            let agg_res: AggregationResults = searcher.search(&AllQuery, &collector)?;
+            Ok(serde_json::to_value(agg_res)?["sum_boosted"]["value"]
+                .as_f64()
+                .unwrap())
+        };
+
+        index.runtime_fields().register(
+            "boosted",
+            RuntimeField::from_expression("score * 2", RuntimeFieldType::F64)?,
+        );
+        assert_eq!(sum_boosted()?, 6.0);
+        assert_eq!(sum_boosted()?, 6.0);
+        assert_eq!(cache.stats().cache_hits, 1);
+        // The result computed with the previous definition is not reused.
+        index.runtime_fields().register(
+            "boosted",
+            RuntimeField::from_expression("score * 3", RuntimeFieldType::F64)?,
+        );
+        assert_eq!(sum_boosted()?, 9.0);
+        assert_eq!(cache.stats().cache_hits, 1);
+        Ok(())
+    }
+}
@@ -10,23 +10,31 @@ use crate::aggregation::accessor_helpers::{
 };
 use crate::aggregation::agg_req::{Aggregation, AggregationVariants, Aggregations};
 use crate::aggregation::bucket::{
-    build_segment_filter_collector, build_segment_range_collector, FilterAggReqData,
-    HistogramAggReqData, HistogramBounds, IncludeExcludeParam, MissingTermAggReqData,
-    RangeAggReqData, SegmentHistogramCollector, TermMissingAgg, TermsAggReqData, TermsAggregation,
-    TermsAggregationInternal,
+    build_segment_filter_collector, build_segment_filters_collector, build_segment_range_collector,
+    collect_rounding_idx, get_ip_compact_space_accessor, AutoDateHistogramReqData,
+    FilterAggReqData, FiltersAggReqData, GlobalAggReqData, HistogramAggReqData, HistogramBounds,
+    IncludeExcludeParam, MissingTermAggReqData, MultiTermsAggReqData, MultiTermsSourceAccessor,
+    RangeAggReqData, RangeAggregation, RareTermsAggReqData, SamplerAggReqData,
+    SegmentGlobalCollector, SegmentHistogramCollector, SegmentRareTermsCollector,
+    SegmentSamplerCollector, SegmentVariableWidthHistogramCollector, TermMissingAgg,
+    TermsAggReqData, TermsAggregation, TermsAggregationInternal, VariableWidthHistogramAggReqData,
 };
 use crate::aggregation::metric::{
-    build_segment_stats_collector, AverageAggregation, CardinalityAggReqData,
+    build_segment_stats_collector, AverageAggregation, BoxplotAggregation, CardinalityAggReqData,
     CardinalityAggregationReq, CountAggregation, ExtendedStatsAggregation, MaxAggregation,
-    MetricAggReqData, MinAggregation, SegmentCardinalityCollector, SegmentExtendedStatsCollector,
-    SegmentPercentilesCollector, StatsAggregation, StatsType, SumAggregation, TopHitsAggReqData,
-    TopHitsSegmentCollector,
+    MedianAbsoluteDeviationAggregation, MetricAggReqData, MinAggregation,
+    SegmentCardinalityCollector, SegmentExtendedStatsCollector, SegmentPercentilesCollector,
+    SegmentStringStatsCollector, SegmentTopMetricsCollector, SegmentWeightedAvgCollector,
+    StatsAggregation, StatsType, StringStatsAggReqData, SumAggregation, TopHitsAggReqData,
+    TopHitsSegmentCollector, TopMetricsAggReqData, WeightedAvgAggReqData,
 };
 use crate::aggregation::segment_agg_result::{
     GenericSegmentAggregationResultsCollector, SegmentAggregationCollector,
 };
-use crate::aggregation::{f64_to_fastfield_u64, AggContextParams, Key};
-use crate::{SegmentOrdinal, SegmentReader};
+use crate::aggregation::{
+    f64_from_fastfield_u64, f64_to_fastfield_u64, AggContextParams, AggregationError, Key,
+};
+use crate::{Score, SegmentOrdinal, SegmentReader};
 
 #[derive(Default)]
 /// Datastructure holding all request data for executing aggregations on a segment.
@@ -36,6 +44,9 @@ pub struct AggregationsSegmentCtx {
     pub per_request: PerRequestAggSegCtx,
     pub context: AggContextParams,
     pub column_block_accessor: ColumnBlockAccessor<u64>,
+    /// The scores of the docs passed to the top-level collectors, in the same order.
+    /// Only filled if the request requires scoring, see `requires_scoring`.
+    pub doc_scores: Vec<Score>,
 }
 
 impl AggregationsSegmentCtx {
@@ -43,6 +54,14 @@ impl AggregationsSegmentCtx {
         self.per_request.term_req_data.push(Some(Box::new(data)));
         self.per_request.term_req_data.len() - 1
     }
+    pub(crate) fn push_multi_terms_req_data(&mut self, data: MultiTermsAggReqData) -> usize {
+        self.per_request.multi_terms_req_data.push(data);
+        self.per_request.multi_terms_req_data.len() - 1
+    }
+    pub(crate) fn push_rare_terms_req_data(&mut self, data: RareTermsAggReqData) -> usize {
+        self.per_request.rare_terms_req_data.push(data);
+        self.per_request.rare_terms_req_data.len() - 1
+    }
     pub(crate) fn push_cardinality_req_data(&mut self, data: CardinalityAggReqData) -> usize {
         self.per_request.cardinality_req_data.push(data);
         self.per_request.cardinality_req_data.len() - 1
@@ -55,6 +74,18 @@ impl AggregationsSegmentCtx {
         self.per_request.top_hits_req_data.push(data);
         self.per_request.top_hits_req_data.len() - 1
     }
+    pub(crate) fn push_weighted_avg_req_data(&mut self, data: WeightedAvgAggReqData) -> usize {
+        self.per_request.weighted_avg_req_data.push(data);
+        self.per_request.weighted_avg_req_data.len() - 1
+    }
+    pub(crate) fn push_top_metrics_req_data(&mut self, data: TopMetricsAggReqData) -> usize {
+        self.per_request.top_metrics_req_data.push(data);
+        self.per_request.top_metrics_req_data.len() - 1
+    }
+    pub(crate) fn push_string_stats_req_data(&mut self, data: StringStatsAggReqData) -> usize {
+        self.per_request.string_stats_req_data.push(data);
+        self.per_request.string_stats_req_data.len() - 1
+    }
     pub(crate) fn push_missing_term_req_data(&mut self, data: MissingTermAggReqData) -> usize {
         self.per_request.missing_term_req_data.push(data);
         self.per_request.missing_term_req_data.len() - 1
@@ -65,6 +96,15 @@ impl AggregationsSegmentCtx {
             .push(Some(Box::new(data)));
         self.per_request.histogram_req_data.len() - 1
     }
+    pub(crate) fn push_variable_width_histogram_req_data(
+        &mut self,
+        data: VariableWidthHistogramAggReqData,
+    ) -> usize {
+        self.per_request
+            .variable_width_histogram_req_data
+            .push(data);
+        self.per_request.variable_width_histogram_req_data.len() - 1
+    }
     pub(crate) fn push_range_req_data(&mut self, data: RangeAggReqData) -> usize {
         self.per_request.range_req_data.push(Some(Box::new(data)));
         self.per_request.range_req_data.len() - 1
@@ -74,6 +114,20 @@ impl AggregationsSegmentCtx {
         self.per_request.filter_req_data.len() - 1
     }
 
+    pub(crate) fn push_global_req_data(&mut self, data: GlobalAggReqData) -> usize {
+        self.per_request.global_req_data.push(data);
+        self.per_request.global_req_data.len() - 1
+    }
+    pub(crate) fn push_sampler_req_data(&mut self, data: SamplerAggReqData) -> usize {
+        self.per_request.sampler_req_data.push(data);
+        self.per_request.sampler_req_data.len() - 1
+    }
+
+    pub(crate) fn push_filters_req_data(&mut self, data: FiltersAggReqData) -> usize {
+        self.per_request.filters_req_data.push(data);
+        self.per_request.filters_req_data.len() - 1
+    }
+
     #[inline]
     pub(crate) fn get_term_req_data(&self, idx: usize) -> &TermsAggReqData {
         self.per_request.term_req_data[idx]
@@ -81,6 +135,26 @@ impl AggregationsSegmentCtx {
             .expect("term_req_data slot is empty (taken)")
     }
     #[inline]
+    pub(crate) fn get_filters_req_data(&self, idx: usize) -> &FiltersAggReqData {
+        &self.per_request.filters_req_data[idx]
+    }
+    #[inline]
+    pub(crate) fn get_global_req_data(&self, idx: usize) -> &GlobalAggReqData {
+        &self.per_request.global_req_data[idx]
+    }
+    #[inline]
+    pub(crate) fn get_sampler_req_data(&self, idx: usize) -> &SamplerAggReqData {
+        &self.per_request.sampler_req_data[idx]
+    }
+    #[inline]
+    pub(crate) fn get_multi_terms_req_data(&self, idx: usize) -> &MultiTermsAggReqData {
+        &self.per_request.multi_terms_req_data[idx]
+    }
+    #[inline]
+    pub(crate) fn get_rare_terms_req_data(&self, idx: usize) -> &RareTermsAggReqData {
+        &self.per_request.rare_terms_req_data[idx]
+    }
+    #[inline]
     pub(crate) fn get_cardinality_req_data(&self, idx: usize) -> &CardinalityAggReqData {
         &self.per_request.cardinality_req_data[idx]
     }
@@ -93,6 +167,18 @@ impl AggregationsSegmentCtx {
         &self.per_request.top_hits_req_data[idx]
     }
     #[inline]
+    pub(crate) fn get_weighted_avg_req_data(&self, idx: usize) -> &WeightedAvgAggReqData {
+        &self.per_request.weighted_avg_req_data[idx]
+    }
+    #[inline]
+    pub(crate) fn get_top_metrics_req_data(&self, idx: usize) -> &TopMetricsAggReqData {
+        &self.per_request.top_metrics_req_data[idx]
+    }
+    #[inline]
+    pub(crate) fn get_string_stats_req_data(&self, idx: usize) -> &StringStatsAggReqData {
+        &self.per_request.string_stats_req_data[idx]
+    }
+    #[inline]
     pub(crate) fn get_missing_term_req_data(&self, idx: usize) -> &MissingTermAggReqData {
         &self.per_request.missing_term_req_data[idx]
     }
@@ -103,6 +189,13 @@ impl AggregationsSegmentCtx {
             .expect("histogram_req_data slot is empty (taken)")
     }
     #[inline]
+    pub(crate) fn get_variable_width_histogram_req_data(
+        &self,
+        idx: usize,
+    ) -> &VariableWidthHistogramAggReqData {
+        &self.per_request.variable_width_histogram_req_data[idx]
+    }
+    #[inline]
     pub(crate) fn get_range_req_data(&self, idx: usize) -> &RangeAggReqData {
         self.per_request.range_req_data[idx]
             .as_deref()
@@ -194,18 +287,38 @@ pub struct PerRequestAggSegCtx {
     // Box for cheap take/put - Only necessary for bucket aggs that have sub-aggregations
     /// TermsAggReqData contains the request data for a terms aggregation.
     pub term_req_data: Vec<Option<Box<TermsAggReqData>>>,
+    /// MultiTermsAggReqData contains the request data for a multi_terms aggregation.
+    pub multi_terms_req_data: Vec<MultiTermsAggReqData>,
+    /// RareTermsAggReqData contains the request data for a rare_terms aggregation.
+    pub rare_terms_req_data: Vec<RareTermsAggReqData>,
     /// HistogramAggReqData contains the request data for a histogram aggregation.
     pub histogram_req_data: Vec<Option<Box<HistogramAggReqData>>>,
+    /// VariableWidthHistogramAggReqData contains the request data for a variable_width_histogram
+    /// aggregation.
+    pub variable_width_histogram_req_data: Vec<VariableWidthHistogramAggReqData>,
     /// RangeAggReqData contains the request data for a range aggregation.
     pub range_req_data: Vec<Option<Box<RangeAggReqData>>>,
     /// FilterAggReqData contains the request data for a filter aggregation.
     pub filter_req_data: Vec<Option<Box<FilterAggReqData>>>,
-    /// Shared by avg, min, max, sum, stats, extended_stats, count
+    /// FiltersAggReqData contains the request data for a filters aggregation.
+    pub filters_req_data: Vec<FiltersAggReqData>,
+    /// GlobalAggReqData contains the request data for a global aggregation.
+    pub global_req_data: Vec<GlobalAggReqData>,
+    /// Shared by sampler, diversified_sampler
+    pub sampler_req_data: Vec<SamplerAggReqData>,
+    /// Shared by avg, min, max, sum, stats, extended_stats, count, percentiles,
+    /// percentile_ranks, median_absolute_deviation, boxplot
     pub stats_metric_req_data: Vec<MetricAggReqData>,
     /// CardinalityAggReqData contains the request data for a cardinality aggregation.
     pub cardinality_req_data: Vec<CardinalityAggReqData>,
     /// TopHitsAggReqData contains the request data for a top_hits aggregation.
     pub top_hits_req_data: Vec<TopHitsAggReqData>,
+    /// WeightedAvgAggReqData contains the request data for a weighted_avg aggregation.
+    pub weighted_avg_req_data: Vec<WeightedAvgAggReqData>,
+    /// TopMetricsAggReqData contains the request data for a top_metrics aggregation.
+    pub top_metrics_req_data: Vec<TopMetricsAggReqData>,
+    /// StringStatsAggReqData contains the request data for a string_stats aggregation.
+    pub string_stats_req_data: Vec<StringStatsAggReqData>,
     /// MissingTermAggReqData contains the request data for a missing term aggregation.
     pub missing_term_req_data: Vec<MissingTermAggReqData>,
 
@@ -220,11 +333,26 @@ impl PerRequestAggSegCtx {
             .iter()
             .map(|b| b.as_ref().unwrap().get_memory_consumption())
             .sum::<usize>()
+            + self
+                .multi_terms_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
+            + self
+                .rare_terms_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
             + self
                 .histogram_req_data
                 .iter()
                 .map(|b| b.as_ref().unwrap().get_memory_consumption())
                 .sum::<usize>()
+            + self
+                .variable_width_histogram_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
             + self
                 .range_req_data
                 .iter()
@@ -235,6 +363,21 @@ impl PerRequestAggSegCtx {
                 .iter()
                 .map(|b| b.as_ref().unwrap().get_memory_consumption())
                 .sum::<usize>()
+            + self
+                .filters_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
+            + self
+                .global_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
+            + self
+                .sampler_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
             + self
                 .stats_metric_req_data
                 .iter()
@@ -250,6 +393,21 @@ impl PerRequestAggSegCtx {
                 .iter()
                 .map(|t| t.get_memory_consumption())
                 .sum::<usize>()
+            + self
+                .weighted_avg_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
+            + self
+                .top_metrics_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
+            + self
+                .string_stats_req_data
+                .iter()
+                .map(|t| t.get_memory_consumption())
+                .sum::<usize>()
             + self
                 .missing_term_req_data
                 .iter()
@@ -267,9 +425,14 @@ impl PerRequestAggSegCtx {
                 .expect("term_req_data slot is empty (taken)")
                 .name
                 .as_str(),
+            AggKind::MultiTerms => &self.multi_terms_req_data[idx].name,
+            AggKind::RareTerms => &self.rare_terms_req_data[idx].name,
             AggKind::Cardinality => &self.cardinality_req_data[idx].name,
             AggKind::StatsKind(_) => &self.stats_metric_req_data[idx].name,
             AggKind::TopHits => &self.top_hits_req_data[idx].name,
+            AggKind::WeightedAvg => &self.weighted_avg_req_data[idx].name,
+            AggKind::TopMetrics => &self.top_metrics_req_data[idx].name,
+            AggKind::StringStats => &self.string_stats_req_data[idx].name,
             AggKind::MissingTerm => &self.missing_term_req_data[idx].name,
             AggKind::Histogram => self.histogram_req_data[idx]
                 .as_deref()
@@ -281,6 +444,12 @@ impl PerRequestAggSegCtx {
                 .expect("histogram_req_data slot is empty (taken)")
                 .name
                 .as_str(),
+            AggKind::AutoDateHistogram => self.histogram_req_data[idx]
+                .as_deref()
+                .expect("histogram_req_data slot is empty (taken)")
+                .name
+                .as_str(),
+            AggKind::VariableWidthHistogram => &self.variable_width_histogram_req_data[idx].name,
             AggKind::Range => self.range_req_data[idx]
                 .as_deref()
                 .expect("range_req_data slot is empty (taken)")
@@ -291,6 +460,9 @@ impl PerRequestAggSegCtx {
                 .expect("filter_req_data slot is empty (taken)")
                 .name
                 .as_str(),
+            AggKind::Filters => &self.filters_req_data[idx].name,
+            AggKind::Global => &self.global_req_data[idx].name,
+            AggKind::Sampler => &self.sampler_req_data[idx].name,
         }
     }
 
@@ -358,6 +530,12 @@ pub(crate) fn build_segment_agg_collector(
 ) -> crate::Result<Box<dyn SegmentAggregationCollector>> {
     match node.kind {
         AggKind::Terms => crate::aggregation::bucket::build_segment_term_collector(req, node),
+        AggKind::MultiTerms => {
+            crate::aggregation::bucket::build_segment_multi_terms_collector(req, node)
+        }
+        AggKind::RareTerms => Ok(Box::new(SegmentRareTermsCollector::from_req_and_validate(
+            req, node,
+        )?)),
         AggKind::MissingTerm => {
             let req_data = &mut req.per_request.missing_term_req_data[node.idx_in_req_data];
             if req_data.accessors.is_empty() {
@@ -388,7 +566,10 @@ pub(crate) fn build_segment_agg_collector(
                 StatsType::ExtendedStats(sigma) => Ok(Box::new(
                     SegmentExtendedStatsCollector::from_req(req_data, sigma),
                 )),
-                StatsType::Percentiles => {
+                StatsType::Percentiles
+                | StatsType::PercentileRanks
+                | StatsType::MedianAbsoluteDeviation
+                | StatsType::Boxplot => {
                     let req_data = req.get_metric_req_data_mut(node.idx_in_req_data);
                     Ok(Box::new(
                         SegmentPercentilesCollector::from_req_and_validate(
@@ -396,6 +577,7 @@ pub(crate) fn build_segment_agg_collector(
                             req_data.missing_u64,
                             req_data.accessor.clone(),
                             node.idx_in_req_data,
+                            stats_type,
                         ),
                     ))
                 }
@@ -409,14 +591,40 @@ pub(crate) fn build_segment_agg_collector(
                 req_data.segment_ordinal,
             )))
         }
+        AggKind::WeightedAvg => Ok(Box::new(SegmentWeightedAvgCollector::from_req(
+            node.idx_in_req_data,
+        ))),
+        AggKind::TopMetrics => {
+            let req_data = req.get_top_metrics_req_data(node.idx_in_req_data);
+            Ok(Box::new(SegmentTopMetricsCollector::from_req(
+                &req_data.req,
+                node.idx_in_req_data,
+            )))
+        }
+        AggKind::StringStats => Ok(Box::new(SegmentStringStatsCollector::from_req(
+            node.idx_in_req_data,
+        ))),
         AggKind::Histogram => Ok(Box::new(SegmentHistogramCollector::from_req_and_validate(
             req, node,
         )?)),
         AggKind::DateHistogram => Ok(Box::new(SegmentHistogramCollector::from_req_and_validate(
             req, node,
         )?)),
+        AggKind::AutoDateHistogram => Ok(Box::new(
+            SegmentHistogramCollector::from_req_and_validate(req, node)?,
+        )),
+        AggKind::VariableWidthHistogram => Ok(Box::new(
+            SegmentVariableWidthHistogramCollector::from_req_and_validate(req, node)?,
+        )),
         AggKind::Range => Ok(build_segment_range_collector(req, node)?),
         AggKind::Filter => build_segment_filter_collector(req, node),
+        AggKind::Filters => build_segment_filters_collector(req, node),
+        AggKind::Global => Ok(Box::new(SegmentGlobalCollector::from_req_and_validate(
+            req, node,
+        )?)),
+        AggKind::Sampler => Ok(Box::new(SegmentSamplerCollector::from_req_and_validate(
+            req, node,
+        )?)),
     }
 }
 
@@ -438,15 +646,28 @@ impl AggRefNode {
 #[derive(Copy, Clone, Debug)]
 pub enum AggKind {
     Terms,
+    MultiTerms,
+    /// Its children are the terms nodes which collect the term counts of the field.
+    RareTerms,
     Cardinality,
-    /// One of: Statistics, Average, Min, Max, Sum, Count, Stats, ExtendedStats
+    /// One of: Average, Min, Max, Sum, Count, Stats, ExtendedStats, Percentiles,
+    /// PercentileRanks, MedianAbsoluteDeviation, Boxplot
     StatsKind(StatsType),
     TopHits,
+    WeightedAvg,
+    TopMetrics,
+    StringStats,
     MissingTerm,
     Histogram,
     DateHistogram,
+    AutoDateHistogram,
+    VariableWidthHistogram,
     Range,
     Filter,
+    Filters,
+    Global,
+    /// One of: Sampler, DiversifiedSampler
+    Sampler,
 }
 
 impl AggKind {
@@ -454,14 +675,24 @@ impl AggKind {
     fn as_str(&self) -> &'static str {
         match self {
             AggKind::Terms => "Terms",
+            AggKind::MultiTerms => "MultiTerms",
+            AggKind::RareTerms => "RareTerms",
             AggKind::Cardinality => "Cardinality",
             AggKind::StatsKind(_) => "Metric",
             AggKind::TopHits => "TopHits",
+            AggKind::WeightedAvg => "WeightedAvg",
+            AggKind::TopMetrics => "TopMetrics",
+            AggKind::StringStats => "StringStats",
             AggKind::MissingTerm => "MissingTerm",
             AggKind::Histogram => "Histogram",
             AggKind::DateHistogram => "DateHistogram",
+            AggKind::AutoDateHistogram => "AutoDateHistogram",
+            AggKind::VariableWidthHistogram => "VariableWidthHistogram",
             AggKind::Range => "Range",
             AggKind::Filter => "Filter",
+            AggKind::Filters => "Filters",
+            AggKind::Global => "Global",
+            AggKind::Sampler => "Sampler",
         }
     }
 }
@@ -477,6 +708,7 @@ pub(crate) fn build_aggregations_data_from_req(
         per_request: Default::default(),
         context,
         column_block_accessor: ColumnBlockAccessor::default(),
+        doc_scores: Vec::new(),
     };
 
     for (name, agg) in aggs.iter() {
@@ -507,6 +739,55 @@ fn build_nodes(
                 field_type,
                 name: agg_name.to_string(),
                 req: range_req.clone(),
+                ip_ranges: None,
+                is_top_level,
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::Range,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        DateRange(date_range_req) => {
+            let (accessor, _) =
+                get_ff_reader(reader, &date_range_req.field, Some(&[ColumnType::DateTime]))?;
+            let range_req = date_range_req.to_range_req(data.context.now_or_init())?;
+            let idx_in_req_data = data.push_range_req_data(RangeAggReqData {
+                accessor,
+                // Keys are formatted as dates, even if the segment has no values.
+                field_type: ColumnType::DateTime,
+                name: agg_name.to_string(),
+                req: range_req,
+                ip_ranges: None,
+                is_top_level,
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::Range,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        IpRange(ip_range_req) => {
+            let (accessor, field_type) =
+                get_ff_reader(reader, &ip_range_req.field, Some(&[ColumnType::IpAddr]))?;
+            let compact_space_accessor = if field_type == ColumnType::IpAddr {
+                get_ip_compact_space_accessor(&accessor)
+            } else {
+                None
+            };
+            let ip_ranges = ip_range_req.to_segment_ranges(compact_space_accessor.as_deref())?;
+            let idx_in_req_data = data.push_range_req_data(RangeAggReqData {
+                accessor,
+                field_type: ColumnType::IpAddr,
+                name: agg_name.to_string(),
+                req: RangeAggregation {
+                    field: ip_range_req.field.clone(),
+                    ranges: Vec::new(),
+                    keyed: ip_range_req.keyed,
+                },
+                ip_ranges: Some(ip_ranges),
                 is_top_level,
             });
             let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
@@ -527,12 +808,14 @@ fn build_nodes(
                 field_type,
                 name: agg_name.to_string(),
                 req: histo_req.clone(),
+                date_rounding: None,
                 is_date_histogram: false,
                 bounds: HistogramBounds {
                     min: f64::MIN,
                     max: f64::MAX,
                 },
                 offset: 0.0,
+                auto_date_histogram: None,
             });
             let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
             Ok(vec![AggRefNode {
@@ -545,19 +828,21 @@ fn build_nodes(
             let (accessor, field_type) =
                 get_ff_reader(reader, &date_req.field, Some(&[ColumnType::DateTime]))?;
             // Convert to histogram request, normalize to ns precision
-            let mut histo_req = date_req.to_histogram_req()?;
+            let (mut histo_req, date_rounding) = date_req.to_histogram_req()?;
             histo_req.normalize_date_time();
             let idx_in_req_data = data.push_histogram_req_data(HistogramAggReqData {
                 accessor,
                 field_type,
                 name: agg_name.to_string(),
                 req: histo_req,
+                date_rounding,
                 is_date_histogram: true,
                 bounds: HistogramBounds {
                     min: f64::MIN,
                     max: f64::MAX,
                 },
                 offset: 0.0,
+                auto_date_histogram: None,
             });
             let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
             Ok(vec![AggRefNode {
@@ -566,6 +851,69 @@ fn build_nodes(
                 children,
             }])
         }
+        AutoDateHistogram(auto_req) => {
+            auto_req.validate()?;
+            let (accessor, field_type) =
+                get_ff_reader(reader, &auto_req.field, Some(&[ColumnType::DateTime]))?;
+            // Pick the rounding from the value range of the segment. The intermediate results
+            // of the segments are re-rounded to a common rounding when merged.
+            let (min_ms, max_ms) = if accessor.values.num_vals() == 0 {
+                (0, 0)
+            } else {
+                let to_ms = |val: u64| {
+                    (f64_from_fastfield_u64(val, field_type) / 1_000_000.0).floor() as i64
+                };
+                (to_ms(accessor.min_value()), to_ms(accessor.max_value()))
+            };
+            let rounding_idx = auto_req.rounding_idx_for_range(min_ms, max_ms)?;
+            let (mut histo_req, date_rounding) =
+                auto_req.to_histogram_req(collect_rounding_idx(rounding_idx))?;
+            histo_req.normalize_date_time();
+            let idx_in_req_data = data.push_histogram_req_data(HistogramAggReqData {
+                accessor,
+                field_type,
+                name: agg_name.to_string(),
+                req: histo_req,
+                date_rounding: Some(date_rounding),
+                is_date_histogram: true,
+                bounds: HistogramBounds {
+                    min: f64::MIN,
+                    max: f64::MAX,
+                },
+                offset: 0.0,
+                auto_date_histogram: Some(AutoDateHistogramReqData {
+                    req: auto_req.clone(),
+                    rounding_idx,
+                }),
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::AutoDateHistogram,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        VariableWidthHistogram(vw_req) => {
+            vw_req.validate()?;
+            let (accessor, field_type) = get_ff_reader(
+                reader,
+                &vw_req.field,
+                Some(&[ColumnType::U64, ColumnType::I64, ColumnType::F64]),
+            )?;
+            let idx_in_req_data =
+                data.push_variable_width_histogram_req_data(VariableWidthHistogramAggReqData {
+                    accessor,
+                    field_type,
+                    name: agg_name.to_string(),
+                    req: vw_req.clone(),
+                });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::VariableWidthHistogram,
+                idx_in_req_data,
+                children,
+            }])
+        }
         Terms(terms_req) => build_terms_or_cardinality_nodes(
             agg_name,
             &terms_req.field,
@@ -577,6 +925,56 @@ fn build_nodes(
             TermsOrCardinalityRequest::Terms(terms_req.clone()),
             is_top_level,
         ),
+        RareTerms(rare_terms_req) => {
+            rare_terms_req.validate()?;
+            // The terms aggregation collects the exact term counts of the segment, the rare
+            // terms collector then splits them into rare and frequent terms.
+            let children = build_terms_or_cardinality_nodes(
+                agg_name,
+                &rare_terms_req.field,
+                &rare_terms_req.missing,
+                reader,
+                segment_ordinal,
+                data,
+                &req.sub_aggregation,
+                TermsOrCardinalityRequest::Terms(rare_terms_req.to_terms_req()),
+                is_top_level,
+            )?;
+            let idx_in_req_data = data.push_rare_terms_req_data(RareTermsAggReqData {
+                name: agg_name.to_string(),
+                req: rare_terms_req.clone(),
+            });
+            Ok(vec![AggRefNode {
+                kind: AggKind::RareTerms,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        MultiTerms(multi_terms_req) => {
+            multi_terms_req.validate()?;
+            let sources = multi_terms_req
+                .terms
+                .iter()
+                .map(|source| {
+                    Ok(MultiTermsSourceAccessor {
+                        columns: get_term_agg_accessors(reader, &source.field, &None)?,
+                        str_dict_column: reader.fast_fields().str(&source.field)?,
+                        missing: source.missing.clone(),
+                    })
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            let idx_in_req_data = data.push_multi_terms_req_data(MultiTermsAggReqData {
+                name: agg_name.to_string(),
+                req: multi_terms_req.clone(),
+                sources,
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::MultiTerms,
+                idx_in_req_data,
+                children,
+            }])
+        }
         Cardinality(card_req) => build_terms_or_cardinality_nodes(
             agg_name,
             &card_req.field,
@@ -646,23 +1044,45 @@ fn build_nodes(
                 children,
             }])
         }
-        // Percentiles handled as Metric as well
-        AggregationVariants::Percentiles(percentiles_req) => {
-            percentiles_req.validate()?;
-            let (accessor, field_type) = get_ff_reader(
-                reader,
-                percentiles_req.field_name(),
-                Some(get_numeric_or_date_column_types()),
-            )?;
+        // Percentiles and the other sketch based metrics are handled as Metric as well
+        AggregationVariants::Percentiles(_)
+        | AggregationVariants::PercentileRanks(_)
+        | AggregationVariants::MedianAbsoluteDeviation(_)
+        | AggregationVariants::Boxplot(_) => {
+            let (field, missing, collecting_for) = match &req.agg {
+                AggregationVariants::Percentiles(percentiles_req) => {
+                    percentiles_req.validate()?;
+                    (
+                        percentiles_req.field_name(),
+                        percentiles_req.missing,
+                        StatsType::Percentiles,
+                    )
+                }
+                AggregationVariants::PercentileRanks(ranks_req) => {
+                    ranks_req.validate()?;
+                    (
+                        ranks_req.field_name(),
+                        ranks_req.missing,
+                        StatsType::PercentileRanks,
+                    )
+                }
+                AggregationVariants::MedianAbsoluteDeviation(
+                    MedianAbsoluteDeviationAggregation { field, missing },
+                ) => (field.as_str(), *missing, StatsType::MedianAbsoluteDeviation),
+                AggregationVariants::Boxplot(BoxplotAggregation { field, missing }) => {
+                    (field.as_str(), *missing, StatsType::Boxplot)
+                }
+                _ => unreachable!("non sketch aggregation in sketch metric handling"),
+            };
+            let (accessor, field_type) =
+                get_ff_reader(reader, field, Some(get_numeric_or_date_column_types()))?;
             let idx_in_req_data = data.push_metric_req_data(MetricAggReqData {
                 accessor,
                 field_type,
                 name: agg_name.to_string(),
-                collecting_for: StatsType::Percentiles,
-                missing: percentiles_req.missing,
-                missing_u64: percentiles_req
-                    .missing
-                    .and_then(|m| f64_to_fastfield_u64(m, &field_type)),
+                collecting_for,
+                missing,
+                missing_u64: missing.and_then(|m| f64_to_fastfield_u64(m, &field_type)),
                 is_number_or_date_type: matches!(
                     field_type,
                     ColumnType::I64 | ColumnType::U64 | ColumnType::F64 | ColumnType::DateTime
@@ -670,7 +1090,82 @@ fn build_nodes(
             });
             let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
             Ok(vec![AggRefNode {
-                kind: AggKind::StatsKind(StatsType::Percentiles),
+                kind: AggKind::StatsKind(collecting_for),
+                idx_in_req_data,
+                children,
+            }])
+        }
+        AggregationVariants::WeightedAvg(weighted_avg_req) => {
+            let (value_accessor, value_field_type) = get_ff_reader(
+                reader,
+                &weighted_avg_req.value.field,
+                Some(get_numeric_or_date_column_types()),
+            )?;
+            let (weight_accessor, weight_field_type) = get_ff_reader(
+                reader,
+                &weighted_avg_req.weight.field,
+                Some(get_numeric_or_date_column_types()),
+            )?;
+            let idx_in_req_data = data.push_weighted_avg_req_data(WeightedAvgAggReqData {
+                value_accessor,
+                value_field_type,
+                value_missing: weighted_avg_req.value.missing,
+                weight_accessor,
+                weight_field_type,
+                weight_missing: weighted_avg_req.weight.missing,
+                name: agg_name.to_string(),
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::WeightedAvg,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        AggregationVariants::TopMetrics(top_metrics_req) => {
+            top_metrics_req.validate()?;
+            let sort_accessor = get_ff_reader(
+                reader,
+                top_metrics_req.sort_field_name(),
+                Some(get_numeric_or_date_column_types()),
+            )?;
+            let metric_accessors = top_metrics_req
+                .metric_field_names()
+                .map(|field| get_ff_reader(reader, field, Some(get_numeric_or_date_column_types())))
+                .collect::<crate::Result<_>>()?;
+            let idx_in_req_data = data.push_top_metrics_req_data(TopMetricsAggReqData {
+                sort_accessor,
+                metric_accessors,
+                name: agg_name.to_string(),
+                req: top_metrics_req.clone(),
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::TopMetrics,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        AggregationVariants::StringStats(string_stats_req) => {
+            let (accessor, _) =
+                get_ff_reader(reader, &string_stats_req.field, Some(&[ColumnType::Str]))?;
+            let str_dict_column = reader.fast_fields().str(&string_stats_req.field)?;
+            // Like terms, documents without a value get a sentinel term ordinal above all
+            // existing ones.
+            let missing_value_for_accessor = string_stats_req
+                .missing
+                .as_ref()
+                .map(|_| accessor.max_value() + 1);
+            let idx_in_req_data = data.push_string_stats_req_data(StringStatsAggReqData {
+                accessor,
+                str_dict_column,
+                missing_value_for_accessor,
+                name: agg_name.to_string(),
+                req: string_stats_req.clone(),
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::StringStats,
                 idx_in_req_data,
                 children,
             }])
@@ -740,9 +1235,97 @@ fn build_nodes(
                 children,
             }])
         }
+        AggregationVariants::Filters(filters_req) => {
+            let schema = reader.schema();
+            let tokenizers = &data.context.tokenizers;
+            let evaluators = filters_req
+                .filters()
+                .into_iter()
+                .map(|filter| {
+                    let query = filter.parse_query(schema, tokenizers)?;
+                    crate::aggregation::bucket::DocumentQueryEvaluator::new(
+                        query,
+                        schema.clone(),
+                        reader,
+                    )
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+
+            let idx_in_req_data = data.push_filters_req_data(FiltersAggReqData {
+                name: agg_name.to_string(),
+                req: filters_req.clone(),
+                evaluators,
+                is_top_level,
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::Filters,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        Global(_) => {
+            if !is_top_level {
+                return Err(top_level_only_error("global", agg_name));
+            }
+            let idx_in_req_data = data.push_global_req_data(GlobalAggReqData {
+                name: agg_name.to_string(),
+                max_doc: reader.max_doc(),
+                alive_bitset: reader.alive_bitset().cloned(),
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::Global,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        Sampler(sampler_req) => {
+            if !is_top_level {
+                return Err(top_level_only_error("sampler", agg_name));
+            }
+            sampler_req.validate()?;
+            let idx_in_req_data = data.push_sampler_req_data(SamplerAggReqData {
+                name: agg_name.to_string(),
+                shard_size: sampler_req.shard_size,
+                diversify_by: None,
+                max_docs_per_value: sampler_req.shard_size,
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::Sampler,
+                idx_in_req_data,
+                children,
+            }])
+        }
+        DiversifiedSampler(sampler_req) => {
+            if !is_top_level {
+                return Err(top_level_only_error("diversified_sampler", agg_name));
+            }
+            sampler_req.validate()?;
+            let (accessor, _) = get_ff_reader(reader, &sampler_req.field, None)?;
+            let idx_in_req_data = data.push_sampler_req_data(SamplerAggReqData {
+                name: agg_name.to_string(),
+                shard_size: sampler_req.shard_size,
+                diversify_by: Some(accessor),
+                max_docs_per_value: sampler_req.max_docs_per_value,
+            });
+            let children = build_children(&req.sub_aggregation, reader, segment_ordinal, data)?;
+            Ok(vec![AggRefNode {
+                kind: AggKind::Sampler,
+                idx_in_req_data,
+                children,
+            }])
+        }
     }
 }
 
+fn top_level_only_error(agg_type: &str, agg_name: &str) -> crate::TantivyError {
+    crate::TantivyError::AggregationError(AggregationError::InvalidRequest(format!(
+        "{agg_type} aggregation `{agg_name}` has to be a top-level aggregation"
+    )))
+}
+
 fn build_children(
     aggs: &Aggregations,
     reader: &SegmentReader,
@@ -32,13 +32,18 @@ use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 
 use super::bucket::{
-    DateHistogramAggregationReq, FilterAggregation, HistogramAggregation, RangeAggregation,
-    TermsAggregation,
+    AutoDateHistogramAggregationReq, DateHistogramAggregationReq, DateRangeAggregation,
+    DateRounding, DiversifiedSamplerAggregation, FilterAggregation, FiltersAggregation,
+    GlobalAggregation, HistogramAggregation, IpRangeAggregation, MultiTermsAggregation,
+    RangeAggregation, RareTermsAggregation, SamplerAggregation, TermsAggregation,
+    VariableWidthHistogramAggregationReq,
 };
 use super::metric::{
-    AverageAggregation, CardinalityAggregationReq, CountAggregation, ExtendedStatsAggregation,
-    MaxAggregation, MinAggregation, PercentilesAggregationReq, StatsAggregation, SumAggregation,
-    TopHitsAggregationReq,
+    AverageAggregation, BoxplotAggregation, CardinalityAggregationReq, CountAggregation,
+    ExtendedStatsAggregation, MaxAggregation, MedianAbsoluteDeviationAggregation, MinAggregation,
+    PercentileRanksAggregationReq, PercentilesAggregationReq, StatsAggregation,
+    StringStatsAggregation, SumAggregation, TopHitsAggregationReq, TopMetricsAggregationReq,
+    WeightedAvgAggregation,
 };
 
 /// The top-level aggregation request structure, which contains [`Aggregation`] and their user
@@ -115,6 +120,19 @@ pub fn get_fast_field_names(aggs: &Aggregations) -> HashSet<String> {
     fast_field_names
 }
 
+/// Returns true if one of the aggregations relies on the scores of the documents, i.e. it
+/// contains a `sampler` or `diversified_sampler` aggregation.
+///
+/// Those aggregations have to be top-level aggregations, so only the top level is checked.
+pub(crate) fn requires_scoring(aggs: &Aggregations) -> bool {
+    aggs.values().any(|agg| {
+        matches!(
+            agg.agg,
+            AggregationVariants::Sampler(_) | AggregationVariants::DiversifiedSampler(_)
+        )
+    })
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// All aggregation types.
 pub enum AggregationVariants {
@@ -122,18 +140,49 @@ pub enum AggregationVariants {
     /// Put data into buckets of user-defined ranges.
     #[serde(rename = "range")]
     Range(RangeAggregation),
+    /// Put data into buckets of user-defined date ranges.
+    #[serde(rename = "date_range")]
+    DateRange(DateRangeAggregation),
+    /// Put data into buckets of user-defined ip address ranges.
+    #[serde(rename = "ip_range")]
+    IpRange(IpRangeAggregation),
     /// Put data into a histogram.
     #[serde(rename = "histogram")]
     Histogram(HistogramAggregation),
     /// Put data into a date histogram.
     #[serde(rename = "date_histogram")]
     DateHistogram(DateHistogramAggregationReq),
+    /// Put data into a date histogram, with an interval picked to fit a number of buckets.
+    #[serde(rename = "auto_date_histogram")]
+    AutoDateHistogram(AutoDateHistogramAggregationReq),
+    /// Put data into a histogram, with buckets derived from clusters of values.
+    #[serde(rename = "variable_width_histogram")]
+    VariableWidthHistogram(VariableWidthHistogramAggregationReq),
     /// Put data into buckets of terms.
     #[serde(rename = "terms")]
     Terms(TermsAggregation),
+    /// Put data into buckets of compound keys over multiple fields.
+    #[serde(rename = "multi_terms")]
+    MultiTerms(MultiTermsAggregation),
+    /// Put data into buckets of terms which occur in few documents.
+    #[serde(rename = "rare_terms")]
+    RareTerms(RareTermsAggregation),
     /// Filter documents into a single bucket.
     #[serde(rename = "filter")]
     Filter(FilterAggregation),
+    /// Filter documents into one bucket per filter.
+    #[serde(rename = "filters")]
+    Filters(FiltersAggregation),
+    /// Put all documents of the index into a single bucket, regardless of the query.
+    #[serde(rename = "global")]
+    Global(GlobalAggregation),
+    /// Put the top scoring documents into a single bucket.
+    #[serde(rename = "sampler")]
+    Sampler(SamplerAggregation),
+    /// Put the top scoring documents into a single bucket, with a limited number of documents
+    /// per value of a field.
+    #[serde(rename = "diversified_sampler")]
+    DiversifiedSampler(DiversifiedSamplerAggregation),
 
     // Metric aggregation types
     /// Computes the average of the extracted values.
@@ -169,6 +218,24 @@ pub enum AggregationVariants {
     /// Computes an estimate of the number of unique values
     #[serde(rename = "cardinality")]
     Cardinality(CardinalityAggregationReq),
+    /// Computes the average of the extracted values, weighted by a second field.
+    #[serde(rename = "weighted_avg")]
+    WeightedAvg(WeightedAvgAggregation),
+    /// Computes the median absolute deviation of the extracted values.
+    #[serde(rename = "median_absolute_deviation")]
+    MedianAbsoluteDeviation(MedianAbsoluteDeviationAggregation),
+    /// Finds the metrics of the top k documents by a sort field.
+    #[serde(rename = "top_metrics")]
+    TopMetrics(TopMetricsAggregationReq),
+    /// Computes the percentile ranks of the given values.
+    #[serde(rename = "percentile_ranks")]
+    PercentileRanks(PercentileRanksAggregationReq),
+    /// Computes the minimum, maximum, quartiles and whiskers of the extracted values.
+    #[serde(rename = "boxplot")]
+    Boxplot(BoxplotAggregation),
+    /// Computes length and character statistics over the values of a text field.
+    #[serde(rename = "string_stats")]
+    StringStats(StringStatsAggregation),
 }
 
 impl AggregationVariants {
@@ -176,10 +243,26 @@ impl AggregationVariants {
     pub fn get_fast_field_names(&self) -> Vec<&str> {
         match self {
             AggregationVariants::Terms(terms) => vec![terms.field.as_str()],
+            AggregationVariants::MultiTerms(multi_terms) => multi_terms.field_names(),
+            AggregationVariants::RareTerms(rare_terms) => vec![rare_terms.field.as_str()],
             AggregationVariants::Range(range) => vec![range.field.as_str()],
+            AggregationVariants::DateRange(range) => vec![range.field.as_str()],
+            AggregationVariants::IpRange(range) => vec![range.field.as_str()],
             AggregationVariants::Histogram(histogram) => vec![histogram.field.as_str()],
             AggregationVariants::DateHistogram(histogram) => vec![histogram.field.as_str()],
+            AggregationVariants::AutoDateHistogram(histogram) => vec![histogram.field.as_str()],
+            AggregationVariants::VariableWidthHistogram(histogram) => {
+                vec![histogram.field.as_str()]
+            }
             AggregationVariants::Filter(filter) => filter.get_fast_field_names(),
+            AggregationVariants::Filters(filters) => filters
+                .filters()
+                .into_iter()
+                .flat_map(|filter| filter.get_fast_field_names())
+                .collect(),
+            AggregationVariants::Global(_) => vec![],
+            AggregationVariants::Sampler(_) => vec![],
+            AggregationVariants::DiversifiedSampler(sampler) => vec![sampler.field_name()],
             AggregationVariants::Average(avg) => vec![avg.field_name()],
             AggregationVariants::Count(count) => vec![count.field_name()],
             AggregationVariants::Max(max) => vec![max.field_name()],
@@ -190,36 +273,94 @@ impl AggregationVariants {
             AggregationVariants::Percentiles(per) => vec![per.field_name()],
             AggregationVariants::TopHits(top_hits) => top_hits.field_names(),
             AggregationVariants::Cardinality(per) => vec![per.field_name()],
+            AggregationVariants::WeightedAvg(weighted_avg) => weighted_avg.field_names(),
+            AggregationVariants::MedianAbsoluteDeviation(mad) => vec![mad.field_name()],
+            AggregationVariants::TopMetrics(top_metrics) => top_metrics.field_names(),
+            AggregationVariants::PercentileRanks(ranks) => vec![ranks.field_name()],
+            AggregationVariants::Boxplot(boxplot) => vec![boxplot.field_name()],
+            AggregationVariants::StringStats(string_stats) => vec![string_stats.field_name()],
         }
     }
 
-    pub(crate) fn as_range(&self) -> Option<&RangeAggregation> {
+    /// Returns whether the buckets of a `range`, `date_range` or `ip_range` aggregation are
+    /// keyed.
+    pub(crate) fn is_keyed_range(&self) -> Option<bool> {
         match &self {
-            AggregationVariants::Range(range) => Some(range),
+            AggregationVariants::Range(range) => Some(range.keyed),
+            AggregationVariants::DateRange(range) => Some(range.keyed),
+            AggregationVariants::IpRange(range) => Some(range.keyed),
             _ => None,
         }
     }
-    pub(crate) fn as_histogram(&self) -> crate::Result<Option<HistogramAggregation>> {
+    pub(crate) fn as_histogram(
+        &self,
+    ) -> crate::Result<Option<(HistogramAggregation, Option<DateRounding>)>> {
         match &self {
-            AggregationVariants::Histogram(histogram) => Ok(Some(histogram.clone())),
+            AggregationVariants::Histogram(histogram) => Ok(Some((histogram.clone(), None))),
             AggregationVariants::DateHistogram(histogram) => {
                 Ok(Some(histogram.to_histogram_req()?))
             }
             _ => Ok(None),
         }
     }
+    pub(crate) fn as_auto_date_histogram(&self) -> Option<&AutoDateHistogramAggregationReq> {
+        match &self {
+            AggregationVariants::AutoDateHistogram(histogram) => Some(histogram),
+            _ => None,
+        }
+    }
+    pub(crate) fn as_variable_width_histogram(
+        &self,
+    ) -> Option<&VariableWidthHistogramAggregationReq> {
+        match &self {
+            AggregationVariants::VariableWidthHistogram(histogram) => Some(histogram),
+            _ => None,
+        }
+    }
     pub(crate) fn as_term(&self) -> Option<&TermsAggregation> {
         match &self {
             AggregationVariants::Terms(terms) => Some(terms),
             _ => None,
         }
     }
+    pub(crate) fn as_multi_terms(&self) -> Option<&MultiTermsAggregation> {
+        match &self {
+            AggregationVariants::MultiTerms(multi_terms) => Some(multi_terms),
+            _ => None,
+        }
+    }
+    pub(crate) fn as_filters(&self) -> Option<&FiltersAggregation> {
+        match &self {
+            AggregationVariants::Filters(filters) => Some(filters),
+            _ => None,
+        }
+    }
     pub(crate) fn as_percentile(&self) -> Option<&PercentilesAggregationReq> {
         match &self {
             AggregationVariants::Percentiles(percentile_req) => Some(percentile_req),
             _ => None,
         }
     }
+    pub(crate) fn as_percentile_ranks(&self) -> Option<&PercentileRanksAggregationReq> {
+        match &self {
+            AggregationVariants::PercentileRanks(percentile_ranks_req) => {
+                Some(percentile_ranks_req)
+            }
+            _ => None,
+        }
+    }
+    pub(crate) fn as_top_metrics(&self) -> Option<&TopMetricsAggregationReq> {
+        match &self {
+            AggregationVariants::TopMetrics(top_metrics) => Some(top_metrics),
+            _ => None,
+        }
+    }
+    pub(crate) fn as_string_stats(&self) -> Option<&StringStatsAggregation> {
+        match &self {
+            AggregationVariants::StringStats(string_stats) => Some(string_stats),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
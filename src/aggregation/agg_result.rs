@@ -10,7 +10,8 @@ use serde::{Deserialize, Serialize};
 
 use super::bucket::GetDocCount;
 use super::metric::{
-    ExtendedStats, PercentilesMetricResult, SingleMetricResult, Stats, TopHitsMetricResult,
+    BoxplotMetricResult, ExtendedStats, PercentilesMetricResult, SingleMetricResult, Stats,
+    StringStatsMetricResult, TopHitsMetricResult, TopMetricsMetricResult,
 };
 use super::{AggregationError, Key};
 use crate::TantivyError;
@@ -101,6 +102,18 @@ pub enum MetricResult {
     TopHits(TopHitsMetricResult),
     /// Cardinality metric result
     Cardinality(SingleMetricResult),
+    /// Weighted average metric result.
+    WeightedAvg(SingleMetricResult),
+    /// Median absolute deviation metric result.
+    MedianAbsoluteDeviation(SingleMetricResult),
+    /// Top metrics metric result.
+    TopMetrics(TopMetricsMetricResult),
+    /// Percentile ranks metric result.
+    PercentileRanks(PercentilesMetricResult),
+    /// Boxplot metric result.
+    Boxplot(BoxplotMetricResult),
+    /// String stats metric result.
+    StringStats(StringStatsMetricResult),
 }
 
 impl MetricResult {
@@ -120,6 +133,18 @@ impl MetricResult {
                 AggregationError::InvalidRequest("top_hits can't be used to order".to_string()),
             )),
             MetricResult::Cardinality(card) => Ok(card.value),
+            MetricResult::WeightedAvg(weighted_avg) => Ok(weighted_avg.value),
+            MetricResult::MedianAbsoluteDeviation(mad) => Ok(mad.value),
+            MetricResult::TopMetrics(_) => Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest("top_metrics can't be used to order".to_string()),
+            )),
+            MetricResult::PercentileRanks(_) => Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(
+                    "percentile_ranks can't be used to order".to_string(),
+                ),
+            )),
+            MetricResult::Boxplot(boxplot) => boxplot.get_value(agg_property),
+            MetricResult::StringStats(string_stats) => string_stats.get_value(agg_property),
         }
     }
 }
@@ -134,6 +159,24 @@ pub enum BucketResult {
         /// The range buckets sorted by range.
         buckets: BucketEntries<RangeBucketEntry>,
     },
+    /// This is the auto_date_histogram result. It has to come before `Histogram`, so that it is
+    /// picked when deserializing.
+    AutoDateHistogram {
+        /// The buckets, without holes between the first and last bucket.
+        ///
+        /// See [`AutoDateHistogramAggregationReq`](super::bucket::AutoDateHistogramAggregationReq)
+        buckets: Vec<BucketEntry>,
+        /// The interval that was picked, e.g. `1d`.
+        interval: String,
+    },
+    /// This is the variable_width_histogram result. It has to come before `Histogram`, so that
+    /// it is picked when deserializing.
+    VariableWidthHistogram {
+        /// The buckets, sorted by key.
+        ///
+        /// See [`VariableWidthHistogramAggregationReq`](super::bucket::VariableWidthHistogramAggregationReq)
+        buckets: Vec<VariableWidthHistogramBucketEntry>,
+    },
     /// This is the histogram entry for a bucket, which contains a key, count, and optionally
     /// sub-aggregations.
     Histogram {
@@ -156,7 +199,27 @@ pub enum BucketResult {
         /// The upper bound error for the doc count of each term.
         doc_count_error_upper_bound: Option<u64>,
     },
-    /// This is the filter result - a single bucket with sub-aggregations
+    /// This is the multi terms result
+    MultiTerms {
+        /// The buckets.
+        ///
+        /// See [`MultiTermsAggregation`](super::bucket::MultiTermsAggregation)
+        buckets: Vec<MultiTermsBucketEntry>,
+        /// The number of documents that didn’t make it into to TOP N due to shard_size or size
+        sum_other_doc_count: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        /// The upper bound error for the doc count of each compound key.
+        doc_count_error_upper_bound: Option<u64>,
+    },
+    /// This is the filters result - one bucket per filter
+    Filters {
+        /// The buckets, keyed by filter name for named filters.
+        ///
+        /// See [`FiltersAggregation`](super::bucket::FiltersAggregation)
+        buckets: BucketEntries<FilterBucketResult>,
+    },
+    /// This is the filter result - a single bucket with sub-aggregations.
+    /// Also returned by the global, sampler and diversified_sampler aggregations.
     Filter(FilterBucketResult),
 }
 
@@ -169,11 +232,24 @@ impl BucketResult {
             BucketResult::Histogram { buckets } => {
                 buckets.iter().map(|bucket| bucket.get_bucket_count()).sum()
             }
+            BucketResult::AutoDateHistogram { buckets, .. } => {
+                buckets.iter().map(|bucket| bucket.get_bucket_count()).sum()
+            }
+            BucketResult::VariableWidthHistogram { buckets } => {
+                buckets.iter().map(|bucket| bucket.get_bucket_count()).sum()
+            }
             BucketResult::Terms {
                 buckets,
                 sum_other_doc_count: _,
                 doc_count_error_upper_bound: _,
             } => buckets.iter().map(|bucket| bucket.get_bucket_count()).sum(),
+            BucketResult::MultiTerms { buckets, .. } => {
+                buckets.iter().map(|bucket| bucket.get_bucket_count()).sum()
+            }
+            BucketResult::Filters { buckets } => buckets
+                .iter()
+                .map(|bucket| 1 + bucket.sub_aggregations.get_bucket_count())
+                .sum(),
             BucketResult::Filter(filter_result) => {
                 // Filter doesn't add to bucket count - it's not a user-facing bucket
                 // Only count sub-aggregation buckets
@@ -258,6 +334,88 @@ impl GetDocCount for BucketEntry {
     }
 }
 
+/// This is the entry for a bucket of a variable width histogram, which contains the centroid of
+/// the bucket as key, the value range, count, and optionally sub-aggregations.
+///
+/// # JSON Format
+/// ```json
+/// {
+///   ...
+///     "prices": {
+///       "buckets": [
+///         {
+///           "key": 2.0,
+///           "min": 1.0,
+///           "max": 3.0,
+///           "doc_count": 3
+///         }
+///       ]
+///    }
+///    ...
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VariableWidthHistogramBucketEntry {
+    /// The centroid of the values in the bucket.
+    pub key: f64,
+    /// The smallest value in the bucket.
+    pub min: f64,
+    /// The largest value in the bucket.
+    pub max: f64,
+    /// Number of values in the bucket.
+    pub doc_count: u64,
+    #[serde(flatten)]
+    /// Sub-aggregations in this bucket.
+    pub sub_aggregation: AggregationResults,
+}
+impl VariableWidthHistogramBucketEntry {
+    pub(crate) fn get_bucket_count(&self) -> u64 {
+        1 + self.sub_aggregation.get_bucket_count()
+    }
+}
+
+/// This is the entry for a bucket of a multi terms aggregation, which contains the compound key,
+/// count, and optionally sub-aggregations.
+///
+/// # JSON Format
+/// ```json
+/// {
+///   ...
+///     "genres_and_products": {
+///       "buckets": [
+///         {
+///           "key": ["rock", "Product A"],
+///           "key_as_string": "rock|Product A",
+///           "doc_count": 2
+///         }
+///       ]
+///    }
+///    ...
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MultiTermsBucketEntry {
+    /// The values of the sources, in the order of the request.
+    pub key: Vec<Key>,
+    /// The values of the key joined by `|`.
+    pub key_as_string: String,
+    /// Number of documents in the bucket.
+    pub doc_count: u64,
+    #[serde(flatten)]
+    /// Sub-aggregations in this bucket.
+    pub sub_aggregation: AggregationResults,
+}
+impl MultiTermsBucketEntry {
+    pub(crate) fn get_bucket_count(&self) -> u64 {
+        1 + self.sub_aggregation.get_bucket_count()
+    }
+}
+impl GetDocCount for MultiTermsBucketEntry {
+    fn doc_count(&self) -> u64 {
+        self.doc_count
+    }
+}
+
 /// This is the range entry for a bucket, which contains a key, count, and optionally
 /// sub-aggregations.
 ///
@@ -550,6 +550,58 @@ fn test_aggregation_flushing(
         "cardinality": {
             "field": "score"
         }
+    },
+    "weighted_avg_score":{
+        "weighted_avg": {
+            "value": { "field": "score" },
+            "weight": { "field": "score" }
+        }
+    },
+    "top_metrics_score":{
+        "top_metrics": {
+            "metrics": { "field": "score" },
+            "sort": { "score": "desc" }
+        }
+    },
+    "percentile_ranks_score":{
+        "percentile_ranks": {
+            "field": "score",
+            "values": [100.0]
+        }
+    },
+    "mad_score":{
+        "median_absolute_deviation": {
+            "field": "score"
+        }
+    },
+    "boxplot_score":{
+        "boxplot": {
+            "field": "score"
+        }
+    },
+    "string_stats_string_id":{
+        "string_stats": {
+            "field": "string_id"
+        }
+    },
+    "global_test":{
+        "global": {},
+        "aggs": {
+            "terms": { "terms": { "field": "string_id" } },
+            "avg_score": { "avg": { "field": "score" } }
+        }
+    },
+    "sampler_test":{
+        "sampler": { "shard_size": 100 },
+        "aggs": {
+            "max_score": { "max": { "field": "score" } }
+        }
+    },
+    "variable_width_test":{
+        "variable_width_histogram": { "field": "score", "buckets": 2 },
+        "aggs": {
+            "max_score": { "max": { "field": "score" } }
+        }
     }
     });
 
@@ -610,6 +662,31 @@ fn test_aggregation_flushing(
     );
     assert_eq!(res["bucketsL1"]["buckets"][2]["doc_count"], 80 - 70);
 
+    // sum(x * x) / sum(x) for x in 0..80
+    assert_eq!(res["weighted_avg_score"]["value"], 53.0);
+    assert_eq!(res["top_metrics_score"]["top"][0]["metrics"]["score"], 79.0);
+    assert_eq!(res["percentile_ranks_score"]["values"]["100.0"], 100.0);
+    assert!(res["mad_score"]["value"].as_f64().unwrap() > 0.0);
+    assert_eq!(res["boxplot_score"]["min"], 0.0);
+    assert_eq!(res["boxplot_score"]["max"], 79.0);
+    assert_eq!(res["string_stats_string_id"]["count"], 80);
+    assert_eq!(res["string_stats_string_id"]["avg_length"], 5.0);
+    assert_eq!(res["global_test"]["doc_count"], 80);
+    assert_eq!(res["global_test"]["avg_score"]["value"], 39.5);
+    assert_eq!(res["global_test"]["terms"]["buckets"][0]["doc_count"], 79);
+    assert_eq!(res["sampler_test"]["doc_count"], 80);
+    assert_eq!(res["sampler_test"]["max_score"]["value"], 79.0);
+    let variable_width_buckets = res["variable_width_test"]["buckets"].as_array().unwrap();
+    assert_eq!(variable_width_buckets.len(), 2);
+    assert_eq!(
+        variable_width_buckets
+            .iter()
+            .map(|bucket| bucket["doc_count"].as_u64().unwrap())
+            .sum::<u64>(),
+        80
+    );
+    assert_eq!(variable_width_buckets[1]["max_score"]["value"], 79.0);
+
     assert_eq!(
         res["term_agg_test"],
         json!(
@@ -0,0 +1,512 @@
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use super::{RangeAggregation, RangeAggregationRange};
+use crate::aggregation::time_zone::{civil_from_days, days_from_civil, TimeZone, MS_PER_DAY};
+use crate::aggregation::AggregationError;
+use crate::DateTime;
+
+/// Provide user-defined date ranges to aggregate on.
+///
+/// The date range aggregation works like the [range aggregation](RangeAggregation) on a date
+/// field, but its bounds are dates. A bound is either
+/// - a RFC3339 date, e.g. `"2024-01-01T00:00:00Z"`,
+/// - a date math expression, e.g. `"now-7d/d"` or `"2024-01-01T00:00:00Z||+1M/M"`,
+/// - a number of milliseconds since the unix epoch.
+///
+/// A date math expression starts with an anchor, `now` or a RFC3339 date followed by `||`,
+/// followed by any number of operations:
+/// - `+1h`, `-7d`: adds or subtracts a duration,
+/// - `/d`: rounds down to the start of the unit.
+///
+/// Supported units are `y` (year), `M` (month), `w` (week), `d` (day), `h`/`H` (hour), `m`
+/// (minute) and `s` (second). Calendar units are applied in the local time of `time_zone`, which
+/// defaults to UTC. The value of `now` is taken from
+/// [`AggContextParams::now`](crate::aggregation::AggContextParams::now).
+///
+/// Bounds are resolved with millisecond precision. As with the range aggregation, `from` is
+/// inclusive, `to` is exclusive, and buckets are added to cover the whole value range.
+///
+/// Result type is the same as for the range aggregation: `from` and `to` are timestamps in
+/// nanoseconds, `from_as_string` and `to_as_string` the corresponding RFC3339 dates.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "recent": {
+///         "field": "timestamp",
+///         "time_zone": "Europe/Berlin",
+///         "ranges": [
+///             { "to": "now-7d/d" },
+///             { "key": "last_week", "from": "now-7d/d", "to": "now/d" },
+///             { "from": "now/d" }
+///         ]
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DateRangeAggregation {
+    /// The date field to aggregate on.
+    pub field: String,
+    /// Note that this aggregation includes the from value and excludes the to value for each
+    /// range. Extra buckets will be created until the first to, and last from, if necessary.
+    pub ranges: Vec<DateRangeAggregationRange>,
+    /// Whether to return the buckets as a hash map
+    #[serde(default)]
+    pub keyed: bool,
+    /// The time zone in which date math expressions are rounded, e.g. `Europe/Paris` (requires
+    /// the `time-zones` feature) or `+02:00`. Defaults to UTC.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub time_zone: Option<String>,
+}
+
+/// The range for one date range bucket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DateRangeAggregationRange {
+    /// Custom key for the range bucket
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub key: Option<String>,
+    /// The from date, which is inclusive in the range.
+    /// `None` equals to an open ended interval.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub from: Option<DateRangeBound>,
+    /// The to date, which is not inclusive in the range.
+    /// `None` equals to an open ended interval.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub to: Option<DateRangeBound>,
+}
+
+/// A bound of a date range bucket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DateRangeBound {
+    /// Milliseconds since the unix epoch.
+    EpochMillis(i64),
+    /// A RFC3339 date or a date math expression.
+    Expression(String),
+}
+
+impl DateRangeAggregation {
+    /// Returns true if a bound is relative to `now`, so that the buckets depend on the time the
+    /// request is run at.
+    pub(crate) fn is_relative_to_now(&self) -> bool {
+        self.ranges
+            .iter()
+            .flat_map(|range| [&range.from, &range.to])
+            .any(|bound| {
+                matches!(bound, Some(DateRangeBound::Expression(expr)) if expr.starts_with("now"))
+            })
+    }
+
+    /// Converts the request into a range aggregation, with the bounds resolved to
+    /// nanoseconds.
+    pub(crate) fn to_range_req(&self, now: DateTime) -> crate::Result<RangeAggregation> {
+        let time_zone = match &self.time_zone {
+            Some(time_zone) => TimeZone::parse(time_zone).ok_or_else(|| {
+                AggregationError::InvalidRequest(format!(
+                    "unknown time zone {time_zone:?} in date range"
+                ))
+            })?,
+            None => TimeZone::utc(),
+        };
+        let now_ms = now.into_timestamp_millis();
+        let resolve = |bound: &Option<DateRangeBound>| -> crate::Result<Option<f64>> {
+            let Some(bound) = bound else {
+                return Ok(None);
+            };
+            let ms = match bound {
+                DateRangeBound::EpochMillis(ms) => *ms,
+                DateRangeBound::Expression(expr) => parse_date_math(expr, now_ms, &time_zone)?,
+            };
+            Ok(Some(ms as f64 * 1_000_000.0))
+        };
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|range| {
+                Ok(RangeAggregationRange {
+                    key: range.key.clone(),
+                    from: resolve(&range.from)?,
+                    to: resolve(&range.to)?,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(RangeAggregation {
+            field: self.field.clone(),
+            ranges,
+            keyed: self.keyed,
+        })
+    }
+}
+
+/// A unit of a date math expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DateMathUnit {
+    Year,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl DateMathUnit {
+    fn from_byte(byte: u8) -> Option<DateMathUnit> {
+        let unit = match byte {
+            b'y' => DateMathUnit::Year,
+            b'M' => DateMathUnit::Month,
+            b'w' => DateMathUnit::Week,
+            b'd' => DateMathUnit::Day,
+            b'h' | b'H' => DateMathUnit::Hour,
+            b'm' => DateMathUnit::Minute,
+            b's' => DateMathUnit::Second,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// Returns the duration of the unit if it does not depend on the time zone.
+    fn fixed_duration_ms(self) -> Option<i64> {
+        match self {
+            DateMathUnit::Hour => Some(3_600_000),
+            DateMathUnit::Minute => Some(60_000),
+            DateMathUnit::Second => Some(1_000),
+            _ => None,
+        }
+    }
+}
+
+/// Resolves a RFC3339 date or a date math expression to milliseconds since the unix epoch.
+fn parse_date_math(expr: &str, now_ms: i64, time_zone: &TimeZone) -> crate::Result<i64> {
+    let invalid = |reason: &str| {
+        AggregationError::InvalidRequest(format!(
+            "could not parse date math expression {expr:?}: {reason}"
+        ))
+    };
+    let (mut ms, mut ops) = if let Some(ops) = expr.strip_prefix("now") {
+        (now_ms, ops.as_bytes())
+    } else if let Some((anchor, ops)) = expr.split_once("||") {
+        (parse_rfc3339_ms(anchor)?, ops.as_bytes())
+    } else {
+        return parse_rfc3339_ms(expr);
+    };
+    while let Some((&op, rest)) = ops.split_first() {
+        match op {
+            b'/' => {
+                let (&unit, rest) = rest.split_first().ok_or_else(|| invalid("missing unit"))?;
+                let unit = DateMathUnit::from_byte(unit).ok_or_else(|| invalid("unknown unit"))?;
+                ms = round_down(ms, unit, time_zone);
+                ops = rest;
+            }
+            b'+' | b'-' => {
+                let num_digits = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+                let amount: i64 = std::str::from_utf8(&rest[..num_digits])
+                    .ok()
+                    .filter(|digits| !digits.is_empty())
+                    .and_then(|digits| digits.parse().ok())
+                    .ok_or_else(|| invalid("missing amount"))?;
+                let amount = if op == b'-' { -amount } else { amount };
+                let (&unit, rest) = rest[num_digits..]
+                    .split_first()
+                    .ok_or_else(|| invalid("missing unit"))?;
+                let unit = DateMathUnit::from_byte(unit).ok_or_else(|| invalid("unknown unit"))?;
+                ms = add(ms, amount, unit, time_zone).ok_or_else(|| invalid("out of bounds"))?;
+                ops = rest;
+            }
+            _ => return Err(invalid("expected one of `+`, `-` or `/`").into()),
+        }
+    }
+    Ok(ms)
+}
+
+fn parse_rfc3339_ms(input: &str) -> crate::Result<i64> {
+    let date_time = OffsetDateTime::parse(input, &Rfc3339).map_err(|err| {
+        AggregationError::InvalidRequest(format!("could not parse date {input:?}: {err}"))
+    })?;
+    Ok((date_time.unix_timestamp_nanos().div_euclid(1_000_000)) as i64)
+}
+
+/// Bound of the timestamps date math expressions can resolve to, in milliseconds: about 273,790
+/// years on both sides of the unix epoch.
+///
+/// Within these bounds, the time zone conversions and the calendar computations cannot overflow.
+const MAX_DATE_MATH_MS: i64 = 8_640_000_000_000_000;
+
+/// Adds `amount` times `unit` to a timestamp in milliseconds.
+///
+/// Returns `None` if the result is out of the bounds of date math.
+fn add(utc_ms: i64, amount: i64, unit: DateMathUnit, time_zone: &TimeZone) -> Option<i64> {
+    let within_bounds = |ms: &i64| (-MAX_DATE_MATH_MS..=MAX_DATE_MATH_MS).contains(ms);
+    if let Some(duration_ms) = unit.fixed_duration_ms() {
+        return utc_ms
+            .checked_add(amount.checked_mul(duration_ms)?)
+            .filter(within_bounds);
+    }
+    let local_ms = time_zone.to_local_ms(utc_ms);
+    let new_local_ms = match unit {
+        DateMathUnit::Day | DateMathUnit::Week => {
+            let days = if unit == DateMathUnit::Week {
+                amount.checked_mul(7)?
+            } else {
+                amount
+            };
+            local_ms.checked_add(days.checked_mul(MS_PER_DAY)?)?
+        }
+        _ => {
+            let num_months = if unit == DateMathUnit::Year {
+                amount.checked_mul(12)?
+            } else {
+                amount
+            };
+            let days = local_ms.div_euclid(MS_PER_DAY);
+            let time_of_day_ms = local_ms.rem_euclid(MS_PER_DAY);
+            let (year, month, day) = civil_from_days(days);
+            let month_idx = (year as i64 * 12 + month as i64 - 1).checked_add(num_months)?;
+            let new_year = i32::try_from(month_idx.div_euclid(12)).ok()?;
+            let new_month = month_idx.rem_euclid(12) as u32 + 1;
+            // Like in most calendar libraries, e.g. January 31st + 1 month is the last day of
+            // February.
+            let day = day.min(days_in_month(new_year, new_month)?);
+            days_from_civil(new_year, new_month, day)
+                .checked_mul(MS_PER_DAY)?
+                .checked_add(time_of_day_ms)?
+        }
+    };
+    let new_local_ms = Some(new_local_ms).filter(within_bounds)?;
+    Some(time_zone.to_utc_ms(new_local_ms)).filter(within_bounds)
+}
+
+fn days_in_month(year: i32, month: u32) -> Option<u32> {
+    let next_month_days = if month == 12 {
+        days_from_civil(year.checked_add(1)?, 1, 1)
+    } else {
+        days_from_civil(year, month + 1, 1)
+    };
+    Some((next_month_days - days_from_civil(year, month, 1)) as u32)
+}
+
+/// Rounds a timestamp in milliseconds down to the start of its unit, in local time.
+fn round_down(utc_ms: i64, unit: DateMathUnit, time_zone: &TimeZone) -> i64 {
+    let local_ms = time_zone.to_local_ms(utc_ms);
+    if let Some(duration_ms) = unit.fixed_duration_ms() {
+        return utc_ms - local_ms.rem_euclid(duration_ms);
+    }
+    let days = local_ms.div_euclid(MS_PER_DAY);
+    let start_days = match unit {
+        // 1970-01-01 was a thursday.
+        DateMathUnit::Week => days - (days + 3).rem_euclid(7),
+        DateMathUnit::Month | DateMathUnit::Year => {
+            let (year, month, _) = civil_from_days(days);
+            let month = if unit == DateMathUnit::Year { 1 } else { month };
+            days_from_civil(year, month, 1)
+        }
+        _ => days,
+    };
+    time_zone.to_utc_ms(start_days * MS_PER_DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::bucket::tests::get_test_index_from_docs;
+    use crate::aggregation::tests::exec_request;
+    use crate::aggregation::{AggContextParams, AggregationCollector};
+    use crate::query::AllQuery;
+
+    fn ms(input: &str) -> i64 {
+        parse_rfc3339_ms(input).unwrap()
+    }
+
+    #[test]
+    fn test_parse_date_math() {
+        let now_ms = ms("2024-03-13T15:42:17.123Z");
+        let utc = TimeZone::utc();
+        let parse = |expr: &str| parse_date_math(expr, now_ms, &utc).unwrap();
+        assert_eq!(parse("now"), now_ms);
+        assert_eq!(parse("now/d"), ms("2024-03-13T00:00:00Z"));
+        assert_eq!(parse("now-7d/d"), ms("2024-03-06T00:00:00Z"));
+        assert_eq!(parse("now+1h/h"), ms("2024-03-13T16:00:00Z"));
+        assert_eq!(parse("now/w"), ms("2024-03-11T00:00:00Z"));
+        assert_eq!(parse("now/M"), ms("2024-03-01T00:00:00Z"));
+        assert_eq!(parse("now/y"), ms("2024-01-01T00:00:00Z"));
+        assert_eq!(parse("now-30m/m"), ms("2024-03-13T15:12:00Z"));
+        assert_eq!(parse("now/s"), ms("2024-03-13T15:42:17Z"));
+        assert_eq!(
+            parse("2024-01-31T10:00:00Z||+1M"),
+            ms("2024-02-29T10:00:00Z")
+        );
+        assert_eq!(
+            parse("2024-02-29T00:00:00Z||-1y/M"),
+            ms("2023-02-01T00:00:00Z")
+        );
+        assert_eq!(
+            parse("2024-01-01T00:00:00+01:00"),
+            ms("2023-12-31T23:00:00Z")
+        );
+
+        for invalid in [
+            "now-",
+            "now-7",
+            "now-7x",
+            "now/",
+            "now*2d",
+            "yesterday",
+            "now-d",
+        ] {
+            assert!(
+                parse_date_math(invalid, now_ms, &utc).is_err(),
+                "{invalid} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_date_math_out_of_bounds() {
+        let now_ms = ms("2024-03-13T15:42:17.123Z");
+        let plus_one = TimeZone::parse("+01:00").unwrap();
+        for expr in [
+            "now+999999999999y",
+            "now-999999999999y",
+            "now+9999999999999M",
+            "now+9999999999999d",
+            "now+999999999999999h",
+            "now+200000y+200000y",
+            "now+9223372036854775807s",
+        ] {
+            for time_zone in [TimeZone::utc(), plus_one.clone()] {
+                let err = parse_date_math(expr, now_ms, &time_zone).unwrap_err();
+                assert!(err.to_string().contains("out of bounds"), "{expr}: {err}");
+            }
+        }
+        assert!(parse_date_math("now+200000y", now_ms, &plus_one).is_ok());
+    }
+
+    #[cfg(feature = "time-zones")]
+    #[test]
+    fn test_parse_date_math_time_zone() {
+        let paris = TimeZone::parse("Europe/Paris").unwrap();
+        let now_ms = ms("2024-03-30T23:30:00Z");
+        let parse = |expr: &str| parse_date_math(expr, now_ms, &paris).unwrap();
+        // It is already March 31st in Paris.
+        assert_eq!(parse("now/d"), ms("2024-03-30T23:00:00Z"));
+        // Daylight saving time starts on March 31st, the next day starts an hour earlier.
+        assert_eq!(parse("now/d+1d"), ms("2024-03-31T22:00:00Z"));
+    }
+
+    fn get_test_index() -> crate::Index {
+        let docs = vec![
+            vec![
+                r#"{ "date": "2024-03-01T10:00:00Z" }"#,
+                r#"{ "date": "2024-03-06T00:00:00Z" }"#,
+            ],
+            vec![
+                r#"{ "date": "2024-03-10T12:00:00Z" }"#,
+                r#"{ "date": "2024-03-13T09:00:00Z" }"#,
+                r#"{ "date": "2024-03-13T15:00:00Z" }"#,
+            ],
+        ];
+        get_test_index_from_docs(false, &docs).unwrap()
+    }
+
+    fn keys_and_doc_counts(buckets: &Value) -> Vec<(String, u64)> {
+        buckets
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| {
+                (
+                    bucket["key"].as_str().unwrap().to_string(),
+                    bucket["doc_count"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn date_range_test_rfc3339_bounds() {
+        let index = get_test_index();
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "dates": {
+                "date_range": {
+                    "field": "date",
+                    "ranges": [
+                        { "to": "2024-03-06T00:00:00Z" },
+                        { "from": "2024-03-06T00:00:00Z", "to": 1710288000000i64 },
+                        { "key": "recent", "from": "2024-03-13T00:00:00Z" }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index).unwrap();
+        let buckets = &res["dates"]["buckets"];
+        assert_eq!(
+            keys_and_doc_counts(buckets),
+            vec![
+                ("*-2024-03-06T00:00:00Z".to_string(), 1),
+                ("2024-03-06T00:00:00Z-2024-03-13T00:00:00Z".to_string(), 2),
+                ("recent".to_string(), 2),
+            ]
+        );
+        assert_eq!(buckets[1]["from_as_string"], "2024-03-06T00:00:00Z");
+        assert_eq!(buckets[1]["to_as_string"], "2024-03-13T00:00:00Z");
+        assert_eq!(buckets[1]["from"], 1709683200000000000.0);
+    }
+
+    #[test]
+    fn date_range_test_date_math_keyed() {
+        let index = get_test_index();
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "dates": {
+                "date_range": {
+                    "field": "date",
+                    "keyed": true,
+                    "ranges": [
+                        { "key": "older", "to": "now-7d/d" },
+                        { "key": "last_week", "from": "now-7d/d", "to": "now/d" },
+                        { "key": "today", "from": "now/d" }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+        let now = DateTime::from_timestamp_millis(ms("2024-03-13T15:42:17Z"));
+        let collector = AggregationCollector::from_aggs(
+            agg_req,
+            AggContextParams::new(Default::default(), index.tokenizers().clone()).with_now(now),
+        );
+        let searcher = index.reader().unwrap().searcher();
+        let res = searcher.search(&AllQuery, &collector).unwrap();
+        let res: Value = serde_json::to_value(res).unwrap();
+        let buckets = &res["dates"]["buckets"];
+        assert_eq!(buckets["older"]["doc_count"], 1);
+        assert_eq!(buckets["older"]["to_as_string"], "2024-03-06T00:00:00Z");
+        assert_eq!(buckets["last_week"]["doc_count"], 2);
+        assert_eq!(buckets["today"]["doc_count"], 2);
+        assert_eq!(buckets["today"]["from_as_string"], "2024-03-13T00:00:00Z");
+    }
+
+    #[test]
+    fn date_range_test_invalid_req() {
+        let index = get_test_index();
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "dates": {
+                "date_range": {
+                    "field": "date",
+                    "ranges": [{ "from": "now-1q" }]
+                }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"could not parse date math expression \\\"now-1q\\\": unknown unit\""
+        );
+    }
+}
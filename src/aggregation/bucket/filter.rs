@@ -497,9 +497,9 @@ impl Debug for DocumentQueryEvaluator {
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
-struct DocCount {
-    doc_count: u64,
-    bucket_id: BucketId,
+pub(super) struct DocCount {
+    pub(super) doc_count: u64,
+    pub(super) bucket_id: BucketId,
 }
 
 /// Segment collector for filter aggregation
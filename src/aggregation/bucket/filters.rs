@@ -0,0 +1,466 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use super::filter::DocCount;
+use super::{DocumentQueryEvaluator, FilterAggregation};
+use crate::aggregation::agg_data::{
+    build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
+};
+use crate::aggregation::cached_sub_aggs::{
+    CachedSubAggs, HighCardSubAggCache, LowCardSubAggCache, SubAggCache,
+};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateBucketResult,
+    IntermediateFiltersBucketEntry,
+};
+use crate::aggregation::segment_agg_result::{BucketIdProvider, SegmentAggregationCollector};
+use crate::aggregation::BucketId;
+
+/// The default key of the bucket for documents matching none of the filters.
+const DEFAULT_OTHER_BUCKET_KEY: &str = "_other_";
+
+/// Filters aggregation creates one bucket per filter query, each bucket containing the documents
+/// matching its query.
+///
+/// All filters are evaluated in a single pass over the documents, which is cheaper than
+/// multiple independent [filter aggregations](FilterAggregation). The filter queries support the
+/// same formats as the filter aggregation, either query strings or custom
+/// [`QueryBuilder`](super::QueryBuilder) objects.
+///
+/// Buckets are not disjunct, a document can match multiple filters. With `other_bucket`
+/// enabled, an additional bucket collects the documents matching none of the filters.
+///
+/// # Request JSON Format
+/// Filters can be named, which returns the buckets keyed by name:
+/// ```json
+/// {
+///     "messages": {
+///         "filters": {
+///             "other_bucket_key": "other_messages",
+///             "filters": {
+///                 "errors": "body:error",
+///                 "warnings": "body:warning"
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// or anonymous, which returns the buckets as an array in the order of the request:
+/// ```json
+/// {
+///     "messages": {
+///         "filters": {
+///             "other_bucket": true,
+///             "filters": ["body:error", "body:warning"]
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Response JSON Format
+/// ```json
+/// {
+///     "messages": {
+///         "buckets": {
+///             "errors": { "doc_count": 4 },
+///             "warnings": { "doc_count": 2 },
+///             "other_messages": { "doc_count": 1 }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FiltersAggregation {
+    /// The filter queries, either named or anonymous.
+    pub filters: FiltersBuckets,
+    /// Add a bucket for documents matching none of the filters.
+    ///
+    /// Defaults to false, unless `other_bucket_key` is set.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub other_bucket: Option<bool>,
+    /// The key of the other bucket for named filters. Defaults to `_other_`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub other_bucket_key: Option<String>,
+}
+
+/// The filter queries of a [`FiltersAggregation`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FiltersBuckets {
+    /// Named filters, the result buckets are keyed by name.
+    Keyed(BTreeMap<String, FilterAggregation>),
+    /// Anonymous filters, the result buckets are returned in the order of the filters.
+    Anonymous(Vec<FilterAggregation>),
+}
+
+impl FiltersAggregation {
+    /// Returns true if the buckets are keyed by the name of the filters.
+    pub fn is_keyed(&self) -> bool {
+        matches!(self.filters, FiltersBuckets::Keyed(_))
+    }
+
+    /// Returns true if documents matching none of the filters are collected in an extra bucket.
+    pub fn has_other_bucket(&self) -> bool {
+        self.other_bucket.unwrap_or(self.other_bucket_key.is_some())
+    }
+
+    /// Returns the key of the other bucket.
+    pub fn other_bucket_key(&self) -> &str {
+        self.other_bucket_key
+            .as_deref()
+            .unwrap_or(DEFAULT_OTHER_BUCKET_KEY)
+    }
+
+    /// Returns the filters in bucket order.
+    pub(crate) fn filters(&self) -> Vec<&FilterAggregation> {
+        match &self.filters {
+            FiltersBuckets::Keyed(filters) => filters.values().collect(),
+            FiltersBuckets::Anonymous(filters) => filters.iter().collect(),
+        }
+    }
+
+    /// Returns the keys of the buckets in bucket order, including the other bucket.
+    ///
+    /// Returns `None` for anonymous filters.
+    pub(crate) fn bucket_keys(&self) -> Option<Vec<&str>> {
+        let FiltersBuckets::Keyed(filters) = &self.filters else {
+            return None;
+        };
+        let mut keys: Vec<&str> = filters.keys().map(String::as_str).collect();
+        if self.has_other_bucket() {
+            keys.push(self.other_bucket_key());
+        }
+        Some(keys)
+    }
+
+    /// The number of buckets, including the other bucket.
+    pub(crate) fn num_buckets(&self) -> usize {
+        let num_filters = match &self.filters {
+            FiltersBuckets::Keyed(filters) => filters.len(),
+            FiltersBuckets::Anonymous(filters) => filters.len(),
+        };
+        num_filters + self.has_other_bucket() as usize
+    }
+}
+
+/// Request data for filters aggregation
+/// This struct holds the per-segment data needed to execute a filters aggregation
+pub struct FiltersAggReqData {
+    /// The name of the filters aggregation
+    pub name: String,
+    /// The filters aggregation
+    pub req: FiltersAggregation,
+    /// One document evaluator per filter, in bucket order
+    pub evaluators: Vec<DocumentQueryEvaluator>,
+    /// True if this filters aggregation is at the top level of the aggregation tree (not nested).
+    pub is_top_level: bool,
+}
+
+impl FiltersAggReqData {
+    pub(crate) fn get_memory_consumption(&self) -> usize {
+        self.name.len()
+            + self
+                .evaluators
+                .iter()
+                .map(|evaluator| evaluator.bitset.len() / 8)
+                .sum::<usize>()
+            + std::mem::size_of::<bool>()
+    }
+}
+
+/// Segment collector for filters aggregation
+pub struct SegmentFiltersCollector<C: SubAggCache> {
+    /// Document counts per parent bucket, one entry per filter bucket
+    parent_buckets: Vec<Vec<DocCount>>,
+    /// Sub-aggregation collectors
+    sub_aggregations: Option<CachedSubAggs<C>>,
+    bucket_id_provider: BucketIdProvider,
+    /// Accessor index for this filters aggregation (to access FiltersAggReqData)
+    accessor_idx: usize,
+    num_buckets: usize,
+    has_other_bucket: bool,
+}
+
+impl<C: SubAggCache> SegmentFiltersCollector<C> {
+    fn from_req(req: &mut AggregationsSegmentCtx, node: &AggRefNode) -> crate::Result<Self> {
+        let sub_agg_collector = if !node.children.is_empty() {
+            Some(build_segment_agg_collectors(req, &node.children)?)
+        } else {
+            None
+        };
+        let req_data = req.get_filters_req_data(node.idx_in_req_data);
+        Ok(SegmentFiltersCollector {
+            parent_buckets: Vec::new(),
+            sub_aggregations: sub_agg_collector.map(CachedSubAggs::new),
+            bucket_id_provider: BucketIdProvider::default(),
+            accessor_idx: node.idx_in_req_data,
+            num_buckets: req_data.req.num_buckets(),
+            has_other_bucket: req_data.req.has_other_bucket(),
+        })
+    }
+}
+
+pub(crate) fn build_segment_filters_collector(
+    req: &mut AggregationsSegmentCtx,
+    node: &AggRefNode,
+) -> crate::Result<Box<dyn SegmentAggregationCollector>> {
+    let is_top_level = req.get_filters_req_data(node.idx_in_req_data).is_top_level;
+    if is_top_level {
+        Ok(Box::new(
+            SegmentFiltersCollector::<LowCardSubAggCache>::from_req(req, node)?,
+        ))
+    } else {
+        Ok(Box::new(
+            SegmentFiltersCollector::<HighCardSubAggCache>::from_req(req, node)?,
+        ))
+    }
+}
+
+impl<C: SubAggCache> Debug for SegmentFiltersCollector<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegmentFiltersCollector")
+            .field("buckets", &self.parent_buckets)
+            .field("has_sub_aggs", &self.sub_aggregations.is_some())
+            .field("accessor_idx", &self.accessor_idx)
+            .finish()
+    }
+}
+
+impl<C: SubAggCache> SegmentAggregationCollector for SegmentFiltersCollector<C> {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        self.prepare_max_bucket(parent_bucket_id, agg_data)?;
+        let mut buckets = Vec::with_capacity(self.num_buckets);
+        for bucket in &self.parent_buckets[parent_bucket_id as usize] {
+            let mut sub_aggregation = IntermediateAggregationResults::default();
+            if let Some(sub_aggs) = &mut self.sub_aggregations {
+                sub_aggs
+                    .get_sub_agg_collector()
+                    .add_intermediate_aggregation_result(
+                        agg_data,
+                        &mut sub_aggregation,
+                        bucket.bucket_id,
+                    )?;
+            }
+            buckets.push(IntermediateFiltersBucketEntry {
+                doc_count: bucket.doc_count,
+                sub_aggregation,
+            });
+        }
+
+        let name = agg_data
+            .get_filters_req_data(self.accessor_idx)
+            .name
+            .clone();
+        results.push(
+            name,
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::Filters { buckets }),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        parent_bucket_id: BucketId,
+        docs: &[crate::DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        if docs.is_empty() {
+            return Ok(());
+        }
+        let evaluators = &agg_data.get_filters_req_data(self.accessor_idx).evaluators;
+        let buckets = &mut self.parent_buckets[parent_bucket_id as usize];
+        for &doc in docs {
+            let mut matched_any = false;
+            for (evaluator, bucket) in evaluators.iter().zip(buckets.iter_mut()) {
+                if evaluator.matches_document(doc) {
+                    matched_any = true;
+                    bucket.doc_count += 1;
+                    if let Some(sub_aggs) = &mut self.sub_aggregations {
+                        sub_aggs.push(bucket.bucket_id, doc);
+                    }
+                }
+            }
+            if !matched_any && self.has_other_bucket {
+                let other_bucket = buckets.last_mut().expect("other bucket exists");
+                other_bucket.doc_count += 1;
+                if let Some(sub_aggs) = &mut self.sub_aggregations {
+                    sub_aggs.push(other_bucket.bucket_id, doc);
+                }
+            }
+        }
+
+        if let Some(sub_aggs) = &mut self.sub_aggregations {
+            sub_aggs.check_flush_local(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, agg_data: &mut AggregationsSegmentCtx) -> crate::Result<()> {
+        if let Some(ref mut sub_aggs) = self.sub_aggregations {
+            sub_aggs.flush(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        max_bucket: BucketId,
+        _agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        while self.parent_buckets.len() <= max_bucket as usize {
+            let buckets = (0..self.num_buckets)
+                .map(|_| DocCount {
+                    doc_count: 0,
+                    bucket_id: self.bucket_id_provider.next_bucket_id(),
+                })
+                .collect();
+            self.parent_buckets.push(buckets);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, exec_request_with_query};
+    use crate::schema::{Schema, FAST, INDEXED, TEXT};
+    use crate::{Index, IndexWriter};
+
+    fn create_test_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let body = schema_builder.add_text_field("body", TEXT | FAST);
+        let severity = schema_builder.add_u64_field("severity", FAST | INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut writer: IndexWriter = index.writer_for_tests()?;
+        writer.add_document(doc!(body => "disk error", severity => 5u64))?;
+        writer.add_document(doc!(body => "network error", severity => 4u64))?;
+        writer.commit()?;
+        writer.add_document(doc!(body => "disk warning error", severity => 3u64))?;
+        writer.add_document(doc!(body => "low memory warning", severity => 2u64))?;
+        writer.add_document(doc!(body => "all good", severity => 1u64))?;
+        writer.commit()?;
+        Ok(index)
+    }
+
+    #[test]
+    fn test_filters_keyed_with_other_bucket() -> crate::Result<()> {
+        let index = create_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "messages": {
+                "filters": {
+                    "other_bucket_key": "other_messages",
+                    "filters": {
+                        "errors": "body:error",
+                        "warnings": "body:warning"
+                    }
+                },
+                "aggs": { "avg_severity": { "avg": { "field": "severity" } } }
+            }
+        }))?;
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["messages"],
+            json!({
+                "buckets": {
+                    "errors": { "doc_count": 3, "avg_severity": { "value": 4.0 } },
+                    "warnings": { "doc_count": 2, "avg_severity": { "value": 2.5 } },
+                    "other_messages": { "doc_count": 1, "avg_severity": { "value": 1.0 } }
+                }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filters_anonymous() -> crate::Result<()> {
+        let index = create_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "messages": {
+                "filters": {
+                    "other_bucket": true,
+                    "filters": ["body:warning", "body:error", "body:unknown"]
+                }
+            }
+        }))?;
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["messages"],
+            json!({
+                "buckets": [
+                    { "doc_count": 2 },
+                    { "doc_count": 3 },
+                    { "doc_count": 0 },
+                    { "doc_count": 1 }
+                ]
+            })
+        );
+
+        // Without other bucket, and no matching docs for the query
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "messages": {
+                "filters": { "filters": ["body:warning", "body:error"] }
+            }
+        }))?;
+        let res = exec_request_with_query(agg_req, &index, Some(("body", "good")))?;
+        assert_eq!(
+            res["messages"],
+            json!({ "buckets": [{ "doc_count": 0 }, { "doc_count": 0 }] })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filters_nested_in_range() -> crate::Result<()> {
+        let index = create_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "by_severity": {
+                "range": {
+                    "field": "severity",
+                    "ranges": [{ "to": 3.0 }, { "from": 3.0 }]
+                },
+                "aggs": {
+                    "messages": {
+                        "filters": {
+                            "other_bucket": true,
+                            "filters": { "errors": "body:error" }
+                        }
+                    }
+                }
+            }
+        }))?;
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["by_severity"]["buckets"];
+        assert_eq!(
+            buckets[0]["messages"]["buckets"],
+            json!({ "errors": { "doc_count": 0 }, "_other_": { "doc_count": 2 } })
+        );
+        assert_eq!(
+            buckets[1]["messages"]["buckets"],
+            json!({ "errors": { "doc_count": 3 }, "_other_": { "doc_count": 0 } })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filters_invalid_query() -> crate::Result<()> {
+        let index = create_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "messages": {
+                "filters": { "filters": { "errors": "unknown_field:error" } }
+            }
+        }))?;
+        assert!(exec_request(agg_req, &index).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::agg_data::{
+    build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
+};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateBucketResult,
+};
+use crate::aggregation::segment_agg_result::SegmentAggregationCollector;
+use crate::aggregation::BucketId;
+use crate::docset::COLLECT_BLOCK_BUFFER_LEN;
+use crate::fastfield::AliveBitSet;
+use crate::DocId;
+
+/// The global aggregation defines a single bucket containing all the documents of the index,
+/// regardless of the search query. Its sub-aggregations are therefore computed over the whole
+/// index, which allows to compare them with aggregations over the matching documents in the
+/// same request.
+///
+/// The global aggregation has to be a top-level aggregation.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "all_products": {
+///         "global": {},
+///         "aggs": {
+///             "avg_price": { "avg": { "field": "price" } }
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Result
+/// The global aggregation returns a single bucket with:
+/// - `doc_count`: Number of (non-deleted) documents in the index
+/// - Sub-aggregation results computed on all documents
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GlobalAggregation {}
+
+/// Request data for the global aggregation.
+pub struct GlobalAggReqData {
+    /// The name of the global aggregation.
+    pub name: String,
+    /// The max doc of the segment.
+    pub max_doc: DocId,
+    /// The deleted documents of the segment.
+    pub alive_bitset: Option<AliveBitSet>,
+}
+
+impl GlobalAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub(crate) fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// Segment collector for the global aggregation.
+///
+/// The documents passed to `collect` are ignored. Instead all alive documents of the segment are
+/// passed to the sub-aggregations when the collector is flushed.
+#[derive(Debug)]
+pub(crate) struct SegmentGlobalCollector {
+    doc_count: u64,
+    sub_aggregations: Option<Box<dyn SegmentAggregationCollector>>,
+    collected: bool,
+    accessor_idx: usize,
+}
+
+impl SegmentGlobalCollector {
+    pub(crate) fn from_req_and_validate(
+        req: &mut AggregationsSegmentCtx,
+        node: &AggRefNode,
+    ) -> crate::Result<Self> {
+        let sub_aggregations = if node.children.is_empty() {
+            None
+        } else {
+            Some(build_segment_agg_collectors(req, &node.children)?)
+        };
+        Ok(Self {
+            doc_count: 0,
+            sub_aggregations,
+            collected: false,
+            accessor_idx: node.idx_in_req_data,
+        })
+    }
+}
+
+impl SegmentAggregationCollector for SegmentGlobalCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        _parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        let mut sub_aggregations = IntermediateAggregationResults::default();
+        if let Some(sub_aggs) = &mut self.sub_aggregations {
+            sub_aggs.add_intermediate_aggregation_result(agg_data, &mut sub_aggregations, 0)?;
+        }
+        let name = agg_data.get_global_req_data(self.accessor_idx).name.clone();
+        results.push(
+            name,
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::Filter {
+                doc_count: self.doc_count,
+                sub_aggregations,
+            }),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        _parent_bucket_id: BucketId,
+        _docs: &[DocId],
+        _agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn flush(&mut self, agg_data: &mut AggregationsSegmentCtx) -> crate::Result<()> {
+        if self.collected {
+            return Ok(());
+        }
+        self.collected = true;
+        let req_data = agg_data.get_global_req_data(self.accessor_idx);
+        let max_doc = req_data.max_doc;
+        let alive_bitset = req_data.alive_bitset.clone();
+        let mut docs = Vec::with_capacity(COLLECT_BLOCK_BUFFER_LEN);
+        let mut doc_start = 0;
+        while doc_start < max_doc {
+            let doc_end = max_doc.min(doc_start + COLLECT_BLOCK_BUFFER_LEN as DocId);
+            docs.clear();
+            docs.extend((doc_start..doc_end).filter(|&doc| {
+                alive_bitset
+                    .as_ref()
+                    .map(|alive_bitset| alive_bitset.is_alive(doc))
+                    .unwrap_or(true)
+            }));
+            self.doc_count += docs.len() as u64;
+            if let Some(sub_aggs) = &mut self.sub_aggregations {
+                sub_aggs.collect(0, &docs, agg_data)?;
+            }
+            doc_start = doc_end;
+        }
+        if let Some(sub_aggs) = &mut self.sub_aggregations {
+            sub_aggs.flush(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        _max_bucket: BucketId,
+        agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        if let Some(sub_aggs) = &mut self.sub_aggregations {
+            sub_aggs.prepare_max_bucket(0, agg_data)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, exec_request_with_query};
+    use crate::aggregation::AggregationCollector;
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, FAST, STRING};
+    use crate::{Index, IndexWriter, Term};
+
+    fn get_global_test_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let category = schema_builder.add_text_field("category", STRING);
+        let price = schema_builder.add_u64_field("price", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(category => "book", price => 10u64))?;
+        index_writer.add_document(doc!(category => "book", price => 20u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(category => "car", price => 1000u64))?;
+        index_writer.add_document(doc!(category => "car", price => 3000u64))?;
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    #[test]
+    fn test_global_ignores_query() -> crate::Result<()> {
+        let index = get_global_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "all": {
+                "global": {},
+                "aggs": { "avg_price": { "avg": { "field": "price" } } }
+            },
+            "avg_price": { "avg": { "field": "price" } }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query(agg_req, &index, Some(("category", "book")))?;
+        assert_eq!(res["avg_price"]["value"], 15.0);
+        assert_eq!(
+            res["all"],
+            json!({ "doc_count": 4, "avg_price": { "value": 1007.5 } })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_without_matches_and_deletes() -> crate::Result<()> {
+        let index = get_global_test_index()?;
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        let category = index.schema().get_field("category").unwrap();
+        index_writer.delete_term(Term::from_field_text(category, "car"));
+        index_writer.commit()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "all": {
+                "global": {},
+                "aggs": { "max_price": { "max": { "field": "price" } } }
+            }
+        }))
+        .unwrap();
+        let collector = AggregationCollector::from_aggs(agg_req, Default::default());
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(category, "bike"),
+            IndexRecordOption::Basic,
+        );
+        let res = serde_json::to_value(searcher.search(&query, &collector)?)?;
+        assert_eq!(
+            res["all"],
+            json!({ "doc_count": 2, "max_price": { "value": 20.0 } })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_has_to_be_top_level() -> crate::Result<()> {
+        let index = get_global_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "prices": {
+                "terms": { "field": "price" },
+                "aggs": { "all": { "global": {} } }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"global aggregation `all` has to be a top-level aggregation\""
+        );
+        Ok(())
+    }
+}
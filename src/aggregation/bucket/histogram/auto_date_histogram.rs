@@ -0,0 +1,509 @@
+use serde::{Deserialize, Serialize};
+
+use super::date_histogram::{parse_time_zone, CalendarUnit, RoundingInterval};
+use super::histogram::{intermediate_histogram_buckets_to_final_buckets, millis_to_nanos};
+use super::{DateRounding, HistogramAggregation};
+use crate::aggregation::agg_req::Aggregations;
+use crate::aggregation::agg_result::BucketResult;
+use crate::aggregation::intermediate_agg_result::IntermediateHistogramBucketEntry;
+use crate::aggregation::time_zone::TimeZone;
+use crate::aggregation::*;
+use crate::TantivyError;
+
+fn default_buckets() -> u32 {
+    10
+}
+
+/// The roundings an auto_date_histogram picks from, from the finest to the coarsest, together
+/// with the interval reported in the result.
+const ROUNDINGS: [(RoundingInterval, &str); 16] = [
+    (RoundingInterval::Fixed(1_000), "1s"),
+    (RoundingInterval::Fixed(5_000), "5s"),
+    (RoundingInterval::Fixed(10_000), "10s"),
+    (RoundingInterval::Fixed(30_000), "30s"),
+    (RoundingInterval::Calendar(CalendarUnit::Minute), "1m"),
+    (RoundingInterval::Fixed(5 * 60_000), "5m"),
+    (RoundingInterval::Fixed(10 * 60_000), "10m"),
+    (RoundingInterval::Fixed(30 * 60_000), "30m"),
+    (RoundingInterval::Calendar(CalendarUnit::Hour), "1h"),
+    (RoundingInterval::Fixed(3 * 3_600_000), "3h"),
+    (RoundingInterval::Fixed(12 * 3_600_000), "12h"),
+    (RoundingInterval::Calendar(CalendarUnit::Day), "1d"),
+    (RoundingInterval::Calendar(CalendarUnit::Week), "7d"),
+    (RoundingInterval::Calendar(CalendarUnit::Month), "1M"),
+    (RoundingInterval::Calendar(CalendarUnit::Quarter), "3M"),
+    (RoundingInterval::Calendar(CalendarUnit::Year), "1y"),
+];
+
+/// AutoDateHistogramAggregation is a `date_histogram` that picks its interval itself, so that
+/// the number of returned buckets is at most the requested number of `buckets`.
+///
+/// The interval is chosen among `1s`, `5s`, `10s`, `30s`, `1m`, `5m`, `10m`, `30m`, `1h`, `3h`,
+/// `12h`, `1d`, `7d`, `1M`, `3M` and `1y`. Intervals of a minute and more are calendar-aware,
+/// like with the `calendar_interval` of the
+/// [`DateHistogramAggregationReq`](super::DateHistogramAggregationReq).
+///
+/// Each segment picks the finest interval that fits the range of values of the field in the
+/// segment. When results are merged, the buckets are rounded again to the coarser interval of
+/// both sides, and to coarser intervals until the buckets fit into `buckets`.
+///
+/// # Limitations/Compatibility
+/// Yearly buckets are the coarsest rounding, so data spanning more years than `buckets` returns
+/// more buckets than requested. The `format` parameter is not supported.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "sales_over_time": {
+///         "auto_date_histogram": {
+///             "field": "date",
+///             "buckets": 20,
+///             "minimum_interval": "hour",
+///             "time_zone": "Europe/Paris"
+///         }
+///     }
+/// }
+/// ```
+///
+/// Response
+/// See [`BucketResult::AutoDateHistogram`](crate::aggregation::agg_result::BucketResult)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutoDateHistogramAggregationReq {
+    /// The field to aggregate on.
+    pub field: String,
+    /// The target number of buckets. Defaults to 10.
+    #[serde(default = "default_buckets")]
+    pub buckets: u32,
+    /// The finest interval to use. One of `second`, `minute`, `hour`, `day`, `month` or `year`.
+    /// Defaults to `second`.
+    pub minimum_interval: Option<String>,
+    /// The time zone used to round dates into buckets. Defaults to UTC.
+    ///
+    /// Accepts the same values as the `time_zone` of the
+    /// [`DateHistogramAggregationReq`](super::DateHistogramAggregationReq).
+    pub time_zone: Option<String>,
+}
+
+impl AutoDateHistogramAggregationReq {
+    /// Creates a new [`AutoDateHistogramAggregationReq`] instance from a field name and a target
+    /// number of buckets.
+    pub fn from_field_name(field_name: String, buckets: u32) -> Self {
+        AutoDateHistogramAggregationReq {
+            field: field_name,
+            buckets,
+            minimum_interval: None,
+            time_zone: None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.buckets == 0 {
+            return Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(
+                    "buckets in auto_date_histogram must be greater than 0".to_string(),
+                ),
+            ));
+        }
+        self.min_rounding_idx()?;
+        self.time_zone()?;
+        Ok(())
+    }
+
+    fn time_zone(&self) -> crate::Result<TimeZone> {
+        Ok(self
+            .time_zone
+            .as_ref()
+            .map(|time_zone| parse_time_zone(time_zone))
+            .transpose()?
+            .unwrap_or_else(TimeZone::utc))
+    }
+
+    fn min_rounding_idx(&self) -> crate::Result<usize> {
+        let Some(minimum_interval) = self.minimum_interval.as_deref() else {
+            return Ok(0);
+        };
+        let interval = match minimum_interval {
+            "second" => "1s",
+            "minute" => "1m",
+            "hour" => "1h",
+            "day" => "1d",
+            "month" => "1M",
+            "year" => "1y",
+            _ => {
+                return Err(TantivyError::AggregationError(
+                    AggregationError::InvalidRequest(format!(
+                        "minimum_interval {minimum_interval:?} in auto_date_histogram is invalid, \
+                         expected one of second, minute, hour, day, month, year"
+                    )),
+                ))
+            }
+        };
+        Ok(ROUNDINGS
+            .iter()
+            .position(|(_, name)| *name == interval)
+            .expect("minimum interval has to be a rounding"))
+    }
+
+    /// Picks the finest rounding for which the values in `[min_ms, max_ms]` fit into the
+    /// target number of buckets.
+    pub(crate) fn rounding_idx_for_range(&self, min_ms: i64, max_ms: i64) -> crate::Result<usize> {
+        let min_rounding_idx = self.min_rounding_idx()?;
+        let range_ms = max_ms.saturating_sub(min_ms).max(0);
+        let rounding_idx = (min_rounding_idx..ROUNDINGS.len())
+            .find(|&idx| {
+                let interval_ms = ROUNDINGS[idx].0.approximate_ms();
+                range_ms / interval_ms < self.buckets as i64
+            })
+            .unwrap_or(ROUNDINGS.len() - 1);
+        Ok(rounding_idx)
+    }
+
+    /// Converts the request into a date histogram request with the rounding at `rounding_idx`.
+    pub(crate) fn to_histogram_req(
+        &self,
+        rounding_idx: usize,
+    ) -> crate::Result<(HistogramAggregation, DateRounding)> {
+        let interval = ROUNDINGS[rounding_idx].0;
+        let histogram_req = HistogramAggregation {
+            field: self.field.to_string(),
+            interval: interval.approximate_ms() as f64,
+            ..Default::default()
+        };
+        let date_rounding = DateRounding::new(interval, self.time_zone()?);
+        Ok((histogram_req, date_rounding))
+    }
+}
+
+/// Contains the auto_date_histogram request and the rounding picked for a segment. The segment
+/// is collected like a date histogram with that rounding.
+pub struct AutoDateHistogramReqData {
+    /// The auto_date_histogram request.
+    pub req: AutoDateHistogramAggregationReq,
+    /// The index of the rounding used in the segment.
+    pub rounding_idx: usize,
+}
+
+/// Intermediate result of the auto_date_histogram aggregation.
+///
+/// All bucket keys are rounded with the same rounding. Merging two results rounds the buckets
+/// again to the coarser rounding of both.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateAutoDateHistogram {
+    /// The index of the rounding of the bucket keys.
+    rounding_idx: usize,
+    /// The target number of buckets.
+    target_buckets: u32,
+    /// The time zone the dates are rounded in.
+    time_zone: Option<String>,
+    /// The buckets, sorted by key. The keys are in nanoseconds.
+    buckets: Vec<IntermediateHistogramBucketEntry>,
+}
+
+impl IntermediateAutoDateHistogram {
+    /// Creates the result of a segment, reducing the buckets to the target number of buckets.
+    pub(crate) fn new(
+        req_data: &AutoDateHistogramReqData,
+        buckets: Vec<IntermediateHistogramBucketEntry>,
+    ) -> crate::Result<Self> {
+        let mut result = IntermediateAutoDateHistogram {
+            rounding_idx: req_data.rounding_idx,
+            target_buckets: req_data.req.buckets,
+            time_zone: req_data.req.time_zone.clone(),
+            buckets,
+        };
+        result.reduce(req_data.rounding_idx)?;
+        Ok(result)
+    }
+
+    /// Returns an empty result for the request.
+    pub(crate) fn empty_from_req(req: &AutoDateHistogramAggregationReq) -> Self {
+        IntermediateAutoDateHistogram {
+            rounding_idx: req.min_rounding_idx().unwrap_or(0),
+            target_buckets: req.buckets,
+            time_zone: req.time_zone.clone(),
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Merges the other intermediate result into self.
+    pub(crate) fn merge_fruits(
+        &mut self,
+        other: IntermediateAutoDateHistogram,
+    ) -> crate::Result<()> {
+        let rounding_idx = self.rounding_idx.max(other.rounding_idx);
+        self.buckets.extend(other.buckets);
+        self.reduce(rounding_idx)
+    }
+
+    fn rounding(&self, rounding_idx: usize) -> crate::Result<DateRounding> {
+        let time_zone = self
+            .time_zone
+            .as_ref()
+            .map(|time_zone| parse_time_zone(time_zone))
+            .transpose()?
+            .unwrap_or_else(TimeZone::utc);
+        Ok(DateRounding::new(ROUNDINGS[rounding_idx].0, time_zone))
+    }
+
+    /// Rounds the buckets with the rounding at `rounding_idx`, or a coarser one if the buckets
+    /// don't fit into the target number of buckets.
+    fn reduce(&mut self, mut rounding_idx: usize) -> crate::Result<()> {
+        loop {
+            self.round_buckets(&self.rounding(collect_rounding_idx(rounding_idx))?)?;
+            let rounding = self.rounding(rounding_idx)?;
+            let num_buckets = match (self.buckets.first(), self.buckets.last()) {
+                (Some(first), Some(last)) => num_buckets_between(
+                    &rounding,
+                    rounding.round(key_to_millis(first.key)),
+                    rounding.round(key_to_millis(last.key)),
+                    self.target_buckets as usize,
+                ),
+                _ => 0,
+            };
+            if num_buckets <= self.target_buckets as usize || rounding_idx + 1 == ROUNDINGS.len() {
+                break;
+            }
+            rounding_idx += 1;
+        }
+        self.rounding_idx = rounding_idx;
+        Ok(())
+    }
+
+    /// Rounds the keys of the buckets and merges the buckets with the same key.
+    fn round_buckets(&mut self, rounding: &DateRounding) -> crate::Result<()> {
+        for bucket in self.buckets.iter_mut() {
+            bucket.key = millis_to_nanos(rounding.round(key_to_millis(bucket.key)));
+        }
+        self.buckets
+            .sort_by(|left, right| left.key.total_cmp(&right.key));
+        let mut merged: Vec<IntermediateHistogramBucketEntry> =
+            Vec::with_capacity(self.buckets.len());
+        for bucket in self.buckets.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.key == bucket.key => {
+                    last.doc_count += bucket.doc_count;
+                    last.sub_aggregation.merge_fruits(bucket.sub_aggregation)?;
+                }
+                _ => merged.push(bucket),
+            }
+        }
+        self.buckets = merged;
+        Ok(())
+    }
+
+    pub(crate) fn into_final_result(
+        self,
+        req: &AutoDateHistogramAggregationReq,
+        sub_aggregation: &Aggregations,
+        limits: &mut AggregationLimitsGuard,
+    ) -> crate::Result<BucketResult> {
+        // Without segments, the request has not been validated yet.
+        req.validate()?;
+        let mut result = self;
+        result.round_buckets(&result.rounding(result.rounding_idx)?)?;
+        let (histogram_req, date_rounding) = req.to_histogram_req(result.rounding_idx)?;
+        let buckets = intermediate_histogram_buckets_to_final_buckets(
+            result.buckets,
+            true,
+            &histogram_req,
+            Some(&date_rounding),
+            sub_aggregation,
+            limits,
+        )?;
+        Ok(BucketResult::AutoDateHistogram {
+            buckets,
+            interval: ROUNDINGS[result.rounding_idx].1.to_string(),
+        })
+    }
+}
+
+/// Returns the rounding the buckets are collected and merged with, when the result is rounded with
+/// the rounding at `rounding_idx`.
+///
+/// Weeks don't add up to months, so buckets of a week could not be rounded to a coarser rounding
+/// later on. They are kept at a day until the final result is computed.
+pub(crate) fn collect_rounding_idx(rounding_idx: usize) -> usize {
+    match ROUNDINGS[rounding_idx].0 {
+        RoundingInterval::Calendar(CalendarUnit::Week) => rounding_idx - 1,
+        _ => rounding_idx,
+    }
+}
+
+/// Bucket keys are the start of a bucket and therefore whole milliseconds.
+fn key_to_millis(key: f64) -> i64 {
+    (key / 1_000_000.0).round() as i64
+}
+
+/// Counts the buckets from the bucket starting at `first_ms` to the bucket starting at
+/// `last_ms`. Stops counting after `limit + 1` buckets.
+fn num_buckets_between(
+    rounding: &DateRounding,
+    first_ms: i64,
+    last_ms: i64,
+    limit: usize,
+) -> usize {
+    let mut num_buckets = 1;
+    let mut bucket_ms = first_ms;
+    while bucket_ms < last_ms && num_buckets <= limit {
+        bucket_ms = rounding.next(bucket_ms);
+        num_buckets += 1;
+    }
+    num_buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    use super::super::date_histogram::tests::get_test_index_from_docs;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::exec_request;
+    use crate::aggregation::DistributedAggregationCollector;
+    use crate::query::AllQuery;
+    use crate::Index;
+
+    fn get_test_index(merge_segments: bool) -> crate::Result<Index> {
+        let docs = vec![
+            vec![
+                r#"{ "date": "2015-01-01T12:10:30Z", "text": "a" }"#,
+                r#"{ "date": "2015-01-01T12:40:00Z", "text": "b" }"#,
+                r#"{ "date": "2015-01-02T10:00:00Z", "text": "a" }"#,
+            ],
+            vec![
+                r#"{ "date": "2015-01-05T00:00:00Z", "text": "a" }"#,
+                r#"{ "date": "2015-01-05T08:00:00Z", "text": "c" }"#,
+            ],
+            vec![r#"{ "date": "2015-01-31T23:00:00Z", "text": "a" }"#],
+        ];
+        get_test_index_from_docs(merge_segments, &docs)
+    }
+
+    fn keys_and_doc_counts(res: &Value) -> Vec<(String, u64)> {
+        res["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| {
+                (
+                    bucket["key_as_string"].as_str().unwrap().to_string(),
+                    bucket["doc_count"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn exec_auto_date_histogram(index: &Index, req: Value) -> crate::Result<Value> {
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histo": { "auto_date_histogram": req }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, index)?;
+        Ok(res["histo"].clone())
+    }
+
+    #[test]
+    fn auto_date_histogram_picks_interval() -> crate::Result<()> {
+        for merge_segments in [false, true] {
+            let index = get_test_index(merge_segments)?;
+            let res = exec_auto_date_histogram(&index, json!({ "field": "date", "buckets": 5 }))?;
+            assert_eq!(res["interval"], "7d");
+            assert_eq!(
+                keys_and_doc_counts(&res),
+                vec![
+                    ("2014-12-29T00:00:00Z".to_string(), 3),
+                    ("2015-01-05T00:00:00Z".to_string(), 2),
+                    ("2015-01-12T00:00:00Z".to_string(), 0),
+                    ("2015-01-19T00:00:00Z".to_string(), 0),
+                    ("2015-01-26T00:00:00Z".to_string(), 1),
+                ]
+            );
+
+            let res = exec_auto_date_histogram(&index, json!({ "field": "date", "buckets": 2 }))?;
+            assert_eq!(res["interval"], "1M");
+            assert_eq!(
+                keys_and_doc_counts(&res),
+                vec![("2015-01-01T00:00:00Z".to_string(), 6)]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn auto_date_histogram_fine_interval() -> crate::Result<()> {
+        let docs = vec![
+            vec![r#"{ "date": "2015-01-01T12:10:30Z" }"#],
+            vec![r#"{ "date": "2015-01-01T12:10:40Z" }"#],
+            vec![r#"{ "date": "2015-01-01T12:10:59Z" }"#],
+        ];
+        let index = get_test_index_from_docs(false, &docs)?;
+        let res = exec_auto_date_histogram(&index, json!({ "field": "date", "buckets": 3 }))?;
+        assert_eq!(res["interval"], "10s");
+        assert_eq!(
+            keys_and_doc_counts(&res),
+            vec![
+                ("2015-01-01T12:10:30Z".to_string(), 1),
+                ("2015-01-01T12:10:40Z".to_string(), 1),
+                ("2015-01-01T12:10:50Z".to_string(), 1),
+            ]
+        );
+
+        let res = exec_auto_date_histogram(
+            &index,
+            json!({ "field": "date", "minimum_interval": "hour", "time_zone": "+05:30" }),
+        )?;
+        assert_eq!(res["interval"], "1h");
+        assert_eq!(
+            keys_and_doc_counts(&res),
+            vec![("2015-01-01T17:00:00+05:30".to_string(), 3)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn auto_date_histogram_sub_aggregation() -> crate::Result<()> {
+        let index = get_test_index(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histo": {
+                "auto_date_histogram": { "field": "date", "buckets": 2 },
+                "aggs": { "texts": { "terms": { "field": "text" } } }
+            }
+        }))
+        .unwrap();
+
+        let collector =
+            DistributedAggregationCollector::from_aggs(agg_req.clone(), Default::default());
+        let searcher = index.reader()?.searcher();
+        let intermediate = searcher.search(&AllQuery, &collector)?;
+        let bytes = postcard::to_allocvec(&intermediate).unwrap();
+        let intermediate: crate::aggregation::intermediate_agg_result::IntermediateAggregationResults =
+            postcard::from_bytes(&bytes).unwrap();
+        let res = intermediate.into_final_result(agg_req, Default::default())?;
+        let res = serde_json::to_value(res)?;
+        assert_eq!(res["histo"]["interval"], "1M");
+        assert_eq!(
+            res["histo"]["buckets"][0]["texts"]["buckets"][0],
+            json!({ "key": "a", "doc_count": 4 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn auto_date_histogram_empty_and_invalid() -> crate::Result<()> {
+        let index = get_test_index_from_docs(false, &[])?;
+        let res = exec_auto_date_histogram(&index, json!({ "field": "date" }))?;
+        assert_eq!(res, json!({ "buckets": [], "interval": "1s" }));
+
+        let err = exec_auto_date_histogram(
+            &index,
+            json!({ "field": "date", "minimum_interval": "week" }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"minimum_interval \\\"week\\\" in auto_date_histogram is invalid, \
+             expected one of second, minute, hour, day, month, year\""
+        );
+        Ok(())
+    }
+}
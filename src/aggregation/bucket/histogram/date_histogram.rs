@@ -1,28 +1,30 @@
 use serde::{Deserialize, Serialize};
 
 use super::{HistogramAggregation, HistogramBounds};
+use crate::aggregation::time_zone::{civil_from_days, days_from_civil, TimeZone, MS_PER_DAY};
 use crate::aggregation::*;
 
 /// DateHistogramAggregation is similar to `HistogramAggregation`, but it can only be used with date
 /// type.
 ///
-/// Currently only **fixed time** intervals are supported. Calendar-aware time intervals are not
-/// supported.
+/// Buckets are either defined by a **fixed time** interval (`fixed_interval`), or by a
+/// **calendar-aware** interval (`calendar_interval`), such as a month or a quarter, whose duration
+/// varies.
 ///
-/// Like the histogram, values are rounded down into the closest bucket.
-///
-/// For this calculation all fastfield values are converted to f64.
+/// Like the histogram, values are rounded down into the closest bucket. With a `time_zone`, the
+/// rounding happens in local time, taking daylight saving time transitions into account.
 ///
 /// # Limitations/Compatibility
-/// Only fixed time intervals are supported.
+/// The `interval` and `format` parameters are not supported.
 ///
 /// # JSON Format
 /// ```json
 /// {
-///     "prices": {
+///     "sales_per_month": {
 ///         "date_histogram": {
-///             "field": "price",
-///             "fixed_interval": "30d"
+///             "field": "date",
+///             "calendar_interval": "month",
+///             "time_zone": "Europe/Paris"
 ///         }
 ///     }
 /// }
@@ -35,8 +37,23 @@ pub struct DateHistogramAggregationReq {
     #[doc(hidden)]
     /// Only for validation
     pub interval: Option<String>,
-    #[doc(hidden)]
-    /// Only for validation
+    /// The calendar-aware interval to chunk your data range.
+    ///
+    /// Calendar-aware intervals understand that daylight savings changes the length of specific
+    /// days, months have different amounts of days, and leap seconds can be tacked onto a
+    /// particular year. Only a single unit is supported (e.g. `1M`, but not `2M`).
+    ///
+    /// The accepted units are:
+    /// * `minute`, `1m`: All minutes begin at 00 seconds.
+    /// * `hour`, `1h`: All hours begin at 00 minutes and 00 seconds.
+    /// * `day`, `1d`: All days begin at the earliest possible time, which is usually 00:00:00
+    ///   (midnight).
+    /// * `week`, `1w`: ISO weeks, starting on Monday.
+    /// * `month`, `1M`: All months begin on the first day of the month.
+    /// * `quarter`, `1q`: Quarters begin on the first day of January, April, July and October.
+    /// * `year`, `1y`: All years begin on the first day of January.
+    ///
+    /// Either `calendar_interval` or `fixed_interval` has to be set.
     pub calendar_interval: Option<String>,
     /// The field to aggregate on.
     pub field: String,
@@ -63,7 +80,7 @@ pub struct DateHistogramAggregationReq {
     /// Fractional time values are not supported, but you can address this by shifting to another
     /// time unit (e.g., `1.5h` could instead be specified as `90m`).
     ///
-    /// `Option` for validation, the parameter is not optional
+    /// Either `calendar_interval` or `fixed_interval` has to be set.
     pub fixed_interval: Option<String>,
     /// Intervals implicitly defines an absolute grid of buckets `[interval * k, interval * (k +
     /// 1))`.
@@ -73,8 +90,16 @@ pub struct DateHistogramAggregationReq {
     /// interval).
     ///
     /// The `offset` parameter is has the same syntax as the `fixed_interval` parameter, but
-    /// also allows for negative values.
+    /// also allows for negative values. With a `time_zone`, the offset is applied in local time,
+    /// e.g. an offset of `+6h` on daily buckets makes them start at 6am local time.
     pub offset: Option<String>,
+    /// The time zone used to round dates into buckets. Defaults to UTC.
+    ///
+    /// Accepts IANA time zone names (e.g. `America/New_York`, requires the `time-zones` feature),
+    /// or fixed UTC offsets (e.g. `+01:00` or `-08:00`). Bucket keys are the UTC timestamps of the
+    /// start of the buckets in that time zone, and `key_as_string` is formatted with the UTC
+    /// offset of the time zone.
+    pub time_zone: Option<String>,
     /// The minimum number of documents in a bucket to be returned. Defaults to 0.
     pub min_doc_count: Option<u64>,
     /// Limits the data range to `[min, max]` closed interval.
@@ -117,36 +142,58 @@ pub struct DateHistogramAggregationReq {
 }
 
 impl DateHistogramAggregationReq {
-    pub(crate) fn to_histogram_req(&self) -> crate::Result<HistogramAggregation> {
+    /// Converts the request into a histogram request, and the rounding of its dates if the
+    /// interval is calendar-aware or the request has a time zone.
+    pub(crate) fn to_histogram_req(
+        &self,
+    ) -> crate::Result<(HistogramAggregation, Option<DateRounding>)> {
         self.validate()?;
-        Ok(HistogramAggregation {
+        let offset_ms = self
+            .offset
+            .as_ref()
+            .map(|offset| parse_offset_into_milliseconds(offset))
+            .transpose()?;
+        let time_zone = self
+            .time_zone
+            .as_ref()
+            .map(|time_zone| parse_time_zone(time_zone))
+            .transpose()?;
+        let interval = match (&self.fixed_interval, &self.calendar_interval) {
+            (Some(fixed_interval), _) => {
+                RoundingInterval::Fixed(parse_into_milliseconds(fixed_interval)?)
+            }
+            (None, Some(calendar_interval)) => {
+                RoundingInterval::Calendar(parse_calendar_interval(calendar_interval)?)
+            }
+            (None, None) => unreachable!("checked by validate"),
+        };
+        // Fixed intervals in UTC are handled by the plain histogram logic.
+        let date_rounding = match (interval, time_zone) {
+            (RoundingInterval::Fixed(_), None) => None,
+            (interval, time_zone) => Some(DateRounding {
+                interval,
+                offset_ms: offset_ms.unwrap_or(0),
+                time_zone: time_zone.unwrap_or_else(TimeZone::utc),
+            }),
+        };
+        let histogram_req = HistogramAggregation {
             field: self.field.to_string(),
-            interval: parse_into_milliseconds(self.fixed_interval.as_ref().unwrap())? as f64,
-            offset: self
-                .offset
-                .as_ref()
-                .map(|offset| parse_offset_into_milliseconds(offset))
-                .transpose()?
-                .map(|el| el as f64),
+            interval: interval.approximate_ms() as f64,
+            offset: offset_ms.map(|el| el as f64),
             min_doc_count: self.min_doc_count,
             hard_bounds: self.hard_bounds,
             extended_bounds: self.extended_bounds,
             keyed: self.keyed,
             is_normalized_to_ns: false,
-        })
+        };
+        Ok((histogram_req, date_rounding))
     }
 
     fn validate(&self) -> crate::Result<()> {
         if let Some(interval) = self.interval.as_ref() {
             return Err(crate::TantivyError::InvalidArgument(format!(
-                "`interval` parameter {interval:?} in date histogram is unsupported, only \
-                 `fixed_interval` is supported"
-            )));
-        }
-        if let Some(interval) = self.calendar_interval.as_ref() {
-            return Err(crate::TantivyError::InvalidArgument(format!(
-                "`calendar_interval` parameter {interval:?} in date histogram is unsupported, \
-                 only `fixed_interval` is supported"
+                "`interval` parameter {interval:?} in date histogram is unsupported, use \
+                 `fixed_interval` or `calendar_interval` instead"
             )));
         }
         if self.format.is_some() {
@@ -155,18 +202,226 @@ impl DateHistogramAggregationReq {
             ));
         }
 
-        if self.fixed_interval.is_none() {
-            return Err(crate::TantivyError::InvalidArgument(
-                "fixed_interval in date histogram is missing".to_string(),
-            ));
+        match (&self.fixed_interval, &self.calendar_interval) {
+            (None, None) => {
+                return Err(crate::TantivyError::InvalidArgument(
+                    "fixed_interval or calendar_interval in date histogram is missing".to_string(),
+                ));
+            }
+            (Some(_), Some(_)) => {
+                return Err(crate::TantivyError::InvalidArgument(
+                    "fixed_interval and calendar_interval in date histogram cannot be set at the \
+                     same time"
+                        .to_string(),
+                ));
+            }
+            (Some(fixed_interval), None) => {
+                parse_into_milliseconds(fixed_interval)?;
+            }
+            (None, Some(calendar_interval)) => {
+                parse_calendar_interval(calendar_interval)?;
+            }
         }
 
-        parse_into_milliseconds(self.fixed_interval.as_ref().unwrap())?;
-
         Ok(())
     }
 }
 
+/// A calendar-aware unit of a `calendar_interval`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum CalendarUnit {
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum RoundingInterval {
+    Calendar(CalendarUnit),
+    /// A fixed interval in milliseconds.
+    Fixed(i64),
+}
+
+impl RoundingInterval {
+    /// Returns the duration of the interval if it is fixed and shorter than a day.
+    ///
+    /// Such intervals are rounded in UTC, using the offset of the time zone at the
+    /// instant being rounded, so that the hours repeated when clocks are turned back
+    /// get their own buckets.
+    fn sub_day_duration_ms(self) -> Option<i64> {
+        match self {
+            RoundingInterval::Calendar(CalendarUnit::Minute) => Some(60_000),
+            RoundingInterval::Calendar(CalendarUnit::Hour) => Some(3_600_000),
+            RoundingInterval::Fixed(interval_ms) if interval_ms < MS_PER_DAY => Some(interval_ms),
+            _ => None,
+        }
+    }
+
+    pub(super) fn approximate_ms(self) -> i64 {
+        match self {
+            RoundingInterval::Fixed(interval_ms) => interval_ms,
+            RoundingInterval::Calendar(unit) => match unit {
+                CalendarUnit::Minute => 60_000,
+                CalendarUnit::Hour => 3_600_000,
+                CalendarUnit::Day => MS_PER_DAY,
+                CalendarUnit::Week => 7 * MS_PER_DAY,
+                CalendarUnit::Month => 30 * MS_PER_DAY,
+                CalendarUnit::Quarter => 91 * MS_PER_DAY,
+                CalendarUnit::Year => 365 * MS_PER_DAY,
+            },
+        }
+    }
+
+    /// Returns a lower bound of the duration of a bucket.
+    fn min_duration_ms(self) -> i64 {
+        let shortest_day_ms = MS_PER_DAY - 3 * 3_600_000;
+        match self {
+            // Offset transitions of half an hour shorten sub-day buckets.
+            RoundingInterval::Calendar(CalendarUnit::Minute | CalendarUnit::Hour) => {
+                (self.approximate_ms() / 2).max(1)
+            }
+            RoundingInterval::Fixed(interval_ms) if interval_ms < MS_PER_DAY => {
+                (interval_ms / 2).max(1)
+            }
+            RoundingInterval::Fixed(interval_ms) => interval_ms - (MS_PER_DAY - shortest_day_ms),
+            RoundingInterval::Calendar(CalendarUnit::Day) => shortest_day_ms,
+            RoundingInterval::Calendar(CalendarUnit::Week) => 6 * MS_PER_DAY + shortest_day_ms,
+            RoundingInterval::Calendar(CalendarUnit::Month) => 27 * MS_PER_DAY + shortest_day_ms,
+            RoundingInterval::Calendar(CalendarUnit::Quarter) => 89 * MS_PER_DAY + shortest_day_ms,
+            RoundingInterval::Calendar(CalendarUnit::Year) => 364 * MS_PER_DAY + shortest_day_ms,
+        }
+    }
+}
+
+/// Rounds timestamps down to the start of their date histogram bucket, in the local
+/// time of a time zone.
+///
+/// Used for calendar-aware intervals and for fixed intervals with a time zone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateRounding {
+    interval: RoundingInterval,
+    offset_ms: i64,
+    time_zone: TimeZone,
+}
+
+impl DateRounding {
+    /// Creates a rounding without offset.
+    pub(super) fn new(interval: RoundingInterval, time_zone: TimeZone) -> Self {
+        DateRounding {
+            interval,
+            offset_ms: 0,
+            time_zone,
+        }
+    }
+
+    /// Returns the start of the bucket of a timestamp, both in milliseconds.
+    pub(crate) fn round(&self, utc_ms: i64) -> i64 {
+        let local_ms = self.time_zone.to_local_ms(utc_ms);
+        if let Some(duration_ms) = self.interval.sub_day_duration_ms() {
+            return utc_ms - (local_ms - self.offset_ms).rem_euclid(duration_ms);
+        }
+        let bucket_local_ms = self.round_local(local_ms - self.offset_ms) + self.offset_ms;
+        self.time_zone.to_utc_ms(bucket_local_ms)
+    }
+
+    /// Returns the start of the bucket following the bucket starting at `bucket_ms`.
+    pub(crate) fn next(&self, bucket_ms: i64) -> i64 {
+        if let Some(duration_ms) = self.interval.sub_day_duration_ms() {
+            return self.round(bucket_ms + duration_ms);
+        }
+        let bucket_local_ms =
+            self.round_local(self.time_zone.to_local_ms(bucket_ms) - self.offset_ms);
+        let days = bucket_local_ms.div_euclid(MS_PER_DAY);
+        let next_bucket_local_ms = match self.interval {
+            RoundingInterval::Fixed(interval_ms) => bucket_local_ms + interval_ms,
+            RoundingInterval::Calendar(unit) => match unit {
+                CalendarUnit::Minute | CalendarUnit::Hour | CalendarUnit::Day => {
+                    bucket_local_ms + MS_PER_DAY
+                }
+                CalendarUnit::Week => bucket_local_ms + 7 * MS_PER_DAY,
+                CalendarUnit::Month | CalendarUnit::Quarter | CalendarUnit::Year => {
+                    let (year, month, _) = civil_from_days(days);
+                    let num_months = match unit {
+                        CalendarUnit::Month => 1,
+                        CalendarUnit::Quarter => 3,
+                        _ => 12,
+                    };
+                    let month_idx = year as i64 * 12 + month as i64 - 1 + num_months;
+                    days_from_civil(
+                        month_idx.div_euclid(12) as i32,
+                        month_idx.rem_euclid(12) as u32 + 1,
+                        1,
+                    ) * MS_PER_DAY
+                }
+            },
+        };
+        self.time_zone
+            .to_utc_ms(next_bucket_local_ms + self.offset_ms)
+    }
+
+    /// Rounds a local time down to the start of its bucket, for intervals of a day or
+    /// more.
+    fn round_local(&self, local_ms: i64) -> i64 {
+        let days = local_ms.div_euclid(MS_PER_DAY);
+        let bucket_days = match self.interval {
+            RoundingInterval::Fixed(interval_ms) => {
+                return local_ms - local_ms.rem_euclid(interval_ms);
+            }
+            RoundingInterval::Calendar(unit) => match unit {
+                CalendarUnit::Minute | CalendarUnit::Hour | CalendarUnit::Day => days,
+                // 1970-01-01 was a thursday.
+                CalendarUnit::Week => days - (days + 3).rem_euclid(7),
+                CalendarUnit::Month | CalendarUnit::Quarter | CalendarUnit::Year => {
+                    let (year, month, _) = civil_from_days(days);
+                    let first_month = match unit {
+                        CalendarUnit::Month => month,
+                        CalendarUnit::Quarter => (month - 1) / 3 * 3 + 1,
+                        _ => 1,
+                    };
+                    days_from_civil(year, first_month, 1)
+                }
+            },
+        };
+        bucket_days * MS_PER_DAY
+    }
+
+    /// Returns an upper bound of the number of buckets between two timestamps.
+    pub(crate) fn max_num_buckets(&self, min_ms: i64, max_ms: i64) -> u64 {
+        (max_ms.saturating_sub(min_ms).max(0) / self.interval.min_duration_ms()) as u64 + 2
+    }
+
+    /// Returns the offset from UTC of the time zone at the given timestamp, in
+    /// milliseconds.
+    pub(crate) fn utc_offset_ms_at(&self, utc_ms: i64) -> i64 {
+        self.time_zone.offset_ms_at(utc_ms)
+    }
+}
+
+fn parse_calendar_interval(input: &str) -> Result<CalendarUnit, AggregationError> {
+    let unit = match input {
+        "minute" | "1m" => CalendarUnit::Minute,
+        "hour" | "1h" => CalendarUnit::Hour,
+        "day" | "1d" => CalendarUnit::Day,
+        "week" | "1w" => CalendarUnit::Week,
+        "month" | "1M" => CalendarUnit::Month,
+        "quarter" | "1q" => CalendarUnit::Quarter,
+        "year" | "1y" => CalendarUnit::Year,
+        _ => {
+            return Err(DateHistogramParseError::InvalidCalendarInterval(input.to_string()).into())
+        }
+    };
+    Ok(unit)
+}
+
+pub(super) fn parse_time_zone(input: &str) -> Result<TimeZone, AggregationError> {
+    TimeZone::parse(input)
+        .ok_or_else(|| DateHistogramParseError::UnknownTimeZone(input.to_string()).into())
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 /// Errors when parsing the fixed interval for `DateHistogramAggregationReq`.
 pub enum DateHistogramParseError {
@@ -185,6 +440,15 @@ pub enum DateHistogramParseError {
     /// Value out of bounds
     #[error("passed value is out of bounds: {0:?}")]
     OutOfBounds(String),
+    /// Calendar interval invalid
+    #[error(
+        "calendar interval {0:?} is invalid, expected one of minute, hour, day, week, month, \
+         quarter, year (or 1m, 1h, 1d, 1w, 1M, 1q, 1y)"
+    )]
+    InvalidCalendarInterval(String),
+    /// Time zone unknown
+    #[error("unknown time zone {0:?}")]
+    UnknownTimeZone(String),
 }
 
 fn parse_offset_into_milliseconds(input: &str) -> Result<i64, AggregationError> {
@@ -669,7 +933,235 @@ pub(crate) mod tests {
         let err = exec_request(agg_req, &index).unwrap_err();
         assert_eq!(
             err.to_string(),
-            r#"An invalid argument was passed: '`interval` parameter "30d" in date histogram is unsupported, only `fixed_interval` is supported'"#
+            r#"An invalid argument was passed: '`interval` parameter "30d" in date histogram is unsupported, use `fixed_interval` or `calendar_interval` instead'"#
+        );
+    }
+
+    fn exec_date_histogram(index: &Index, date_histogram: serde_json::Value) -> serde_json::Value {
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histo": { "date_histogram": date_histogram }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, index).unwrap();
+        res["histo"]["buckets"].clone()
+    }
+
+    fn keys_as_string_and_doc_counts(buckets: &serde_json::Value) -> Vec<(String, u64)> {
+        buckets
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| {
+                (
+                    bucket["key_as_string"].as_str().unwrap().to_string(),
+                    bucket["doc_count"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "time-zones")]
+    #[test]
+    fn histogram_test_calendar_interval_month_time_zone() {
+        let docs = vec![
+            vec![
+                r#"{ "date": "2024-01-15T12:00:00Z" }"#,
+                // 2024-02-01T00:30:00+01:00
+                r#"{ "date": "2024-01-31T23:30:00Z" }"#,
+            ],
+            vec![
+                // 2024-04-01T00:30:00+02:00
+                r#"{ "date": "2024-03-31T22:30:00Z" }"#,
+            ],
+        ];
+        let index = get_test_index_from_docs(false, &docs).unwrap();
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "month", "time_zone": "Europe/Paris" }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2024-01-01T00:00:00+01:00".to_string(), 1),
+                ("2024-02-01T00:00:00+01:00".to_string(), 1),
+                ("2024-03-01T00:00:00+01:00".to_string(), 0),
+                ("2024-04-01T00:00:00+02:00".to_string(), 1),
+            ]
+        );
+        assert_eq!(buckets[3]["key"], 1711922400000.0);
+
+        // Without a time zone, the buckets are UTC months.
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "1M" }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2024-01-01T00:00:00Z".to_string(), 2),
+                ("2024-02-01T00:00:00Z".to_string(), 0),
+                ("2024-03-01T00:00:00Z".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn histogram_test_calendar_interval_units() {
+        let docs = vec![vec![
+            // A sunday
+            r#"{ "date": "2023-12-31T10:00:00Z" }"#,
+            // A monday
+            r#"{ "date": "2024-01-01T00:00:00Z" }"#,
+            r#"{ "date": "2024-05-20T08:30:00Z" }"#,
+        ]];
+        let index = get_test_index_from_docs(true, &docs).unwrap();
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "week", "min_doc_count": 1 }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2023-12-25T00:00:00Z".to_string(), 1),
+                ("2024-01-01T00:00:00Z".to_string(), 1),
+                ("2024-05-20T00:00:00Z".to_string(), 1),
+            ]
+        );
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "quarter" }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2023-10-01T00:00:00Z".to_string(), 1),
+                ("2024-01-01T00:00:00Z".to_string(), 1),
+                ("2024-04-01T00:00:00Z".to_string(), 1),
+            ]
+        );
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "year" }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2023-01-01T00:00:00Z".to_string(), 1),
+                ("2024-01-01T00:00:00Z".to_string(), 2),
+            ]
+        );
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "hour", "min_doc_count": 1 }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets)[2],
+            ("2024-05-20T08:00:00Z".to_string(), 1)
+        );
+    }
+
+    #[cfg(feature = "time-zones")]
+    #[test]
+    fn histogram_test_calendar_interval_dst() {
+        let docs = vec![vec![
+            // 2024-03-09T23:00:00-05:00
+            r#"{ "date": "2024-03-10T04:00:00Z" }"#,
+            // 2024-03-10T23:00:00-04:00, the day only has 23 hours.
+            r#"{ "date": "2024-03-11T03:00:00Z" }"#,
+            // 2024-03-11T00:00:00-04:00
+            r#"{ "date": "2024-03-11T04:00:00Z" }"#,
+        ]];
+        let index = get_test_index_from_docs(false, &docs).unwrap();
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "day", "time_zone": "America/New_York" }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2024-03-09T00:00:00-05:00".to_string(), 1),
+                ("2024-03-10T00:00:00-05:00".to_string(), 1),
+                ("2024-03-11T00:00:00-04:00".to_string(), 1),
+            ]
+        );
+        // Fixed intervals are rounded in local time as well.
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "fixed_interval": "1d", "time_zone": "America/New_York" }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets)[2],
+            ("2024-03-11T00:00:00-04:00".to_string(), 1)
+        );
+        // The offset is applied in local time.
+        let buckets = exec_date_histogram(
+            &index,
+            json!({
+                "field": "date",
+                "calendar_interval": "day",
+                "time_zone": "America/New_York",
+                "offset": "+6h"
+            }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2024-03-09T06:00:00-05:00".to_string(), 1),
+                ("2024-03-10T06:00:00-04:00".to_string(), 2),
+            ]
+        );
+        // Fixed offsets are supported too.
+        let buckets = exec_date_histogram(
+            &index,
+            json!({ "field": "date", "calendar_interval": "day", "time_zone": "-05:00" }),
+        );
+        assert_eq!(
+            keys_as_string_and_doc_counts(&buckets),
+            vec![
+                ("2024-03-09T00:00:00-05:00".to_string(), 1),
+                ("2024-03-10T00:00:00-05:00".to_string(), 2),
+            ]
+        );
+    }
+
+    #[cfg(feature = "time-zones")]
+    #[test]
+    fn test_date_rounding_repeated_hour() {
+        let date_rounding = DateRounding {
+            interval: RoundingInterval::Calendar(CalendarUnit::Hour),
+            offset_ms: 0,
+            time_zone: TimeZone::parse("America/New_York").unwrap(),
+        };
+        // 2024-11-03 01:00 happens twice, at 05:00Z and at 06:00Z.
+        let first_one_am = 1730610000000;
+        let second_one_am = first_one_am + 3_600_000;
+        assert_eq!(date_rounding.round(first_one_am + 1_000), first_one_am);
+        assert_eq!(date_rounding.round(second_one_am + 1_000), second_one_am);
+        assert_eq!(date_rounding.next(first_one_am), second_one_am);
+    }
+
+    #[test]
+    fn histogram_test_calendar_interval_invalid_req() {
+        let index = get_test_index_from_docs(false, &[]).unwrap();
+        let exec_err = |date_histogram: serde_json::Value| {
+            let agg_req: Aggregations = serde_json::from_value(json!({
+                "histo": { "date_histogram": date_histogram }
+            }))
+            .unwrap();
+            exec_request(agg_req, &index).unwrap_err().to_string()
+        };
+        assert!(
+            exec_err(json!({ "field": "date", "calendar_interval": "2M" }))
+                .contains("InvalidCalendarInterval(\"2M\")")
         );
+        assert!(exec_err(
+            json!({ "field": "date", "calendar_interval": "month", "time_zone": "Mars/Olympus" })
+        )
+        .contains("UnknownTimeZone(\"Mars/Olympus\")"));
+        assert!(exec_err(
+            json!({ "field": "date", "calendar_interval": "month", "fixed_interval": "30d" })
+        )
+        .contains("cannot be set at the same time"));
+        assert!(exec_err(json!({ "field": "date" })).contains("is missing"));
     }
 }
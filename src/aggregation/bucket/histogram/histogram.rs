@@ -5,6 +5,7 @@ use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use tantivy_bitpacker::minmax;
 
+use super::{AutoDateHistogramReqData, DateRounding, IntermediateAutoDateHistogram};
 use crate::aggregation::agg_data::{
     build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
 };
@@ -30,12 +31,18 @@ pub struct HistogramAggReqData {
     pub name: String,
     /// The histogram aggregation request.
     pub req: HistogramAggregation,
+    /// Rounds dates into buckets, in place of `interval` and `offset`. Set by date histograms
+    /// with a calendar interval or a time zone.
+    pub date_rounding: Option<DateRounding>,
     /// True if this is a date_histogram aggregation.
     pub is_date_histogram: bool,
     /// The bounds to limit the buckets to.
     pub bounds: HistogramBounds,
     /// The offset used to calculate the bucket position.
     pub offset: f64,
+    /// Set if this is an auto_date_histogram aggregation, collected with the rounding picked
+    /// for the segment.
+    pub auto_date_histogram: Option<AutoDateHistogramReqData>,
 }
 impl HistogramAggReqData {
     /// Estimate the memory consumption of this struct in bytes.
@@ -330,7 +337,12 @@ impl SegmentAggregationCollector for SegmentHistogramCollector {
         let bounds = req.bounds;
         let interval = req.req.interval;
         let offset = req.offset;
-        let get_bucket_pos = |val| get_bucket_pos_f64(val, interval, offset) as i64;
+        let date_rounding = req.date_rounding.as_ref();
+        // With a date rounding, the position of a bucket is its key in milliseconds.
+        let get_bucket_pos = |val| match date_rounding {
+            Some(date_rounding) => date_rounding.round(nanos_to_millis(val)),
+            None => get_bucket_pos_f64(val, interval, offset) as i64,
+        };
 
         agg_data
             .column_block_accessor
@@ -343,7 +355,10 @@ impl SegmentAggregationCollector for SegmentHistogramCollector {
             let bucket_pos = get_bucket_pos(val);
             if bounds.contains(val) {
                 let bucket = buckets.entry(bucket_pos).or_insert_with(|| {
-                    let key = get_bucket_key_from_pos(bucket_pos as f64, interval, offset);
+                    let key = match date_rounding {
+                        Some(_) => millis_to_nanos(bucket_pos),
+                        None => get_bucket_key_from_pos(bucket_pos as f64, interval, offset),
+                    };
                     SegmentHistogramBucketEntry {
                         key,
                         doc_count: 0,
@@ -415,10 +430,13 @@ impl SegmentHistogramCollector {
         }
         buckets.sort_unstable_by(|b1, b2| b1.key.total_cmp(&b2.key));
 
-        let is_date_agg = agg_data
-            .get_histogram_req_data(self.accessor_idx)
-            .field_type
-            == ColumnType::DateTime;
+        let req_data = agg_data.get_histogram_req_data(self.accessor_idx);
+        if let Some(auto_date_histogram) = &req_data.auto_date_histogram {
+            return Ok(IntermediateBucketResult::AutoDateHistogram(
+                IntermediateAutoDateHistogram::new(auto_date_histogram, buckets)?,
+            ));
+        }
+        let is_date_agg = req_data.field_type == ColumnType::DateTime;
         Ok(IntermediateBucketResult::Histogram {
             buckets,
             is_date_agg,
@@ -465,10 +483,21 @@ fn get_bucket_key_from_pos(bucket_pos: f64, interval: f64, offset: f64) -> f64 {
     bucket_pos * interval + offset
 }
 
+#[inline]
+pub(super) fn nanos_to_millis(val: f64) -> i64 {
+    (val / 1_000_000.0).floor() as i64
+}
+
+#[inline]
+pub(super) fn millis_to_nanos(val: i64) -> f64 {
+    val as f64 * 1_000_000.0
+}
+
 // Convert to BucketEntry and fill gaps
 fn intermediate_buckets_to_final_buckets_fill_gaps(
     buckets: Vec<IntermediateHistogramBucketEntry>,
     histogram_req: &HistogramAggregation,
+    date_rounding: Option<&DateRounding>,
     sub_aggregation: &Aggregations,
     limits: &mut AggregationLimitsGuard,
 ) -> crate::Result<Vec<BucketEntry>> {
@@ -479,17 +508,25 @@ fn intermediate_buckets_to_final_buckets_fill_gaps(
     let min_max = minmax(buckets.iter().map(|bucket| bucket.key));
 
     // memory check upfront
-    let (_, first_bucket_num, last_bucket_num) =
-        generate_bucket_pos_with_opt_minmax(histogram_req, min_max);
-
-    // It's based on user input, so we need to account for overflows
-    let added_buckets = ((last_bucket_num.saturating_sub(first_bucket_num)).max(0) as u64)
-        .saturating_sub(buckets.len() as u64);
+    let num_buckets = if let Some(date_rounding) = date_rounding {
+        let (min, max) = get_req_min_max(histogram_req, min_max);
+        if min > max {
+            0
+        } else {
+            date_rounding.max_num_buckets(nanos_to_millis(min), nanos_to_millis(max))
+        }
+    } else {
+        let (_, first_bucket_num, last_bucket_num) =
+            generate_bucket_pos_with_opt_minmax(histogram_req, min_max);
+        // It's based on user input, so we need to account for overflows
+        (last_bucket_num.saturating_sub(first_bucket_num)).max(0) as u64
+    };
+    let added_buckets = num_buckets.saturating_sub(buckets.len() as u64);
     limits.add_memory_consumed(
         added_buckets * std::mem::size_of::<IntermediateHistogramBucketEntry>() as u64,
     )?;
     // create buckets
-    let fill_gaps_buckets = generate_buckets_with_opt_minmax(histogram_req, min_max);
+    let fill_gaps_buckets = generate_buckets_with_opt_minmax(histogram_req, date_rounding, min_max);
 
     let empty_sub_aggregation = IntermediateAggregationResults::empty_from_req(sub_aggregation);
 
@@ -527,6 +564,7 @@ pub(crate) fn intermediate_histogram_buckets_to_final_buckets(
     buckets: Vec<IntermediateHistogramBucketEntry>,
     is_date_agg: bool,
     histogram_req: &HistogramAggregation,
+    date_rounding: Option<&DateRounding>,
     sub_aggregation: &Aggregations,
     limits: &mut AggregationLimitsGuard,
 ) -> crate::Result<Vec<BucketEntry>> {
@@ -544,6 +582,7 @@ pub(crate) fn intermediate_histogram_buckets_to_final_buckets(
         intermediate_buckets_to_final_buckets_fill_gaps(
             buckets,
             &histogram_req,
+            date_rounding,
             sub_aggregation,
             limits,
         )?
@@ -562,7 +601,13 @@ pub(crate) fn intermediate_histogram_buckets_to_final_buckets(
     if is_date_agg {
         for bucket in buckets.iter_mut() {
             if let crate::aggregation::Key::F64(ref mut val) = bucket.key {
-                let key_as_string = format_date(*val as i64)?;
+                let key_as_string = match date_rounding {
+                    Some(date_rounding) => {
+                        let utc_offset_ms = date_rounding.utc_offset_ms_at(nanos_to_millis(*val));
+                        format_date_with_utc_offset(*val as i64, utc_offset_ms)?
+                    }
+                    None => format_date(*val as i64)?,
+                };
                 *val /= 1_000_000.0;
                 bucket.key_as_string = Some(key_as_string);
             }
@@ -611,8 +656,23 @@ pub(crate) fn generate_bucket_pos_with_opt_minmax(
 /// returns empty vec when there is no range to span
 pub(crate) fn generate_buckets_with_opt_minmax(
     req: &HistogramAggregation,
+    date_rounding: Option<&DateRounding>,
     min_max: Option<(f64, f64)>,
 ) -> Vec<f64> {
+    if let Some(date_rounding) = date_rounding {
+        let (min, max) = get_req_min_max(req, min_max);
+        let mut buckets = Vec::new();
+        if min > max {
+            return buckets;
+        }
+        let max = nanos_to_millis(max);
+        let mut bucket_key = date_rounding.round(nanos_to_millis(min));
+        while bucket_key <= max {
+            buckets.push(millis_to_nanos(bucket_key));
+            bucket_key = date_rounding.next(bucket_key);
+        }
+        return buckets;
+    }
     let (offset, first_bucket_num, last_bucket_num) =
         generate_bucket_pos_with_opt_minmax(req, min_max);
     let mut buckets = Vec::with_capacity((first_bucket_num..=last_bucket_num).count());
@@ -1,4 +1,8 @@
+mod auto_date_histogram;
 mod date_histogram;
 mod histogram;
+mod variable_width_histogram;
+pub use auto_date_histogram::*;
 pub use date_histogram::*;
 pub use histogram::*;
+pub use variable_width_histogram::*;
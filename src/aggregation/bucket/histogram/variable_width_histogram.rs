@@ -0,0 +1,595 @@
+use columnar::{Column, ColumnType};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::agg_data::{
+    build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
+};
+use crate::aggregation::agg_req::Aggregations;
+use crate::aggregation::agg_result::{BucketResult, VariableWidthHistogramBucketEntry};
+use crate::aggregation::cached_sub_aggs::{CachedSubAggs, HighCardCachedSubAggs};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateBucketResult,
+};
+use crate::aggregation::segment_agg_result::{BucketIdProvider, SegmentAggregationCollector};
+use crate::aggregation::*;
+use crate::{DocId, TantivyError};
+
+fn default_buckets() -> u32 {
+    10
+}
+
+/// VariableWidthHistogramAggregation is a histogram over a numeric field, where the bucket
+/// boundaries are not fixed, but derived from the data: the values are clustered into at most
+/// `buckets` buckets, so that close values end up in the same bucket.
+///
+/// Each segment buffers its first `initial_buffer` values, and splits them into at most
+/// `shard_size` clusters at the largest gaps between the values. The following values are added
+/// to the closest cluster. When results are merged, the two clusters with the closest centroids
+/// are merged until there are at most `shard_size` clusters, and `buckets` clusters in the final
+/// result.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "prices": {
+///         "variable_width_histogram": {
+///             "field": "price",
+///             "buckets": 5
+///         }
+///     }
+/// }
+/// ```
+///
+/// Response
+/// See [`VariableWidthHistogramBucketEntry`]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VariableWidthHistogramAggregationReq {
+    /// The field to aggregate on.
+    pub field: String,
+    /// The maximum number of buckets. Defaults to 10.
+    #[serde(default = "default_buckets")]
+    pub buckets: u32,
+    /// The maximum number of clusters per segment. Defaults to `buckets * 50`.
+    pub shard_size: Option<u32>,
+    /// The number of values of a segment that are buffered before the clusters are created.
+    /// Defaults to `min(shard_size * 50, 50000)`.
+    pub initial_buffer: Option<u32>,
+}
+
+impl VariableWidthHistogramAggregationReq {
+    /// Creates a new [`VariableWidthHistogramAggregationReq`] instance from a field name and a
+    /// number of buckets.
+    pub fn from_field_name(field_name: String, buckets: u32) -> Self {
+        VariableWidthHistogramAggregationReq {
+            field: field_name,
+            buckets,
+            shard_size: None,
+            initial_buffer: None,
+        }
+    }
+
+    pub(crate) fn shard_size(&self) -> u32 {
+        self.shard_size
+            .unwrap_or_else(|| self.buckets.saturating_mul(50))
+    }
+
+    pub(crate) fn initial_buffer(&self) -> u32 {
+        self.initial_buffer
+            .unwrap_or_else(|| self.shard_size().saturating_mul(50).min(50_000))
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        let invalid_request = |message: &str| {
+            Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(format!("{message} in variable_width_histogram")),
+            ))
+        };
+        if self.buckets == 0 {
+            return invalid_request("buckets must be greater than 0");
+        }
+        if self.shard_size() < self.buckets {
+            return invalid_request("shard_size must be greater than or equal to buckets");
+        }
+        if self.initial_buffer() == 0 {
+            return invalid_request("initial_buffer must be greater than 0");
+        }
+        Ok(())
+    }
+}
+
+/// Contains all information required by the SegmentVariableWidthHistogramCollector to perform
+/// the variable_width_histogram aggregation on a segment.
+pub struct VariableWidthHistogramAggReqData {
+    /// The column accessor to access the fast field values.
+    pub accessor: Column<u64>,
+    /// The field type of the fast field.
+    pub field_type: ColumnType,
+    /// The name of the aggregation.
+    pub name: String,
+    /// The variable_width_histogram aggregation request.
+    pub req: VariableWidthHistogramAggregationReq,
+}
+
+impl VariableWidthHistogramAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// Intermediate result of the variable_width_histogram aggregation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateVariableWidthHistogram {
+    /// The maximum number of buckets kept when merging.
+    shard_size: u32,
+    /// The buckets, sorted by centroid.
+    buckets: Vec<IntermediateVariableWidthBucketEntry>,
+}
+
+/// A cluster of values of the variable_width_histogram aggregation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateVariableWidthBucketEntry {
+    /// The smallest value in the bucket.
+    pub min: f64,
+    /// The largest value in the bucket.
+    pub max: f64,
+    /// The sum of the values in the bucket.
+    pub sum: f64,
+    /// The number of values in the bucket.
+    pub doc_count: u64,
+    /// The sub_aggregation in this bucket.
+    pub sub_aggregation: IntermediateAggregationResults,
+}
+
+impl IntermediateVariableWidthBucketEntry {
+    fn centroid(&self) -> f64 {
+        self.sum / self.doc_count as f64
+    }
+
+    fn merge_fruits(&mut self, other: IntermediateVariableWidthBucketEntry) -> crate::Result<()> {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.doc_count += other.doc_count;
+        self.sub_aggregation.merge_fruits(other.sub_aggregation)
+    }
+}
+
+impl IntermediateVariableWidthHistogram {
+    /// Creates a result from the buckets of a segment.
+    pub(crate) fn new(
+        shard_size: u32,
+        mut buckets: Vec<IntermediateVariableWidthBucketEntry>,
+    ) -> crate::Result<Self> {
+        buckets.sort_by(|left, right| left.centroid().total_cmp(&right.centroid()));
+        let mut result = IntermediateVariableWidthHistogram {
+            shard_size,
+            buckets,
+        };
+        result.merge_closest_buckets(shard_size as usize)?;
+        Ok(result)
+    }
+
+    /// Returns an empty result for the request.
+    pub(crate) fn empty_from_req(req: &VariableWidthHistogramAggregationReq) -> Self {
+        IntermediateVariableWidthHistogram {
+            shard_size: req.shard_size(),
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Merges the other intermediate result into self.
+    pub(crate) fn merge_fruits(
+        &mut self,
+        other: IntermediateVariableWidthHistogram,
+    ) -> crate::Result<()> {
+        self.buckets.extend(other.buckets);
+        self.buckets
+            .sort_by(|left, right| left.centroid().total_cmp(&right.centroid()));
+        self.merge_closest_buckets(self.shard_size as usize)
+    }
+
+    /// Merges the neighboring buckets with the closest centroids, until there are at most
+    /// `max_buckets` buckets.
+    fn merge_closest_buckets(&mut self, max_buckets: usize) -> crate::Result<()> {
+        while self.buckets.len() > max_buckets.max(1) {
+            let pos = (0..self.buckets.len() - 1)
+                .min_by(|&left, &right| {
+                    let distance = |pos: usize| {
+                        self.buckets[pos + 1].centroid() - self.buckets[pos].centroid()
+                    };
+                    distance(left).total_cmp(&distance(right))
+                })
+                .expect("there are at least two buckets");
+            let right = self.buckets.remove(pos + 1);
+            self.buckets[pos].merge_fruits(right)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_final_result(
+        mut self,
+        req: &VariableWidthHistogramAggregationReq,
+        sub_aggregation: &Aggregations,
+        limits: &mut AggregationLimitsGuard,
+    ) -> crate::Result<BucketResult> {
+        self.merge_closest_buckets(req.buckets as usize)?;
+        let buckets = self
+            .buckets
+            .into_iter()
+            .map(|bucket| {
+                Ok(VariableWidthHistogramBucketEntry {
+                    key: bucket.centroid(),
+                    min: bucket.min,
+                    max: bucket.max,
+                    doc_count: bucket.doc_count,
+                    sub_aggregation: bucket
+                        .sub_aggregation
+                        .into_final_result_internal(sub_aggregation, limits)?,
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(BucketResult::VariableWidthHistogram { buckets })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Cluster {
+    min: f64,
+    max: f64,
+    sum: f64,
+    doc_count: u64,
+    bucket_id: BucketId,
+}
+
+impl Cluster {
+    fn centroid(&self) -> f64 {
+        self.sum / self.doc_count as f64
+    }
+
+    fn collect(&mut self, val: f64) {
+        self.min = self.min.min(val);
+        self.max = self.max.max(val);
+        self.sum += val;
+        self.doc_count += 1;
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct VariableWidthBuckets {
+    /// The values collected before the clusters are created, in collection order.
+    buffer: Vec<(DocId, f64)>,
+    /// The clusters, sorted by their value range. The value ranges don't overlap.
+    clusters: Vec<Cluster>,
+}
+
+impl VariableWidthBuckets {
+    fn get_memory_consumption(&self) -> usize {
+        self.buffer.capacity() * std::mem::size_of::<(DocId, f64)>()
+            + self.clusters.capacity() * std::mem::size_of::<Cluster>()
+    }
+
+    /// Splits the buffered values into at most `shard_size` clusters, at the largest gaps
+    /// between the values.
+    fn create_clusters(
+        &mut self,
+        shard_size: usize,
+        bucket_id_provider: &mut BucketIdProvider,
+        sub_agg: &mut Option<HighCardCachedSubAggs>,
+    ) {
+        let buffer = std::mem::take(&mut self.buffer);
+        let mut values: Vec<f64> = buffer.iter().map(|(_, val)| *val).collect();
+        values.sort_by(|left, right| left.total_cmp(right));
+        let mut gaps: Vec<(f64, usize)> = values
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[1] > pair[0])
+            .map(|(pos, pair)| (pair[1] - pair[0], pos + 1))
+            .collect();
+        gaps.sort_by(|left, right| right.0.total_cmp(&left.0));
+        let mut cuts: Vec<usize> = gaps
+            .into_iter()
+            .take(shard_size.saturating_sub(1))
+            .map(|(_, pos)| pos)
+            .collect();
+        cuts.sort_unstable();
+        let mut start = 0;
+        for end in cuts.into_iter().chain(std::iter::once(values.len())) {
+            self.clusters.push(Cluster {
+                min: values[start],
+                max: values[end - 1],
+                sum: 0.0,
+                doc_count: 0,
+                bucket_id: bucket_id_provider.next_bucket_id(),
+            });
+            start = end;
+        }
+        // The docs are passed to the sub aggregations in collection order.
+        for (doc, val) in buffer {
+            self.collect(doc, val, sub_agg);
+        }
+    }
+
+    /// Adds the value to the cluster containing it, or to the closest cluster.
+    fn collect(&mut self, doc: DocId, val: f64, sub_agg: &mut Option<HighCardCachedSubAggs>) {
+        let pos = self.clusters.partition_point(|cluster| cluster.max < val);
+        let pos = if pos == self.clusters.len() {
+            pos - 1
+        } else if pos == 0 || self.clusters[pos].min <= val {
+            pos
+        } else {
+            // The value is in the gap between two clusters.
+            let previous = &self.clusters[pos - 1];
+            let next = &self.clusters[pos];
+            if val - previous.centroid() <= next.centroid() - val {
+                pos - 1
+            } else {
+                pos
+            }
+        };
+        let cluster = &mut self.clusters[pos];
+        cluster.collect(val);
+        if let Some(sub_agg) = sub_agg {
+            sub_agg.push(cluster.bucket_id, doc);
+        }
+    }
+}
+
+/// The collector clusters the values of the fast field and passes the docs of each cluster to
+/// the sub aggregations.
+#[derive(Debug)]
+pub(crate) struct SegmentVariableWidthHistogramCollector {
+    /// One set of clusters per parent bucket id.
+    parent_buckets: Vec<VariableWidthBuckets>,
+    sub_agg: Option<HighCardCachedSubAggs>,
+    accessor_idx: usize,
+    bucket_id_provider: BucketIdProvider,
+}
+
+impl SegmentVariableWidthHistogramCollector {
+    pub(crate) fn from_req_and_validate(
+        agg_data: &mut AggregationsSegmentCtx,
+        node: &AggRefNode,
+    ) -> crate::Result<Self> {
+        let sub_agg = if !node.children.is_empty() {
+            Some(CachedSubAggs::new(build_segment_agg_collectors(
+                agg_data,
+                &node.children,
+            )?))
+        } else {
+            None
+        };
+        agg_data
+            .get_variable_width_histogram_req_data(node.idx_in_req_data)
+            .req
+            .validate()?;
+        Ok(Self {
+            parent_buckets: Vec::new(),
+            sub_agg,
+            accessor_idx: node.idx_in_req_data,
+            bucket_id_provider: BucketIdProvider::default(),
+        })
+    }
+}
+
+impl SegmentAggregationCollector for SegmentVariableWidthHistogramCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        self.prepare_max_bucket(parent_bucket_id, agg_data)?;
+        let req_data = agg_data.get_variable_width_histogram_req_data(self.accessor_idx);
+        let name = req_data.name.clone();
+        let shard_size = req_data.req.shard_size();
+        let buckets = std::mem::take(&mut self.parent_buckets[parent_bucket_id as usize]);
+        let mut entries = Vec::with_capacity(buckets.clusters.len());
+        for cluster in buckets.clusters {
+            let mut sub_aggregation = IntermediateAggregationResults::default();
+            if let Some(sub_agg) = &mut self.sub_agg {
+                sub_agg
+                    .get_sub_agg_collector()
+                    .add_intermediate_aggregation_result(
+                        agg_data,
+                        &mut sub_aggregation,
+                        cluster.bucket_id,
+                    )?;
+            }
+            entries.push(IntermediateVariableWidthBucketEntry {
+                min: cluster.min,
+                max: cluster.max,
+                sum: cluster.sum,
+                doc_count: cluster.doc_count,
+                sub_aggregation,
+            });
+        }
+        let histogram = IntermediateVariableWidthHistogram::new(shard_size, entries)?;
+        results.push(
+            name,
+            IntermediateAggregationResult::Bucket(
+                IntermediateBucketResult::VariableWidthHistogram(histogram),
+            ),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        parent_bucket_id: BucketId,
+        docs: &[DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        let req_data = &agg_data.per_request.variable_width_histogram_req_data[self.accessor_idx];
+        let shard_size = req_data.req.shard_size() as usize;
+        let initial_buffer = req_data.req.initial_buffer() as usize;
+        let buckets = &mut self.parent_buckets[parent_bucket_id as usize];
+        let mem_pre = buckets.get_memory_consumption();
+
+        agg_data
+            .column_block_accessor
+            .fetch_block(docs, &req_data.accessor);
+        for (doc, val) in agg_data
+            .column_block_accessor
+            .iter_docid_vals(docs, &req_data.accessor)
+        {
+            let val = f64_from_fastfield_u64(val, req_data.field_type);
+            if buckets.clusters.is_empty() {
+                buckets.buffer.push((doc, val));
+                if buckets.buffer.len() >= initial_buffer {
+                    buckets.create_clusters(
+                        shard_size,
+                        &mut self.bucket_id_provider,
+                        &mut self.sub_agg,
+                    );
+                }
+            } else {
+                buckets.collect(doc, val, &mut self.sub_agg);
+            }
+        }
+
+        let mem_delta = buckets.get_memory_consumption().saturating_sub(mem_pre);
+        if mem_delta > 0 {
+            agg_data
+                .context
+                .limits
+                .add_memory_consumed(mem_delta as u64)?;
+        }
+        if let Some(sub_agg) = &mut self.sub_agg {
+            sub_agg.check_flush_local(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, agg_data: &mut AggregationsSegmentCtx) -> crate::Result<()> {
+        let shard_size = agg_data
+            .get_variable_width_histogram_req_data(self.accessor_idx)
+            .req
+            .shard_size() as usize;
+        // Create the clusters of the parent buckets that didn't reach `initial_buffer` values.
+        for buckets in self.parent_buckets.iter_mut() {
+            if !buckets.buffer.is_empty() {
+                buckets.create_clusters(
+                    shard_size,
+                    &mut self.bucket_id_provider,
+                    &mut self.sub_agg,
+                );
+            }
+        }
+        if let Some(sub_agg) = &mut self.sub_agg {
+            sub_agg.flush(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        max_bucket: BucketId,
+        _agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        if self.parent_buckets.len() <= max_bucket as usize {
+            self.parent_buckets
+                .resize_with(max_bucket as usize + 1, Default::default);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{
+        exec_request, get_test_index_from_values, get_test_index_from_values_and_terms,
+    };
+
+    fn keys_and_doc_counts(res: &Value) -> Vec<(f64, f64, f64, u64)> {
+        res["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| {
+                (
+                    bucket["min"].as_f64().unwrap(),
+                    bucket["key"].as_f64().unwrap(),
+                    bucket["max"].as_f64().unwrap(),
+                    bucket["doc_count"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn variable_width_histogram_clusters_values() -> crate::Result<()> {
+        let values = [1.0, 2.0, 3.0, 50.0, 51.0, 100.0, 101.0, 102.0, 103.0];
+        for merge_segments in [false, true] {
+            let index = get_test_index_from_values(merge_segments, &values)?;
+            let agg_req: Aggregations = serde_json::from_value(json!({
+                "histo": {
+                    "variable_width_histogram": { "field": "score", "buckets": 3 },
+                    "aggs": { "sum": { "sum": { "field": "score" } } }
+                }
+            }))
+            .unwrap();
+            let res = exec_request(agg_req, &index)?;
+            assert_eq!(
+                keys_and_doc_counts(&res["histo"]),
+                vec![
+                    (1.0, 2.0, 3.0, 3),
+                    (50.0, 50.5, 51.0, 2),
+                    (100.0, 101.5, 103.0, 4),
+                ]
+            );
+            assert_eq!(res["histo"]["buckets"][2]["sum"]["value"], 406.0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn variable_width_histogram_after_initial_buffer() -> crate::Result<()> {
+        // A single segment, so that the values are collected in this order.
+        let values = [1.0, 100.0, 2.0, 99.0, 50.0, 3.0, 98.0, 60.0]
+            .map(|val| (val, val.to_string()))
+            .to_vec();
+        let index = get_test_index_from_values_and_terms(false, &[values])?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histo": {
+                "variable_width_histogram": {
+                    "field": "score",
+                    "buckets": 2,
+                    "shard_size": 2,
+                    "initial_buffer": 2
+                }
+            }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        // The clusters are created from the first two values, the others are added to the
+        // closest cluster.
+        assert_eq!(
+            keys_and_doc_counts(&res["histo"]),
+            vec![(1.0, 14.0, 50.0, 4), (60.0, 89.25, 100.0, 4)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn variable_width_histogram_invalid_request() -> crate::Result<()> {
+        let index = get_test_index_from_values(false, &[1.0])?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histo": {
+                "variable_width_histogram": { "field": "score", "buckets": 10, "shard_size": 5 }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"shard_size must be greater than or equal to buckets in \
+             variable_width_histogram\""
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+use std::net::{IpAddr, Ipv6Addr};
+use std::ops::Range;
+
+use columnar::column_values::CompactSpaceU64Accessor;
+use columnar::Column;
+use serde::{Deserialize, Serialize};
+
+use super::range::{fill_range_holes, InternalRangeAggregationRange};
+use crate::aggregation::AggregationError;
+use crate::schema::IntoIpv6Addr;
+
+/// Provide user-defined ip address ranges to aggregate on.
+///
+/// The ip range aggregation works like the [range aggregation](super::RangeAggregation) on an ip
+/// field. A range is either defined by `from` and `to` addresses, or by a CIDR `mask`, e.g.
+/// `10.0.0.0/25` or `2001:db8::/32`. As with the range aggregation, `from` is inclusive, `to` is
+/// exclusive, and buckets are added to cover the whole address space.
+///
+/// Buckets are identified by their mask or by `from-to`, unless a custom `key` is set. `from`
+/// and `to` are returned as `from_as_string` and `to_as_string`. IPv4 addresses are returned in
+/// their IPv4 representation.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "networks": {
+///         "field": "ip",
+///         "ranges": [
+///             { "to": "10.0.0.5" },
+///             { "from": "10.0.0.5" },
+///             { "key": "private", "mask": "192.168.0.0/16" }
+///         ]
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IpRangeAggregation {
+    /// The ip field to aggregate on.
+    pub field: String,
+    /// The ranges to aggregate on. Ranges may not overlap.
+    pub ranges: Vec<IpRangeAggregationRange>,
+    /// Whether to return the buckets as a hash map
+    #[serde(default)]
+    pub keyed: bool,
+}
+
+/// The range for one ip range bucket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IpRangeAggregationRange {
+    /// Custom key for the range bucket
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub key: Option<String>,
+    /// The from address, which is inclusive in the range.
+    /// `None` equals to an open ended interval.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub from: Option<IpAddr>,
+    /// The to address, which is not inclusive in the range.
+    /// `None` equals to an open ended interval.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub to: Option<IpAddr>,
+    /// A CIDR mask covering the range, e.g. `10.0.0.0/8`. Can't be combined with `from` or
+    /// `to`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mask: Option<String>,
+}
+
+/// A bucket of an `ip_range` aggregation, with its bounds mapped to the compact space of the ip
+/// column of a segment.
+#[derive(Clone, Debug)]
+pub(crate) struct SegmentIpRange {
+    pub(crate) key: String,
+    pub(crate) range: Range<u64>,
+    pub(crate) from: Option<String>,
+    pub(crate) to: Option<String>,
+}
+
+impl IpRangeAggregationRange {
+    fn to_internal_range(&self) -> crate::Result<InternalRangeAggregationRange<u128>> {
+        let Some(mask) = &self.mask else {
+            let start = self.from.map(|ip| u128::from(ip.into_ipv6_addr()));
+            let end = self.to.map(|ip| u128::from(ip.into_ipv6_addr()));
+            return Ok(InternalRangeAggregationRange {
+                key: self.key.clone(),
+                range: start.unwrap_or(u128::MIN)..end.unwrap_or(u128::MAX),
+            });
+        };
+        if self.from.is_some() || self.to.is_some() {
+            return Err(AggregationError::InvalidRequest(format!(
+                "ip range with mask {mask:?} can't have a from or to address"
+            ))
+            .into());
+        }
+        let range = parse_mask(mask)?;
+        Ok(InternalRangeAggregationRange {
+            key: Some(self.key.clone().unwrap_or_else(|| mask.to_string())),
+            range,
+        })
+    }
+}
+
+impl IpRangeAggregation {
+    /// Returns the buckets of the aggregation for the ip column of a segment.
+    ///
+    /// `accessor` is `None` if the segment has no ip column for the field.
+    pub(crate) fn to_segment_ranges(
+        &self,
+        accessor: Option<&CompactSpaceU64Accessor>,
+    ) -> crate::Result<Vec<SegmentIpRange>> {
+        if self.ranges.is_empty() {
+            return Err(AggregationError::InvalidRequest(
+                "ip range aggregation requires at least one range".to_string(),
+            )
+            .into());
+        }
+        let ranges = self
+            .ranges
+            .iter()
+            .map(IpRangeAggregationRange::to_internal_range)
+            .collect::<crate::Result<Vec<_>>>()?;
+        // Values in the ip column are stored in a compact space, which is specific to the
+        // segment. The mapping preserves the order, but values missing in the segment have no
+        // representation, so we map bounds to the next value present.
+        let to_compact = |val: u128| match accessor {
+            Some(accessor) => accessor.u128_to_next_compact(val),
+            None => u64::MIN,
+        };
+        let segment_ranges = fill_range_holes(ranges, u128::MIN, u128::MAX)?
+            .into_iter()
+            .map(|range| {
+                let from = (range.range.start != u128::MIN).then(|| format_ip(range.range.start));
+                let to = (range.range.end != u128::MAX).then(|| format_ip(range.range.end));
+                let start = if range.range.start == u128::MIN {
+                    u64::MIN
+                } else {
+                    to_compact(range.range.start)
+                };
+                let end = if range.range.end == u128::MAX {
+                    u64::MAX
+                } else {
+                    to_compact(range.range.end)
+                };
+                let key = range
+                    .key
+                    .unwrap_or_else(|| ip_range_to_string(from.as_deref(), to.as_deref()));
+                SegmentIpRange {
+                    key,
+                    range: start..end,
+                    from,
+                    to,
+                }
+            })
+            .collect();
+        Ok(segment_ranges)
+    }
+}
+
+/// Parses a CIDR mask into the range of addresses it covers.
+fn parse_mask(mask: &str) -> crate::Result<Range<u128>> {
+    let invalid = || AggregationError::InvalidRequest(format!("invalid ip range mask {mask:?}"));
+    let (ip, prefix_len) = mask.split_once('/').ok_or_else(invalid)?;
+    let ip: IpAddr = ip.parse().map_err(|_| invalid())?;
+    let prefix_len: u32 = prefix_len.parse().map_err(|_| invalid())?;
+    // IPv4 addresses are stored as IPv4-mapped IPv6 addresses, with a 96 bit prefix.
+    let prefix_len = match ip {
+        IpAddr::V4(_) if prefix_len <= 32 => prefix_len + 96,
+        IpAddr::V6(_) if prefix_len <= 128 => prefix_len,
+        _ => return Err(invalid().into()),
+    };
+    let host_mask = u128::MAX.checked_shr(prefix_len).unwrap_or(0);
+    let start = u128::from(ip.into_ipv6_addr()) & !host_mask;
+    // The end is exclusive, the mask covering the whole address space is open ended.
+    let end = (start | host_mask).saturating_add(1);
+    Ok(start..end)
+}
+
+fn format_ip(val: u128) -> String {
+    let ip = Ipv6Addr::from(val);
+    // Prefer to use the IPv4 representation if possible
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        ip.to_string()
+    } else {
+        ip.to_string()
+    }
+}
+
+/// Returns the key of an ip range, e.g. `10.0.0.0-10.0.0.5` or `*-10.0.0.5`.
+pub(crate) fn ip_range_to_string(from: Option<&str>, to: Option<&str>) -> String {
+    format!("{}-{}", from.unwrap_or("*"), to.unwrap_or("*"))
+}
+
+/// Returns the compact space accessor of an ip column, if the segment has one.
+pub(crate) fn get_ip_compact_space_accessor(
+    column: &Column<u64>,
+) -> Option<std::sync::Arc<CompactSpaceU64Accessor>> {
+    column
+        .values
+        .clone()
+        .downcast_arc::<CompactSpaceU64Accessor>()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::exec_request;
+    use crate::schema::{Schema, FAST};
+    use crate::{Index, IndexWriter, TantivyDocument};
+
+    #[test]
+    fn test_parse_mask() {
+        let v4 = |ip: &str| u128::from(ip.parse::<IpAddr>().unwrap().into_ipv6_addr());
+        assert_eq!(
+            parse_mask("10.0.0.0/25").unwrap(),
+            v4("10.0.0.0")..v4("10.0.0.128")
+        );
+        assert_eq!(
+            parse_mask("10.1.2.3/8").unwrap(),
+            v4("10.0.0.0")..v4("11.0.0.0")
+        );
+        assert_eq!(
+            parse_mask("2001:db8::/32").unwrap(),
+            v4("2001:db8::")..v4("2001:db9::")
+        );
+        assert_eq!(parse_mask("::/0").unwrap(), u128::MIN..u128::MAX);
+        assert!(parse_mask("10.0.0.0/33").is_err());
+        assert!(parse_mask("10.0.0.0").is_err());
+        assert!(parse_mask("10.0.0/8").is_err());
+    }
+
+    fn get_test_index(segments: &[&[&str]]) -> Index {
+        let mut schema_builder = Schema::builder();
+        let ip_field = schema_builder.add_ip_addr_field("ip", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests().unwrap();
+        for segment in segments {
+            for ip in segment.iter() {
+                let ip: IpAddr = ip.parse().unwrap();
+                let mut doc = TantivyDocument::new();
+                doc.add_ip_addr(ip_field, ip.into_ipv6_addr());
+                index_writer.add_document(doc).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index
+    }
+
+    fn exec_ip_range(index: &Index, ip_range: Value) -> Value {
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "ips": { "ip_range": ip_range }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, index).unwrap();
+        res["ips"]["buckets"].clone()
+    }
+
+    fn keys_and_doc_counts(buckets: &Value) -> Vec<(String, u64)> {
+        buckets
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| {
+                (
+                    bucket["key"].as_str().unwrap().to_string(),
+                    bucket["doc_count"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ip_range_test_from_to() {
+        let index = get_test_index(&[
+            &["10.0.0.1", "10.0.0.4", "10.0.0.200"],
+            &["10.0.0.5", "192.168.1.1", "2001:db8::1"],
+        ]);
+        let buckets = exec_ip_range(
+            &index,
+            json!({
+                "field": "ip",
+                "ranges": [
+                    { "to": "10.0.0.5" },
+                    { "from": "10.0.0.5", "to": "10.0.1.0" },
+                    { "key": "v6", "from": "2001:db8::" }
+                ]
+            }),
+        );
+        assert_eq!(
+            keys_and_doc_counts(&buckets),
+            vec![
+                ("*-10.0.0.5".to_string(), 2),
+                ("10.0.0.5-10.0.1.0".to_string(), 2),
+                ("10.0.1.0-2001:db8::".to_string(), 1),
+                ("v6".to_string(), 1),
+            ]
+        );
+        assert_eq!(buckets[1]["from_as_string"], "10.0.0.5");
+        assert_eq!(buckets[1]["to_as_string"], "10.0.1.0");
+        assert_eq!(buckets[3]["from_as_string"], "2001:db8::");
+        assert!(buckets[3].get("to_as_string").is_none());
+    }
+
+    #[test]
+    fn ip_range_test_mask_keyed() {
+        let index = get_test_index(&[
+            &["10.0.0.1", "10.0.0.127", "10.0.0.128"],
+            &["172.16.0.1", "10.0.0.2"],
+            &["192.168.1.1"],
+        ]);
+        let buckets = exec_ip_range(
+            &index,
+            json!({
+                "field": "ip",
+                "keyed": true,
+                "ranges": [
+                    { "mask": "10.0.0.0/25" },
+                    { "key": "private", "mask": "192.168.0.0/16" }
+                ]
+            }),
+        );
+        assert_eq!(buckets["10.0.0.0/25"]["doc_count"], 3);
+        assert_eq!(buckets["10.0.0.0/25"]["from_as_string"], "10.0.0.0");
+        assert_eq!(buckets["10.0.0.0/25"]["to_as_string"], "10.0.0.128");
+        assert_eq!(buckets["10.0.0.128-192.168.0.0"]["doc_count"], 2);
+        assert_eq!(buckets["private"]["doc_count"], 1);
+        assert_eq!(buckets["*-10.0.0.0"]["doc_count"], 0);
+        assert_eq!(buckets["192.169.0.0-*"]["doc_count"], 0);
+    }
+
+    #[test]
+    fn ip_range_test_invalid_req() {
+        let index = get_test_index(&[&["10.0.0.1"]]);
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "ips": { "ip_range": {
+                "field": "ip",
+                "ranges": [{ "from": "10.0.0.0", "mask": "10.0.0.0/8" }]
+            } }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"ip range with mask \\\"10.0.0.0/8\\\" can't have a from or to \
+             address\""
+        );
+    }
+}
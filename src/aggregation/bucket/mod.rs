@@ -19,21 +19,46 @@
 //! ## Supported Bucket Aggregations
 //! - [Histogram](HistogramAggregation)
 //! - [DateHistogram](DateHistogramAggregationReq)
+//! - [AutoDateHistogram](AutoDateHistogramAggregationReq)
+//! - [VariableWidthHistogram](VariableWidthHistogramAggregationReq)
 //! - [Range](RangeAggregation)
+//! - [DateRange](DateRangeAggregation)
+//! - [IpRange](IpRangeAggregation)
 //! - [Terms](TermsAggregation)
-
+//! - [Filter](FilterAggregation)
+//! - [Filters](FiltersAggregation)
+//! - [MultiTerms](MultiTermsAggregation)
+//! - [RareTerms](RareTermsAggregation)
+//! - [Global](GlobalAggregation)
+//! - [Sampler](SamplerAggregation)
+//! - [DiversifiedSampler](DiversifiedSamplerAggregation)
+
+mod date_range;
 mod filter;
+mod filters;
+mod global;
 mod histogram;
+mod ip_range;
+mod multi_terms_agg;
 mod range;
+mod rare_terms;
+mod sampler;
 mod term_agg;
 mod term_missing_agg;
 
 use std::collections::HashMap;
 use std::fmt;
 
+pub use date_range::*;
 pub use filter::*;
+pub use filters::*;
+pub use global::*;
 pub use histogram::*;
+pub use ip_range::*;
+pub use multi_terms_agg::*;
 pub use range::*;
+pub use rare_terms::*;
+pub use sampler::*;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 pub use term_agg::*;
 pub use term_missing_agg::*;
@@ -0,0 +1,801 @@
+use std::fmt::Debug;
+use std::io;
+use std::net::Ipv6Addr;
+use std::sync::Arc;
+
+use columnar::column_values::CompactSpaceU64Accessor;
+use columnar::{
+    Column, ColumnType, MonotonicallyMappableToU128, MonotonicallyMappableToU64, NumericalValue,
+    StrColumn,
+};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cut_off_buckets, get_agg_name_and_property, CustomOrder, GetDocCount, Order, OrderTarget,
+};
+use crate::aggregation::agg_data::{
+    build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
+};
+use crate::aggregation::cached_sub_aggs::HighCardCachedSubAggs;
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateBucketResult,
+    IntermediateKey, IntermediateMultiTermsBucketResult, IntermediateTermBucketEntry,
+};
+use crate::aggregation::segment_agg_result::{BucketIdProvider, SegmentAggregationCollector};
+use crate::aggregation::{format_date, AggregationError, BucketId, Key};
+use crate::TantivyError;
+
+/// Creates a bucket for every unique combination of values over several fields and counts the
+/// number of occurrences.
+///
+/// The multi terms aggregation behaves like the [terms aggregation](super::TermsAggregation),
+/// but the key of a bucket is the compound of the values of all configured sources. A document
+/// with multiple values in a field contributes to every combination of its values.
+///
+/// Documents without a value for one of the sources are ignored, unless a `missing` value is
+/// configured for that source.
+///
+/// ## Prerequisite
+/// Multi terms aggregations work only on [fast fields](`crate::fastfield`) of type `u64`, `f64`,
+/// `i64`, `bool`, `date`, `ip` and text.
+///
+/// ## Document count error
+/// Like for the terms aggregation, results from one segment are cut off at `segment_size`, so
+/// `doc_count` values may be approximate on indices with multiple segments. See
+/// [`TermsAggregation`](super::TermsAggregation) for details.
+///
+/// # Limitations/Compatibility
+///
+/// `min_doc_count: 0` does not create buckets for combinations that don't occur in the index.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "genres_and_products": {
+///         "multi_terms": {
+///             "terms": [
+///                 { "field": "genre" },
+///                 { "field": "product", "missing": "N/A" }
+///             ]
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Response JSON Format
+/// ```json
+/// {
+///     ...
+///     "aggregations": {
+///         "genres_and_products": {
+///             "doc_count_error_upper_bound": 0,
+///             "sum_other_doc_count": 0,
+///             "buckets": [
+///                 { "key": ["rock", "Product A"], "key_as_string": "rock|Product A", "doc_count": 2 },
+///                 { "key": ["jazz", "Product B"], "key_as_string": "jazz|Product B", "doc_count": 1 }
+///             ]
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MultiTermsAggregation {
+    /// The value sources the compound key is built from. At least two sources are required.
+    pub terms: Vec<MultiTermsSource>,
+    /// By default, the top 10 combinations with the most documents are returned.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size: Option<u32>,
+
+    /// To get more accurate results, we fetch more than `size` from each segment.
+    ///
+    /// Defaults to 10 * size.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[serde(alias = "shard_size")]
+    #[serde(alias = "split_size")]
+    pub segment_size: Option<u32>,
+
+    /// Include `doc_count_error_upper_bound` in the result.
+    ///
+    /// Defaults to true when ordering by count desc.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub show_term_doc_count_error: Option<bool>,
+
+    /// Filter all buckets that are lower than `min_doc_count`. Defaults to 1.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min_doc_count: Option<u64>,
+
+    /// Set the order. `String` is here a target, which is either "_count", "_key", or the name of
+    /// a metric sub_aggregation.
+    ///
+    /// Ordering by `_key` compares the compound keys value by value.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub order: Option<CustomOrder>,
+}
+
+/// A value source of the [`MultiTermsAggregation`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MultiTermsSource {
+    /// The field to read the values from.
+    pub field: String,
+    /// The value used for documents without a value in `field`.
+    ///
+    /// By default such documents are ignored by the aggregation.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub missing: Option<Key>,
+}
+
+impl MultiTermsAggregation {
+    /// Returns the names of the fields used by the aggregation.
+    pub fn field_names(&self) -> Vec<&str> {
+        self.terms
+            .iter()
+            .map(|source| source.field.as_str())
+            .collect()
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.terms.len() < 2 {
+            return Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(format!(
+                    "multi_terms aggregation requires at least two sources, but got {}",
+                    self.terms.len()
+                )),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn size(&self) -> u32 {
+        self.size.unwrap_or(10)
+    }
+
+    pub(crate) fn segment_size(&self) -> u32 {
+        self.segment_size
+            .unwrap_or(self.size() * 10)
+            .max(self.size())
+    }
+
+    pub(crate) fn min_doc_count(&self) -> u64 {
+        self.min_doc_count.unwrap_or(1)
+    }
+
+    pub(crate) fn order(&self) -> CustomOrder {
+        self.order.clone().unwrap_or_default()
+    }
+
+    pub(crate) fn show_term_doc_count_error(&self) -> bool {
+        self.show_term_doc_count_error
+            .unwrap_or_else(|| self.order() == CustomOrder::default())
+    }
+}
+
+/// The fast field columns of a single [`MultiTermsSource`] on a segment.
+#[derive(Debug, Clone)]
+pub struct MultiTermsSourceAccessor {
+    /// All columns of the field, there can be more than one for JSON fields.
+    pub columns: Vec<(Column<u64>, ColumnType)>,
+    /// The string dictionary column if the field has a text column.
+    pub str_dict_column: Option<StrColumn>,
+    /// The value used for documents without a value.
+    pub missing: Option<Key>,
+}
+
+/// Contains all information required by the SegmentMultiTermsCollector to perform the
+/// multi terms aggregation on a segment.
+#[derive(Debug, Clone)]
+pub struct MultiTermsAggReqData {
+    /// The name of the aggregation.
+    pub name: String,
+    /// The multi terms aggregation request.
+    pub req: MultiTermsAggregation,
+    /// One accessor per source of the request.
+    pub sources: Vec<MultiTermsSourceAccessor>,
+}
+
+impl MultiTermsAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.sources.len() * std::mem::size_of::<MultiTermsSourceAccessor>()
+    }
+}
+
+/// Column ordinal in a segment compound key for a source without value.
+const MISSING_COLUMN_ORD: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct MultiTermsBucket {
+    count: u32,
+    bucket_id: BucketId,
+}
+
+/// Compound keys on a segment are encoded as `[column_ord, value]` pairs, one pair per source.
+type SegmentCompoundKey = Box<[u64]>;
+
+impl GetDocCount for (SegmentCompoundKey, MultiTermsBucket) {
+    fn doc_count(&self) -> u64 {
+        self.1.count as u64
+    }
+}
+
+impl GetDocCount for (Vec<IntermediateKey>, MultiTermsBucket) {
+    fn doc_count(&self) -> u64 {
+        self.1.count as u64
+    }
+}
+
+pub(crate) fn build_segment_multi_terms_collector(
+    req_data: &mut AggregationsSegmentCtx,
+    node: &AggRefNode,
+) -> crate::Result<Box<dyn SegmentAggregationCollector>> {
+    let multi_terms_req_data = req_data
+        .get_multi_terms_req_data(node.idx_in_req_data)
+        .clone();
+
+    if let OrderTarget::SubAggregation(sub_agg_name) = &multi_terms_req_data.req.order().target {
+        let (agg_name, _agg_property) = get_agg_name_and_property(sub_agg_name);
+        node.get_sub_agg(agg_name, &req_data.per_request)
+            .ok_or_else(|| {
+                TantivyError::InvalidArgument(format!(
+                    "could not find aggregation with name {agg_name} in metric sub_aggregations"
+                ))
+            })?;
+    }
+
+    let sub_agg = if node.children.is_empty() {
+        None
+    } else {
+        Some(HighCardCachedSubAggs::new(build_segment_agg_collectors(
+            req_data,
+            &node.children,
+        )?))
+    };
+    let num_sources = multi_terms_req_data.sources.len();
+    Ok(Box::new(SegmentMultiTermsCollector {
+        parent_buckets: Vec::new(),
+        sub_agg,
+        bucket_id_provider: BucketIdProvider::default(),
+        req_data: multi_terms_req_data,
+        source_values: vec![Vec::new(); num_sources],
+        key_buffer: Vec::with_capacity(num_sources * 2),
+    }))
+}
+
+#[derive(Debug)]
+struct SegmentMultiTermsCollector {
+    /// The compound key buckets per parent bucket.
+    parent_buckets: Vec<FxHashMap<SegmentCompoundKey, MultiTermsBucket>>,
+    sub_agg: Option<HighCardCachedSubAggs>,
+    bucket_id_provider: BucketIdProvider,
+    req_data: MultiTermsAggReqData,
+    /// Reused buffer for the `(column_ord, value)` pairs of the current doc, one vec per source.
+    source_values: Vec<Vec<(u64, u64)>>,
+    /// Reused buffer for the compound key.
+    key_buffer: Vec<u64>,
+}
+
+impl SegmentAggregationCollector for SegmentMultiTermsCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        bucket: BucketId,
+    ) -> crate::Result<()> {
+        self.prepare_max_bucket(bucket, agg_data)?;
+        let buckets = std::mem::take(&mut self.parent_buckets[bucket as usize]);
+        let bucket = self.build_intermediate_bucket_result(buckets, agg_data)?;
+        results.push(
+            self.req_data.name.clone(),
+            IntermediateAggregationResult::Bucket(bucket),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        parent_bucket_id: BucketId,
+        docs: &[crate::DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        let mem_pre = self.get_memory_consumption();
+
+        let buckets = &mut self.parent_buckets[parent_bucket_id as usize];
+        let bucket_id_provider = &mut self.bucket_id_provider;
+        let mut sub_agg = self.sub_agg.as_mut();
+        'docs: for &doc in docs {
+            for (source, values) in self
+                .req_data
+                .sources
+                .iter()
+                .zip(self.source_values.iter_mut())
+            {
+                values.clear();
+                for (column_ord, (column, _column_type)) in source.columns.iter().enumerate() {
+                    values.extend(
+                        column
+                            .values_for_doc(doc)
+                            .map(|val| (column_ord as u64, val)),
+                    );
+                }
+                if values.is_empty() {
+                    if source.missing.is_none() {
+                        continue 'docs;
+                    }
+                    values.push((MISSING_COLUMN_ORD, 0));
+                }
+            }
+            for_each_compound_key(&self.source_values, &mut self.key_buffer, &mut |key| {
+                let bucket_id = match buckets.get_mut(key) {
+                    Some(bucket) => {
+                        bucket.count += 1;
+                        bucket.bucket_id
+                    }
+                    None => {
+                        let bucket_id = bucket_id_provider.next_bucket_id();
+                        buckets.insert(
+                            key.into(),
+                            MultiTermsBucket {
+                                count: 1,
+                                bucket_id,
+                            },
+                        );
+                        bucket_id
+                    }
+                };
+                if let Some(sub_agg) = sub_agg.as_mut() {
+                    sub_agg.push(bucket_id, doc);
+                }
+            });
+        }
+
+        let mem_delta = self.get_memory_consumption() - mem_pre;
+        if mem_delta > 0 {
+            agg_data
+                .context
+                .limits
+                .add_memory_consumed(mem_delta as u64)?;
+        }
+        if let Some(sub_agg) = &mut self.sub_agg {
+            sub_agg.check_flush_local(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, agg_data: &mut AggregationsSegmentCtx) -> crate::Result<()> {
+        if let Some(sub_agg) = &mut self.sub_agg {
+            sub_agg.flush(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        max_bucket: BucketId,
+        _agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        while self.parent_buckets.len() <= max_bucket as usize {
+            self.parent_buckets.push(FxHashMap::default());
+        }
+        Ok(())
+    }
+}
+
+/// Calls `cb` for every combination of the values of the sources.
+fn for_each_compound_key(
+    source_values: &[Vec<(u64, u64)>],
+    key: &mut Vec<u64>,
+    cb: &mut impl FnMut(&[u64]),
+) {
+    let Some((values, remaining_sources)) = source_values.split_first() else {
+        cb(key);
+        return;
+    };
+    for &(column_ord, val) in values {
+        key.push(column_ord);
+        key.push(val);
+        for_each_compound_key(remaining_sources, key, cb);
+        key.truncate(key.len() - 2);
+    }
+}
+
+impl SegmentMultiTermsCollector {
+    fn get_memory_consumption(&self) -> usize {
+        let entry_size = std::mem::size_of::<(SegmentCompoundKey, MultiTermsBucket)>()
+            + self.req_data.sources.len() * 2 * std::mem::size_of::<u64>();
+        self.parent_buckets
+            .iter()
+            .map(|buckets| buckets.capacity() * entry_size)
+            .sum()
+    }
+
+    fn build_intermediate_bucket_result(
+        &mut self,
+        buckets: FxHashMap<SegmentCompoundKey, MultiTermsBucket>,
+        agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<IntermediateBucketResult> {
+        let req = &self.req_data.req;
+        let order = req.order();
+        let segment_size = req.segment_size() as usize;
+
+        let mut entries: Vec<(SegmentCompoundKey, MultiTermsBucket)> =
+            buckets.into_iter().collect();
+        let (doc_count_error_upper_bound, sum_other_doc_count, entries) = match order.target {
+            OrderTarget::Count => {
+                if order.order == Order::Desc {
+                    entries.sort_unstable_by_key(|entry| std::cmp::Reverse(entry.1.count));
+                } else {
+                    entries.sort_unstable_by_key(|entry| entry.1.count);
+                }
+                let (doc_count_error_upper_bound, sum_other_doc_count) =
+                    cut_off_buckets(&mut entries, segment_size);
+                let entries = self.resolve_keys(entries)?;
+                (doc_count_error_upper_bound, sum_other_doc_count, entries)
+            }
+            OrderTarget::Key => {
+                // Segment local values are not comparable across columns, so the keys need to be
+                // resolved before sorting.
+                let mut entries = self.resolve_keys(entries)?;
+                entries.sort_by(|left, right| {
+                    let ordering = left
+                        .0
+                        .partial_cmp(&right.0)
+                        .unwrap_or(std::cmp::Ordering::Equal);
+                    if order.order == Order::Desc {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                });
+                let (doc_count_error_upper_bound, sum_other_doc_count) =
+                    cut_off_buckets(&mut entries, segment_size);
+                (doc_count_error_upper_bound, sum_other_doc_count, entries)
+            }
+            OrderTarget::SubAggregation(_) => {
+                // Like the terms aggregation, we don't cut off when ordering by a
+                // sub_aggregation.
+                (0, 0, self.resolve_keys(entries)?)
+            }
+        };
+
+        let mut dict: FxHashMap<Vec<IntermediateKey>, IntermediateTermBucketEntry> =
+            FxHashMap::default();
+        dict.reserve(entries.len());
+        for (key, bucket) in entries {
+            let mut sub_aggregation = IntermediateAggregationResults::default();
+            if let Some(sub_agg) = &mut self.sub_agg {
+                sub_agg
+                    .get_sub_agg_collector()
+                    .add_intermediate_aggregation_result(
+                        agg_data,
+                        &mut sub_aggregation,
+                        bucket.bucket_id,
+                    )?;
+            }
+            // Different segment keys can resolve to the same key, e.g. the same number stored in
+            // an i64 and a f64 column of a JSON field.
+            let entry = dict.entry(key).or_default();
+            entry.doc_count += bucket.count;
+            entry.sub_aggregation.merge_fruits(sub_aggregation)?;
+        }
+
+        Ok(IntermediateBucketResult::MultiTerms {
+            buckets: IntermediateMultiTermsBucketResult {
+                entries: dict,
+                sum_other_doc_count,
+                doc_count_error_upper_bound,
+            },
+        })
+    }
+
+    /// Converts the segment local compound keys into intermediate keys.
+    fn resolve_keys(
+        &self,
+        entries: Vec<(SegmentCompoundKey, MultiTermsBucket)>,
+    ) -> crate::Result<Vec<(Vec<IntermediateKey>, MultiTermsBucket)>> {
+        let sources = &self.req_data.sources;
+        let mut resolved: Vec<Vec<IntermediateKey>> = (0..entries.len())
+            .map(|_| Vec::with_capacity(sources.len()))
+            .collect();
+        for (source_ord, source) in sources.iter().enumerate() {
+            let column_ord_pos = source_ord * 2;
+            let terms = resolve_str_terms(source, &entries, column_ord_pos)?;
+            let ip_accessors = source
+                .columns
+                .iter()
+                .map(|(column, column_type)| {
+                    if *column_type != ColumnType::IpAddr {
+                        return Ok(None);
+                    }
+                    column
+                        .values
+                        .clone()
+                        .downcast_arc::<CompactSpaceU64Accessor>()
+                        .map(Some)
+                        .map_err(|_| {
+                            TantivyError::AggregationError(AggregationError::InternalError(
+                                "Type mismatch: Could not downcast to CompactSpaceU64Accessor"
+                                    .to_string(),
+                            ))
+                        })
+                })
+                .collect::<crate::Result<Vec<Option<Arc<CompactSpaceU64Accessor>>>>>()?;
+
+            for ((raw_key, _bucket), key) in entries.iter().zip(resolved.iter_mut()) {
+                let column_ord = raw_key[column_ord_pos];
+                let val = raw_key[column_ord_pos + 1];
+                let key_part = if column_ord == MISSING_COLUMN_ORD {
+                    source
+                        .missing
+                        .clone()
+                        .expect("missing column ord without missing value")
+                        .into()
+                } else {
+                    let column_type = source.columns[column_ord as usize].1;
+                    match column_type {
+                        ColumnType::Str => IntermediateKey::Str(
+                            terms
+                                .get(&val)
+                                .cloned()
+                                .expect("term ord was resolved from the dictionary"),
+                        ),
+                        ColumnType::IpAddr => {
+                            let accessor = ip_accessors[column_ord as usize]
+                                .as_ref()
+                                .expect("ip accessor for ip column");
+                            let val: u128 = accessor.compact_to_u128(val as u32);
+                            IntermediateKey::IpAddr(Ipv6Addr::from_u128(val))
+                        }
+                        ColumnType::DateTime => {
+                            IntermediateKey::Str(format_date(i64::from_u64(val))?)
+                        }
+                        ColumnType::Bool => IntermediateKey::Bool(bool::from_u64(val)),
+                        ColumnType::U64 => IntermediateKey::U64(val),
+                        ColumnType::I64 => IntermediateKey::I64(i64::from_u64(val)),
+                        ColumnType::F64 => {
+                            let val: NumericalValue = f64::from_u64(val).into();
+                            match val.normalize() {
+                                NumericalValue::U64(val) => IntermediateKey::U64(val),
+                                NumericalValue::I64(val) => IntermediateKey::I64(val),
+                                NumericalValue::F64(val) => IntermediateKey::F64(val),
+                            }
+                        }
+                        ColumnType::Bytes => {
+                            return Err(TantivyError::InvalidArgument(format!(
+                                "multi_terms aggregation is not supported for column type \
+                                 {column_type:?}"
+                            )));
+                        }
+                    }
+                };
+                key.push(key_part);
+            }
+        }
+        Ok(resolved
+            .into_iter()
+            .zip(entries)
+            .map(|(key, (_raw_key, bucket))| (key, bucket))
+            .collect())
+    }
+}
+
+/// Looks up the terms of the string column ordinals used by `entries` in a single sorted pass
+/// over the dictionary.
+fn resolve_str_terms(
+    source: &MultiTermsSourceAccessor,
+    entries: &[(SegmentCompoundKey, MultiTermsBucket)],
+    column_ord_pos: usize,
+) -> crate::Result<FxHashMap<u64, String>> {
+    let mut terms = FxHashMap::default();
+    let Some(str_column) = source.str_dict_column.as_ref() else {
+        return Ok(terms);
+    };
+    let Some(str_column_ord) = source
+        .columns
+        .iter()
+        .position(|(_column, column_type)| *column_type == ColumnType::Str)
+    else {
+        return Ok(terms);
+    };
+    let mut term_ords: Vec<u64> = entries
+        .iter()
+        .filter(|(raw_key, _)| raw_key[column_ord_pos] == str_column_ord as u64)
+        .map(|(raw_key, _)| raw_key[column_ord_pos + 1])
+        .collect();
+    term_ords.sort_unstable();
+    term_ords.dedup();
+    let mut term_ords_it = term_ords.iter();
+    str_column
+        .dictionary()
+        .sorted_ords_to_term_cb(term_ords.iter().copied(), |term| {
+            let term_ord = term_ords_it.next().expect("one callback per term ord");
+            let term = String::from_utf8(term.to_vec()).map_err(io::Error::other)?;
+            terms.insert(*term_ord, term);
+            Ok(())
+        })?;
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::exec_request;
+    use crate::indexer::NoMergePolicy;
+    use crate::schema::{Schema, FAST, STRING};
+    use crate::{Index, IndexWriter};
+
+    fn get_multi_terms_test_index(merge_segments: bool) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let genre = schema_builder.add_text_field("genre", STRING | FAST);
+        let product = schema_builder.add_text_field("product", STRING | FAST);
+        let year = schema_builder.add_u64_field("year", FAST);
+        let price = schema_builder.add_f64_field("price", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer: IndexWriter = index.writer_for_tests()?;
+            index_writer.set_merge_policy(Box::new(NoMergePolicy));
+            index_writer.add_document(doc!(
+                genre => "rock", product => "A", year => 2020u64, price => 1.0f64
+            ))?;
+            index_writer.add_document(doc!(
+                genre => "rock", product => "A", year => 2021u64, price => 3.0f64
+            ))?;
+            index_writer.add_document(doc!(genre => "jazz", product => "B", year => 2020u64))?;
+            index_writer.commit()?;
+            index_writer.add_document(doc!(
+                genre => "rock", product => "A", year => 2020u64, price => 5.0f64
+            ))?;
+            index_writer.add_document(doc!(genre => "rock", product => "B", year => 2020u64))?;
+            index_writer.add_document(doc!(genre => "jazz", year => 2021u64))?;
+            index_writer.add_document(doc!(
+                genre => "jazz", genre => "rock", product => "C", year => 2022u64
+            ))?;
+            index_writer.commit()?;
+        }
+        if merge_segments {
+            let segment_ids = index.searchable_segment_ids()?;
+            let mut index_writer: IndexWriter = index.writer_for_tests()?;
+            index_writer.merge(&segment_ids).wait()?;
+            index_writer.wait_merging_threads()?;
+        }
+        Ok(index)
+    }
+
+    fn multi_terms_test(merge_segments: bool) -> crate::Result<()> {
+        let index = get_multi_terms_test_index(merge_segments)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "genre_product": {
+                "multi_terms": {
+                    "terms": [{ "field": "genre" }, { "field": "product" }]
+                },
+                "aggs": { "avg_price": { "avg": { "field": "price" } } }
+            }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["genre_product"]["buckets"],
+            json!([
+                {
+                    "key": ["rock", "A"], "key_as_string": "rock|A", "doc_count": 3,
+                    "avg_price": { "value": 3.0 }
+                },
+                {
+                    "key": ["jazz", "B"], "key_as_string": "jazz|B", "doc_count": 1,
+                    "avg_price": { "value": null }
+                },
+                {
+                    "key": ["jazz", "C"], "key_as_string": "jazz|C", "doc_count": 1,
+                    "avg_price": { "value": null }
+                },
+                {
+                    "key": ["rock", "B"], "key_as_string": "rock|B", "doc_count": 1,
+                    "avg_price": { "value": null }
+                },
+                {
+                    "key": ["rock", "C"], "key_as_string": "rock|C", "doc_count": 1,
+                    "avg_price": { "value": null }
+                }
+            ])
+        );
+        assert_eq!(res["genre_product"]["sum_other_doc_count"], 0);
+        assert_eq!(res["genre_product"]["doc_count_error_upper_bound"], 0);
+        Ok(())
+    }
+
+    #[test]
+    fn multi_terms_aggregation_test() -> crate::Result<()> {
+        multi_terms_test(false)
+    }
+
+    #[test]
+    fn multi_terms_aggregation_test_merged_segment() -> crate::Result<()> {
+        multi_terms_test(true)
+    }
+
+    #[test]
+    fn multi_terms_aggregation_missing_numeric_and_order() -> crate::Result<()> {
+        let index = get_multi_terms_test_index(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "year_product": {
+                "multi_terms": {
+                    "terms": [
+                        { "field": "year" },
+                        { "field": "product", "missing": "none" }
+                    ],
+                    "order": { "_key": "desc" },
+                    "size": 3
+                }
+            }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["year_product"]["buckets"],
+            json!([
+                { "key": [2022, "C"], "key_as_string": "2022|C", "doc_count": 1 },
+                { "key": [2021, "none"], "key_as_string": "2021|none", "doc_count": 1 },
+                { "key": [2021, "A"], "key_as_string": "2021|A", "doc_count": 1 }
+            ])
+        );
+        assert_eq!(res["year_product"]["sum_other_doc_count"], 4);
+        Ok(())
+    }
+
+    #[test]
+    fn multi_terms_aggregation_order_by_sub_agg_and_min_doc_count() -> crate::Result<()> {
+        let index = get_multi_terms_test_index(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "genre_year": {
+                "multi_terms": {
+                    "terms": [{ "field": "genre" }, { "field": "year" }],
+                    "order": { "max_price": "asc" },
+                    "min_doc_count": 2
+                },
+                "aggs": { "max_price": { "max": { "field": "price" } } }
+            }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["genre_year"]["buckets"],
+            json!([
+                {
+                    "key": ["rock", 2020], "key_as_string": "rock|2020", "doc_count": 3,
+                    "max_price": { "value": 5.0 }
+                }
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn multi_terms_aggregation_invalid_request() -> crate::Result<()> {
+        let index = get_multi_terms_test_index(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "genre": { "multi_terms": { "terms": [{ "field": "genre" }] } }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("multi_terms aggregation requires at least two sources"));
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "genre_product": {
+                "multi_terms": {
+                    "terms": [{ "field": "genre" }, { "field": "product" }],
+                    "order": { "unknown": "asc" }
+                }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("could not find aggregation with name unknown"));
+        Ok(())
+    }
+}
@@ -9,6 +9,7 @@ use crate::aggregation::agg_data::{
     build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
 };
 use crate::aggregation::agg_limits::AggregationLimitsGuard;
+use crate::aggregation::bucket::ip_range::{ip_range_to_string, SegmentIpRange};
 use crate::aggregation::cached_sub_aggs::{
     CachedSubAggs, HighCardSubAggCache, LowCardCachedSubAggs, LowCardSubAggCache, SubAggCache,
 };
@@ -29,6 +30,9 @@ pub struct RangeAggReqData {
     pub field_type: ColumnType,
     /// The range aggregation request.
     pub req: RangeAggregation,
+    /// The buckets of an `ip_range` aggregation, whose bounds can't be expressed in the range
+    /// aggregation request. `None` for other range aggregations.
+    pub(crate) ip_ranges: Option<Vec<SegmentIpRange>>,
     /// The name of the aggregation.
     pub name: String,
     /// Whether this is a top-level aggregation.
@@ -40,6 +44,13 @@ impl RangeAggReqData {
     pub fn get_memory_consumption(&self) -> usize {
         std::mem::size_of::<Self>()
     }
+
+    fn num_ranges(&self) -> usize {
+        match &self.ip_ranges {
+            Some(ip_ranges) => ip_ranges.len(),
+            None => self.req.ranges.len(),
+        }
+    }
 }
 
 /// Provide user-defined buckets to aggregate on.
@@ -133,16 +144,16 @@ impl From<Range<f64>> for RangeAggregationRange {
 }
 
 #[derive(Clone, Debug, PartialEq)]
-/// Internally used u64 range for one range bucket.
-pub(crate) struct InternalRangeAggregationRange {
+/// Internally used range for one range bucket, `u64` unless the values are wider.
+pub(crate) struct InternalRangeAggregationRange<T = u64> {
     /// Custom key for the range bucket
-    key: Option<String>,
-    /// `u64` range value
-    range: Range<u64>,
+    pub(crate) key: Option<String>,
+    /// The range value
+    pub(crate) range: Range<T>,
 }
 
-impl From<Range<u64>> for InternalRangeAggregationRange {
-    fn from(range: Range<u64>) -> Self {
+impl<T> From<Range<T>> for InternalRangeAggregationRange<T> {
+    fn from(range: Range<T>) -> Self {
         InternalRangeAggregationRange { key: None, range }
     }
 }
@@ -201,6 +212,10 @@ pub(crate) struct SegmentRangeBucketEntry {
     /// The to range of the bucket. Equals `f64::MAX` when `None`. Open interval, `to` is not
     /// inclusive.
     pub to: Option<f64>,
+    /// The string representation of the from range, for bounds that are not numbers.
+    pub from_as_string: Option<String>,
+    /// The string representation of the to range, for bounds that are not numbers.
+    pub to_as_string: Option<String>,
 }
 
 impl Debug for SegmentRangeBucketEntry {
@@ -210,6 +225,8 @@ impl Debug for SegmentRangeBucketEntry {
             .field("doc_count", &self.doc_count)
             .field("from", &self.from)
             .field("to", &self.to)
+            .field("from_as_string", &self.from_as_string)
+            .field("to_as_string", &self.to_as_string)
             .finish()
     }
 }
@@ -225,6 +242,8 @@ impl SegmentRangeBucketEntry {
             sub_aggregation_res: sub_aggregation,
             from: self.from,
             to: self.to,
+            from_as_string: self.from_as_string,
+            to_as_string: self.to_as_string,
         })
     }
 }
@@ -248,6 +267,16 @@ impl<C: SubAggCache> SegmentAggregationCollector for SegmentRangeCollector<C> {
         let buckets: FxHashMap<SerializedKey, IntermediateRangeBucketEntry> = buckets
             .into_iter()
             .map(|range_bucket| {
+                // The u64 values of ip columns are specific to the segment, so ip ranges are
+                // identified by their addresses instead.
+                let serialized_key = if field_type == ColumnType::IpAddr {
+                    ip_range_to_string(
+                        range_bucket.bucket.from_as_string.as_deref(),
+                        range_bucket.bucket.to_as_string.as_deref(),
+                    )
+                } else {
+                    range_to_string(&range_bucket.range, &field_type)?
+                };
                 let bucket_id = range_bucket.bucket.bucket_id;
                 let mut agg = range_bucket.bucket.into_intermediate_bucket_entry()?;
                 if let Some(sub_aggregation) = &mut self.sub_agg {
@@ -259,7 +288,7 @@ impl<C: SubAggCache> SegmentAggregationCollector for SegmentRangeCollector<C> {
                             bucket_id,
                         )?;
                 }
-                Ok((range_to_string(&range_bucket.range, &field_type)?, agg))
+                Ok((serialized_key, agg))
             })
             .collect::<crate::Result<_>>()?;
 
@@ -341,7 +370,7 @@ pub(crate) fn build_segment_range_collector(
     // TODO: A better metric instead of is_top_level would be the number of buckets expected.
     // E.g. If range agg is not top level, but the parent is a bucket agg with less than 10 buckets,
     // we can are still in low cardinality territory.
-    let is_low_card = req_data.is_top_level && req_data.req.ranges.len() <= 64;
+    let is_low_card = req_data.is_top_level && req_data.num_ranges() <= 64;
 
     let sub_agg = if !node.children.is_empty() {
         Some(build_segment_agg_collectors(agg_data, &node.children)?)
@@ -377,6 +406,9 @@ impl<C: SubAggCache> SegmentRangeCollector<C> {
     ) -> crate::Result<Vec<SegmentRangeAndBucketEntry>> {
         let field_type = self.column_type;
         let req_data = agg_data.get_range_req_data(self.accessor_idx);
+        if let Some(ip_ranges) = &req_data.ip_ranges {
+            return self.create_new_ip_buckets(ip_ranges);
+        }
         // The range input on the request is f64.
         // We need to convert to u64 ranges, because we read the values as u64.
         // The mapping from the conversion is monotonic so ordering is preserved.
@@ -409,6 +441,8 @@ impl<C: SubAggCache> SegmentRangeCollector<C> {
                         key,
                         from,
                         to,
+                        from_as_string: None,
+                        to_as_string: None,
                     },
                 })
             })
@@ -419,12 +453,40 @@ impl<C: SubAggCache> SegmentRangeCollector<C> {
         )?;
         Ok(buckets)
     }
+
+    /// Creates the buckets of an `ip_range` aggregation, whose ranges are already resolved
+    /// for the segment.
+    fn create_new_ip_buckets(
+        &mut self,
+        ip_ranges: &[SegmentIpRange],
+    ) -> crate::Result<Vec<SegmentRangeAndBucketEntry>> {
+        let buckets: Vec<_> = ip_ranges
+            .iter()
+            .map(|ip_range| SegmentRangeAndBucketEntry {
+                range: ip_range.range.clone(),
+                bucket: SegmentRangeBucketEntry {
+                    doc_count: 0,
+                    bucket_id: self.bucket_id_provider.next_bucket_id(),
+                    key: Key::Str(ip_range.key.clone()),
+                    from: None,
+                    to: None,
+                    from_as_string: ip_range.from.clone(),
+                    to_as_string: ip_range.to.clone(),
+                },
+            })
+            .collect();
+
+        self.limits.add_memory_consumed(
+            buckets.len() as u64 * std::mem::size_of::<SegmentRangeAndBucketEntry>() as u64,
+        )?;
+        Ok(buckets)
+    }
 }
 #[inline]
 fn get_bucket_pos(val: u64, buckets: &[SegmentRangeAndBucketEntry]) -> usize {
-    let pos = buckets
-        .binary_search_by_key(&val, |probe| probe.range.start)
-        .unwrap_or_else(|pos| pos - 1);
+    // Ranges that contain no values of the segment can be empty, so we pick the last range
+    // starting at or before the value.
+    let pos = buckets.partition_point(|probe| probe.range.start <= val) - 1;
     debug_assert!(buckets[pos].range.contains(&val));
     pos
 }
@@ -471,23 +533,32 @@ fn extend_validate_ranges(
     buckets: &[RangeAggregationRange],
     field_type: &ColumnType,
 ) -> crate::Result<Vec<InternalRangeAggregationRange>> {
-    let mut converted_buckets = buckets
+    let converted_buckets = buckets
         .iter()
         .map(|range| to_u64_range(range, field_type))
         .collect::<crate::Result<Vec<_>>>()?;
+    fill_range_holes(converted_buckets, u64::MIN, u64::MAX)
+}
 
+/// Extends the provided buckets to contain the whole value range `min..max`, by inserting
+/// buckets at the beginning and end and filling gaps.
+pub(crate) fn fill_range_holes<T: Copy + Ord + Debug>(
+    mut converted_buckets: Vec<InternalRangeAggregationRange<T>>,
+    min: T,
+    max: T,
+) -> crate::Result<Vec<InternalRangeAggregationRange<T>>> {
     converted_buckets.sort_by_key(|bucket| bucket.range.start);
-    if converted_buckets[0].range.start != u64::MIN {
-        converted_buckets.insert(0, (u64::MIN..converted_buckets[0].range.start).into());
+    if converted_buckets[0].range.start != min {
+        converted_buckets.insert(0, (min..converted_buckets[0].range.start).into());
     }
 
-    if converted_buckets[converted_buckets.len() - 1].range.end != u64::MAX {
+    if converted_buckets[converted_buckets.len() - 1].range.end != max {
         converted_buckets
-            .push((converted_buckets[converted_buckets.len() - 1].range.end..u64::MAX).into());
+            .push((converted_buckets[converted_buckets.len() - 1].range.end..max).into());
     }
 
     // fill up holes in the ranges
-    let find_hole = |converted_buckets: &[InternalRangeAggregationRange]| {
+    let find_hole = |converted_buckets: &[InternalRangeAggregationRange<T>]| {
         for (pos, ranges) in converted_buckets.windows(2).enumerate() {
             if ranges[0].range.end > ranges[1].range.start {
                 return Err(TantivyError::InvalidArgument(format!(
@@ -588,6 +659,8 @@ mod tests {
                         key,
                         from,
                         to,
+                        from_as_string: None,
+                        to_as_string: None,
                         bucket_id: 0,
                     },
                 }
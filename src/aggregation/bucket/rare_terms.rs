@@ -0,0 +1,675 @@
+use std::hash::{Hash, Hasher};
+
+use fnv::FnvHasher;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+
+use super::{CustomOrder, IncludeExcludeParam, Order, OrderTarget, TermsAggregation};
+use crate::aggregation::agg_data::{
+    build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
+};
+use crate::aggregation::agg_req::Aggregations;
+use crate::aggregation::agg_result::{BucketEntry, BucketResult};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateBucketResult,
+    IntermediateKey, IntermediateTermBucketEntry, IntermediateTermBucketResult,
+};
+use crate::aggregation::segment_agg_result::{AggregationLimitsGuard, SegmentAggregationCollector};
+use crate::aggregation::{AggregationError, BucketId, Key};
+use crate::{DocId, TantivyError};
+
+fn default_max_doc_count() -> u64 {
+    1
+}
+
+fn default_precision() -> f64 {
+    0.001
+}
+
+/// The largest accepted `max_doc_count`.
+const MAX_MAX_DOC_COUNT: u64 = 100;
+/// The smallest accepted `precision`.
+const MIN_PRECISION: f64 = 0.00001;
+
+/// The rare terms aggregation finds the terms of a field which occur in at most `max_doc_count`
+/// documents, the "long tail" of the field.
+///
+/// Ordering a [terms aggregation](super::TermsAggregation) by ascending `_count` is unreliable:
+/// a term that is rare in one segment may be frequent in another segment, but cut off there.
+/// Instead, the rare terms aggregation counts all terms of a segment, keeps the terms with a
+/// count up to `max_doc_count` and remembers all other terms in a filter. When the results of
+/// segments are merged, a term is dropped if its total count exceeds `max_doc_count` or if it is
+/// in the filter of another segment.
+///
+/// The filter is exact for the first 10,000 frequent terms. For more frequent terms, it uses
+/// bloom filters with a false positive rate of `precision`. A false positive drops a rare term
+/// from the result, so the aggregation may miss some rare terms, but never returns a term that
+/// is not rare.
+///
+/// ## Prerequisite
+/// Rare terms aggregations work only on [fast fields](`crate::fastfield`) of the same types as
+/// the terms aggregation.
+///
+/// ## Memory
+/// Like a terms aggregation with a large `segment_size`, all terms of a segment are counted. The
+/// frequent terms are then kept in the filter, which is much smaller than the term buckets.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "rare_merchants": {
+///         "rare_terms": {
+///             "field": "merchant",
+///             "max_doc_count": 2,
+///             "exclude": ["test-merchant"]
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Response JSON Format
+/// The buckets are ordered by ascending `doc_count`, then by key.
+/// ```json
+/// {
+///     "rare_merchants": {
+///         "buckets": [
+///             { "key": "acme", "doc_count": 1 },
+///             { "key": "globex", "doc_count": 2 }
+///         ],
+///         "sum_other_doc_count": 0
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RareTermsAggregation {
+    /// The field to aggregate on.
+    pub field: String,
+    /// The maximum number of documents a term may occur in to be considered rare.
+    ///
+    /// Defaults to 1, the maximum is 100.
+    #[serde(default = "default_max_doc_count")]
+    pub max_doc_count: u64,
+    /// The false positive rate of the filter of frequent terms, once it is no longer exact.
+    ///
+    /// Defaults to 0.001, the minimum is 0.00001.
+    #[serde(default = "default_precision")]
+    pub precision: f64,
+    /// The value used for documents without a value, like for the
+    /// [terms aggregation](super::TermsAggregation::missing).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub missing: Option<Key>,
+    /// Include terms by either regex (single string) or exact values (array).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub include: Option<IncludeExcludeParam>,
+    /// Exclude terms by either regex (single string) or exact values (array).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub exclude: Option<IncludeExcludeParam>,
+}
+
+impl RareTermsAggregation {
+    /// Creates a rare terms aggregation on the field.
+    pub fn from_field_name(field_name: String) -> Self {
+        RareTermsAggregation {
+            field: field_name,
+            max_doc_count: default_max_doc_count(),
+            precision: default_precision(),
+            missing: None,
+            include: None,
+            exclude: None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.max_doc_count == 0 || self.max_doc_count > MAX_MAX_DOC_COUNT {
+            return Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(format!(
+                    "max_doc_count must be between 1 and {MAX_MAX_DOC_COUNT} in rare_terms, got {}",
+                    self.max_doc_count
+                )),
+            ));
+        }
+        if !(MIN_PRECISION..1.0).contains(&self.precision) {
+            return Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(format!(
+                    "precision must be between {MIN_PRECISION} and 1 in rare_terms, got {}",
+                    self.precision
+                )),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The terms aggregation which collects the exact term counts of a segment.
+    pub(crate) fn to_terms_req(&self) -> TermsAggregation {
+        TermsAggregation {
+            field: self.field.to_string(),
+            size: None,
+            // All terms of the segment are needed, `size` is not used on segments.
+            segment_size: Some(u32::MAX),
+            show_term_doc_count_error: Some(false),
+            min_doc_count: Some(1),
+            order: Some(CustomOrder {
+                target: OrderTarget::Key,
+                order: Order::Asc,
+            }),
+            missing: self.missing.clone(),
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+}
+
+/// Request data for the rare terms aggregation.
+///
+/// The term counts are collected by the terms aggregation nodes, which are the children of the
+/// rare terms node.
+pub struct RareTermsAggReqData {
+    /// The name of the rare terms aggregation.
+    pub name: String,
+    /// The rare terms aggregation request.
+    pub req: RareTermsAggregation,
+}
+
+impl RareTermsAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub(crate) fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// Segment collector for the rare terms aggregation.
+///
+/// Collects the exact term counts with the terms aggregation collectors and splits them into
+/// rare terms and frequent terms.
+#[derive(Debug)]
+pub(crate) struct SegmentRareTermsCollector {
+    terms_collector: Box<dyn SegmentAggregationCollector>,
+    accessor_idx: usize,
+}
+
+impl SegmentRareTermsCollector {
+    pub(crate) fn from_req_and_validate(
+        req: &mut AggregationsSegmentCtx,
+        node: &AggRefNode,
+    ) -> crate::Result<Self> {
+        let terms_collector = build_segment_agg_collectors(req, &node.children)?;
+        Ok(Self {
+            terms_collector,
+            accessor_idx: node.idx_in_req_data,
+        })
+    }
+}
+
+impl SegmentAggregationCollector for SegmentRareTermsCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        let mut terms_results = IntermediateAggregationResults::default();
+        self.terms_collector.add_intermediate_aggregation_result(
+            agg_data,
+            &mut terms_results,
+            parent_bucket_id,
+        )?;
+        let req_data = agg_data.get_rare_terms_req_data(self.accessor_idx);
+        let terms = match terms_results.aggs_res.remove(&req_data.name) {
+            Some(IntermediateAggregationResult::Bucket(IntermediateBucketResult::Terms {
+                buckets,
+            })) => buckets,
+            Some(_) => {
+                return Err(TantivyError::InternalError(
+                    "rare_terms expected a terms result".to_string(),
+                ))
+            }
+            None => IntermediateTermBucketResult::default(),
+        };
+        let rare_terms = IntermediateRareTerms::from_terms(&req_data.req, terms)?;
+        results.push(
+            req_data.name.clone(),
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::RareTerms(rare_terms)),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        parent_bucket_id: BucketId,
+        docs: &[DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        self.terms_collector
+            .collect(parent_bucket_id, docs, agg_data)
+    }
+
+    fn collect_multiple(
+        &mut self,
+        bucket_ids: &[BucketId],
+        docs: &[DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        self.terms_collector
+            .collect_multiple(bucket_ids, docs, agg_data)
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        max_bucket: BucketId,
+        agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        self.terms_collector
+            .prepare_max_bucket(max_bucket, agg_data)
+    }
+
+    fn flush(&mut self, agg_data: &mut AggregationsSegmentCtx) -> crate::Result<()> {
+        self.terms_collector.flush(agg_data)
+    }
+}
+
+/// Intermediate result of the rare terms aggregation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateRareTerms {
+    /// The maximum doc count of a rare term.
+    max_doc_count: u64,
+    /// The terms which are rare so far.
+    buckets: FxHashMap<IntermediateKey, IntermediateTermBucketEntry>,
+    /// The terms which are known to be frequent.
+    frequent_terms: FrequentTermsFilter,
+}
+
+impl IntermediateRareTerms {
+    /// Splits the term counts of a segment into rare and frequent terms.
+    fn from_terms(
+        req: &RareTermsAggregation,
+        terms: IntermediateTermBucketResult,
+    ) -> crate::Result<Self> {
+        let mut rare_terms = Self::empty_from_req(req);
+        rare_terms.buckets = terms.entries;
+        rare_terms.remove_frequent_terms();
+        Ok(rare_terms)
+    }
+
+    /// Returns an empty result for the request.
+    pub(crate) fn empty_from_req(req: &RareTermsAggregation) -> Self {
+        IntermediateRareTerms {
+            max_doc_count: req.max_doc_count,
+            buckets: FxHashMap::default(),
+            frequent_terms: FrequentTermsFilter::new(req.precision),
+        }
+    }
+
+    /// Merges the other intermediate result into self.
+    pub(crate) fn merge_fruits(&mut self, other: IntermediateRareTerms) -> crate::Result<()> {
+        self.frequent_terms.merge(other.frequent_terms);
+        for (key, entry) in other.buckets {
+            match self.buckets.get_mut(&key) {
+                Some(existing) => {
+                    existing.doc_count += entry.doc_count;
+                    existing
+                        .sub_aggregation
+                        .merge_fruits(entry.sub_aggregation)?;
+                }
+                None => {
+                    self.buckets.insert(key, entry);
+                }
+            }
+        }
+        self.remove_frequent_terms();
+        Ok(())
+    }
+
+    /// Moves the terms which exceed `max_doc_count` into the filter, and drops the terms which
+    /// are frequent somewhere else.
+    fn remove_frequent_terms(&mut self) {
+        let max_doc_count = self.max_doc_count;
+        let frequent_terms = &mut self.frequent_terms;
+        self.buckets.retain(|key, entry| {
+            let hash = hash_key(key);
+            if entry.doc_count as u64 > max_doc_count {
+                frequent_terms.insert(hash);
+                false
+            } else {
+                !frequent_terms.contains(hash)
+            }
+        });
+    }
+
+    pub(crate) fn into_final_result(
+        self,
+        sub_aggregation_req: &Aggregations,
+        limits: &mut AggregationLimitsGuard,
+    ) -> crate::Result<BucketResult> {
+        let mut buckets: Vec<BucketEntry> = self
+            .buckets
+            .into_iter()
+            .map(|(key, entry)| {
+                let key_as_string = match key {
+                    IntermediateKey::Bool(key) => Some(key.to_string()),
+                    _ => None,
+                };
+                Ok(BucketEntry {
+                    key_as_string,
+                    key: key.into(),
+                    doc_count: entry.doc_count as u64,
+                    sub_aggregation: entry
+                        .sub_aggregation
+                        .into_final_result_internal(sub_aggregation_req, limits)?,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        buckets.sort_by(|left, right| {
+            left.doc_count.cmp(&right.doc_count).then_with(|| {
+                left.key
+                    .partial_cmp(&right.key)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+        });
+        Ok(BucketResult::Terms {
+            buckets,
+            sum_other_doc_count: 0,
+            doc_count_error_upper_bound: None,
+        })
+    }
+}
+
+/// Hashes a term for the filter of frequent terms. The hash has to be stable across processes,
+/// since the intermediate results may be computed on different nodes.
+fn hash_key(key: &IntermediateKey) -> u64 {
+    let mut hasher = FnvHasher::default();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The number of frequent terms kept exactly, before switching to bloom filters.
+const EXACT_FREQUENT_TERMS_LIMIT: usize = 10_000;
+/// The number of terms a single bloom filter is sized for.
+const BLOOM_FILTER_CAPACITY: u64 = 100_000;
+
+/// The set of term hashes which are known to be frequent.
+///
+/// The hashes are kept exactly up to [`EXACT_FREQUENT_TERMS_LIMIT`], then in a list of bloom
+/// filters. A new bloom filter is added whenever the last one is full, so that the false
+/// positive rate of each filter stays at `precision`. Merging two filters concatenates their
+/// bloom filters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct FrequentTermsFilter {
+    precision: f64,
+    hashes: FxHashSet<u64>,
+    bloom_filters: Vec<BloomFilter>,
+}
+
+impl FrequentTermsFilter {
+    fn new(precision: f64) -> Self {
+        FrequentTermsFilter {
+            precision,
+            hashes: FxHashSet::default(),
+            bloom_filters: Vec::new(),
+        }
+    }
+
+    fn contains(&self, hash: u64) -> bool {
+        self.hashes.contains(&hash)
+            || self
+                .bloom_filters
+                .iter()
+                .any(|bloom_filter| bloom_filter.contains(hash))
+    }
+
+    fn insert(&mut self, hash: u64) {
+        if self.bloom_filters.is_empty() {
+            self.hashes.insert(hash);
+            if self.hashes.len() > EXACT_FREQUENT_TERMS_LIMIT {
+                for hash in std::mem::take(&mut self.hashes) {
+                    self.insert_into_bloom_filter(hash);
+                }
+            }
+        } else if !self.contains(hash) {
+            self.insert_into_bloom_filter(hash);
+        }
+    }
+
+    fn insert_into_bloom_filter(&mut self, hash: u64) {
+        let needs_new_filter = self
+            .bloom_filters
+            .last()
+            .map(|bloom_filter| bloom_filter.len >= BLOOM_FILTER_CAPACITY)
+            .unwrap_or(true);
+        if needs_new_filter {
+            self.bloom_filters
+                .push(BloomFilter::new(BLOOM_FILTER_CAPACITY, self.precision));
+        }
+        self.bloom_filters.last_mut().unwrap().insert(hash);
+    }
+
+    fn merge(&mut self, other: FrequentTermsFilter) {
+        self.bloom_filters.extend(other.bloom_filters);
+        for hash in other.hashes {
+            self.insert(hash);
+        }
+    }
+}
+
+/// A bloom filter over term hashes, using double hashing to derive the bit positions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct BloomFilter {
+    bits: Vec<u64>,
+    num_hashes: u32,
+    len: u64,
+}
+
+impl BloomFilter {
+    /// Creates a bloom filter with a false positive rate of `precision` for `capacity` items.
+    fn new(capacity: u64, precision: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(capacity as f64) * precision.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_words = num_bits.div_ceil(64).max(1);
+        let num_hashes = ((num_words * 64) as f64 / capacity as f64 * ln2).round() as u32;
+        BloomFilter {
+            bits: vec![0; num_words as usize],
+            num_hashes: num_hashes.max(1),
+            len: 0,
+        }
+    }
+
+    fn bit_positions(&self, hash: u64) -> impl Iterator<Item = u64> {
+        let num_bits = self.bits.len() as u64 * 64;
+        let hash1 = hash;
+        let hash2 = hash.rotate_left(32) | 1;
+        (0..self.num_hashes as u64)
+            .map(move |idx| hash1.wrapping_add(idx.wrapping_mul(hash2)) % num_bits)
+    }
+
+    fn insert(&mut self, hash: u64) {
+        for bit in self.bit_positions(hash).collect::<Vec<_>>() {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.len += 1;
+    }
+
+    fn contains(&self, hash: u64) -> bool {
+        self.bit_positions(hash)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::FrequentTermsFilter;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_from_values_and_terms};
+    use crate::aggregation::DistributedAggregationCollector;
+    use crate::query::AllQuery;
+    use crate::Index;
+
+    fn get_test_index(merge_segments: bool) -> crate::Result<Index> {
+        let segment_and_terms = vec![
+            vec![
+                (1.0, "terma".to_string()),
+                (2.0, "terma".to_string()),
+                (3.0, "termb".to_string()),
+                (4.0, "termc".to_string()),
+            ],
+            vec![
+                (5.0, "terma".to_string()),
+                (6.0, "termc".to_string()),
+                (7.0, "termd".to_string()),
+                (8.0, "terme".to_string()),
+            ],
+            vec![(9.0, "termb".to_string()), (10.0, "termf".to_string())],
+        ];
+        get_test_index_from_values_and_terms(merge_segments, &segment_and_terms)
+    }
+
+    fn keys_and_doc_counts(res: &Value) -> Vec<(String, u64)> {
+        res["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| {
+                (
+                    bucket["key"].as_str().unwrap().to_string(),
+                    bucket["doc_count"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn exec_rare_terms(index: &Index, req: Value) -> crate::Result<Value> {
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "rare": { "rare_terms": req }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, index)?;
+        Ok(res["rare"].clone())
+    }
+
+    fn pairs(expected: &[(&str, u64)]) -> Vec<(String, u64)> {
+        expected
+            .iter()
+            .map(|(key, doc_count)| (key.to_string(), *doc_count))
+            .collect()
+    }
+
+    #[test]
+    fn rare_terms_test() -> crate::Result<()> {
+        for merge_segments in [false, true] {
+            let index = get_test_index(merge_segments)?;
+
+            let res = exec_rare_terms(&index, json!({ "field": "string_id" }))?;
+            assert_eq!(
+                keys_and_doc_counts(&res),
+                pairs(&[("termd", 1), ("terme", 1), ("termf", 1)])
+            );
+
+            let res = exec_rare_terms(&index, json!({ "field": "string_id", "max_doc_count": 2 }))?;
+            assert_eq!(
+                keys_and_doc_counts(&res),
+                pairs(&[
+                    ("termd", 1),
+                    ("terme", 1),
+                    ("termf", 1),
+                    ("termb", 2),
+                    ("termc", 2)
+                ])
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rare_terms_include_exclude_test() -> crate::Result<()> {
+        for merge_segments in [false, true] {
+            let index = get_test_index(merge_segments)?;
+
+            let res = exec_rare_terms(
+                &index,
+                json!({ "field": "string_id", "exclude": ["termd"] }),
+            )?;
+            assert_eq!(
+                keys_and_doc_counts(&res),
+                pairs(&[("terme", 1), ("termf", 1)])
+            );
+
+            let res = exec_rare_terms(
+                &index,
+                json!({ "field": "string_id", "max_doc_count": 2, "include": "term[bd]" }),
+            )?;
+            assert_eq!(
+                keys_and_doc_counts(&res),
+                pairs(&[("termd", 1), ("termb", 2)])
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rare_terms_sub_aggregation_distributed_test() -> crate::Result<()> {
+        let index = get_test_index(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "rare": {
+                "rare_terms": { "field": "string_id", "max_doc_count": 2 },
+                "aggs": { "avg_score": { "avg": { "field": "score" } } }
+            }
+        }))
+        .unwrap();
+
+        let collector =
+            DistributedAggregationCollector::from_aggs(agg_req.clone(), Default::default());
+        let searcher = index.reader()?.searcher();
+        let intermediate = searcher.search(&AllQuery, &collector)?;
+        let bytes = postcard::to_allocvec(&intermediate).unwrap();
+        let intermediate: crate::aggregation::intermediate_agg_result::IntermediateAggregationResults =
+            postcard::from_bytes(&bytes).unwrap();
+        let res = intermediate.into_final_result(agg_req, Default::default())?;
+        let res = serde_json::to_value(res)?;
+
+        assert_eq!(res["rare"]["buckets"][3]["key"], "termb");
+        assert_eq!(res["rare"]["buckets"][3]["avg_score"]["value"], 6.0);
+        assert_eq!(res["rare"]["buckets"][4]["key"], "termc");
+        assert_eq!(res["rare"]["buckets"][4]["avg_score"]["value"], 5.0);
+        Ok(())
+    }
+
+    #[test]
+    fn rare_terms_invalid_request_test() -> crate::Result<()> {
+        let index = get_test_index(false)?;
+        let err = exec_rare_terms(&index, json!({ "field": "string_id", "max_doc_count": 0 }))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"max_doc_count must be between 1 and 100 in rare_terms, got 0\""
+        );
+        let err =
+            exec_rare_terms(&index, json!({ "field": "string_id", "precision": 1.5 })).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"precision must be between 0.00001 and 1 in rare_terms, got 1.5\""
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn frequent_terms_filter_test() {
+        let mut left = FrequentTermsFilter::new(0.001);
+        let mut right = FrequentTermsFilter::new(0.001);
+        for hash in 0..20_000u64 {
+            left.insert(hash.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        }
+        assert!(left.hashes.is_empty());
+        assert_eq!(left.bloom_filters.len(), 1);
+        right.insert(42);
+        left.merge(right);
+
+        for hash in 0..20_000u64 {
+            assert!(left.contains(hash.wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+        }
+        assert!(left.contains(42));
+        let false_positives = (20_000..120_000u64)
+            .filter(|hash| left.contains(hash.wrapping_mul(0x9E37_79B9_7F4A_7C15)))
+            .count();
+        assert!(false_positives < 100, "{false_positives} false positives");
+    }
+}
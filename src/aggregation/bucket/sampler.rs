@@ -0,0 +1,469 @@
+use columnar::Column;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::agg_data::{
+    build_segment_agg_collectors, AggRefNode, AggregationsSegmentCtx,
+};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateBucketResult,
+};
+use crate::aggregation::segment_agg_result::SegmentAggregationCollector;
+use crate::aggregation::{AggregationError, BucketId};
+use crate::docset::COLLECT_BLOCK_BUFFER_LEN;
+use crate::{DocId, Score, TantivyError};
+
+fn default_shard_size() -> u32 {
+    100
+}
+
+fn default_max_docs_per_value() -> u32 {
+    1
+}
+
+/// The sampler aggregation limits its sub-aggregations to the best scoring documents of the
+/// search query. This is useful to focus expensive sub-aggregations (e.g. `terms`) on the most
+/// relevant documents instead of the long tail of weak matches.
+///
+/// The sample is taken per segment: each segment contributes at most `shard_size` documents.
+///
+/// The sampler aggregation has to be a top-level aggregation.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "sample": {
+///         "sampler": { "shard_size": 200 },
+///         "aggs": {
+///             "keywords": { "terms": { "field": "tags" } }
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Result
+/// The sampler aggregation returns a single bucket with:
+/// - `doc_count`: Number of sampled documents
+/// - Sub-aggregation results computed on the sampled documents
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SamplerAggregation {
+    /// The maximum number of top scoring documents to sample per segment. Defaults to 100.
+    #[serde(default = "default_shard_size")]
+    pub shard_size: u32,
+}
+
+impl Default for SamplerAggregation {
+    fn default() -> Self {
+        Self {
+            shard_size: default_shard_size(),
+        }
+    }
+}
+
+impl SamplerAggregation {
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        validate_shard_size(self.shard_size)
+    }
+}
+
+/// The diversified sampler aggregation is a [`SamplerAggregation`] that limits the number of
+/// sampled documents sharing the same value of `field`. It avoids that the sample is dominated
+/// by a single source, e.g. many near duplicate documents from the same author.
+///
+/// Only the first value of `field` is taken into account. Documents without a value are sampled
+/// together, as if they shared the same value.
+///
+/// The diversified sampler aggregation has to be a top-level aggregation.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "sample": {
+///         "diversified_sampler": {
+///             "shard_size": 200,
+///             "field": "author",
+///             "max_docs_per_value": 3
+///         },
+///         "aggs": {
+///             "keywords": { "terms": { "field": "tags" } }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiversifiedSamplerAggregation {
+    /// The maximum number of top scoring documents to sample per segment. Defaults to 100.
+    #[serde(default = "default_shard_size")]
+    pub shard_size: u32,
+    /// The fast field used to de-duplicate the sample.
+    pub field: String,
+    /// The maximum number of sampled documents sharing the same value. Defaults to 1.
+    #[serde(default = "default_max_docs_per_value")]
+    pub max_docs_per_value: u32,
+}
+
+impl DiversifiedSamplerAggregation {
+    /// Returns the field name the aggregation is computed on.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        validate_shard_size(self.shard_size)?;
+        if self.max_docs_per_value == 0 {
+            return Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(
+                    "max_docs_per_value must be greater than 0".to_string(),
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn validate_shard_size(shard_size: u32) -> crate::Result<()> {
+    if shard_size == 0 {
+        return Err(TantivyError::AggregationError(
+            AggregationError::InvalidRequest("shard_size must be greater than 0".to_string()),
+        ));
+    }
+    Ok(())
+}
+
+/// Request data for the sampler and diversified_sampler aggregations.
+pub struct SamplerAggReqData {
+    /// The name of the sampler aggregation.
+    pub name: String,
+    /// The maximum number of sampled documents.
+    pub shard_size: u32,
+    /// The column to diversify the sample on, for the diversified_sampler aggregation.
+    pub diversify_by: Option<Column<u64>>,
+    /// The maximum number of sampled documents per value of `diversify_by`.
+    pub max_docs_per_value: u32,
+}
+
+impl SamplerAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub(crate) fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+
+    /// The maximum number of candidates to keep per key.
+    fn max_candidates_per_key(&self) -> usize {
+        if self.diversify_by.is_some() {
+            self.max_docs_per_value.min(self.shard_size) as usize
+        } else {
+            self.shard_size as usize
+        }
+    }
+}
+
+/// Sorts the candidates by descending score and keeps the `limit` best ones.
+/// Ties are broken by doc id, so that the sample is deterministic.
+fn keep_top_candidates(candidates: &mut Vec<(Score, DocId)>, limit: usize) {
+    candidates.sort_unstable_by(|(left_score, left_doc), (right_score, right_doc)| {
+        right_score
+            .total_cmp(left_score)
+            .then(left_doc.cmp(right_doc))
+    });
+    candidates.truncate(limit);
+}
+
+/// Segment collector for the sampler and diversified_sampler aggregations.
+///
+/// The best scoring documents are kept as candidates during collection, the scores are read from
+/// [`AggregationsSegmentCtx::doc_scores`]. The sample is passed to the sub-aggregations when the
+/// collector is flushed.
+#[derive(Debug)]
+pub(crate) struct SegmentSamplerCollector {
+    /// Candidates per value of the diversification field. Keyed by 0 for the plain sampler.
+    candidates: FxHashMap<u64, Vec<(Score, DocId)>>,
+    doc_count: u64,
+    sub_aggregations: Option<Box<dyn SegmentAggregationCollector>>,
+    collected: bool,
+    accessor_idx: usize,
+}
+
+impl SegmentSamplerCollector {
+    pub(crate) fn from_req_and_validate(
+        req: &mut AggregationsSegmentCtx,
+        node: &AggRefNode,
+    ) -> crate::Result<Self> {
+        let sub_aggregations = if node.children.is_empty() {
+            None
+        } else {
+            Some(build_segment_agg_collectors(req, &node.children)?)
+        };
+        Ok(Self {
+            candidates: FxHashMap::default(),
+            doc_count: 0,
+            sub_aggregations,
+            collected: false,
+            accessor_idx: node.idx_in_req_data,
+        })
+    }
+}
+
+impl SegmentAggregationCollector for SegmentSamplerCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        _parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        let mut sub_aggregations = IntermediateAggregationResults::default();
+        if let Some(sub_aggs) = &mut self.sub_aggregations {
+            sub_aggs.add_intermediate_aggregation_result(agg_data, &mut sub_aggregations, 0)?;
+        }
+        let name = agg_data
+            .get_sampler_req_data(self.accessor_idx)
+            .name
+            .clone();
+        results.push(
+            name,
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::Filter {
+                doc_count: self.doc_count,
+                sub_aggregations,
+            }),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        _parent_bucket_id: BucketId,
+        docs: &[DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        let req_data = agg_data.get_sampler_req_data(self.accessor_idx);
+        let max_candidates = req_data.max_candidates_per_key();
+        for (pos, &doc) in docs.iter().enumerate() {
+            let score = agg_data.doc_scores.get(pos).copied().unwrap_or(0.0);
+            let key = req_data
+                .diversify_by
+                .as_ref()
+                .map(|column| column.first(doc).unwrap_or(u64::MAX))
+                .unwrap_or(0);
+            let candidates = self.candidates.entry(key).or_default();
+            candidates.push((score, doc));
+            // Amortize the sorting cost by only truncating when the candidates doubled.
+            if candidates.len() >= max_candidates * 2 {
+                keep_top_candidates(candidates, max_candidates);
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, agg_data: &mut AggregationsSegmentCtx) -> crate::Result<()> {
+        if self.collected {
+            return Ok(());
+        }
+        self.collected = true;
+        let req_data = agg_data.get_sampler_req_data(self.accessor_idx);
+        let max_candidates = req_data.max_candidates_per_key();
+        let mut sample: Vec<(Score, DocId)> = Vec::new();
+        for (_, mut candidates) in self.candidates.drain() {
+            keep_top_candidates(&mut candidates, max_candidates);
+            sample.extend(candidates);
+        }
+        keep_top_candidates(&mut sample, req_data.shard_size as usize);
+        let mut docs: Vec<DocId> = sample.into_iter().map(|(_, doc)| doc).collect();
+        docs.sort_unstable();
+        self.doc_count = docs.len() as u64;
+        if let Some(sub_aggs) = &mut self.sub_aggregations {
+            for docs_chunk in docs.chunks(COLLECT_BLOCK_BUFFER_LEN) {
+                sub_aggs.collect(0, docs_chunk, agg_data)?;
+            }
+            sub_aggs.flush(agg_data)?;
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        _max_bucket: BucketId,
+        agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        if let Some(sub_aggs) = &mut self.sub_aggregations {
+            sub_aggs.prepare_max_bucket(0, agg_data)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::AggregationCollector;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, FAST, STRING, TEXT};
+    use crate::{Index, IndexWriter, Term};
+
+    /// Documents with a shorter text score higher for the term "rust".
+    fn get_sampler_test_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let author = schema_builder.add_text_field("author", STRING | FAST);
+        let rank = schema_builder.add_u64_field("rank", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        let docs = [
+            ("rust", "alice", 1u64),
+            ("rust is nice", "alice", 2),
+            ("rust is a nice language", "bob", 3),
+            ("rust is a nice language to write search engines", "bob", 4),
+            (
+                "rust is a nice language to write search engines and many other things",
+                "carol",
+                5,
+            ),
+            ("python", "carol", 6),
+        ];
+        for (text_val, author_val, rank_val) in docs {
+            index_writer.add_document(doc!(
+                text => text_val,
+                author => author_val,
+                rank => rank_val,
+            ))?;
+        }
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    fn exec_rust_query(index: &Index, agg_req: Value) -> crate::Result<Value> {
+        let agg_req: Aggregations = serde_json::from_value(agg_req).unwrap();
+        let collector = AggregationCollector::from_aggs(agg_req, Default::default());
+        let text = index.schema().get_field("text").unwrap();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "rust"),
+            IndexRecordOption::WithFreqs,
+        );
+        let searcher = index.reader()?.searcher();
+        let res = searcher.search(&query, &collector)?;
+        Ok(serde_json::to_value(res)?)
+    }
+
+    #[test]
+    fn test_sampler_keeps_best_scoring_docs() -> crate::Result<()> {
+        let index = get_sampler_test_index()?;
+        let res = exec_rust_query(
+            &index,
+            json!({
+                "sample": {
+                    "sampler": { "shard_size": 2 },
+                    "aggs": { "max_rank": { "max": { "field": "rank" } } }
+                },
+                "max_rank": { "max": { "field": "rank" } }
+            }),
+        )?;
+        assert_eq!(res["max_rank"]["value"], 5.0);
+        assert_eq!(
+            res["sample"],
+            json!({ "doc_count": 2, "max_rank": { "value": 2.0 } })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sampler_default_shard_size() -> crate::Result<()> {
+        let index = get_sampler_test_index()?;
+        let res = exec_rust_query(
+            &index,
+            json!({
+                "sample": {
+                    "sampler": {},
+                    "aggs": { "max_rank": { "max": { "field": "rank" } } }
+                }
+            }),
+        )?;
+        assert_eq!(
+            res["sample"],
+            json!({ "doc_count": 5, "max_rank": { "value": 5.0 } })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_diversified_sampler() -> crate::Result<()> {
+        let index = get_sampler_test_index()?;
+        let res = exec_rust_query(
+            &index,
+            json!({
+                "sample": {
+                    "diversified_sampler": { "shard_size": 2, "field": "author" },
+                    "aggs": {
+                        "authors": { "terms": { "field": "author", "order": { "_key": "asc" } } },
+                        "max_rank": { "max": { "field": "rank" } }
+                    }
+                }
+            }),
+        )?;
+        // alice's second document is skipped in favor of bob's best document.
+        assert_eq!(res["sample"]["doc_count"], 2);
+        assert_eq!(res["sample"]["max_rank"]["value"], 3.0);
+        assert_eq!(
+            res["sample"]["authors"]["buckets"],
+            json!([
+                { "key": "alice", "doc_count": 1 },
+                { "key": "bob", "doc_count": 1 }
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sampler_with_other_collectors() -> crate::Result<()> {
+        let index = get_sampler_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "sample": { "sampler": { "shard_size": 3 } }
+        }))
+        .unwrap();
+        let collector = AggregationCollector::from_aggs(agg_req, Default::default());
+        let text = index.schema().get_field("text").unwrap();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "rust"),
+            IndexRecordOption::WithFreqs,
+        );
+        let searcher = index.reader()?.searcher();
+        let (count, top_docs, res) = searcher.search(
+            &query,
+            &(Count, TopDocs::with_limit(1).order_by_score(), collector),
+        )?;
+        assert_eq!(count, 5);
+        assert_eq!(top_docs.len(), 1);
+        assert_eq!(serde_json::to_value(res)?["sample"]["doc_count"], 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sampler_invalid_requests() -> crate::Result<()> {
+        let index = get_sampler_test_index()?;
+        let err = exec_rust_query(
+            &index,
+            json!({ "sample": { "sampler": { "shard_size": 0 } } }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"shard_size must be greater than 0\""
+        );
+        let err = exec_rust_query(
+            &index,
+            json!({
+                "terms": {
+                    "terms": { "field": "author" },
+                    "aggs": { "sample": { "sampler": {} } }
+                }
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"sampler aggregation `sample` has to be a top-level aggregation\""
+        );
+        Ok(())
+    }
+}
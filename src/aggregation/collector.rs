@@ -1,4 +1,9 @@
-use super::agg_req::Aggregations;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+
+use super::agg_cache::{AggregationCache, AggregationCacheContext};
+use super::agg_req::{get_fast_field_names, requires_scoring, Aggregations};
 use super::agg_result::AggregationResults;
 use super::cached_sub_aggs::LowCardCachedSubAggs;
 use super::intermediate_agg_result::IntermediateAggregationResults;
@@ -7,8 +12,10 @@ use super::AggContextParams;
 use crate::aggregation::agg_data::{
     build_aggregations_data_from_req, build_segment_agg_collectors_root, AggregationsSegmentCtx,
 };
-use crate::collector::{Collector, SegmentCollector};
+use crate::collector::{default_collect_segment_impl, Collector, SegmentCollector};
+use crate::docset::COLLECT_BLOCK_BUFFER_LEN;
 use crate::index::SegmentReader;
+use crate::query::{Query, Weight};
 use crate::{DocId, SegmentOrdinal, TantivyError};
 
 /// The default max bucket count, before the aggregation fails.
@@ -23,6 +30,7 @@ pub const DEFAULT_MEMORY_LIMIT: u64 = 500_000_000;
 pub struct AggregationCollector {
     agg: Aggregations,
     context: AggContextParams,
+    cache: Option<AggregationCacheContext>,
 }
 
 impl AggregationCollector {
@@ -30,8 +38,22 @@ impl AggregationCollector {
     ///
     /// Aggregation fails when the limits in `AggregationLimits` is exceeded. (memory limit and
     /// bucket limit)
-    pub fn from_aggs(agg: Aggregations, context: AggContextParams) -> Self {
-        Self { agg, context }
+    pub fn from_aggs(agg: Aggregations, mut context: AggContextParams) -> Self {
+        context.now_or_init();
+        Self {
+            agg,
+            context,
+            cache: None,
+        }
+    }
+
+    /// Reuses and populates the per-segment results stored in the [`AggregationCache`].
+    ///
+    /// `query` must be the query the collector is used with. The cache is bypassed if the
+    /// request cannot be serialized into a cache key.
+    pub fn with_cache(mut self, cache: Arc<AggregationCache>, query: &dyn Query) -> Self {
+        self.cache = AggregationCacheContext::new(cache, query, &self.agg, self.context.now);
+        self
     }
 }
 
@@ -46,6 +68,7 @@ impl AggregationCollector {
 pub struct DistributedAggregationCollector {
     agg: Aggregations,
     context: AggContextParams,
+    cache: Option<AggregationCacheContext>,
 }
 
 impl DistributedAggregationCollector {
@@ -53,8 +76,22 @@ impl DistributedAggregationCollector {
     ///
     /// Aggregation fails when the limits in `AggregationLimits` is exceeded. (memory limit and
     /// bucket limit)
-    pub fn from_aggs(agg: Aggregations, context: AggContextParams) -> Self {
-        Self { agg, context }
+    pub fn from_aggs(agg: Aggregations, mut context: AggContextParams) -> Self {
+        context.now_or_init();
+        Self {
+            agg,
+            context,
+            cache: None,
+        }
+    }
+
+    /// Reuses and populates the per-segment results stored in the [`AggregationCache`].
+    ///
+    /// `query` must be the query the collector is used with. The cache is bypassed if the
+    /// request cannot be serialized into a cache key.
+    pub fn with_cache(mut self, cache: Arc<AggregationCache>, query: &dyn Query) -> Self {
+        self.cache = AggregationCacheContext::new(cache, query, &self.agg, self.context.now);
+        self
     }
 }
 
@@ -63,6 +100,10 @@ impl Collector for DistributedAggregationCollector {
 
     type Child = AggregationSegmentCollector;
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(warm_fast_fields(&self.agg, segment))
+    }
+
     fn for_segment(
         &self,
         segment_local_id: crate::SegmentOrdinal,
@@ -77,7 +118,7 @@ impl Collector for DistributedAggregationCollector {
     }
 
     fn requires_scoring(&self) -> bool {
-        false
+        requires_scoring(&self.agg)
     }
 
     fn merge_fruits(
@@ -86,6 +127,15 @@ impl Collector for DistributedAggregationCollector {
     ) -> crate::Result<Self::Fruit> {
         merge_fruits(segment_fruits)
     }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        collect_segment_with_cache(self, self.cache.as_ref(), weight, segment_ord, reader)
+    }
 }
 
 impl Collector for AggregationCollector {
@@ -93,6 +143,10 @@ impl Collector for AggregationCollector {
 
     type Child = AggregationSegmentCollector;
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(warm_fast_fields(&self.agg, segment))
+    }
+
     fn for_segment(
         &self,
         segment_local_id: crate::SegmentOrdinal,
@@ -107,7 +161,7 @@ impl Collector for AggregationCollector {
     }
 
     fn requires_scoring(&self) -> bool {
-        false
+        requires_scoring(&self.agg)
     }
 
     fn merge_fruits(
@@ -117,6 +171,52 @@ impl Collector for AggregationCollector {
         let res = merge_fruits(segment_fruits)?;
         res.into_final_result(self.agg.clone(), self.context.limits.clone())
     }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        collect_segment_with_cache(self, self.cache.as_ref(), weight, segment_ord, reader)
+    }
+}
+
+/// Collects the segment, unless its result is available in the cache.
+fn collect_segment_with_cache<C: Collector<Child = AggregationSegmentCollector>>(
+    collector: &C,
+    cache_opt: Option<&AggregationCacheContext>,
+    weight: &dyn Weight,
+    segment_ord: u32,
+    reader: &SegmentReader,
+) -> crate::Result<crate::Result<IntermediateAggregationResults>> {
+    let collect = || {
+        let mut segment_collector = collector.for_segment(segment_ord, reader)?;
+        default_collect_segment_impl(
+            &mut segment_collector,
+            weight,
+            reader,
+            collector.requires_scoring(),
+        )?;
+        Ok(segment_collector.harvest())
+    };
+    match cache_opt {
+        Some(cache) => cache.get_or_collect(reader, collect),
+        None => collect(),
+    }
+}
+
+/// Prefetches the columns of all fast fields used in the aggregation tree.
+async fn warm_fast_fields(aggs: &Aggregations, segment: &SegmentReader) -> crate::Result<()> {
+    let fast_field_names = get_fast_field_names(aggs);
+    let fast_field_readers = segment.fast_fields();
+    futures_util::future::try_join_all(
+        fast_field_names
+            .iter()
+            .map(|field_name| fast_field_readers.warm_fast_field(field_name, false)),
+    )
+    .await?;
+    Ok(())
 }
 
 fn merge_fruits(
@@ -137,6 +237,9 @@ fn merge_fruits(
 pub struct AggregationSegmentCollector {
     aggs_with_accessor: AggregationsSegmentCtx,
     agg_collector: LowCardCachedSubAggs,
+    /// Buffer for the collected docs, if the request requires scoring. The scores are buffered
+    /// in `AggregationsSegmentCtx::doc_scores`.
+    scored_docs: Option<Vec<DocId>>,
     error: Option<TantivyError>,
 }
 
@@ -156,23 +259,57 @@ impl AggregationSegmentCollector {
         result
             .get_sub_agg_collector()
             .prepare_max_bucket(0, &agg_data)?; // prepare for bucket zero
+        let scored_docs =
+            requires_scoring(agg).then(|| Vec::with_capacity(COLLECT_BLOCK_BUFFER_LEN));
 
         Ok(AggregationSegmentCollector {
             aggs_with_accessor: agg_data,
             agg_collector: result,
+            scored_docs,
             error: None,
         })
     }
+
+    /// Passes the buffered scored docs to the collectors.
+    ///
+    /// The docs bypass the sub aggregation cache, so that the collectors see the docs in the
+    /// same order as `AggregationsSegmentCtx::doc_scores`.
+    fn flush_scored_docs(&mut self) -> crate::Result<()> {
+        let Some(scored_docs) = &mut self.scored_docs else {
+            return Ok(());
+        };
+        if scored_docs.is_empty() {
+            return Ok(());
+        }
+        self.agg_collector.get_sub_agg_collector().collect(
+            0,
+            scored_docs,
+            &mut self.aggs_with_accessor,
+        )?;
+        scored_docs.clear();
+        self.aggs_with_accessor.doc_scores.clear();
+        Ok(())
+    }
 }
 
 impl SegmentCollector for AggregationSegmentCollector {
     type Fruit = crate::Result<IntermediateAggregationResults>;
 
     #[inline]
-    fn collect(&mut self, doc: DocId, _score: crate::Score) {
+    fn collect(&mut self, doc: DocId, score: crate::Score) {
         if self.error.is_some() {
             return;
         }
+        if let Some(scored_docs) = &mut self.scored_docs {
+            scored_docs.push(doc);
+            self.aggs_with_accessor.doc_scores.push(score);
+            if scored_docs.len() >= COLLECT_BLOCK_BUFFER_LEN {
+                if let Err(e) = self.flush_scored_docs() {
+                    self.error = Some(e);
+                }
+            }
+            return;
+        }
         self.agg_collector.push(0, doc);
         match self
             .agg_collector
@@ -205,6 +342,7 @@ impl SegmentCollector for AggregationSegmentCollector {
         if let Some(err) = self.error {
             return Err(err);
         }
+        self.flush_scored_docs()?;
         self.agg_collector.flush(&mut self.aggs_with_accessor)?;
 
         let mut sub_aggregation_res = IntermediateAggregationResults::default();
@@ -1,14 +1,27 @@
 use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
+use time::{OffsetDateTime, UtcOffset};
 
 use crate::TantivyError;
 
 pub(crate) fn format_date(val: i64) -> crate::Result<String> {
-    let datetime = OffsetDateTime::from_unix_timestamp_nanos(val as i128).map_err(|err| {
-        TantivyError::InvalidArgument(format!(
-            "Could not convert {val:?} to OffsetDateTime, err {err:?}"
-        ))
-    })?;
+    format_date_with_utc_offset(val, 0)
+}
+
+/// Formats a timestamp in nanoseconds as RFC3339, in the local time of the given UTC offset.
+pub(crate) fn format_date_with_utc_offset(val: i64, utc_offset_ms: i64) -> crate::Result<String> {
+    let utc_offset =
+        UtcOffset::from_whole_seconds((utc_offset_ms / 1000) as i32).map_err(|err| {
+            TantivyError::InvalidArgument(format!(
+                "Invalid UTC offset {utc_offset_ms:?}ms, err {err:?}"
+            ))
+        })?;
+    let datetime = OffsetDateTime::from_unix_timestamp_nanos(val as i128)
+        .map_err(|err| {
+            TantivyError::InvalidArgument(format!(
+                "Could not convert {val:?} to OffsetDateTime, err {err:?}"
+            ))
+        })?
+        .to_offset(utc_offset);
     let key_as_string = datetime
         .format(&Rfc3339)
         .map_err(|_err| TantivyError::InvalidArgument("Could not serialize date".to_string()))?;
@@ -5,7 +5,7 @@
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
 use std::hash::Hash;
-use std::net::Ipv6Addr;
+use std::net::{IpAddr, Ipv6Addr};
 
 use columnar::ColumnType;
 use itertools::Itertools;
@@ -16,19 +16,23 @@ use super::agg_req::{Aggregation, AggregationVariants, Aggregations};
 use super::agg_result::{AggregationResult, BucketResult, MetricResult, RangeBucketEntry};
 use super::bucket::{
     cut_off_buckets, get_agg_name_and_property, intermediate_histogram_buckets_to_final_buckets,
-    GetDocCount, Order, OrderTarget, RangeAggregation, TermsAggregation,
+    GetDocCount, IntermediateAutoDateHistogram, IntermediateRareTerms,
+    IntermediateVariableWidthHistogram, MultiTermsAggregation, Order, OrderTarget,
+    TermsAggregation,
 };
 use super::metric::{
     IntermediateAverage, IntermediateCount, IntermediateExtendedStats, IntermediateMax,
-    IntermediateMin, IntermediateStats, IntermediateSum, PercentilesCollector, TopHitsTopNComputer,
+    IntermediateMin, IntermediateStats, IntermediateStringStats, IntermediateSum,
+    IntermediateTopMetrics, IntermediateWeightedAverage, PercentilesCollector, TopHitsTopNComputer,
 };
 use super::segment_agg_result::AggregationLimitsGuard;
 use super::{format_date, AggregationError, Key, SerializedKey};
 use crate::aggregation::agg_result::{
-    AggregationResults, BucketEntries, BucketEntry, FilterBucketResult,
+    AggregationResults, BucketEntries, BucketEntry, FilterBucketResult, MultiTermsBucketEntry,
 };
 use crate::aggregation::bucket::TermsAggregationInternal;
 use crate::aggregation::metric::CardinalityCollector;
+use crate::schema::IntoIpv6Addr;
 use crate::TantivyError;
 
 /// Contains the intermediate aggregation result, which is optimized to be merged with other
@@ -203,9 +207,17 @@ pub(crate) fn empty_from_req(req: &Aggregation) -> IntermediateAggregationResult
         Terms(_) => IntermediateAggregationResult::Bucket(IntermediateBucketResult::Terms {
             buckets: Default::default(),
         }),
-        Range(_) => IntermediateAggregationResult::Bucket(IntermediateBucketResult::Range(
-            Default::default(),
-        )),
+        MultiTerms(_) => {
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::MultiTerms {
+                buckets: Default::default(),
+            })
+        }
+        RareTerms(ref req) => IntermediateAggregationResult::Bucket(
+            IntermediateBucketResult::RareTerms(IntermediateRareTerms::empty_from_req(req)),
+        ),
+        Range(_) | DateRange(_) | IpRange(_) => IntermediateAggregationResult::Bucket(
+            IntermediateBucketResult::Range(Default::default()),
+        ),
         Histogram(_) => {
             IntermediateAggregationResult::Bucket(IntermediateBucketResult::Histogram {
                 buckets: Vec::new(),
@@ -218,6 +230,16 @@ pub(crate) fn empty_from_req(req: &Aggregation) -> IntermediateAggregationResult
                 is_date_agg: true,
             })
         }
+        AutoDateHistogram(ref req) => {
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::AutoDateHistogram(
+                IntermediateAutoDateHistogram::empty_from_req(req),
+            ))
+        }
+        VariableWidthHistogram(ref req) => {
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::VariableWidthHistogram(
+                IntermediateVariableWidthHistogram::empty_from_req(req),
+            ))
+        }
         Average(_) => IntermediateAggregationResult::Metric(IntermediateMetricResult::Average(
             IntermediateAverage::default(),
         )),
@@ -248,10 +270,35 @@ pub(crate) fn empty_from_req(req: &Aggregation) -> IntermediateAggregationResult
         Cardinality(_) => IntermediateAggregationResult::Metric(
             IntermediateMetricResult::Cardinality(CardinalityCollector::default()),
         ),
-        Filter(_) => IntermediateAggregationResult::Bucket(IntermediateBucketResult::Filter {
-            doc_count: 0,
-            sub_aggregations: IntermediateAggregationResults::default(),
-        }),
+        WeightedAvg(_) => IntermediateAggregationResult::Metric(
+            IntermediateMetricResult::WeightedAvg(IntermediateWeightedAverage::default()),
+        ),
+        MedianAbsoluteDeviation(_) => IntermediateAggregationResult::Metric(
+            IntermediateMetricResult::MedianAbsoluteDeviation(PercentilesCollector::default()),
+        ),
+        TopMetrics(ref req) => IntermediateAggregationResult::Metric(
+            IntermediateMetricResult::TopMetrics(IntermediateTopMetrics::new(req)),
+        ),
+        PercentileRanks(_) => IntermediateAggregationResult::Metric(
+            IntermediateMetricResult::PercentileRanks(PercentilesCollector::default()),
+        ),
+        Boxplot(_) => IntermediateAggregationResult::Metric(IntermediateMetricResult::Boxplot(
+            PercentilesCollector::default(),
+        )),
+        StringStats(_) => IntermediateAggregationResult::Metric(
+            IntermediateMetricResult::StringStats(IntermediateStringStats::default()),
+        ),
+        Filters(ref req) => {
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::Filters {
+                buckets: vec![Default::default(); req.num_buckets()],
+            })
+        }
+        Filter(_) | Global(_) | Sampler(_) | DiversifiedSampler(_) => {
+            IntermediateAggregationResult::Bucket(IntermediateBucketResult::Filter {
+                doc_count: 0,
+                sub_aggregations: IntermediateAggregationResults::default(),
+            })
+        }
     }
 }
 
@@ -319,6 +366,18 @@ pub enum IntermediateMetricResult {
     TopHits(TopHitsTopNComputer),
     /// Intermediate cardinality result
     Cardinality(CardinalityCollector),
+    /// Intermediate weighted average result.
+    WeightedAvg(IntermediateWeightedAverage),
+    /// Intermediate median absolute deviation result.
+    MedianAbsoluteDeviation(PercentilesCollector),
+    /// Intermediate top_metrics result.
+    TopMetrics(IntermediateTopMetrics),
+    /// Intermediate percentile ranks result.
+    PercentileRanks(PercentilesCollector),
+    /// Intermediate boxplot result.
+    Boxplot(PercentilesCollector),
+    /// Intermediate string_stats result.
+    StringStats(IntermediateStringStats),
 }
 
 impl IntermediateMetricResult {
@@ -355,6 +414,29 @@ impl IntermediateMetricResult {
             IntermediateMetricResult::Cardinality(cardinality) => {
                 MetricResult::Cardinality(cardinality.finalize().into())
             }
+            IntermediateMetricResult::WeightedAvg(weighted_avg) => {
+                MetricResult::WeightedAvg(weighted_avg.finalize().into())
+            }
+            IntermediateMetricResult::MedianAbsoluteDeviation(sketch) => {
+                MetricResult::MedianAbsoluteDeviation(sketch.median_absolute_deviation().into())
+            }
+            IntermediateMetricResult::TopMetrics(top_metrics) => MetricResult::TopMetrics(
+                top_metrics
+                    .into_final_result(req.agg.as_top_metrics().expect("unexpected metric type")),
+            ),
+            IntermediateMetricResult::PercentileRanks(sketch) => MetricResult::PercentileRanks(
+                sketch.into_percentile_ranks_result(
+                    req.agg
+                        .as_percentile_ranks()
+                        .expect("unexpected metric type"),
+                ),
+            ),
+            IntermediateMetricResult::Boxplot(sketch) => {
+                MetricResult::Boxplot(sketch.into_boxplot_result())
+            }
+            IntermediateMetricResult::StringStats(string_stats) => MetricResult::StringStats(
+                string_stats.finalize(req.agg.as_string_stats().expect("unexpected metric type")),
+            ),
         }
     }
 
@@ -409,6 +491,36 @@ impl IntermediateMetricResult {
             ) => {
                 left.merge_fruits(right)?;
             }
+            (
+                IntermediateMetricResult::WeightedAvg(left),
+                IntermediateMetricResult::WeightedAvg(right),
+            ) => {
+                left.merge_fruits(right);
+            }
+            (
+                IntermediateMetricResult::MedianAbsoluteDeviation(left),
+                IntermediateMetricResult::MedianAbsoluteDeviation(right),
+            )
+            | (
+                IntermediateMetricResult::PercentileRanks(left),
+                IntermediateMetricResult::PercentileRanks(right),
+            )
+            | (IntermediateMetricResult::Boxplot(left), IntermediateMetricResult::Boxplot(right)) =>
+            {
+                left.merge_fruits(right)?;
+            }
+            (
+                IntermediateMetricResult::TopMetrics(left),
+                IntermediateMetricResult::TopMetrics(right),
+            ) => {
+                left.merge_fruits(right);
+            }
+            (
+                IntermediateMetricResult::StringStats(left),
+                IntermediateMetricResult::StringStats(right),
+            ) => {
+                left.merge_fruits(right);
+            }
             _ => {
                 panic!("incompatible fruit types in tree or missing merge_fruits handler");
             }
@@ -438,13 +550,30 @@ pub enum IntermediateBucketResult {
         /// The term buckets
         buckets: IntermediateTermBucketResult,
     },
-    /// Filter aggregation - a single bucket with sub-aggregations
+    /// Multi terms aggregation
+    MultiTerms {
+        /// The compound key buckets
+        buckets: IntermediateMultiTermsBucketResult,
+    },
+    /// Filters aggregation - one bucket per filter, in request order
+    Filters {
+        /// The filter buckets, the other bucket is the last one if requested
+        buckets: Vec<IntermediateFiltersBucketEntry>,
+    },
+    /// Filter aggregation - a single bucket with sub-aggregations.
+    /// Also used by the global, sampler and diversified_sampler aggregations.
     Filter {
         /// Document count in the filter bucket
         doc_count: u64,
         /// Sub-aggregation results
         sub_aggregations: IntermediateAggregationResults,
     },
+    /// Auto date histogram aggregation
+    AutoDateHistogram(IntermediateAutoDateHistogram),
+    /// Variable width histogram aggregation
+    VariableWidthHistogram(IntermediateVariableWidthHistogram),
+    /// Rare terms aggregation
+    RareTerms(IntermediateRareTerms),
 }
 
 impl IntermediateBucketResult {
@@ -461,26 +590,33 @@ impl IntermediateBucketResult {
                     .map(|bucket| {
                         bucket.into_final_bucket_entry(
                             req.sub_aggregation(),
-                            req.agg
-                                .as_range()
-                                .expect("unexpected aggregation, expected histogram aggregation"),
                             range_res.column_type,
                             limits,
                         )
                     })
                     .collect::<crate::Result<Vec<_>>>()?;
 
-                buckets.sort_by(|left, right| {
-                    left.from
-                        .unwrap_or(f64::MIN)
-                        .total_cmp(&right.from.unwrap_or(f64::MIN))
-                });
+                if range_res.column_type == Some(ColumnType::IpAddr) {
+                    // Ip range bounds are only available as strings.
+                    buckets.sort_by_cached_key(|bucket| {
+                        bucket
+                            .from_as_string
+                            .as_deref()
+                            .and_then(|ip| ip.parse::<IpAddr>().ok())
+                            .map(|ip| ip.into_ipv6_addr())
+                    });
+                } else {
+                    buckets.sort_by(|left, right| {
+                        left.from
+                            .unwrap_or(f64::MIN)
+                            .total_cmp(&right.from.unwrap_or(f64::MIN))
+                    });
+                }
 
                 let is_keyed = req
                     .agg
-                    .as_range()
-                    .expect("unexpected aggregation, expected range aggregation")
-                    .keyed;
+                    .is_keyed_range()
+                    .expect("unexpected aggregation, expected range aggregation");
                 let buckets = if is_keyed {
                     let mut bucket_map =
                         FxHashMap::with_capacity_and_hasher(buckets.len(), Default::default());
@@ -497,14 +633,15 @@ impl IntermediateBucketResult {
                 is_date_agg,
                 buckets,
             } => {
-                let histogram_req = &req
+                let (histogram_req, date_rounding) = req
                     .agg
                     .as_histogram()?
                     .expect("unexpected aggregation, expected histogram aggregation");
                 let buckets = intermediate_histogram_buckets_to_final_buckets(
                     buckets,
                     is_date_agg,
-                    histogram_req,
+                    &histogram_req,
+                    date_rounding.as_ref(),
                     req.sub_aggregation(),
                     limits,
                 )?;
@@ -521,6 +658,21 @@ impl IntermediateBucketResult {
                 };
                 Ok(BucketResult::Histogram { buckets })
             }
+            IntermediateBucketResult::AutoDateHistogram(histogram) => histogram.into_final_result(
+                req.agg
+                    .as_auto_date_histogram()
+                    .expect("unexpected aggregation, expected auto_date_histogram aggregation"),
+                req.sub_aggregation(),
+                limits,
+            ),
+            IntermediateBucketResult::VariableWidthHistogram(histogram) => histogram
+                .into_final_result(
+                    req.agg.as_variable_width_histogram().expect(
+                        "unexpected aggregation, expected variable_width_histogram aggregation",
+                    ),
+                    req.sub_aggregation(),
+                    limits,
+                ),
             IntermediateBucketResult::Terms { buckets: terms } => terms.into_final_result(
                 req.agg
                     .as_term()
@@ -528,6 +680,33 @@ impl IntermediateBucketResult {
                 req.sub_aggregation(),
                 limits,
             ),
+            IntermediateBucketResult::RareTerms(rare_terms) => {
+                rare_terms.into_final_result(req.sub_aggregation(), limits)
+            }
+            IntermediateBucketResult::MultiTerms { buckets } => buckets.into_final_result(
+                req.agg
+                    .as_multi_terms()
+                    .expect("unexpected aggregation, expected multi terms aggregation"),
+                req.sub_aggregation(),
+                limits,
+            ),
+            IntermediateBucketResult::Filters { buckets } => {
+                let filters_req = req
+                    .agg
+                    .as_filters()
+                    .expect("unexpected aggregation, expected filters aggregation");
+                let buckets = buckets
+                    .into_iter()
+                    .map(|bucket| bucket.into_final_bucket_entry(req.sub_aggregation(), limits))
+                    .collect::<crate::Result<Vec<_>>>()?;
+                let buckets = if let Some(keys) = filters_req.bucket_keys() {
+                    let bucket_map = keys.into_iter().map(str::to_string).zip(buckets).collect();
+                    BucketEntries::HashMap(bucket_map)
+                } else {
+                    BucketEntries::Vec(buckets)
+                };
+                Ok(BucketResult::Filters { buckets })
+            }
             IntermediateBucketResult::Filter {
                 doc_count,
                 sub_aggregations,
@@ -558,12 +737,28 @@ impl IntermediateBucketResult {
                 term_res_left.doc_count_error_upper_bound +=
                     term_res_right.doc_count_error_upper_bound;
             }
+            (
+                IntermediateBucketResult::MultiTerms {
+                    buckets: multi_terms_left,
+                },
+                IntermediateBucketResult::MultiTerms {
+                    buckets: multi_terms_right,
+                },
+            ) => {
+                merge_maps(&mut multi_terms_left.entries, multi_terms_right.entries)?;
+                multi_terms_left.sum_other_doc_count += multi_terms_right.sum_other_doc_count;
+                multi_terms_left.doc_count_error_upper_bound +=
+                    multi_terms_right.doc_count_error_upper_bound;
+            }
 
             (
                 IntermediateBucketResult::Range(range_res_left),
                 IntermediateBucketResult::Range(range_res_right),
             ) => {
                 merge_maps(&mut range_res_left.buckets, range_res_right.buckets)?;
+                if range_res_left.column_type.is_none() {
+                    range_res_left.column_type = range_res_right.column_type;
+                }
             }
             (
                 IntermediateBucketResult::Histogram {
@@ -606,6 +801,44 @@ impl IntermediateBucketResult {
                 *doc_count_left += doc_count_right;
                 sub_aggs_left.merge_fruits(sub_aggs_right)?;
             }
+            (
+                IntermediateBucketResult::Filters {
+                    buckets: buckets_left,
+                },
+                IntermediateBucketResult::Filters {
+                    buckets: buckets_right,
+                },
+            ) => {
+                if buckets_left.len() != buckets_right.len() {
+                    return Err(TantivyError::InternalError(format!(
+                        "can't merge filters aggregations with different number of buckets: {} \
+                         and {}",
+                        buckets_left.len(),
+                        buckets_right.len()
+                    )));
+                }
+                for (left, right) in buckets_left.iter_mut().zip(buckets_right) {
+                    left.merge_fruits(right)?;
+                }
+            }
+            (
+                IntermediateBucketResult::AutoDateHistogram(left),
+                IntermediateBucketResult::AutoDateHistogram(right),
+            ) => {
+                left.merge_fruits(right)?;
+            }
+            (
+                IntermediateBucketResult::VariableWidthHistogram(left),
+                IntermediateBucketResult::VariableWidthHistogram(right),
+            ) => {
+                left.merge_fruits(right)?;
+            }
+            (
+                IntermediateBucketResult::RareTerms(left),
+                IntermediateBucketResult::RareTerms(right),
+            ) => {
+                left.merge_fruits(right)?;
+            }
             (IntermediateBucketResult::Range(_), _) => {
                 panic!("try merge on different types")
             }
@@ -615,9 +848,24 @@ impl IntermediateBucketResult {
             (IntermediateBucketResult::Terms { .. }, _) => {
                 panic!("try merge on different types")
             }
+            (IntermediateBucketResult::MultiTerms { .. }, _) => {
+                panic!("try merge on different types")
+            }
+            (IntermediateBucketResult::Filters { .. }, _) => {
+                panic!("try merge on different types")
+            }
             (IntermediateBucketResult::Filter { .. }, _) => {
                 panic!("try merge on different types")
             }
+            (IntermediateBucketResult::AutoDateHistogram(_), _) => {
+                panic!("try merge on different types")
+            }
+            (IntermediateBucketResult::VariableWidthHistogram(_), _) => {
+                panic!("try merge on different types")
+            }
+            (IntermediateBucketResult::RareTerms(_), _) => {
+                panic!("try merge on different types")
+            }
         }
         Ok(())
     }
@@ -734,6 +982,111 @@ impl IntermediateTermBucketResult {
     }
 }
 
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Multi terms aggregation including error counts
+pub struct IntermediateMultiTermsBucketResult {
+    pub(crate) entries: FxHashMap<Vec<IntermediateKey>, IntermediateTermBucketEntry>,
+    pub(crate) sum_other_doc_count: u64,
+    pub(crate) doc_count_error_upper_bound: u64,
+}
+
+impl IntermediateMultiTermsBucketResult {
+    pub(crate) fn into_final_result(
+        self,
+        req: &MultiTermsAggregation,
+        sub_aggregation_req: &Aggregations,
+        limits: &mut AggregationLimitsGuard,
+    ) -> crate::Result<BucketResult> {
+        let min_doc_count = req.min_doc_count();
+        let mut buckets: Vec<MultiTermsBucketEntry> = self
+            .entries
+            .into_iter()
+            .filter(|bucket| bucket.1.doc_count as u64 >= min_doc_count)
+            .map(|(key, entry)| {
+                let key_as_string = key
+                    .iter()
+                    .map(|key| match key {
+                        IntermediateKey::Bool(key) => key.to_string(),
+                        key => Key::from(key.clone()).to_string(),
+                    })
+                    .join("|");
+                Ok(MultiTermsBucketEntry {
+                    key: key.into_iter().map(Key::from).collect(),
+                    key_as_string,
+                    doc_count: entry.doc_count as u64,
+                    sub_aggregation: entry
+                        .sub_aggregation
+                        .into_final_result_internal(sub_aggregation_req, limits)?,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+
+        let cmp_keys = |left: &MultiTermsBucketEntry, right: &MultiTermsBucketEntry| {
+            left.key.partial_cmp(&right.key).unwrap_or(Ordering::Equal)
+        };
+        let order = req.order();
+        match order.target {
+            OrderTarget::Key => {
+                buckets.sort_by(|left, right| {
+                    if order.order == Order::Asc {
+                        cmp_keys(left, right)
+                    } else {
+                        cmp_keys(right, left)
+                    }
+                });
+            }
+            OrderTarget::Count => {
+                // Ties are broken by key, so the result doesn't depend on the merge order.
+                buckets.sort_by(|left, right| {
+                    let count_ordering = if order.order == Order::Desc {
+                        right.doc_count.cmp(&left.doc_count)
+                    } else {
+                        left.doc_count.cmp(&right.doc_count)
+                    };
+                    count_ordering.then_with(|| cmp_keys(left, right))
+                });
+            }
+            OrderTarget::SubAggregation(name) => {
+                let (agg_name, agg_property) = get_agg_name_and_property(&name);
+                let mut buckets_with_val = buckets
+                    .into_iter()
+                    .map(|bucket| {
+                        let val = bucket
+                            .sub_aggregation
+                            .get_value_from_aggregation(agg_name, agg_property)?
+                            .unwrap_or(f64::MIN);
+                        Ok((bucket, val))
+                    })
+                    .collect::<crate::Result<Vec<_>>>()?;
+
+                buckets_with_val.sort_by(|(_, val1), (_, val2)| match &order.order {
+                    Order::Desc => val2.total_cmp(val1),
+                    Order::Asc => val1.total_cmp(val2),
+                });
+                buckets = buckets_with_val
+                    .into_iter()
+                    .map(|(bucket, _val)| bucket)
+                    .collect_vec();
+            }
+        }
+
+        let (_doc_count_before_cutoff, sum_other_doc_count) =
+            cut_off_buckets(&mut buckets, req.size() as usize);
+
+        let doc_count_error_upper_bound = if req.show_term_doc_count_error() {
+            Some(self.doc_count_error_upper_bound)
+        } else {
+            None
+        };
+
+        Ok(BucketResult::MultiTerms {
+            buckets,
+            sum_other_doc_count: self.sum_other_doc_count + sum_other_doc_count,
+            doc_count_error_upper_bound,
+        })
+    }
+}
+
 trait MergeFruits {
     fn merge_fruits(&mut self, other: Self) -> crate::Result<()>;
 }
@@ -797,13 +1150,18 @@ pub struct IntermediateRangeBucketEntry {
     pub from: Option<f64>,
     /// The to range of the bucket. Equals `f64::MAX` when `None`.
     pub to: Option<f64>,
+    /// The string representation of the from range, for bounds that are not numbers.
+    #[serde(default)]
+    pub from_as_string: Option<String>,
+    /// The string representation of the to range, for bounds that are not numbers.
+    #[serde(default)]
+    pub to_as_string: Option<String>,
 }
 
 impl IntermediateRangeBucketEntry {
     pub(crate) fn into_final_bucket_entry(
         self,
         req: &Aggregations,
-        _range_req: &RangeAggregation,
         column_type: Option<ColumnType>,
         limits: &mut AggregationLimitsGuard,
     ) -> crate::Result<RangeBucketEntry> {
@@ -815,8 +1173,8 @@ impl IntermediateRangeBucketEntry {
                 .into_final_result_internal(req, limits)?,
             to: self.to,
             from: self.from,
-            to_as_string: None,
-            from_as_string: None,
+            to_as_string: self.to_as_string,
+            from_as_string: self.from_as_string,
         };
 
         // If we have a date type on the histogram buckets, we add the `key_as_string` field as
@@ -854,6 +1212,39 @@ impl MergeFruits for IntermediateTermBucketEntry {
     }
 }
 
+/// This is the entry for a bucket of the filters aggregation, which contains a count, and
+/// optionally sub_aggregations.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateFiltersBucketEntry {
+    /// The number of documents in the bucket.
+    pub doc_count: u64,
+    /// The sub_aggregation in this bucket.
+    pub sub_aggregation: IntermediateAggregationResults,
+}
+
+impl IntermediateFiltersBucketEntry {
+    pub(crate) fn into_final_bucket_entry(
+        self,
+        req: &Aggregations,
+        limits: &mut AggregationLimitsGuard,
+    ) -> crate::Result<FilterBucketResult> {
+        Ok(FilterBucketResult {
+            doc_count: self.doc_count,
+            sub_aggregations: self
+                .sub_aggregation
+                .into_final_result_internal(req, limits)?,
+        })
+    }
+}
+
+impl MergeFruits for IntermediateFiltersBucketEntry {
+    fn merge_fruits(&mut self, other: IntermediateFiltersBucketEntry) -> crate::Result<()> {
+        self.doc_count += other.doc_count;
+        self.sub_aggregation.merge_fruits(other.sub_aggregation)?;
+        Ok(())
+    }
+}
+
 impl MergeFruits for IntermediateRangeBucketEntry {
     fn merge_fruits(&mut self, other: IntermediateRangeBucketEntry) -> crate::Result<()> {
         self.doc_count += other.doc_count;
@@ -891,6 +1282,8 @@ mod tests {
                     sub_aggregation_res: Default::default(),
                     from: None,
                     to: None,
+                    from_as_string: None,
+                    to_as_string: None,
                 },
             );
         }
@@ -921,6 +1314,8 @@ mod tests {
                     doc_count: *doc_count,
                     from: None,
                     to: None,
+                    from_as_string: None,
+                    to_as_string: None,
                     sub_aggregation_res: get_sub_test_tree(&[(
                         sub_aggregation_key.to_string(),
                         *sub_aggregation_count,
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::aggregation::*;
+use crate::TantivyError;
+
+/// # Boxplot
+///
+/// The boxplot aggregation returns the information required to draw a box plot of the values of
+/// a numeric field: the minimum, the maximum, the quartiles `q1`, `q2` (the median) and `q3`,
+/// and the `lower` and `upper` whiskers. The whiskers are the most extreme values within 1.5
+/// times the interquartile range (`q3 - q1`) of the quartiles.
+///
+/// ```JSON
+/// {
+///     "boxplot": {
+///         "field": "load_time"
+///     }
+/// }
+/// ```
+///
+/// The quartiles are estimated from the same DDSketch that backs the
+/// [percentiles](super::PercentilesAggregationReq) aggregation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoxplotAggregation {
+    /// The field name to compute the boxplot on.
+    pub field: String,
+    /// The missing parameter defines how documents that are missing a value should be treated.
+    /// By default they will be ignored but it is also possible to treat them as if they had a
+    /// value. Examples in JSON format:
+    /// { "field": "my_numbers", "missing": "10.0" }
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        deserialize_with = "deserialize_option_f64"
+    )]
+    pub missing: Option<f64>,
+}
+
+impl BoxplotAggregation {
+    /// Creates a new [`BoxplotAggregation`] instance from a field name.
+    pub fn from_field_name(field_name: String) -> Self {
+        BoxplotAggregation {
+            field: field_name,
+            missing: None,
+        }
+    }
+    /// Returns the field name the aggregation is computed on.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+}
+
+/// The boxplot metric result.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoxplotMetricResult {
+    /// The smallest value.
+    pub min: Option<f64>,
+    /// The largest value.
+    pub max: Option<f64>,
+    /// The first quartile (25th percentile).
+    pub q1: Option<f64>,
+    /// The median (50th percentile).
+    pub q2: Option<f64>,
+    /// The third quartile (75th percentile).
+    pub q3: Option<f64>,
+    /// The lower whisker, `max(min, q1 - 1.5 * (q3 - q1))`.
+    pub lower: Option<f64>,
+    /// The upper whisker, `min(max, q3 + 1.5 * (q3 - q1))`.
+    pub upper: Option<f64>,
+}
+
+impl BoxplotMetricResult {
+    pub(crate) fn get_value(&self, agg_property: &str) -> crate::Result<Option<f64>> {
+        match agg_property {
+            "min" => Ok(self.min),
+            "max" => Ok(self.max),
+            "q1" => Ok(self.q1),
+            "q2" => Ok(self.q2),
+            "q3" => Ok(self.q3),
+            "lower" => Ok(self.lower),
+            "upper" => Ok(self.upper),
+            _ => Err(TantivyError::InvalidArgument(format!(
+                "Unknown property {agg_property} on boxplot metric aggregation"
+            ))),
+        }
+    }
+}
+
+impl PercentilesCollector {
+    /// Convert result into the final boxplot result.
+    pub(crate) fn into_boxplot_result(self) -> BoxplotMetricResult {
+        let (min, max) = (self.min(), self.max());
+        let (q1, q2, q3) = (self.quantile(0.25), self.quantile(0.5), self.quantile(0.75));
+        let (lower, upper) = match (min, max, q1, q3) {
+            (Some(min), Some(max), Some(q1), Some(q3)) => {
+                let iqr = q3 - q1;
+                (Some(min.max(q1 - 1.5 * iqr)), Some(max.min(q3 + 1.5 * iqr)))
+            }
+            _ => (None, None),
+        };
+        BoxplotMetricResult {
+            min,
+            max,
+            q1,
+            q2,
+            q3,
+            lower,
+            upper,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_from_values};
+
+    #[test]
+    fn test_aggregation_boxplot() -> crate::Result<()> {
+        let mut values: Vec<f64> = (1..=100).map(|val| val as f64).collect();
+        values.push(1000.0);
+        let index = get_test_index_from_values(true, &values)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "box": { "boxplot": { "field": "score" } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let get = |key: &str| res["box"][key].as_f64().unwrap();
+        assert_eq!(get("min"), 1.0);
+        assert_eq!(get("max"), 1000.0);
+        assert!((24.0..=27.0).contains(&get("q1")));
+        assert!((49.0..=52.0).contains(&get("q2")));
+        assert!((74.0..=78.0).contains(&get("q3")));
+        assert_eq!(get("lower"), 1.0);
+        // The outlier is excluded by the upper whisker.
+        let upper = get("upper");
+        assert!(upper > 100.0 && upper < 200.0, "{upper}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_boxplot_empty_index() -> crate::Result<()> {
+        let index = get_test_index_from_values(false, &[])?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "box": { "boxplot": { "field": "score" } }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(res["box"]["q2"], Value::Null);
+        assert_eq!(res["box"]["upper"], Value::Null);
+        Ok(())
+    }
+}
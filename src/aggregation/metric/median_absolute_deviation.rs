@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::aggregation::*;
+
+/// # Median Absolute Deviation
+///
+/// The median absolute deviation (MAD) is a robust measure of variability. It is defined as the
+/// median of the absolute deviations of the values from the median of the values:
+/// `median(|x - median(x)|)`. Unlike the standard deviation it is barely affected by outliers.
+///
+/// ```JSON
+/// {
+///     "median_absolute_deviation": {
+///         "field": "rating"
+///     }
+/// }
+/// ```
+///
+/// The value is estimated from the same DDSketch that backs the
+/// [percentiles](super::PercentilesAggregationReq) aggregation, so the result is approximate.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MedianAbsoluteDeviationAggregation {
+    /// The field name to compute the median absolute deviation on.
+    pub field: String,
+    /// The missing parameter defines how documents that are missing a value should be treated.
+    /// By default they will be ignored but it is also possible to treat them as if they had a
+    /// value. Examples in JSON format:
+    /// { "field": "my_numbers", "missing": "10.0" }
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        deserialize_with = "deserialize_option_f64"
+    )]
+    pub missing: Option<f64>,
+}
+
+impl MedianAbsoluteDeviationAggregation {
+    /// Creates a new [`MedianAbsoluteDeviationAggregation`] instance from a field name.
+    pub fn from_field_name(field_name: String) -> Self {
+        MedianAbsoluteDeviationAggregation {
+            field: field_name,
+            missing: None,
+        }
+    }
+    /// Returns the field name the aggregation is computed on.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+}
+
+/// Number of evenly spaced quantiles sampled from the sketch to approximate the distribution of
+/// the absolute deviations.
+const NUM_DEVIATION_SAMPLES: usize = 1000;
+
+impl PercentilesCollector {
+    /// Estimates the median absolute deviation of the collected values. Returns `None` if no
+    /// value has been collected.
+    pub(crate) fn median_absolute_deviation(&self) -> Option<f64> {
+        let median = self.quantile(0.5)?;
+        let num_samples = self.count().min(NUM_DEVIATION_SAMPLES);
+        let mut deviations: Vec<f64> = (0..num_samples)
+            .filter_map(|idx| self.quantile((idx as f64 + 0.5) / num_samples as f64))
+            .map(|value| (value - median).abs())
+            .collect();
+        deviations.sort_unstable_by(f64::total_cmp);
+        let mid = deviations.len() / 2;
+        if deviations.len() % 2 == 1 {
+            Some(deviations[mid])
+        } else {
+            Some((deviations[mid - 1] + deviations[mid]) / 2.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_from_values};
+
+    #[test]
+    fn test_aggregation_median_absolute_deviation() -> crate::Result<()> {
+        // median is 3, absolute deviations are [2, 1, 1, 0, 0, 1, 2, 97] -> median 1
+        let values = vec![1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 5.0, 100.0];
+        let index = get_test_index_from_values(true, &values)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "mad": { "median_absolute_deviation": { "field": "score" } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let mad = res["mad"]["value"].as_f64().unwrap();
+        assert!((0.95..=1.05).contains(&mad), "{mad}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_median_absolute_deviation_empty_index() -> crate::Result<()> {
+        let index = get_test_index_from_values(false, &[])?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "mad": { "median_absolute_deviation": { "field": "score" } }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(res["mad"]["value"], Value::Null);
+        Ok(())
+    }
+}
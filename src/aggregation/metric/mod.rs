@@ -15,38 +15,56 @@
 //! - [Sum](SumAggregation)
 //! - [Count](CountAggregation)
 //! - [Percentiles](PercentilesAggregationReq)
+//! - [PercentileRanks](PercentileRanksAggregationReq)
+//! - [WeightedAvg](WeightedAvgAggregation)
+//! - [MedianAbsoluteDeviation](MedianAbsoluteDeviationAggregation)
+//! - [Boxplot](BoxplotAggregation)
+//! - [TopMetrics](TopMetricsAggregationReq)
+//! - [StringStats](StringStatsAggregation)
 
 mod average;
+mod boxplot;
 mod cardinality;
 mod count;
 mod extended_stats;
 mod max;
+mod median_absolute_deviation;
 mod min;
+mod percentile_ranks;
 mod percentiles;
 mod stats;
+mod string_stats;
 mod sum;
 mod top_hits;
+mod top_metrics;
+mod weighted_avg;
 
 use std::collections::HashMap;
 
 pub use average::*;
+pub use boxplot::*;
 pub use cardinality::*;
 use columnar::{Column, ColumnType};
 pub use count::*;
 pub use extended_stats::*;
 pub use max::*;
+pub use median_absolute_deviation::*;
 pub use min::*;
+pub use percentile_ranks::*;
 pub use percentiles::*;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 pub use stats::*;
+pub use string_stats::*;
 pub use sum::*;
 pub use top_hits::*;
+pub use top_metrics::*;
+pub use weighted_avg::*;
 
 use crate::schema::OwnedValue;
 
 /// Contains all information required by metric aggregations like avg, min, max, sum, stats,
-/// extended_stats, count, percentiles.
+/// extended_stats, count, percentiles, percentile_ranks, median_absolute_deviation, boxplot.
 #[repr(C)]
 pub struct MetricAggReqData {
     /// True if the field is of number or date type.
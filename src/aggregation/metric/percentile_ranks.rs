@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::aggregation::*;
+use crate::TantivyError;
+
+/// # Percentile Ranks
+///
+/// The percentile ranks aggregation is the inverse of the
+/// [percentiles](super::PercentilesAggregationReq) aggregation. For each requested value it
+/// returns the percentage of collected values that are less than or equal to it.
+///
+/// For example, to find out which share of requests was served in at most 200 and 500
+/// milliseconds:
+///
+/// ```JSON
+/// {
+///     "percentile_ranks": {
+///         "field": "load_time",
+///         "values": [200, 500]
+///     }
+/// }
+/// ```
+///
+/// The response contains the ranks keyed by the requested values, e.g. `{"200.0": 72.5,
+/// "500.0": 98.1}`. Like percentiles, ranks are estimated from a DDSketch and are therefore
+/// approximate.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PercentileRanksAggregationReq {
+    /// The field name to compute the percentile ranks on.
+    pub field: String,
+    /// The values for which to compute the percentile ranks.
+    pub values: Vec<f64>,
+    /// Whether to return the percentile ranks as a hash map
+    #[serde(default = "default_as_true")]
+    pub keyed: bool,
+    /// The missing parameter defines how documents that are missing a value should be treated.
+    /// By default they will be ignored but it is also possible to treat them as if they had a
+    /// value. Examples in JSON format:
+    /// { "field": "my_numbers", "missing": "10.0" }
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        deserialize_with = "deserialize_option_f64"
+    )]
+    pub missing: Option<f64>,
+}
+
+fn default_as_true() -> bool {
+    true
+}
+
+impl PercentileRanksAggregationReq {
+    /// Creates a new [`PercentileRanksAggregationReq`] instance from a field name and the values
+    /// to rank.
+    pub fn from_field_name(field_name: String, values: Vec<f64>) -> Self {
+        PercentileRanksAggregationReq {
+            field: field_name,
+            values,
+            keyed: default_as_true(),
+            missing: None,
+        }
+    }
+    /// Returns the field name the aggregation is computed on.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+
+    /// Validates the request parameters.
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.values.is_empty() {
+            return Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(
+                    "percentile_ranks requires at least one value".to_string(),
+                ),
+            ));
+        }
+        if self.values.iter().any(|value| !value.is_finite()) {
+            return Err(TantivyError::AggregationError(
+                AggregationError::InvalidRequest(
+                    "percentile_ranks values have to be finite numbers".to_string(),
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Number of bisection steps used to invert the quantile function of the sketch.
+const RANK_BISECTION_STEPS: usize = 64;
+
+impl PercentilesCollector {
+    /// Estimates the percentage (between 0.0 and 100.0) of collected values that are less than
+    /// or equal to `value`. Returns `None` if no value has been collected.
+    pub(crate) fn percentile_rank(&self, value: f64) -> Option<f64> {
+        let (min, max) = (self.min()?, self.max()?);
+        if value < min {
+            return Some(0.0);
+        }
+        if value >= max {
+            return Some(100.0);
+        }
+        // The quantile function is monotonic, so we search the largest quantile whose value is
+        // still <= `value`.
+        let (mut low, mut high) = (0.0f64, 1.0f64);
+        for _ in 0..RANK_BISECTION_STEPS {
+            let mid = (low + high) / 2.0;
+            match self.quantile(mid) {
+                Some(quantile_value) if quantile_value <= value => low = mid,
+                _ => high = mid,
+            }
+        }
+        Some(low * 100.0)
+    }
+
+    /// Convert result into the final percentile ranks result.
+    pub(crate) fn into_percentile_ranks_result(
+        self,
+        req: &PercentileRanksAggregationReq,
+    ) -> PercentilesMetricResult {
+        let iter_value_and_ranks = req
+            .values
+            .iter()
+            .map(|&value| (value, self.percentile_rank(value).unwrap_or(f64::NAN)));
+        let values = if req.keyed {
+            PercentileValues::HashMap(
+                iter_value_and_ranks
+                    .map(|(value, rank)| (format_percentile(value), rank))
+                    .collect(),
+            )
+        } else {
+            PercentileValues::Vec(
+                iter_value_and_ranks
+                    .map(|(key, value)| PercentileValuesVecEntry { key, value })
+                    .collect(),
+            )
+        };
+        PercentilesMetricResult { values }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_from_values};
+
+    #[test]
+    fn test_aggregation_percentile_ranks() -> crate::Result<()> {
+        let values: Vec<f64> = (1..=100).map(|val| val as f64).collect();
+        let index = get_test_index_from_values(false, &values)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "ranks": {
+                "percentile_ranks": {
+                    "field": "score",
+                    "values": [0, 25, 50, 100, 200]
+                }
+            },
+            "ranks_vec": {
+                "percentile_ranks": {
+                    "field": "score",
+                    "values": [50],
+                    "keyed": false
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let ranks = &res["ranks"]["values"];
+        assert_eq!(ranks["0.0"], 0.0);
+        assert_eq!(ranks["100.0"], 100.0);
+        assert_eq!(ranks["200.0"], 100.0);
+        let rank_25 = ranks["25.0"].as_f64().unwrap();
+        assert!((23.0..=27.0).contains(&rank_25), "{rank_25}");
+        let rank_50 = ranks["50.0"].as_f64().unwrap();
+        assert!((48.0..=52.0).contains(&rank_50), "{rank_50}");
+
+        assert_eq!(res["ranks_vec"]["values"][0]["key"], 50.0);
+        let rank_50 = res["ranks_vec"]["values"][0]["value"].as_f64().unwrap();
+        assert!((48.0..=52.0).contains(&rank_50), "{rank_50}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_percentile_ranks_empty_index() -> crate::Result<()> {
+        let index = get_test_index_from_values(false, &[])?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "ranks": { "percentile_ranks": { "field": "score", "values": [10] } }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(res["ranks"]["values"]["10.0"], Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_percentile_ranks_requires_values() -> crate::Result<()> {
+        let index = get_test_index_from_values(false, &[1.0])?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "ranks": { "percentile_ranks": { "field": "score", "values": [] } }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"percentile_ranks requires at least one value\""
+        );
+        Ok(())
+    }
+}
@@ -140,6 +140,8 @@ pub(crate) struct SegmentPercentilesCollector {
     pub missing_u64: Option<u64>,
     /// The column accessor to access the fast field values.
     pub accessor: Column<u64>,
+    /// Which metric the collected sketch is used for (percentiles, percentile_ranks, ...).
+    pub collecting_for: StatsType,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -166,7 +168,7 @@ impl PartialEq for PercentilesCollector {
     }
 }
 
-fn format_percentile(percentile: f64) -> String {
+pub(crate) fn format_percentile(percentile: f64) -> String {
     let mut out = percentile.to_string();
     // Slightly silly way to format trailing decimals
     if !out.contains('.') {
@@ -213,7 +215,28 @@ impl PercentilesCollector {
         PercentilesMetricResult { values }
     }
 
-    fn new() -> Self {
+    /// Returns the estimated value at quantile `q` (between 0.0 and 1.0), or `None` if no value
+    /// has been collected.
+    pub(crate) fn quantile(&self, q: f64) -> Option<f64> {
+        self.sketch.quantile(q).ok().flatten()
+    }
+
+    /// Returns the number of collected values.
+    pub(crate) fn count(&self) -> usize {
+        self.sketch.count()
+    }
+
+    /// Returns the smallest collected value.
+    pub(crate) fn min(&self) -> Option<f64> {
+        self.sketch.min()
+    }
+
+    /// Returns the largest collected value.
+    pub(crate) fn max(&self) -> Option<f64> {
+        self.sketch.max()
+    }
+
+    pub(crate) fn new() -> Self {
         let ddsketch_config = sketches_ddsketch::Config::defaults();
         let sketch = sketches_ddsketch::DDSketch::new(ddsketch_config);
         Self { sketch }
@@ -239,6 +262,7 @@ impl SegmentPercentilesCollector {
         missing_u64: Option<u64>,
         accessor: Column<u64>,
         accessor_idx: usize,
+        collecting_for: StatsType,
     ) -> Self {
         Self {
             buckets: Vec::with_capacity(64),
@@ -246,6 +270,7 @@ impl SegmentPercentilesCollector {
             missing_u64,
             accessor,
             accessor_idx,
+            collecting_for,
         }
     }
 }
@@ -263,8 +288,16 @@ impl SegmentAggregationCollector for SegmentPercentilesCollector {
         // Swap collector with an empty one to avoid cloning
         let percentiles_collector = std::mem::take(&mut self.buckets[parent_bucket_id as usize]);
 
-        let intermediate_metric_result =
-            IntermediateMetricResult::Percentiles(percentiles_collector);
+        let intermediate_metric_result = match self.collecting_for {
+            StatsType::PercentileRanks => {
+                IntermediateMetricResult::PercentileRanks(percentiles_collector)
+            }
+            StatsType::MedianAbsoluteDeviation => {
+                IntermediateMetricResult::MedianAbsoluteDeviation(percentiles_collector)
+            }
+            StatsType::Boxplot => IntermediateMetricResult::Boxplot(percentiles_collector),
+            _ => IntermediateMetricResult::Percentiles(percentiles_collector),
+        };
 
         results.push(
             name,
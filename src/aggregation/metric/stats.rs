@@ -185,6 +185,12 @@ pub enum StatsType {
     Sum,
     /// The percentiles of the values.
     Percentiles,
+    /// The percentile ranks of the requested values.
+    PercentileRanks,
+    /// The median absolute deviation of the values.
+    MedianAbsoluteDeviation,
+    /// The boxplot (min, max, quartiles and whiskers) of the values.
+    Boxplot,
 }
 
 fn create_collector<const TYPE_ID: u8>(
@@ -0,0 +1,376 @@
+use columnar::{Column, Dictionary, StrColumn};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::agg_data::AggregationsSegmentCtx;
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateMetricResult,
+};
+use crate::aggregation::segment_agg_result::SegmentAggregationCollector;
+use crate::aggregation::*;
+use crate::TantivyError;
+
+/// # String Stats
+///
+/// The string_stats aggregation computes statistics over the values of a text fast field:
+/// the number of values, the minimum, maximum and average length (in characters) and the
+/// Shannon entropy of the characters of all values.
+///
+/// ```JSON
+/// {
+///     "string_stats": {
+///         "field": "tag",
+///         "show_distribution": true
+///     }
+/// }
+/// ```
+///
+/// With `show_distribution` the probability of each character is returned as well.
+///
+/// # Response
+/// ```JSON
+/// {
+///     "count": 5,
+///     "min_length": 2,
+///     "max_length": 4,
+///     "avg_length": 3.0,
+///     "entropy": 2.32,
+///     "distribution": { "a": 0.2, ... }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StringStatsAggregation {
+    /// The field name to compute the stats on.
+    pub field: String,
+    /// Whether to return the probability distribution of the characters.
+    #[serde(default)]
+    pub show_distribution: bool,
+    /// The missing parameter defines how documents that are missing a value should be treated.
+    /// By default they will be ignored but it is also possible to treat them as if they had a
+    /// value. Examples in JSON format:
+    /// { "field": "my_text", "missing": "unknown" }
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub missing: Option<String>,
+}
+
+impl StringStatsAggregation {
+    /// Creates a new [`StringStatsAggregation`] instance from a field name.
+    pub fn from_field_name(field_name: String) -> Self {
+        Self {
+            field: field_name,
+            show_distribution: false,
+            missing: None,
+        }
+    }
+    /// Returns the field name the aggregation is computed on.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+}
+
+/// Contains all information required by the SegmentStringStatsCollector to perform the
+/// string_stats aggregation on a segment.
+pub struct StringStatsAggReqData {
+    /// The column accessor to access the term ordinals.
+    pub accessor: Column<u64>,
+    /// The string dictionary column, if the field exists in the segment.
+    pub str_dict_column: Option<StrColumn>,
+    /// The sentinel term ordinal used for documents without a value, if `missing` is set.
+    pub missing_value_for_accessor: Option<u64>,
+    /// The name of the aggregation.
+    pub name: String,
+    /// The aggregation request.
+    pub req: StringStatsAggregation,
+}
+
+impl StringStatsAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// Intermediate result of the string_stats aggregation that can be combined with other
+/// intermediate results.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateStringStats {
+    count: u64,
+    total_length: u64,
+    min_length: Option<u64>,
+    max_length: Option<u64>,
+    char_counts: FxHashMap<char, u64>,
+}
+
+impl IntermediateStringStats {
+    /// Adds `term` with `term_count` occurrences.
+    fn collect_term(&mut self, term: &str, term_count: u64) {
+        let mut length = 0u64;
+        for ch in term.chars() {
+            length += 1;
+            *self.char_counts.entry(ch).or_default() += term_count;
+        }
+        self.count += term_count;
+        self.total_length += length * term_count;
+        self.min_length = Some(self.min_length.map_or(length, |min| min.min(length)));
+        self.max_length = Some(self.max_length.map_or(length, |max| max.max(length)));
+    }
+
+    /// Merges the other intermediate result into self.
+    pub fn merge_fruits(&mut self, other: IntermediateStringStats) {
+        self.count += other.count;
+        self.total_length += other.total_length;
+        self.min_length = match (self.min_length, other.min_length) {
+            (Some(left), Some(right)) => Some(left.min(right)),
+            (left, right) => left.or(right),
+        };
+        self.max_length = match (self.max_length, other.max_length) {
+            (Some(left), Some(right)) => Some(left.max(right)),
+            (left, right) => left.or(right),
+        };
+        for (ch, count) in other.char_counts {
+            *self.char_counts.entry(ch).or_default() += count;
+        }
+    }
+
+    /// Computes the final string stats.
+    pub fn finalize(self, req: &StringStatsAggregation) -> StringStatsMetricResult {
+        let total_chars = self.total_length as f64;
+        let probabilities = self
+            .char_counts
+            .into_iter()
+            .map(|(ch, count)| (ch, count as f64 / total_chars));
+        let mut entropy = 0.0;
+        let mut distribution = FxHashMap::default();
+        for (ch, probability) in probabilities {
+            entropy -= probability * probability.log2();
+            if req.show_distribution {
+                distribution.insert(ch.to_string(), probability);
+            }
+        }
+        let has_values = self.count > 0;
+        StringStatsMetricResult {
+            count: self.count,
+            min_length: self.min_length,
+            max_length: self.max_length,
+            avg_length: has_values.then(|| total_chars / self.count as f64),
+            entropy: has_values.then_some(entropy),
+            distribution: req.show_distribution.then_some(distribution),
+        }
+    }
+}
+
+/// The string_stats metric result.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StringStatsMetricResult {
+    /// The number of values.
+    pub count: u64,
+    /// The length of the shortest value, in characters.
+    pub min_length: Option<u64>,
+    /// The length of the longest value, in characters.
+    pub max_length: Option<u64>,
+    /// The average length of the values, in characters.
+    pub avg_length: Option<f64>,
+    /// The Shannon entropy of the characters of all values.
+    pub entropy: Option<f64>,
+    /// The probability of each character. Only set if `show_distribution` is requested.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub distribution: Option<FxHashMap<String, f64>>,
+}
+
+impl StringStatsMetricResult {
+    pub(crate) fn get_value(&self, agg_property: &str) -> crate::Result<Option<f64>> {
+        match agg_property {
+            "count" => Ok(Some(self.count as f64)),
+            "min_length" => Ok(self.min_length.map(|len| len as f64)),
+            "max_length" => Ok(self.max_length.map(|len| len as f64)),
+            "avg_length" => Ok(self.avg_length),
+            "entropy" => Ok(self.entropy),
+            _ => Err(TantivyError::InvalidArgument(format!(
+                "Unknown property {agg_property} on string_stats metric aggregation"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SegmentStringStatsCollector {
+    /// Term ordinal counts per bucket.
+    buckets: Vec<FxHashMap<u64, u64>>,
+    accessor_idx: usize,
+}
+
+impl SegmentStringStatsCollector {
+    pub fn from_req(accessor_idx: usize) -> Self {
+        Self {
+            buckets: vec![FxHashMap::default()],
+            accessor_idx,
+        }
+    }
+
+    fn into_intermediate_metric_result(
+        term_counts: FxHashMap<u64, u64>,
+        req_data: &StringStatsAggReqData,
+    ) -> crate::Result<IntermediateStringStats> {
+        let mut stats = IntermediateStringStats::default();
+        let fallback_dict = Dictionary::empty();
+        let dict = req_data
+            .str_dict_column
+            .as_ref()
+            .map(|el| el.dictionary())
+            .unwrap_or(&fallback_dict);
+        let mut missing_count = 0;
+        let mut term_ords_and_counts: Vec<(u64, u64)> = Vec::with_capacity(term_counts.len());
+        for (term_ord, count) in term_counts {
+            if Some(term_ord) == req_data.missing_value_for_accessor {
+                missing_count = count;
+            } else {
+                term_ords_and_counts.push((term_ord, count));
+            }
+        }
+        term_ords_and_counts.sort_unstable_by_key(|(term_ord, _)| *term_ord);
+        let mut counts = term_ords_and_counts.iter().map(|(_, count)| *count);
+        dict.sorted_ords_to_term_cb(
+            term_ords_and_counts.iter().map(|(term_ord, _)| *term_ord),
+            |term| {
+                let count = counts.next().expect("one count per term ordinal");
+                stats.collect_term(&String::from_utf8_lossy(term), count);
+                Ok(())
+            },
+        )?;
+        if missing_count > 0 {
+            let missing = req_data
+                .req
+                .missing
+                .as_ref()
+                .expect("Found sentinel term ordinal but `missing` is not set");
+            stats.collect_term(missing, missing_count);
+        }
+        Ok(stats)
+    }
+}
+
+impl SegmentAggregationCollector for SegmentStringStatsCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        self.prepare_max_bucket(parent_bucket_id, agg_data)?;
+        let req_data = agg_data.get_string_stats_req_data(self.accessor_idx);
+        let term_counts = std::mem::take(&mut self.buckets[parent_bucket_id as usize]);
+        let stats = Self::into_intermediate_metric_result(term_counts, req_data)?;
+        results.push(
+            req_data.name.clone(),
+            IntermediateAggregationResult::Metric(IntermediateMetricResult::StringStats(stats)),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        parent_bucket_id: BucketId,
+        docs: &[crate::DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        let req_data = &agg_data.per_request.string_stats_req_data[self.accessor_idx];
+        agg_data.column_block_accessor.fetch_block_with_missing(
+            docs,
+            &req_data.accessor,
+            req_data.missing_value_for_accessor,
+        );
+        let bucket = &mut self.buckets[parent_bucket_id as usize];
+        for term_ord in agg_data.column_block_accessor.iter_vals() {
+            *bucket.entry(term_ord).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        max_bucket: BucketId,
+        _agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        if max_bucket as usize >= self.buckets.len() {
+            self.buckets
+                .resize_with(max_bucket as usize + 1, Default::default);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::exec_request;
+    use crate::schema::{Schema, FAST, STRING};
+    use crate::{Index, IndexWriter};
+
+    fn get_string_stats_test_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let tag = schema_builder.add_text_field("tag", STRING | FAST);
+        let score = schema_builder.add_u64_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(tag => "ab", score => 1u64))?;
+        index_writer.add_document(doc!(tag => "abba", score => 2u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(tag => "ab", score => 3u64))?;
+        index_writer.add_document(doc!(score => 4u64))?;
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    #[test]
+    fn test_aggregation_string_stats() -> crate::Result<()> {
+        let index = get_string_stats_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "stats": { "string_stats": { "field": "tag", "show_distribution": true } },
+            "stats_missing": { "string_stats": { "field": "tag", "missing": "abab" } },
+            "stats_no_field": { "string_stats": { "field": "does_not_exist" } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["stats"],
+            json!({
+                "count": 3,
+                "min_length": 2,
+                "max_length": 4,
+                "avg_length": 8.0 / 3.0,
+                "entropy": 1.0,
+                "distribution": { "a": 0.5, "b": 0.5 }
+            })
+        );
+        assert_eq!(res["stats_missing"]["count"], 4);
+        assert_eq!(res["stats_missing"]["avg_length"], 3.0);
+        assert_eq!(res["stats_missing"]["max_length"], 4);
+        assert_eq!(res["stats_missing"].get("distribution"), None);
+        assert_eq!(res["stats_no_field"]["count"], 0);
+        assert_eq!(res["stats_no_field"]["avg_length"], Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_string_stats_as_sub_aggregation() -> crate::Result<()> {
+        let index = get_string_stats_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "scores": {
+                "range": { "field": "score", "ranges": [{ "to": 2.0 }, { "from": 2.0 }] },
+                "aggs": { "tags": { "string_stats": { "field": "tag" } } }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["scores"]["buckets"];
+        assert_eq!(buckets[0]["tags"]["count"], 1);
+        assert_eq!(buckets[0]["tags"]["max_length"], 2);
+        assert_eq!(buckets[1]["tags"]["count"], 2);
+        assert_eq!(buckets[1]["tags"]["max_length"], 4);
+        Ok(())
+    }
+}
@@ -133,9 +133,9 @@ pub struct TopHitsAggregationReq {
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
-struct KeyOrder {
-    field: String,
-    order: Order,
+pub(crate) struct KeyOrder {
+    pub(crate) field: String,
+    pub(crate) order: Order,
 }
 
 impl Serialize for KeyOrder {
@@ -152,12 +152,11 @@ impl<'de> Deserialize<'de> for KeyOrder {
     where D: Deserializer<'de> {
         let mut key_order = <HashMap<String, Order>>::deserialize(deserializer)?.into_iter();
         let (field, order) = key_order.next().ok_or(serde::de::Error::custom(
-            "Expected exactly one key-value pair in sort parameter of top_hits, found none",
+            "Expected exactly one key-value pair in sort parameter, found none",
         ))?;
         if key_order.next().is_some() {
             return Err(serde::de::Error::custom(format!(
-                "Expected exactly one key-value pair in sort parameter of top_hits, found \
-                 {key_order:?}"
+                "Expected exactly one key-value pair in sort parameter, found {key_order:?}"
             )));
         }
         Ok(Self { field, order })
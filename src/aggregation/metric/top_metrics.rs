@@ -0,0 +1,422 @@
+use std::cmp::Ordering;
+
+use columnar::{Column, ColumnType};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::top_hits::KeyOrder;
+use crate::aggregation::agg_data::AggregationsSegmentCtx;
+use crate::aggregation::bucket::Order;
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateMetricResult,
+};
+use crate::aggregation::segment_agg_result::SegmentAggregationCollector;
+use crate::aggregation::*;
+
+/// # Top Metrics
+///
+/// The top_metrics aggregation selects the metrics of the documents with the largest or
+/// smallest value of a sort field. It is a lightweight alternative to
+/// [top_hits](super::TopHitsAggregationReq): all values are read from fast fields, the document
+/// store is never accessed and only numeric and date fields are supported.
+///
+/// ```JSON
+/// {
+///     "top_metrics": {
+///         "metrics": [{ "field": "price" }, { "field": "rating" }],
+///         "sort": { "date": "desc" },
+///         "size": 1
+///     }
+/// }
+/// ```
+///
+/// `metrics` accepts a single field object or a list of them. `size` defaults to 1.
+/// Documents without a value for the sort field are ignored. If a document has multiple values,
+/// the first one is used.
+///
+/// # Response
+/// ```JSON
+/// {
+///     "top": [
+///         { "sort": [1700000000000000000], "metrics": { "price": 10.0, "rating": 4.0 } }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopMetricsAggregationReq {
+    #[serde(deserialize_with = "deserialize_one_or_many")]
+    metrics: Vec<TopMetricsField>,
+    sort: KeyOrder,
+    #[serde(default = "default_size")]
+    size: usize,
+}
+
+/// A field whose value is returned by the [`TopMetricsAggregationReq`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopMetricsField {
+    /// The field name.
+    pub field: String,
+}
+
+fn default_size() -> usize {
+    1
+}
+
+fn deserialize_one_or_many<'de, D>(deserializer: D) -> Result<Vec<TopMetricsField>, D::Error>
+where D: Deserializer<'de> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(TopMetricsField),
+        Many(Vec<TopMetricsField>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(field) => vec![field],
+        OneOrMany::Many(fields) => fields,
+    })
+}
+
+impl TopMetricsAggregationReq {
+    /// Return fields accessed by the aggregator: the sort field followed by the metric fields.
+    pub fn field_names(&self) -> Vec<&str> {
+        std::iter::once(self.sort.field.as_str())
+            .chain(self.metric_field_names())
+            .collect()
+    }
+
+    /// Return the fields whose values are returned.
+    pub fn metric_field_names(&self) -> impl Iterator<Item = &str> {
+        self.metrics.iter().map(|metric| metric.field.as_str())
+    }
+
+    /// Returns the field used to sort the documents.
+    pub fn sort_field_name(&self) -> &str {
+        &self.sort.field
+    }
+
+    /// Validates the request parameters.
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.size == 0 {
+            return Err(crate::TantivyError::AggregationError(
+                AggregationError::InvalidRequest(
+                    "top_metrics size has to be at least 1".to_string(),
+                ),
+            ));
+        }
+        if self.metrics.is_empty() {
+            return Err(crate::TantivyError::AggregationError(
+                AggregationError::InvalidRequest(
+                    "top_metrics requires at least one metric field".to_string(),
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Contains all information required by the SegmentTopMetricsCollector to perform the
+/// top_metrics aggregation on a segment.
+pub struct TopMetricsAggReqData {
+    /// The accessor of the sort field.
+    pub sort_accessor: (Column<u64>, ColumnType),
+    /// The accessors of the metric fields, in the order of the request.
+    pub metric_accessors: Vec<(Column<u64>, ColumnType)>,
+    /// The name of the aggregation.
+    pub name: String,
+    /// The top_metrics aggregation request.
+    pub req: TopMetricsAggregationReq,
+}
+
+impl TopMetricsAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// The sort value and the metric values of a single document.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopMetricsEntry {
+    sort: f64,
+    metrics: Vec<Option<f64>>,
+}
+
+/// Intermediate result of the top_metrics aggregation that can be combined with other
+/// intermediate results.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateTopMetrics {
+    size: usize,
+    order: Order,
+    top: Vec<TopMetricsEntry>,
+}
+
+impl IntermediateTopMetrics {
+    /// Creates an empty intermediate result for the request.
+    pub(crate) fn new(req: &TopMetricsAggregationReq) -> Self {
+        Self {
+            size: req.size,
+            order: req.sort.order,
+            top: Vec::new(),
+        }
+    }
+
+    fn compare(order: Order, left: &TopMetricsEntry, right: &TopMetricsEntry) -> Ordering {
+        match order {
+            Order::Asc => left.sort.total_cmp(&right.sort),
+            Order::Desc => right.sort.total_cmp(&left.sort),
+        }
+    }
+
+    fn truncate_top(&mut self) {
+        let order = self.order;
+        self.top
+            .sort_unstable_by(|left, right| Self::compare(order, left, right));
+        self.top.truncate(self.size);
+    }
+
+    fn collect(&mut self, entry: TopMetricsEntry) {
+        self.top.push(entry);
+        // Amortize sorting by only truncating once the buffer is twice the requested size.
+        if self.top.len() >= self.size * 2 {
+            self.truncate_top();
+        }
+    }
+
+    /// Merges the other intermediate result into self.
+    pub fn merge_fruits(&mut self, other: IntermediateTopMetrics) {
+        self.top.extend(other.top);
+        self.truncate_top();
+    }
+
+    /// Computes the final top_metrics result.
+    pub fn into_final_result(mut self, req: &TopMetricsAggregationReq) -> TopMetricsMetricResult {
+        self.truncate_top();
+        let top = self
+            .top
+            .into_iter()
+            .map(|entry| TopMetricsVecEntry {
+                sort: vec![entry.sort],
+                metrics: req
+                    .metric_field_names()
+                    .map(str::to_string)
+                    .zip(entry.metrics)
+                    .collect(),
+            })
+            .collect();
+        TopMetricsMetricResult { top }
+    }
+}
+
+/// The top_metrics metric results entry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopMetricsVecEntry {
+    /// The sort value of the document.
+    pub sort: Vec<f64>,
+    /// The metric values of the document, keyed by field name.
+    pub metrics: FxHashMap<String, Option<f64>>,
+}
+
+/// The top_metrics metric aggregation results a list of documents by sort criteria.
+///
+/// The main reason for wrapping it in `top` is to match elasticsearch output structure.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopMetricsMetricResult {
+    /// The result of the top_metrics metric.
+    pub top: Vec<TopMetricsVecEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SegmentTopMetricsCollector {
+    buckets: Vec<IntermediateTopMetrics>,
+    empty: IntermediateTopMetrics,
+    accessor_idx: usize,
+}
+
+impl SegmentTopMetricsCollector {
+    pub fn from_req(req: &TopMetricsAggregationReq, accessor_idx: usize) -> Self {
+        let empty = IntermediateTopMetrics::new(req);
+        Self {
+            buckets: vec![empty.clone()],
+            empty,
+            accessor_idx,
+        }
+    }
+}
+
+impl SegmentAggregationCollector for SegmentTopMetricsCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        self.prepare_max_bucket(parent_bucket_id, agg_data)?;
+        let name = agg_data
+            .get_top_metrics_req_data(self.accessor_idx)
+            .name
+            .clone();
+        let mut top_metrics = std::mem::replace(
+            &mut self.buckets[parent_bucket_id as usize],
+            self.empty.clone(),
+        );
+        top_metrics.truncate_top();
+        results.push(
+            name,
+            IntermediateAggregationResult::Metric(IntermediateMetricResult::TopMetrics(
+                top_metrics,
+            )),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        parent_bucket_id: BucketId,
+        docs: &[crate::DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        let req_data = agg_data.get_top_metrics_req_data(self.accessor_idx);
+        let bucket = &mut self.buckets[parent_bucket_id as usize];
+        let (sort_accessor, sort_field_type) = &req_data.sort_accessor;
+        for &doc in docs {
+            let Some(sort) = sort_accessor.first(doc) else {
+                continue;
+            };
+            let metrics = req_data
+                .metric_accessors
+                .iter()
+                .map(|(accessor, field_type)| {
+                    accessor
+                        .first(doc)
+                        .map(|val| f64_from_fastfield_u64(val, *field_type))
+                })
+                .collect();
+            bucket.collect(TopMetricsEntry {
+                sort: f64_from_fastfield_u64(sort, *sort_field_type),
+                metrics,
+            });
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        max_bucket: BucketId,
+        _agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        if max_bucket as usize >= self.buckets.len() {
+            self.buckets
+                .resize(max_bucket as usize + 1, self.empty.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::exec_request;
+    use crate::schema::{Schema, FAST};
+    use crate::{Index, IndexWriter};
+
+    fn get_top_metrics_test_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let rank = schema_builder.add_i64_field("rank", FAST);
+        let price = schema_builder.add_f64_field("price", FAST);
+        let rating = schema_builder.add_u64_field("rating", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(rank => 3i64, price => 30.0, rating => 1u64))?;
+        index_writer.add_document(doc!(rank => 1i64, price => 10.0))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(rank => 4i64, price => 40.0, rating => 5u64))?;
+        index_writer.add_document(doc!(rank => 2i64, price => 20.0, rating => 3u64))?;
+        index_writer.add_document(doc!(price => 100.0))?;
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    #[test]
+    fn test_aggregation_top_metrics() -> crate::Result<()> {
+        let index = get_top_metrics_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "top_desc": {
+                "top_metrics": {
+                    "metrics": { "field": "price" },
+                    "sort": { "rank": "desc" }
+                }
+            },
+            "top_asc": {
+                "top_metrics": {
+                    "metrics": [{ "field": "price" }, { "field": "rating" }],
+                    "sort": { "rank": "asc" },
+                    "size": 3
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["top_desc"],
+            json!({ "top": [{ "sort": [4.0], "metrics": { "price": 40.0 } }] })
+        );
+        assert_eq!(
+            res["top_asc"]["top"],
+            json!([
+                { "sort": [1.0], "metrics": { "price": 10.0, "rating": Value::Null } },
+                { "sort": [2.0], "metrics": { "price": 20.0, "rating": 3.0 } },
+                { "sort": [3.0], "metrics": { "price": 30.0, "rating": 1.0 } },
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_top_metrics_as_sub_aggregation() -> crate::Result<()> {
+        let index = get_top_metrics_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "rated": {
+                "range": { "field": "rating", "ranges": [{ "to": 2.0 }, { "from": 2.0 }] },
+                "aggs": {
+                    "best": {
+                        "top_metrics": {
+                            "metrics": { "field": "price" },
+                            "sort": { "rank": "asc" }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["rated"]["buckets"];
+        assert_eq!(buckets[0]["best"]["top"][0]["metrics"]["price"], 30.0);
+        assert_eq!(buckets[1]["best"]["top"][0]["metrics"]["price"], 20.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_top_metrics_invalid_size() -> crate::Result<()> {
+        let index = get_top_metrics_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "top": {
+                "top_metrics": {
+                    "metrics": { "field": "price" },
+                    "sort": { "rank": "asc" },
+                    "size": 0
+                }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidRequest: \"top_metrics size has to be at least 1\""
+        );
+        Ok(())
+    }
+}
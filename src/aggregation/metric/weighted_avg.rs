@@ -0,0 +1,281 @@
+use std::fmt::Debug;
+
+use columnar::{Column, ColumnType};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::agg_data::AggregationsSegmentCtx;
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResult, IntermediateAggregationResults, IntermediateMetricResult,
+};
+use crate::aggregation::segment_agg_result::SegmentAggregationCollector;
+use crate::aggregation::*;
+
+/// A single-value metric aggregation that computes the weighted average of numeric values that
+/// are extracted from the aggregated documents. Each value is weighted by the value of a second
+/// field of the same document: `sum(value * weight) / sum(weight)`.
+/// See [super::SingleMetricResult] for return value.
+///
+/// If a document has multiple values for the `value` field, each of them is weighted with the
+/// document's weight. Only the first value of the `weight` field is used.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "weighted_avg": {
+///         "value": { "field": "grade" },
+///         "weight": { "field": "credits", "missing": 1 }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeightedAvgAggregation {
+    /// The source of the values to average.
+    pub value: WeightedAvgValueSource,
+    /// The source of the weights.
+    pub weight: WeightedAvgValueSource,
+}
+
+/// A numeric field used as value or weight source of a [`WeightedAvgAggregation`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeightedAvgValueSource {
+    /// The field name.
+    pub field: String,
+    /// The missing parameter defines how documents that are missing a value should be treated.
+    /// By default they will be ignored but it is also possible to treat them as if they had a
+    /// value. Examples in JSON format:
+    /// { "field": "my_numbers", "missing": "10.0" }
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        deserialize_with = "deserialize_option_f64"
+    )]
+    pub missing: Option<f64>,
+}
+
+impl WeightedAvgAggregation {
+    /// Creates a new [`WeightedAvgAggregation`] instance from a value and a weight field name.
+    pub fn from_field_names(value_field: String, weight_field: String) -> Self {
+        Self {
+            value: WeightedAvgValueSource {
+                field: value_field,
+                missing: None,
+            },
+            weight: WeightedAvgValueSource {
+                field: weight_field,
+                missing: None,
+            },
+        }
+    }
+    /// Returns the field names the aggregation is computed on.
+    pub fn field_names(&self) -> Vec<&str> {
+        vec![self.value.field.as_str(), self.weight.field.as_str()]
+    }
+}
+
+/// Contains all information required by the SegmentWeightedAvgCollector to perform the
+/// weighted_avg aggregation on a segment.
+pub struct WeightedAvgAggReqData {
+    /// The column accessor of the value field.
+    pub value_accessor: Column<u64>,
+    /// The type of the value field.
+    pub value_field_type: ColumnType,
+    /// The missing value of the value field, in f64.
+    pub value_missing: Option<f64>,
+    /// The column accessor of the weight field.
+    pub weight_accessor: Column<u64>,
+    /// The type of the weight field.
+    pub weight_field_type: ColumnType,
+    /// The missing value of the weight field, in f64.
+    pub weight_missing: Option<f64>,
+    /// The name of the aggregation.
+    pub name: String,
+}
+
+impl WeightedAvgAggReqData {
+    /// Estimate the memory consumption of this struct in bytes.
+    pub fn get_memory_consumption(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// Intermediate result of the weighted_avg aggregation that can be combined with other
+/// intermediate results.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateWeightedAverage {
+    weighted_sum: f64,
+    weight_sum: f64,
+}
+
+impl IntermediateWeightedAverage {
+    /// Merges the other intermediate result into self.
+    pub fn merge_fruits(&mut self, other: IntermediateWeightedAverage) {
+        self.weighted_sum += other.weighted_sum;
+        self.weight_sum += other.weight_sum;
+    }
+    /// Computes the final weighted average value.
+    pub fn finalize(&self) -> Option<f64> {
+        if self.weight_sum == 0.0 {
+            None
+        } else {
+            Some(self.weighted_sum / self.weight_sum)
+        }
+    }
+    fn collect(&mut self, value: f64, weight: f64) {
+        self.weighted_sum += value * weight;
+        self.weight_sum += weight;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SegmentWeightedAvgCollector {
+    buckets: Vec<IntermediateWeightedAverage>,
+    accessor_idx: usize,
+}
+
+impl SegmentWeightedAvgCollector {
+    pub fn from_req(accessor_idx: usize) -> Self {
+        Self {
+            buckets: vec![IntermediateWeightedAverage::default()],
+            accessor_idx,
+        }
+    }
+}
+
+impl SegmentAggregationCollector for SegmentWeightedAvgCollector {
+    fn add_intermediate_aggregation_result(
+        &mut self,
+        agg_data: &AggregationsSegmentCtx,
+        results: &mut IntermediateAggregationResults,
+        parent_bucket_id: BucketId,
+    ) -> crate::Result<()> {
+        self.prepare_max_bucket(parent_bucket_id, agg_data)?;
+        let name = agg_data
+            .get_weighted_avg_req_data(self.accessor_idx)
+            .name
+            .clone();
+        let weighted_avg = std::mem::take(&mut self.buckets[parent_bucket_id as usize]);
+        results.push(
+            name,
+            IntermediateAggregationResult::Metric(IntermediateMetricResult::WeightedAvg(
+                weighted_avg,
+            )),
+        )?;
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        parent_bucket_id: BucketId,
+        docs: &[crate::DocId],
+        agg_data: &mut AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        let req_data = agg_data.get_weighted_avg_req_data(self.accessor_idx);
+        let bucket = &mut self.buckets[parent_bucket_id as usize];
+        for &doc in docs {
+            let Some(weight) = req_data
+                .weight_accessor
+                .first(doc)
+                .map(|weight| f64_from_fastfield_u64(weight, req_data.weight_field_type))
+                .or(req_data.weight_missing)
+            else {
+                continue;
+            };
+            let mut has_value = false;
+            for value in req_data.value_accessor.values_for_doc(doc) {
+                has_value = true;
+                bucket.collect(
+                    f64_from_fastfield_u64(value, req_data.value_field_type),
+                    weight,
+                );
+            }
+            if !has_value {
+                if let Some(missing) = req_data.value_missing {
+                    bucket.collect(missing, weight);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn prepare_max_bucket(
+        &mut self,
+        max_bucket: BucketId,
+        _agg_data: &AggregationsSegmentCtx,
+    ) -> crate::Result<()> {
+        if max_bucket as usize >= self.buckets.len() {
+            self.buckets
+                .resize_with(max_bucket as usize + 1, Default::default);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::exec_request;
+    use crate::schema::{Schema, FAST};
+    use crate::{Index, IndexWriter};
+
+    fn get_weighted_test_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let grade = schema_builder.add_f64_field("grade", FAST);
+        let credits = schema_builder.add_u64_field("credits", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(grade => 1.0, credits => 2u64))?;
+        index_writer.add_document(doc!(grade => 4.0, credits => 1u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(grade => 2.0))?;
+        index_writer.add_document(doc!(credits => 3u64))?;
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    #[test]
+    fn test_aggregation_weighted_avg() -> crate::Result<()> {
+        let index = get_weighted_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "weighted": {
+                "weighted_avg": {
+                    "value": { "field": "grade" },
+                    "weight": { "field": "credits" }
+                }
+            },
+            "weighted_missing": {
+                "weighted_avg": {
+                    "value": { "field": "grade", "missing": 0.0 },
+                    "weight": { "field": "credits", "missing": 3 }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        // (1 * 2 + 4 * 1) / 3
+        assert_eq!(res["weighted"]["value"], 2.0);
+        // (1 * 2 + 4 * 1 + 2 * 3 + 0 * 3) / 9
+        assert_eq!(res["weighted_missing"]["value"], 12.0 / 9.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_weighted_avg_no_weights() -> crate::Result<()> {
+        let index = get_weighted_test_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "weighted": {
+                "weighted_avg": {
+                    "value": { "field": "grade" },
+                    "weight": { "field": "does_not_exist" }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(res["weighted"]["value"], Value::Null);
+        Ok(())
+    }
+}
@@ -50,6 +50,12 @@
 //!     - [Sum](metric::SumAggregation)
 //!     - [Count](metric::CountAggregation)
 //!     - [Percentiles](metric::PercentilesAggregationReq)
+//!     - [PercentileRanks](metric::PercentileRanksAggregationReq)
+//!     - [WeightedAvg](metric::WeightedAvgAggregation)
+//!     - [MedianAbsoluteDeviation](metric::MedianAbsoluteDeviationAggregation)
+//!     - [Boxplot](metric::BoxplotAggregation)
+//!     - [TopMetrics](metric::TopMetricsAggregationReq)
+//!     - [StringStats](metric::StringStatsAggregation)
 //!     - [Cardinality](metric::CardinalityAggregationReq)
 //!     - [TopHits](metric::TopHitsAggregationReq)
 //!
@@ -128,6 +134,7 @@
 //! [`into_final_result`](intermediate_agg_result::IntermediateAggregationResults::into_final_result) method.
 
 mod accessor_helpers;
+mod agg_cache;
 mod agg_data;
 mod agg_limits;
 pub mod agg_req;
@@ -141,6 +148,7 @@ pub mod intermediate_agg_result;
 pub mod metric;
 
 mod segment_agg_result;
+pub(crate) mod time_zone;
 use std::fmt::Display;
 
 #[cfg(test)]
@@ -148,19 +156,21 @@ mod agg_tests;
 
 use core::fmt;
 
+pub use agg_cache::{AggregationCache, DEFAULT_AGGREGATION_CACHE_BUDGET_NUM_BYTES};
 pub use agg_limits::AggregationLimitsGuard;
 pub use collector::{
     AggregationCollector, AggregationSegmentCollector, DistributedAggregationCollector,
     DEFAULT_BUCKET_LIMIT,
 };
 use columnar::{ColumnType, MonotonicallyMappableToU64};
-pub(crate) use date::format_date;
+pub(crate) use date::{format_date, format_date_with_utc_offset};
 pub use error::AggregationError;
 use itertools::Itertools;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::tokenizer::TokenizerManager;
+use crate::DateTime;
 
 /// A bucket id is a dense identifier for a bucket within an aggregation.
 /// It is used to index into a Vec that hold per-bucket data.
@@ -180,18 +190,42 @@ pub type BucketId = u32;
 /// This struct holds shared resources needed during aggregation execution:
 /// - `limits`: Memory and bucket limits for the aggregation
 /// - `tokenizers`: TokenizerManager for parsing query strings in filter aggregations
+/// - `now`: The instant `now` refers to in date math expressions
 #[derive(Clone, Default)]
 pub struct AggContextParams {
     /// Aggregation limits (memory and bucket count)
     pub limits: AggregationLimitsGuard,
     /// Tokenizer manager for query string parsing
     pub tokenizers: TokenizerManager,
+    /// The instant `now` refers to in date math expressions, e.g. in the bounds of a
+    /// `date_range` aggregation.
+    ///
+    /// If `None`, it is set to the current time when the collector is created, so that all
+    /// segments see the same value.
+    pub now: Option<DateTime>,
 }
 
 impl AggContextParams {
     /// Create new aggregation context parameters
     pub fn new(limits: AggregationLimitsGuard, tokenizers: TokenizerManager) -> Self {
-        Self { limits, tokenizers }
+        Self {
+            limits,
+            tokenizers,
+            now: None,
+        }
+    }
+
+    /// Sets the instant `now` refers to in date math expressions.
+    pub fn with_now(mut self, now: DateTime) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Returns the instant `now` refers to, setting it to the current time if unset.
+    pub(crate) fn now_or_init(&mut self) -> DateTime {
+        *self
+            .now
+            .get_or_insert_with(|| DateTime::from_utc(time::OffsetDateTime::now_utc()))
     }
 }
 
@@ -0,0 +1,306 @@
+//! Time zones for calendar-aware date aggregations.
+//!
+//! Time zones are given either as fixed UTC offsets, or as IANA time zone
+//! names. IANA time zones require the `time-zones` feature: they are looked up
+//! in the copy of the [tz database](https://www.iana.org/time-zones) bundled
+//! with [`jiff`](https://docs.rs/jiff), so that results do not depend on the
+//! time zones installed on the host. The database is updated along with the
+//! `jiff-tzdb` crate, e.g. with `cargo update -p jiff-tzdb`.
+
+use std::fmt;
+use std::sync::Arc;
+
+const MS_PER_SECOND: i64 = 1_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+pub(crate) const MS_PER_DAY: i64 = SECONDS_PER_DAY * MS_PER_SECOND;
+
+/// Returns the number of days since 1970-01-01 of the given proleptic
+/// gregorian date.
+///
+/// `month` is in `1..=12`.
+pub(crate) fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    // See http://howardhinnant.github.io/date_algorithms.html
+    let year = year as i64 - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the `(year, month, day)` of a number of days since 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year as i32, month, day)
+}
+
+/// A time zone: maps UTC instants to local time and back.
+#[derive(Clone)]
+pub(crate) struct TimeZone {
+    name: Arc<str>,
+    kind: TimeZoneKind,
+}
+
+#[derive(Clone)]
+enum TimeZoneKind {
+    Fixed {
+        offset_ms: i64,
+    },
+    #[cfg(feature = "time-zones")]
+    Iana(jiff::tz::TimeZone),
+}
+
+impl fmt::Debug for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TimeZone({})", self.name)
+    }
+}
+
+impl PartialEq for TimeZone {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl TimeZone {
+    /// Returns the UTC time zone.
+    pub fn utc() -> TimeZone {
+        TimeZone::fixed("UTC", 0)
+    }
+
+    fn fixed(name: &str, offset_ms: i64) -> TimeZone {
+        TimeZone {
+            name: Arc::from(name),
+            kind: TimeZoneKind::Fixed { offset_ms },
+        }
+    }
+
+    /// Parses a time zone, given either as an IANA time zone name (e.g.
+    /// `Europe/Paris`), or as a fixed UTC offset (e.g. `+01:00` or `-0530`).
+    ///
+    /// Returns `None` if the time zone is unknown, which is always the case of
+    /// IANA time zone names without the `time-zones` feature.
+    pub fn parse(name: &str) -> Option<TimeZone> {
+        if let Some(offset_ms) = parse_fixed_offset(name) {
+            return Some(TimeZone::fixed(name, offset_ms));
+        }
+        #[cfg(feature = "time-zones")]
+        if let Ok(time_zone) = jiff::tz::TimeZone::get(name) {
+            return Some(TimeZone {
+                name: Arc::from(name),
+                kind: TimeZoneKind::Iana(time_zone),
+            });
+        }
+        None
+    }
+
+    /// Returns the offset from UTC, in milliseconds, at the given UTC instant.
+    #[cfg_attr(not(feature = "time-zones"), allow(unused_variables))]
+    pub fn offset_ms_at(&self, utc_ms: i64) -> i64 {
+        match &self.kind {
+            TimeZoneKind::Fixed { offset_ms } => *offset_ms,
+            #[cfg(feature = "time-zones")]
+            TimeZoneKind::Iana(time_zone) => {
+                offset_to_ms(time_zone.to_offset(timestamp_from_ms(utc_ms)))
+            }
+        }
+    }
+
+    /// Converts a UTC instant to local time, both in milliseconds.
+    pub fn to_local_ms(&self, utc_ms: i64) -> i64 {
+        utc_ms + self.offset_ms_at(utc_ms)
+    }
+
+    /// Converts a local time to a UTC instant, both in milliseconds.
+    ///
+    /// Local times occurring twice, when clocks are turned back, resolve to
+    /// the earliest instant. Local times skipped when clocks are turned forward
+    /// resolve to the instant of the transition.
+    pub fn to_utc_ms(&self, local_ms: i64) -> i64 {
+        match &self.kind {
+            TimeZoneKind::Fixed { offset_ms } => local_ms - offset_ms,
+            #[cfg(feature = "time-zones")]
+            TimeZoneKind::Iana(time_zone) => {
+                use jiff::tz::{AmbiguousOffset, Offset};
+
+                let local_datetime = Offset::UTC.to_datetime(timestamp_from_ms(local_ms));
+                match time_zone.to_ambiguous_timestamp(local_datetime).offset() {
+                    AmbiguousOffset::Unambiguous { offset } => local_ms - offset_to_ms(offset),
+                    AmbiguousOffset::Fold { before, after } => {
+                        local_ms - offset_to_ms(before).max(offset_to_ms(after))
+                    }
+                    AmbiguousOffset::Gap { after, .. } => {
+                        // The local time read with the offset after the gap is
+                        // just before the transition.
+                        let before_transition_ms = local_ms - offset_to_ms(after);
+                        time_zone
+                            .following(timestamp_from_ms(before_transition_ms))
+                            .next()
+                            .map(|transition| transition.timestamp().as_millisecond())
+                            .unwrap_or(before_transition_ms)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the timestamp of an instant in milliseconds, saturated to the range
+/// supported by `jiff`.
+#[cfg(feature = "time-zones")]
+fn timestamp_from_ms(ms: i64) -> jiff::Timestamp {
+    jiff::Timestamp::from_millisecond(ms).unwrap_or(if ms < 0 {
+        jiff::Timestamp::MIN
+    } else {
+        jiff::Timestamp::MAX
+    })
+}
+
+#[cfg(feature = "time-zones")]
+fn offset_to_ms(offset: jiff::tz::Offset) -> i64 {
+    offset.seconds() as i64 * MS_PER_SECOND
+}
+
+/// Parses a fixed UTC offset such as `Z`, `UTC`, `+01:00`, `-0530` or `+02`.
+fn parse_fixed_offset(input: &str) -> Option<i64> {
+    if matches!(input, "Z" | "UTC" | "GMT") {
+        return Some(0);
+    }
+    let (sign, rest) = match input.as_bytes().first()? {
+        b'+' => (1, &input[1..]),
+        b'-' => (-1, &input[1..]),
+        _ => return None,
+    };
+    if !rest.is_ascii() {
+        return None;
+    }
+    let (hours, minutes) = match rest.len() {
+        2 => (rest, "0"),
+        4 => rest.split_at(2),
+        5 if rest.as_bytes()[2] == b':' => (&rest[..2], &rest[3..]),
+        _ => return None,
+    };
+    if !(hours.bytes().chain(minutes.bytes())).all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    if hours > 18 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60) * MS_PER_SECOND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_days_roundtrip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        for days in -800_000..800_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_parse_fixed_offset() {
+        assert_eq!(parse_fixed_offset("Z"), Some(0));
+        assert_eq!(parse_fixed_offset("+01:00"), Some(3_600_000));
+        assert_eq!(parse_fixed_offset("-0530"), Some(-19_800_000));
+        assert_eq!(parse_fixed_offset("+02"), Some(7_200_000));
+        assert_eq!(parse_fixed_offset("+2"), None);
+        assert_eq!(parse_fixed_offset("+01:0"), None);
+        assert_eq!(parse_fixed_offset("Europe/Paris"), None);
+
+        let time_zone = TimeZone::parse("-0530").unwrap();
+        assert_eq!(time_zone.to_local_ms(0), -19_800_000);
+        assert_eq!(time_zone.to_utc_ms(-19_800_000), 0);
+        assert!(TimeZone::parse("Mars/Olympus_Mons").is_none());
+    }
+
+    #[cfg(feature = "time-zones")]
+    #[test]
+    fn test_dst_transitions() {
+        fn utc_ms(year: i32, month: u32, day: u32, hour: i64, minute: i64) -> i64 {
+            days_from_civil(year, month, day) * MS_PER_DAY + (hour * 60 + minute) * 60_000
+        }
+
+        let new_york = TimeZone::parse("America/New_York").unwrap();
+        let hour_ms = 3_600_000;
+        // 2024-03-10 02:00 EST -> 03:00 EDT
+        assert_eq!(
+            new_york.offset_ms_at(utc_ms(2024, 3, 10, 6, 59)),
+            -5 * hour_ms
+        );
+        assert_eq!(
+            new_york.offset_ms_at(utc_ms(2024, 3, 10, 7, 0)),
+            -4 * hour_ms
+        );
+        // 2024-11-03 02:00 EDT -> 01:00 EST
+        assert_eq!(
+            new_york.offset_ms_at(utc_ms(2024, 11, 3, 5, 59)),
+            -4 * hour_ms
+        );
+        assert_eq!(
+            new_york.offset_ms_at(utc_ms(2024, 11, 3, 6, 0)),
+            -5 * hour_ms
+        );
+        // Rules keep on applying in the future.
+        assert_eq!(
+            new_york.offset_ms_at(utc_ms(2100, 7, 1, 0, 0)),
+            -4 * hour_ms
+        );
+        // Before 1883, local mean time.
+        assert_eq!(
+            new_york.offset_ms_at(utc_ms(1850, 1, 1, 0, 0)),
+            -(4 * 3600 + 56 * 60 + 2) * 1000
+        );
+        // Instants out of the range supported by jiff saturate.
+        assert_eq!(new_york.offset_ms_at(i64::MAX), -5 * hour_ms);
+
+        // Skipped local time resolves to the transition.
+        assert_eq!(
+            new_york.to_utc_ms(utc_ms(2024, 3, 10, 2, 30)),
+            utc_ms(2024, 3, 10, 7, 0)
+        );
+        // Repeated local time resolves to the first occurrence.
+        assert_eq!(
+            new_york.to_utc_ms(utc_ms(2024, 11, 3, 1, 30)),
+            utc_ms(2024, 11, 3, 5, 30)
+        );
+        assert_eq!(
+            new_york.to_utc_ms(utc_ms(2024, 11, 3, 0, 0)),
+            utc_ms(2024, 11, 3, 4, 0)
+        );
+
+        let paris = TimeZone::parse("Europe/Paris").unwrap();
+        assert_eq!(paris.offset_ms_at(utc_ms(2024, 3, 31, 0, 59)), hour_ms);
+        assert_eq!(paris.offset_ms_at(utc_ms(2024, 3, 31, 1, 0)), 2 * hour_ms);
+        // Links resolve to their target zone.
+        let kolkata = TimeZone::parse("Asia/Calcutta").unwrap();
+        assert_eq!(kolkata.offset_ms_at(utc_ms(2024, 1, 1, 0, 0)), 19_800_000);
+        let lord_howe = TimeZone::parse("Australia/Lord_Howe").unwrap();
+        assert_eq!(
+            lord_howe.offset_ms_at(utc_ms(2024, 1, 1, 0, 0)),
+            11 * hour_ms
+        );
+        assert_eq!(
+            lord_howe.offset_ms_at(utc_ms(2024, 7, 1, 0, 0)),
+            10 * hour_ms + hour_ms / 2
+        );
+    }
+}
@@ -10,6 +10,7 @@ use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use columnar::{BytesColumn, Column, DynamicColumn, HasAssociatedColumnType};
+use futures_util::future::BoxFuture;
 
 use crate::collector::{Collector, SegmentCollector};
 use crate::schema::Schema;
@@ -110,6 +111,17 @@ where
         Ok(())
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            futures_util::future::try_join(
+                self.collector.warmup(segment),
+                segment.fast_fields().warm_fast_field(&self.field, false),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
     fn for_segment(
         &self,
         segment_local_id: u32,
@@ -284,6 +296,17 @@ where
         self.collector.check_schema(schema)
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            futures_util::future::try_join(
+                self.collector.warmup(segment),
+                segment.fast_fields().warm_fast_field(&self.field, false),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
     fn for_segment(
         &self,
         segment_local_id: u32,
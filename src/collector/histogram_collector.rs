@@ -2,11 +2,12 @@ use std::sync::Arc;
 
 use columnar::ColumnValues;
 use fastdivide::DividerU64;
+use futures_util::future::BoxFuture;
 
 use crate::collector::{Collector, SegmentCollector};
 use crate::fastfield::{FastFieldNotAvailableError, FastValue};
 use crate::schema::Type;
-use crate::{DocId, Score};
+use crate::{DocId, Score, SegmentReader};
 
 /// Histogram builds an histogram of the values of a fastfield for the
 /// collected DocSet.
@@ -107,6 +108,10 @@ impl Collector for HistogramCollector {
     type Fruit = Vec<u64>;
     type Child = SegmentHistogramCollector;
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(segment.fast_fields().warm_fast_field(&self.field, false))
+    }
+
     fn for_segment(
         &self,
         _segment_local_id: crate::SegmentOrdinal,
@@ -82,7 +82,9 @@
 //! See the `custom_collector` example.
 
 use downcast_rs::impl_downcast;
+use futures_util::future::BoxFuture;
 
+use crate::docset::{DocSet, TERMINATED};
 use crate::schema::Schema;
 use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
 
@@ -116,6 +118,12 @@ pub use self::docset_collector::DocSetCollector;
 mod filter_collector_wrapper;
 pub use self::filter_collector_wrapper::{BytesFilterCollector, FilterCollector};
 
+mod terminate_after;
+pub use self::terminate_after::TerminateAfter;
+
+mod total_hits_collector;
+pub use self::total_hits_collector::{TotalHits, TotalHitsRelation, TrackTotalHits};
+
 /// `Fruit` is the type for the result of our collection.
 /// e.g. `usize` for the `Count` collector.
 pub trait Fruit: Send + downcast_rs::Downcast {}
@@ -151,6 +159,16 @@ pub trait Collector: Sync + Send {
         Ok(())
     }
 
+    /// Prefetches, through the async read path, the data the collector
+    /// will need to collect the given segment (e.g. fast field columns).
+    ///
+    /// This is called by [`Searcher::search_async`](crate::Searcher::search_async)
+    /// before [`Collector::for_segment`]. The default implementation does not
+    /// prefetch anything.
+    fn warmup<'a>(&'a self, _segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
     /// `set_segment` is called before beginning to enumerate
     /// on this segment.
     fn for_segment(
@@ -189,6 +207,9 @@ pub(crate) fn default_collect_segment_impl<TSegmentCollector: SegmentCollector>(
     reader: &SegmentReader,
     with_scoring: bool,
 ) -> crate::Result<()> {
+    if reader.search_context().should_stop() {
+        return Ok(());
+    }
     match (reader.alive_bitset(), with_scoring) {
         (Some(alive_bitset), true) => {
             weight.for_each(reader, &mut |doc, score| {
@@ -220,6 +241,83 @@ pub(crate) fn default_collect_segment_impl<TSegmentCollector: SegmentCollector>(
     Ok(())
 }
 
+/// Feeds the alive documents matching the query to `callback`, until it returns false.
+///
+/// Unlike [`default_collect_segment_impl`], documents are fed one at a time, so that the
+/// iteration can stop on any document.
+///
+/// Returns the last document visited, or `TERMINATED` if all of the documents were visited.
+pub(crate) fn collect_segment_until(
+    weight: &dyn Weight,
+    reader: &SegmentReader,
+    with_scoring: bool,
+    mut callback: impl FnMut(DocId, Score) -> bool,
+) -> crate::Result<DocId> {
+    let search_context = reader.search_context();
+    let alive_bitset = reader.alive_bitset();
+    let mut scorer = weight.scorer(reader, 1.0)?;
+    let mut num_docs = 0u32;
+    let mut doc = scorer.doc();
+    while doc != TERMINATED {
+        if alive_bitset.is_none_or(|alive_bitset| alive_bitset.is_alive(doc)) {
+            let score = if with_scoring { scorer.score() } else { 0.0 };
+            if !callback(doc, score) {
+                return Ok(doc);
+            }
+        }
+        if search_context.should_stop_every(&mut num_docs) {
+            return Ok(doc);
+        }
+        doc = scorer.advance();
+    }
+    Ok(TERMINATED)
+}
+
+/// Collects a segment with a collector made of several ones, such as a tuple.
+///
+/// Documents are collected one at a time until every part of the collector has a
+/// [pruning threshold](SegmentCollector::pruning_threshold). The rest of the segment is then
+/// collected with [`Weight::for_each_pruning`], which skips the documents that do not exceed
+/// it, e.g. with block-WAND.
+pub(crate) fn collect_segment_with_pruning<TSegmentCollector: SegmentCollector>(
+    segment_collector: &mut TSegmentCollector,
+    weight: &dyn Weight,
+    reader: &SegmentReader,
+    with_scoring: bool,
+) -> crate::Result<()> {
+    // Pruning thresholds are scores.
+    if !with_scoring {
+        return default_collect_segment_impl(segment_collector, weight, reader, with_scoring);
+    }
+    if reader.search_context().should_stop() {
+        return Ok(());
+    }
+    let last_doc = collect_segment_until(weight, reader, true, |doc, score| {
+        segment_collector.collect(doc, score);
+        segment_collector.pruning_threshold().is_none()
+    })?;
+    if last_doc == TERMINATED || reader.search_context().should_stop() {
+        return Ok(());
+    }
+    // The documents up to `last_doc` were already collected.
+    let threshold = segment_collector.pruning_threshold().unwrap_or(Score::MIN);
+    weight.for_each_pruning(threshold, reader, &mut |doc, score| {
+        if doc > last_doc && !reader.is_deleted(doc) {
+            segment_collector.collect(doc, score);
+        }
+        segment_collector.pruning_threshold().unwrap_or(Score::MIN)
+    })?;
+    Ok(())
+}
+
+/// Returns the lowest of the pruning thresholds of the parts of a collector, or `None` if one
+/// of them has none.
+fn lowest_pruning_threshold(thresholds: impl IntoIterator<Item = Option<Score>>) -> Option<Score> {
+    thresholds
+        .into_iter()
+        .try_fold(Score::MAX, |lowest, threshold| Some(lowest.min(threshold?)))
+}
+
 impl<TSegmentCollector: SegmentCollector> SegmentCollector for Option<TSegmentCollector> {
     type Fruit = Option<TSegmentCollector::Fruit>;
 
@@ -235,6 +333,13 @@ impl<TSegmentCollector: SegmentCollector> SegmentCollector for Option<TSegmentCo
         }
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        match self {
+            Some(segment_collector) => segment_collector.pruning_threshold(),
+            None => Some(Score::MAX),
+        }
+    }
+
     fn harvest(self) -> Self::Fruit {
         self.map(|segment_collector| segment_collector.harvest())
     }
@@ -252,6 +357,14 @@ impl<TCollector: Collector> Collector for Option<TCollector> {
         Ok(())
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        if let Some(underlying_collector) = self {
+            underlying_collector.warmup(segment)
+        } else {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
     fn for_segment(
         &self,
         segment_local_id: SegmentOrdinal,
@@ -312,6 +425,18 @@ pub trait SegmentCollector: 'static {
         }
     }
 
+    /// Returns the score a document has to exceed to make a difference to the collector, or
+    /// `None` if every document does.
+    ///
+    /// Once every part of a collector made of several ones (e.g. a tuple) has a threshold, the
+    /// rest of the segment is collected with [`Weight::for_each_pruning`], which can skip the
+    /// documents that do not exceed the lowest one. A collector that does not need any more
+    /// documents returns `Some(Score::MAX)`. Once a collector returned a threshold, it is
+    /// expected to return one until the end of the segment.
+    fn pruning_threshold(&self) -> Option<Score> {
+        None
+    }
+
     /// Extract the fruit of the collection from the `SegmentCollector`.
     fn harvest(self) -> Self::Fruit;
 }
@@ -333,6 +458,13 @@ where
         Ok(())
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            futures_util::future::try_join(self.0.warmup(segment), self.1.warmup(segment)).await?;
+            Ok(())
+        })
+    }
+
     fn for_segment(
         &self,
         segment_local_id: u32,
@@ -362,6 +494,22 @@ where
             self.1.merge_fruits(right_fruits)?,
         ))
     }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        collect_segment_with_pruning(
+            &mut segment_collector,
+            weight,
+            reader,
+            self.requires_scoring(),
+        )?;
+        Ok(segment_collector.harvest())
+    }
 }
 
 impl<Left, Right> SegmentCollector for (Left, Right)
@@ -381,6 +529,10 @@ where
         self.1.collect_block(docs);
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        lowest_pruning_threshold([self.0.pruning_threshold(), self.1.pruning_threshold()])
+    }
+
     fn harvest(self) -> <Self as SegmentCollector>::Fruit {
         (self.0.harvest(), self.1.harvest())
     }
@@ -404,6 +556,18 @@ where
         Ok(())
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            futures_util::future::try_join3(
+                self.0.warmup(segment),
+                self.1.warmup(segment),
+                self.2.warmup(segment),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
     fn for_segment(
         &self,
         segment_local_id: u32,
@@ -437,6 +601,22 @@ where
             self.2.merge_fruits(three_fruits)?,
         ))
     }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        collect_segment_with_pruning(
+            &mut segment_collector,
+            weight,
+            reader,
+            self.requires_scoring(),
+        )?;
+        Ok(segment_collector.harvest())
+    }
 }
 
 impl<One, Two, Three> SegmentCollector for (One, Two, Three)
@@ -459,6 +639,14 @@ where
         self.2.collect_block(docs);
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        lowest_pruning_threshold([
+            self.0.pruning_threshold(),
+            self.1.pruning_threshold(),
+            self.2.pruning_threshold(),
+        ])
+    }
+
     fn harvest(self) -> <Self as SegmentCollector>::Fruit {
         (self.0.harvest(), self.1.harvest(), self.2.harvest())
     }
@@ -484,6 +672,19 @@ where
         Ok(())
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            futures_util::future::try_join4(
+                self.0.warmup(segment),
+                self.1.warmup(segment),
+                self.2.warmup(segment),
+                self.3.warmup(segment),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
     fn for_segment(
         &self,
         segment_local_id: u32,
@@ -524,6 +725,22 @@ where
             self.3.merge_fruits(four_fruits)?,
         ))
     }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        collect_segment_with_pruning(
+            &mut segment_collector,
+            weight,
+            reader,
+            self.requires_scoring(),
+        )?;
+        Ok(segment_collector.harvest())
+    }
 }
 
 impl<One, Two, Three, Four> SegmentCollector for (One, Two, Three, Four)
@@ -549,6 +766,15 @@ where
         self.3.collect_block(docs);
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        lowest_pruning_threshold([
+            self.0.pruning_threshold(),
+            self.1.pruning_threshold(),
+            self.2.pruning_threshold(),
+            self.3.pruning_threshold(),
+        ])
+    }
+
     fn harvest(self) -> <Self as SegmentCollector>::Fruit {
         (
             self.0.harvest(),
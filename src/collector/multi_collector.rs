@@ -1,8 +1,11 @@
 use std::marker::PhantomData;
 use std::ops::Deref;
 
-use super::{Collector, SegmentCollector};
+use futures_util::future::BoxFuture;
+
+use super::{collect_segment_with_pruning, lowest_pruning_threshold, Collector, SegmentCollector};
 use crate::collector::Fruit;
+use crate::query::Weight;
 use crate::schema::Schema;
 use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
 
@@ -21,6 +24,10 @@ impl<TCollector: Collector> Collector for CollectorWrapper<TCollector> {
         self.0.check_schema(schema)
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        self.0.warmup(segment)
+    }
+
     fn for_segment(
         &self,
         segment_local_id: u32,
@@ -67,6 +74,10 @@ impl SegmentCollector for Box<dyn BoxableSegmentCollector> {
         self.as_mut().collect_block(docs);
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        self.as_ref().pruning_threshold()
+    }
+
     fn harvest(self) -> Box<dyn Fruit> {
         BoxableSegmentCollector::harvest_from_box(self)
     }
@@ -79,6 +90,9 @@ pub trait BoxableSegmentCollector {
             self.collect(doc, 0.0);
         }
     }
+    fn pruning_threshold(&self) -> Option<Score> {
+        None
+    }
     fn harvest_from_box(self: Box<Self>) -> Box<dyn Fruit>;
 }
 
@@ -96,6 +110,10 @@ impl<TSegmentCollector: SegmentCollector> BoxableSegmentCollector
         self.0.collect_block(docs);
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        self.0.pruning_threshold()
+    }
+
     fn harvest_from_box(self: Box<Self>) -> Box<dyn Fruit> {
         Box::new(self.0.harvest())
     }
@@ -206,6 +224,18 @@ impl Collector for MultiCollector<'_> {
         Ok(())
     }
 
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            futures_util::future::try_join_all(
+                self.collector_wrappers
+                    .iter()
+                    .map(|collector| collector.warmup(segment)),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
     fn for_segment(
         &self,
         segment_local_id: SegmentOrdinal,
@@ -247,6 +277,22 @@ impl Collector for MultiCollector<'_> {
             .collect::<crate::Result<_>>()?;
         Ok(MultiFruit { sub_fruits })
     }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<MultiFruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        collect_segment_with_pruning(
+            &mut segment_collector,
+            weight,
+            reader,
+            self.requires_scoring(),
+        )?;
+        Ok(segment_collector.harvest())
+    }
 }
 
 pub struct MultiCollectorChild {
@@ -268,6 +314,10 @@ impl SegmentCollector for MultiCollectorChild {
         }
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        lowest_pruning_threshold(self.children.iter().map(|child| child.pruning_threshold()))
+    }
+
     fn harvest(self) -> MultiFruit {
         MultiFruit {
             sub_fruits: self
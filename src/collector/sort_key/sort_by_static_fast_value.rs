@@ -5,12 +5,13 @@ use columnar::Column;
 use crate::collector::sort_key::NaturalComparator;
 use crate::collector::{SegmentSortKeyComputer, SortKeyComputer};
 use crate::fastfield::{FastFieldNotAvailableError, FastValue};
+use crate::schema::value_type_to_column_type;
 use crate::{DocId, Score, SegmentReader};
 
 /// Sorts by a fast value (u64, i64, f64, bool).
 ///
 /// The field must appear explicitly in the schema, with the right type, and declared as
-/// a fast field..
+/// a fast field, or be a [runtime field](crate::fastfield::RuntimeField) of the right type.
 ///
 /// If the field is multivalued, only the first value is considered.
 ///
@@ -40,7 +41,11 @@ impl<T: FastValue> SortKeyComputer for SortByStaticFastValue<T> {
     fn check_schema(&self, schema: &crate::schema::Schema) -> crate::Result<()> {
         // At the segment sort key computer level, we rely on the u64 representation.
         // The mapping is monotonic, so it is sufficient to compute our top-K docs.
-        let field = schema.get_field(&self.field)?;
+        let Ok(field) = schema.get_field(&self.field) else {
+            // Runtime fields are not known to the schema. Whether the name is one is checked
+            // when creating the segment sort key computers.
+            return Ok(());
+        };
         let field_entry = schema.get_field_entry(field);
         if !field_entry.is_fast() {
             return Err(crate::TantivyError::SchemaError(format!(
@@ -63,7 +68,27 @@ impl<T: FastValue> SortKeyComputer for SortByStaticFastValue<T> {
         &self,
         segment_reader: &SegmentReader,
     ) -> crate::Result<Self::Child> {
-        let sort_column_opt = segment_reader.fast_fields().u64_lenient(&self.field)?;
+        let column_type_opt = value_type_to_column_type(T::to_type());
+        let fast_fields = segment_reader.fast_fields();
+        if let Some(runtime_field) = fast_fields.runtime_field(&self.field) {
+            if Some(runtime_field.column_type()) != column_type_opt {
+                return Err(crate::TantivyError::SchemaError(format!(
+                    "Runtime field `{}` is of type {:?}, not of the type {:?}.",
+                    &self.field,
+                    runtime_field.column_type(),
+                    T::to_type()
+                )));
+            }
+        } else if segment_reader.schema().get_field(&self.field).is_err() {
+            return Err(crate::TantivyError::SchemaError(format!(
+                "Field `{}` is neither a field of the schema nor a runtime field.",
+                &self.field,
+            )));
+        }
+        let sort_column_opt = fast_fields.u64_lenient_for_type(
+            column_type_opt.as_ref().map(std::slice::from_ref),
+            &self.field,
+        )?;
         let (sort_column, _sort_column_type) =
             sort_column_opt.ok_or_else(|| FastFieldNotAvailableError {
                 field_name: self.field.clone(),
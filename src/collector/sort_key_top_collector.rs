@@ -1,6 +1,9 @@
+use std::any::{Any, TypeId};
 use std::ops::Range;
 
-use crate::collector::sort_key::{Comparator, SegmentSortKeyComputer, SortKeyComputer};
+use crate::collector::sort_key::{
+    Comparator, NaturalComparator, SegmentSortKeyComputer, SortBySimilarityScore, SortKeyComputer,
+};
 use crate::collector::{Collector, SegmentCollector, TopNComputer};
 use crate::query::Weight;
 use crate::schema::Schema;
@@ -65,6 +68,9 @@ where TSortKeyComputer: SortKeyComputer + Send + Sync + 'static
         segment_ord: u32,
         reader: &SegmentReader,
     ) -> crate::Result<Vec<(TSortKeyComputer::SortKey, DocAddress)>> {
+        if reader.search_context().should_stop() {
+            return Ok(Vec::new());
+        }
         let k = self.doc_range.end;
         let docs = self
             .sort_key_computer
@@ -120,6 +126,17 @@ where
         );
     }
 
+    fn pruning_threshold(&self) -> Option<Score> {
+        // Only the top documents by decreasing score can tell which scores are too low.
+        if TypeId::of::<TSegmentSortKeyComputer>() != TypeId::of::<SortBySimilarityScore>() {
+            return None;
+        }
+        let topn_computer =
+            (&self.topn_computer as &dyn Any)
+                .downcast_ref::<TopNComputer<Score, DocId, NaturalComparator>>()?;
+        Some(topn_computer.threshold.unwrap_or(Score::MIN))
+    }
+
     fn harvest(self) -> Self::Fruit {
         let segment_ord = self.segment_ord;
         let segment_hits: Vec<(TSegmentSortKeyComputer::SortKey, DocAddress)> = self
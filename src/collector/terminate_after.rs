@@ -0,0 +1,177 @@
+use futures_util::future::BoxFuture;
+
+use super::{collect_segment_until, Collector, SegmentCollector};
+use crate::query::Weight;
+use crate::schema::Schema;
+use crate::{SegmentOrdinal, SegmentReader};
+
+/// `TerminateAfter` wraps a collector, and stops collecting a segment once
+/// `max_docs_per_segment` documents were collected.
+///
+/// The documents of a segment are collected in ascending doc id order, so that the
+/// wrapped collector sees the first `max_docs_per_segment` matching documents of each segment.
+/// With `TopDocs`, the returned documents are therefore not the overall best ones anymore,
+/// but the best ones among the collected documents.
+///
+/// ```rust
+/// use tantivy::collector::{Count, TerminateAfter};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 20_000_000)?;
+/// for _ in 0..10 {
+///     index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let count = searcher.search(&AllQuery, &TerminateAfter::new(Count, 3))?;
+/// assert_eq!(count, 3);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TerminateAfter<TCollector> {
+    collector: TCollector,
+    max_docs_per_segment: usize,
+}
+
+impl<TCollector: Collector> TerminateAfter<TCollector> {
+    /// Wraps `collector`, collecting at most `max_docs_per_segment` documents per segment.
+    pub fn new(collector: TCollector, max_docs_per_segment: usize) -> TerminateAfter<TCollector> {
+        TerminateAfter {
+            collector,
+            max_docs_per_segment,
+        }
+    }
+}
+
+impl<TCollector: Collector> Collector for TerminateAfter<TCollector> {
+    type Fruit = TCollector::Fruit;
+
+    type Child = TCollector::Child;
+
+    fn check_schema(&self, schema: &Schema) -> crate::Result<()> {
+        self.collector.check_schema(schema)
+    }
+
+    fn warmup<'a>(&'a self, segment: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        self.collector.warmup(segment)
+    }
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<TCollector::Child> {
+        self.collector.for_segment(segment_local_id, segment)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<TCollector::Child as SegmentCollector>::Fruit>,
+    ) -> crate::Result<TCollector::Fruit> {
+        self.collector.merge_fruits(segment_fruits)
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<TCollector::Child as SegmentCollector>::Fruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        if self.max_docs_per_segment > 0 && !reader.search_context().should_stop() {
+            let mut num_docs = 0;
+            collect_segment_until(weight, reader, self.requires_scoring(), |doc, score| {
+                segment_collector.collect(doc, score);
+                num_docs += 1;
+                num_docs < self.max_docs_per_segment
+            })?;
+        }
+        Ok(segment_collector.harvest())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TerminateAfter;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{DocAddress, Index, IndexWriter, Term};
+
+    #[test]
+    fn test_terminate_after() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for i in 0..6 {
+            index_writer.add_document(doc!(text => format!("a doc{i}")))?;
+        }
+        index_writer.commit()?;
+        for i in 6..11 {
+            index_writer.add_document(doc!(text => format!("a doc{i}")))?;
+        }
+        index_writer.delete_term(Term::from_field_text(text, "doc1"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+
+        // Deleted documents do not count toward the limit.
+        let top_docs = searcher.search(
+            &query,
+            &TerminateAfter::new(TopDocs::with_limit(10).order_by_score(), 3),
+        )?;
+        assert_eq!(top_docs.len(), 6);
+        for (_, doc_address) in &top_docs {
+            let segment_reader = searcher.segment_reader(doc_address.segment_ord);
+            assert!(!segment_reader.is_deleted(doc_address.doc_id));
+        }
+        assert_eq!(searcher.search(&query, &TerminateAfter::new(Count, 3))?, 6);
+        assert_eq!(searcher.search(&query, &TerminateAfter::new(Count, 0))?, 0);
+        assert_eq!(
+            searcher.search(&query, &TerminateAfter::new(Count, 100))?,
+            10
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminate_after_top_docs() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for i in 0..10 {
+            let body = if i < 5 { "a" } else { "a a a" };
+            index_writer.add_document(doc!(text => body))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+
+        // Only the first 4 documents are collected, the best ones are not among them.
+        let collector = TerminateAfter::new(TopDocs::with_limit(2).order_by_score(), 4);
+        let top_docs = searcher.search(&query, &collector)?;
+        let doc_addresses: Vec<DocAddress> = top_docs.into_iter().map(|(_, doc)| doc).collect();
+        assert_eq!(
+            doc_addresses,
+            vec![DocAddress::new(0, 0), DocAddress::new(0, 1)]
+        );
+        Ok(())
+    }
+}
@@ -11,7 +11,7 @@ use crate::collector::sort_key::{
 };
 use crate::collector::sort_key_top_collector::TopBySortKeyCollector;
 use crate::collector::top_collector::ComparableDoc;
-use crate::collector::{SegmentSortKeyComputer, SortKeyComputer};
+use crate::collector::{SegmentSortKeyComputer, SortKeyComputer, TotalHits, TrackTotalHits};
 use crate::fastfield::FastValue;
 use crate::{DocAddress, DocId, Order, Score, SegmentReader};
 
@@ -227,6 +227,50 @@ impl TopDocs {
         TopBySortKeyCollector::new(SortBySimilarityScore, self.doc_range())
     }
 
+    /// Set top-K to rank documents by their score, and count the hits of the query up to
+    /// `track_total_hits`.
+    ///
+    /// This is equivalent to combining [`TopDocs::order_by_score`] with
+    /// [`TrackTotalHits`](crate::collector::TrackTotalHits) in a tuple: a segment is only visited
+    /// exhaustively until `track_total_hits` hits are found. The rest of the segment is collected
+    /// with block-WAND, as with [`TopDocs::order_by_score`].
+    ///
+    /// ```rust
+    /// use tantivy::collector::TopDocs;
+    /// use tantivy::query::QueryParser;
+    /// use tantivy::schema::{Schema, TEXT};
+    /// use tantivy::{doc, Index};
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let title = schema_builder.add_text_field("title", TEXT);
+    /// let index = Index::create_in_ram(schema_builder.build());
+    ///
+    /// let mut index_writer = index.writer_with_num_threads(1, 20_000_000)?;
+    /// for _ in 0..100 {
+    ///     index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+    /// }
+    /// index_writer.commit()?;
+    ///
+    /// let searcher = index.reader()?.searcher();
+    /// let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+    /// let collector = TopDocs::with_limit(10).order_by_score_with_total_hits(50);
+    /// let (top_docs, total_hits) = searcher.search(&query, &collector)?;
+    /// assert_eq!(top_docs.len(), 10);
+    /// assert_eq!(total_hits.to_string(), "≥ 50");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn order_by_score_with_total_hits(
+        self,
+        track_total_hits: usize,
+    ) -> impl Collector<Fruit = (Vec<(Score, DocAddress)>, TotalHits)> {
+        (
+            TopBySortKeyCollector::new(SortBySimilarityScore, self.doc_range()),
+            TrackTotalHits::new(track_total_hits),
+        )
+    }
+
     /// Set top-K to rank documents by a given fast field.
     ///
     /// If the field is not a fast field, or its field type does not match the generic type, this
@@ -0,0 +1,338 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{collect_segment_until, Collector, SegmentCollector};
+use crate::query::Weight;
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// Tells whether [`TotalHits::value`] is the exact number of hits, or a lower bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalHitsRelation {
+    /// The number of hits is exact.
+    EqualTo,
+    /// The number of hits is a lower bound.
+    GreaterThanOrEqualTo,
+}
+
+/// Number of documents matching a query, as computed by [`TrackTotalHits`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotalHits {
+    /// Number of hits, or a lower bound of the number of hits.
+    pub value: usize,
+    /// Relation between `value` and the actual number of hits.
+    pub relation: TotalHitsRelation,
+}
+
+impl TotalHits {
+    /// Returns true if `value` is the exact number of hits.
+    pub fn is_exact(&self) -> bool {
+        self.relation == TotalHitsRelation::EqualTo
+    }
+}
+
+impl fmt::Display for TotalHits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.relation {
+            TotalHitsRelation::EqualTo => write!(f, "{}", self.value),
+            TotalHitsRelation::GreaterThanOrEqualTo => write!(f, "≥ {}", self.value),
+        }
+    }
+}
+
+/// `TrackTotalHits` counts the documents matching a query, up to a threshold.
+///
+/// If the query has more than `threshold` matching documents, the count is `threshold` and is
+/// flagged as a lower bound. Segments are counted independently: the collection of a segment
+/// stops once it has more than `threshold` matching documents. This makes counting the hits of a
+/// query matching millions of documents cheap, when only "10,000+ results" is displayed.
+///
+/// Combined with other collectors in a tuple or a
+/// [`MultiCollector`](crate::collector::MultiCollector), the collection of a segment stops once
+/// the threshold is reached if the other collectors do not need the remaining documents either.
+/// In particular, combined with [`TopDocs::order_by_score`](crate::collector::TopDocs::order_by_score),
+/// the rest of the segment is collected with block-WAND, which skips the documents that cannot
+/// make it to the top documents.
+///
+/// ```rust
+/// use tantivy::collector::{TotalHitsRelation, TrackTotalHits};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 20_000_000)?;
+/// for _ in 0..10 {
+///     index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let total_hits = searcher.search(&AllQuery, &TrackTotalHits::new(5))?;
+/// assert_eq!(total_hits.value, 5);
+/// assert_eq!(total_hits.relation, TotalHitsRelation::GreaterThanOrEqualTo);
+/// assert_eq!(total_hits.to_string(), "≥ 5");
+///
+/// let total_hits = searcher.search(&AllQuery, &TrackTotalHits::new(100))?;
+/// assert_eq!(total_hits.to_string(), "10");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct TrackTotalHits {
+    threshold: usize,
+}
+
+impl TrackTotalHits {
+    /// Creates a collector counting hits exactly up to `threshold`.
+    pub fn new(threshold: usize) -> TrackTotalHits {
+        TrackTotalHits { threshold }
+    }
+}
+
+impl Collector for TrackTotalHits {
+    type Fruit = TotalHits;
+
+    type Child = SegmentTotalHitsCollector;
+
+    fn for_segment(
+        &self,
+        _: SegmentOrdinal,
+        _: &SegmentReader,
+    ) -> crate::Result<SegmentTotalHitsCollector> {
+        Ok(SegmentTotalHitsCollector {
+            count: 0,
+            threshold: self.threshold,
+            relation: TotalHitsRelation::EqualTo,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_total_hits: Vec<TotalHits>) -> crate::Result<TotalHits> {
+        let num_hits: usize = segment_total_hits
+            .iter()
+            .map(|total_hits| total_hits.value)
+            .sum();
+        if num_hits <= self.threshold && segment_total_hits.iter().all(TotalHits::is_exact) {
+            return Ok(TotalHits {
+                value: num_hits,
+                relation: TotalHitsRelation::EqualTo,
+            });
+        }
+        // A segment that stopped early counted `threshold` hits, so the query has more than
+        // `threshold` hits either way.
+        Ok(TotalHits {
+            value: self.threshold,
+            relation: TotalHitsRelation::GreaterThanOrEqualTo,
+        })
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<TotalHits> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        if !reader.search_context().should_stop() {
+            collect_segment_until(weight, reader, false, |doc, score| {
+                segment_collector.collect(doc, score);
+                !segment_collector.is_terminated()
+            })?;
+        }
+        Ok(segment_collector.harvest())
+    }
+}
+
+/// Segment collector of [`TrackTotalHits`].
+pub struct SegmentTotalHitsCollector {
+    count: usize,
+    threshold: usize,
+    relation: TotalHitsRelation,
+}
+
+impl SegmentTotalHitsCollector {
+    /// Returns true once more than `threshold` documents were collected.
+    fn is_terminated(&self) -> bool {
+        self.relation == TotalHitsRelation::GreaterThanOrEqualTo
+    }
+}
+
+impl SegmentCollector for SegmentTotalHitsCollector {
+    type Fruit = TotalHits;
+
+    fn collect(&mut self, _: DocId, _: Score) {
+        if self.count < self.threshold {
+            self.count += 1;
+        } else {
+            self.relation = TotalHitsRelation::GreaterThanOrEqualTo;
+        }
+    }
+
+    fn pruning_threshold(&self) -> Option<Score> {
+        // Once the threshold is reached, no document makes a difference.
+        self.is_terminated().then_some(Score::MAX)
+    }
+
+    fn harvest(self) -> TotalHits {
+        TotalHits {
+            value: self.count,
+            relation: self.relation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TotalHits, TotalHitsRelation, TrackTotalHits};
+    use crate::collector::{Count, MultiCollector, TopDocs};
+    use crate::query::{QueryProfile, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{Index, IndexWriter, Term};
+
+    #[test]
+    fn test_track_total_hits() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for i in 0..6 {
+            index_writer.add_document(doc!(text => format!("a doc{i}")))?;
+        }
+        index_writer.commit()?;
+        for i in 6..9 {
+            index_writer.add_document(doc!(text => format!("a doc{i}")))?;
+        }
+        index_writer.delete_term(Term::from_field_text(text, "doc1"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+
+        let exact = TotalHits {
+            value: 8,
+            relation: TotalHitsRelation::EqualTo,
+        };
+        assert_eq!(searcher.search(&query, &TrackTotalHits::new(8))?, exact);
+        assert_eq!(searcher.search(&query, &TrackTotalHits::new(100))?, exact);
+        // No segment has more than 5 hits, but the query has.
+        let lower_bound = |value| TotalHits {
+            value,
+            relation: TotalHitsRelation::GreaterThanOrEqualTo,
+        };
+        assert_eq!(
+            searcher.search(&query, &TrackTotalHits::new(5))?,
+            lower_bound(5)
+        );
+        // Both segments have more than 2 hits.
+        assert_eq!(
+            searcher.search(&query, &TrackTotalHits::new(2))?,
+            lower_bound(2)
+        );
+        assert_eq!(
+            searcher.search(&query, &(Count, TrackTotalHits::new(2)))?,
+            (8, lower_bound(2))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_score_with_total_hits() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for i in 0..200 {
+            let body = match i % 7 {
+                0 => "a a a b",
+                1 => "a b c d e f",
+                _ => "a b",
+            };
+            index_writer.add_document(doc!(text => body))?;
+            if i % 50 == 49 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.delete_term(Term::from_field_text(text, "c"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+
+        let (expected_top_docs, count) =
+            searcher.search(&query, &(TopDocs::with_limit(10).order_by_score(), Count))?;
+        for threshold in [0, 1, 5, 20, 1_000] {
+            let collector = TopDocs::with_limit(10).order_by_score_with_total_hits(threshold);
+            let (top_docs, total_hits) = searcher.search(&query, &collector)?;
+            assert_eq!(top_docs, expected_top_docs);
+            assert_eq!(total_hits.is_exact(), threshold == 1_000);
+            if total_hits.is_exact() {
+                assert_eq!(total_hits.value, count);
+            } else {
+                assert_eq!(total_hits.value, threshold);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_track_total_hits_stops_early_with_top_docs() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for _ in 0..10_000 {
+            index_writer.add_document(doc!(text => "a"))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let num_docs_visited = |profile: QueryProfile| -> u64 {
+            profile
+                .segments
+                .iter()
+                .map(|segment| segment.num_docs_matched)
+                .sum()
+        };
+
+        let expected_top_docs =
+            searcher.search(&query, &TopDocs::with_limit(10).order_by_score())?;
+        let collector = (
+            TopDocs::with_limit(10).order_by_score(),
+            TrackTotalHits::new(100),
+        );
+        let ((top_docs, total_hits), profile) = searcher.search_with_profile(&query, &collector)?;
+        assert_eq!(top_docs, expected_top_docs);
+        assert_eq!(total_hits.to_string(), "≥ 100");
+        // All of the documents have the same score: none of them is visited once the threshold
+        // is reached.
+        assert_eq!(num_docs_visited(profile), 101);
+
+        let mut multi_collector = MultiCollector::new();
+        let top_docs_handle =
+            multi_collector.add_collector(TopDocs::with_limit(10).order_by_score());
+        let total_hits_handle = multi_collector.add_collector(TrackTotalHits::new(100));
+        let (mut multi_fruit, profile) = searcher.search_with_profile(&query, &multi_collector)?;
+        assert_eq!(top_docs_handle.extract(&mut multi_fruit), expected_top_docs);
+        assert_eq!(total_hits_handle.extract(&mut multi_fruit).value, 100);
+        assert_eq!(num_docs_visited(profile), 101);
+
+        // `Count` needs every document.
+        let collector = (Count, TrackTotalHits::new(100));
+        let ((count, _), profile) = searcher.search_with_profile(&query, &collector)?;
+        assert_eq!(count, 10_000);
+        assert_eq!(num_docs_visited(profile), 10_000);
+        Ok(())
+    }
+}
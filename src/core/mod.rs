@@ -1,6 +1,7 @@
 mod executor;
 #[doc(hidden)]
 pub mod json_utils;
+mod search_context;
 pub mod searcher;
 
 use std::path::Path;
@@ -8,6 +9,8 @@ use std::path::Path;
 use once_cell::sync::Lazy;
 
 pub use self::executor::Executor;
+pub use self::search_context::SearchContext;
+pub(crate) use self::search_context::SEARCH_CONTEXT_CHECK_INTERVAL;
 pub use self::searcher::{Searcher, SearcherGeneration};
 
 /// The meta file contains all the information about the list of segments and the schema
@@ -0,0 +1,208 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::TantivyError;
+
+/// Number of documents visited between two checks of the [`SearchContext`].
+pub(crate) const SEARCH_CONTEXT_CHECK_INTERVAL: u32 = 4_096;
+
+#[derive(Default)]
+struct SearchState {
+    cancelled: AtomicBool,
+    timed_out: AtomicBool,
+}
+
+/// Deadline and cancellation token of a search.
+///
+/// The loops iterating over the matching documents of a segment check the context every few
+/// thousand documents, and stop early once the deadline is exceeded or the search was cancelled.
+/// Depending on [`SearchContext::allow_partial_results`],
+/// [`Searcher::search_with_context`](crate::Searcher::search_with_context) then either returns
+/// the results collected so far, or a [`TantivyError::Timeout`].
+///
+/// The context can be cloned, e.g. to cancel the search from another thread.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use tantivy::SearchContext;
+///
+/// let search_context = SearchContext::default()
+///     .with_timeout(Duration::from_millis(100))
+///     .allow_partial_results(true);
+/// let cancellation_token = search_context.clone();
+/// cancellation_token.cancel();
+/// assert!(search_context.is_cancelled());
+/// ```
+#[derive(Clone, Default)]
+pub struct SearchContext {
+    deadline: Option<Instant>,
+    allow_partial_results: bool,
+    state: Arc<SearchState>,
+}
+
+impl SearchContext {
+    /// Sets the instant after which the search is interrupted.
+    pub fn with_deadline(mut self, deadline: Instant) -> SearchContext {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets the deadline of the search to `timeout` from now.
+    pub fn with_timeout(self, timeout: Duration) -> SearchContext {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// If true, an interrupted search returns the results collected so far instead of an error.
+    ///
+    /// [`SearchContext::timed_out`] tells whether the results are partial.
+    /// Defaults to false.
+    pub fn allow_partial_results(mut self, allow_partial_results: bool) -> SearchContext {
+        self.allow_partial_results = allow_partial_results;
+        self
+    }
+
+    /// Returns the deadline of the search, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Returns true if partial results are returned when the search is interrupted.
+    pub fn allows_partial_results(&self) -> bool {
+        self.allow_partial_results
+    }
+
+    /// Cancels the search.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the search was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the search was interrupted, because it was cancelled or because it
+    /// exceeded its deadline.
+    pub fn timed_out(&self) -> bool {
+        self.state.timed_out.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the search should stop.
+    pub(crate) fn should_stop(&self) -> bool {
+        let should_stop = self.is_cancelled()
+            || self
+                .deadline
+                .map(|deadline| Instant::now() >= deadline)
+                .unwrap_or(false);
+        if should_stop {
+            self.state.timed_out.store(true, Ordering::Relaxed);
+        }
+        should_stop
+    }
+
+    /// Returns true if the search should stop, checking the context only every
+    /// [`SEARCH_CONTEXT_CHECK_INTERVAL`] calls.
+    #[inline]
+    pub(crate) fn should_stop_every(&self, num_calls: &mut u32) -> bool {
+        *num_calls += 1;
+        *num_calls % SEARCH_CONTEXT_CHECK_INTERVAL == 0 && self.should_stop()
+    }
+
+    /// Returns the error of an interrupted search.
+    pub(crate) fn timeout_error(&self) -> TantivyError {
+        if self.is_cancelled() {
+            TantivyError::Timeout("The search was cancelled".to_string())
+        } else {
+            TantivyError::Timeout("The search exceeded its deadline".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{SearchContext, SEARCH_CONTEXT_CHECK_INTERVAL};
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{EnableScoring, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{Index, IndexWriter, TantivyError, Term};
+
+    #[test]
+    fn test_search_with_context() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for _ in 0..3 * SEARCH_CONTEXT_CHECK_INTERVAL {
+            index_writer.add_document(doc!(text => "a"))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        let num_docs = 3 * SEARCH_CONTEXT_CHECK_INTERVAL as usize;
+
+        let search_context = SearchContext::default().with_timeout(Duration::from_secs(3_600));
+        assert_eq!(
+            searcher.search_with_context(&query, &Count, &search_context)?,
+            num_docs
+        );
+        assert!(!search_context.timed_out());
+
+        let search_context = SearchContext::default().with_deadline(Instant::now());
+        assert!(matches!(
+            searcher.search_with_context(&query, &Count, &search_context),
+            Err(TantivyError::Timeout(_))
+        ));
+        assert!(search_context.timed_out());
+
+        let search_context = SearchContext::default().allow_partial_results(true);
+        search_context.cancel();
+        let top_docs = TopDocs::with_limit(10).order_by_score();
+        assert!(searcher
+            .search_with_context(&query, &top_docs, &search_context)?
+            .is_empty());
+        assert!(search_context.timed_out());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_context_interrupts_loops() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for _ in 0..3 * SEARCH_CONTEXT_CHECK_INTERVAL {
+            index_writer.add_document(doc!(text => "a"))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        let weight = query.weight(EnableScoring::enabled_from_searcher(&searcher))?;
+
+        let search_context = SearchContext::default();
+        let reader = searcher
+            .segment_reader(0)
+            .with_search_context(search_context.clone());
+        let mut num_docs = 0;
+        weight.for_each(&reader, &mut |_, _| {
+            search_context.cancel();
+            num_docs += 1;
+        })?;
+        assert_eq!(num_docs, SEARCH_CONTEXT_CHECK_INTERVAL);
+
+        let search_context = SearchContext::default();
+        let reader = searcher
+            .segment_reader(0)
+            .with_search_context(search_context.clone());
+        let mut num_docs = 0;
+        weight.for_each_pruning(0.0, &reader, &mut |_, _| {
+            search_context.cancel();
+            num_docs += 1;
+            0.0
+        })?;
+        assert_eq!(num_docs, SEARCH_CONTEXT_CHECK_INTERVAL);
+        Ok(())
+    }
+}
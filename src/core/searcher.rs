@@ -5,12 +5,12 @@ use std::{fmt, io};
 use crate::collector::Collector;
 use crate::core::Executor;
 use crate::index::{SegmentId, SegmentReader};
-use crate::query::{Bm25StatisticsProvider, EnableScoring, Query};
+use crate::query::{Bm25StatisticsProvider, EnableScoring, Query, QueryProfile, QueryProfiler};
 use crate::schema::document::DocumentDeserialize;
-use crate::schema::{Schema, Term};
+use crate::schema::{Field, Schema, Term};
 use crate::space_usage::SearcherSpaceUsage;
 use crate::store::{CacheStats, StoreReader};
-use crate::{DocAddress, Index, Opstamp, TrackedObject};
+use crate::{DocAddress, Index, Opstamp, SearchContext, TantivyError, TrackedObject};
 
 /// Identifies the searcher generation accessed by a [`Searcher`].
 ///
@@ -90,6 +90,20 @@ impl Searcher {
         store_reader.get(doc_address.doc_id)
     }
 
+    /// Fetches the given fields of a document from tantivy's store given a [`DocAddress`].
+    ///
+    /// The values of the other stored fields are skipped without being allocated,
+    /// and the fields [stored separately](crate::schema::FieldEntry::set_stored_separately)
+    /// are only read if some of them are requested.
+    pub fn doc_with_fields<D: DocumentDeserialize>(
+        &self,
+        doc_address: DocAddress,
+        fields: &[Field],
+    ) -> crate::Result<D> {
+        let store_reader = &self.inner.store_readers[doc_address.segment_ord as usize];
+        store_reader.get_with_fields(doc_address.doc_id, fields)
+    }
+
     /// The cache stats for the underlying store reader.
     ///
     /// Aggregates the sum for each segment store reader.
@@ -114,6 +128,20 @@ impl Searcher {
         store_reader.get_async(doc_address.doc_id, executor).await
     }
 
+    /// Fetches the given fields of a document in an asynchronous manner.
+    /// Async version of [`doc_with_fields`](Self::doc_with_fields).
+    pub async fn doc_with_fields_async<D: DocumentDeserialize>(
+        &self,
+        doc_address: DocAddress,
+        fields: &[Field],
+    ) -> crate::Result<D> {
+        let executor = self.inner.index.search_executor();
+        let store_reader = &self.inner.store_readers[doc_address.segment_ord as usize];
+        store_reader
+            .get_with_fields_async(doc_address.doc_id, fields, executor)
+            .await
+    }
+
     /// Access the schema associated with the index of this searcher.
     pub fn schema(&self) -> &Schema {
         &self.inner.schema
@@ -236,6 +264,132 @@ impl Searcher {
         collector.merge_fruits(fruits)
     }
 
+    /// Same as [`search(...)`](Searcher::search), but the search stops early once the deadline
+    /// of the `search_context` is exceeded or once it is cancelled.
+    ///
+    /// An interrupted search returns a [`TantivyError::Timeout`], or the results collected so
+    /// far if the context allows partial results. In that case,
+    /// [`SearchContext::timed_out`] tells whether the results are partial.
+    ///
+    /// A search context is meant to be used for a single search.
+    pub fn search_with_context<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+        search_context: &SearchContext,
+    ) -> crate::Result<C::Fruit> {
+        let enabled_scoring = if collector.requires_scoring() {
+            EnableScoring::enabled_from_searcher(self)
+        } else {
+            EnableScoring::disabled_from_searcher(self)
+        };
+        let weight = query.weight(enabled_scoring)?;
+        collector.check_schema(self.schema())?;
+        let segment_readers: Vec<SegmentReader> = self
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.with_search_context(search_context.clone()))
+            .collect();
+        let fruits = self.inner.index.search_executor().map(
+            |(segment_ord, segment_reader)| {
+                collector.collect_segment(weight.as_ref(), segment_ord as u32, segment_reader)
+            },
+            segment_readers.iter().enumerate(),
+        )?;
+        if search_context.timed_out() && !search_context.allows_partial_results() {
+            return Err(search_context.timeout_error());
+        }
+        collector.merge_fruits(fruits)
+    }
+
+    /// Same as [`search(...)`](Searcher::search), but also returns a [`QueryProfile`] with the
+    /// execution statistics of each clause of the query on each segment.
+    ///
+    /// Profiling has an overhead (for instance, the documents matching a query collected by
+    /// [`TopDocs`](crate::collector::TopDocs) are counted on top of being collected), so it is
+    /// meant to investigate slow queries rather than to be enabled on every search.
+    pub fn search_with_profile<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<(C::Fruit, QueryProfile)> {
+        let enabled_scoring = if collector.requires_scoring() {
+            EnableScoring::enabled_from_searcher(self)
+        } else {
+            EnableScoring::disabled_from_searcher(self)
+        };
+        let profiler = QueryProfiler::default();
+        let weight = query.weight_with_profiler(enabled_scoring, &profiler)?;
+        collector.check_schema(self.schema())?;
+        let segment_readers = self.segment_readers();
+        let fruits = self.inner.index.search_executor().map(
+            |(segment_ord, segment_reader)| {
+                collector.collect_segment(weight.as_ref(), segment_ord as u32, segment_reader)
+            },
+            segment_readers.iter().enumerate(),
+        )?;
+        let fruit = collector.merge_fruits(fruits)?;
+        let profile = profiler.into_profile().ok_or_else(|| {
+            TantivyError::InternalError(format!("The weight of {query:?} is not instrumented."))
+        })?;
+        Ok((fruit, profile))
+    }
+
+    /// Async version of [`search(...)`](Searcher::search).
+    ///
+    /// Before any scorer is built, the term infos of the query terms, the byte ranges
+    /// needed by the [`Weight`](crate::query::Weight) and the data needed by the
+    /// collector are prefetched through the async read path of the directory
+    /// (see [`Weight::warmup`](crate::query::Weight::warmup) and
+    /// [`Collector::warmup`]). Segments are warmed up concurrently.
+    ///
+    /// This makes it possible to serve queries from a directory backed by a slow
+    /// storage (e.g. an object storage) without blocking threads on I/O, provided
+    /// the directory caches the data it has fetched asynchronously.
+    pub async fn search_async<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<C::Fruit> {
+        let mut terms: Vec<&Term> = Vec::new();
+        query.query_terms(&mut |term, _| terms.push(term));
+        terms.sort_unstable();
+        terms.dedup();
+        let segment_readers = self.segment_readers();
+        futures_util::future::try_join_all(segment_readers.iter().flat_map(|segment_reader| {
+            terms.iter().map(move |term| async move {
+                let inverted_index = segment_reader.inverted_index(term.field())?;
+                inverted_index.get_term_info_async(term).await?;
+                crate::Result::Ok(())
+            })
+        }))
+        .await?;
+
+        let enabled_scoring = if collector.requires_scoring() {
+            EnableScoring::enabled_from_searcher(self)
+        } else {
+            EnableScoring::disabled_from_searcher(self)
+        };
+        let weight = query.weight(enabled_scoring)?;
+        collector.check_schema(self.schema())?;
+        futures_util::future::try_join_all(segment_readers.iter().map(|segment_reader| {
+            futures_util::future::try_join(
+                weight.warmup(segment_reader),
+                collector.warmup(segment_reader),
+            )
+        }))
+        .await?;
+
+        let executor = self.inner.index.search_executor();
+        let fruits = executor.map(
+            |(segment_ord, segment_reader)| {
+                collector.collect_segment(weight.as_ref(), segment_ord as u32, segment_reader)
+            },
+            segment_readers.iter().enumerate(),
+        )?;
+        collector.merge_fruits(fruits)
+    }
+
     /// Summarize total space usage of this searcher.
     pub fn space_usage(&self) -> io::Result<SearcherSpaceUsage> {
         let mut space_usage = SearcherSpaceUsage::new();
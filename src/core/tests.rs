@@ -466,3 +466,75 @@ fn test_non_text_json_term_freq_bitpacked() {
         assert_eq!(postings.term_freq(), 1u32);
     }
 }
+
+#[test]
+fn test_search_async() -> crate::Result<()> {
+    use futures::executor::block_on;
+
+    use crate::collector::TopDocs;
+    use crate::query::QueryParser;
+    use crate::schema::{FAST, STORED};
+
+    let mut schema_builder = Schema::builder();
+    let text = schema_builder.add_text_field("text", TEXT | STORED);
+    let num = schema_builder.add_u64_field("num", INDEXED | FAST);
+    let schema = schema_builder.build();
+    let index = Index::create_in_ram(schema);
+    let mut index_writer: IndexWriter = index.writer_for_tests()?;
+    index_writer.set_merge_policy(Box::new(NoMergePolicy));
+    let texts = [
+        "the quick brown fox",
+        "the lazy dog",
+        "quick brown dogs jump",
+        "a fox and a dog",
+    ];
+    for (i, body) in texts.iter().enumerate() {
+        index_writer.add_document(doc!(text => *body, num => i as u64))?;
+        if i % 2 == 1 {
+            index_writer.commit()?;
+        }
+    }
+    let searcher = index.reader()?.searcher();
+    assert_eq!(searcher.segment_readers().len(), 2);
+
+    let query_parser = QueryParser::for_index(&index, vec![text]);
+    for query_str in [
+        "fox",
+        "quick AND brown",
+        "\"quick brown\"",
+        "\"quick bro\"*",
+        "dog OR -fox",
+        "do*",
+        "num:[1 TO 2]",
+        "text:[dog TO fox]",
+        "missing",
+    ] {
+        let query = query_parser.parse_query(query_str)?;
+        let collector = (TopDocs::with_limit(10).order_by_score(), Count);
+        let expected = searcher.search(&query, &collector)?;
+        let fruit = block_on(searcher.search_async(&query, &collector))?;
+        assert_eq!(fruit, expected, "query {query_str}");
+    }
+
+    let histogram = crate::collector::HistogramCollector::new("num".to_string(), 0u64, 2, 2);
+    let query = query_parser.parse_query("dog")?;
+    assert_eq!(
+        block_on(searcher.search_async(&query, &histogram))?,
+        searcher.search(&query, &histogram)?
+    );
+
+    let query = query_parser.parse_query("fox")?;
+    let weight = query.weight(crate::query::EnableScoring::enabled_from_searcher(
+        &searcher,
+    ))?;
+    let mut num_docs = 0;
+    for segment_reader in searcher.segment_readers() {
+        let mut scorer = block_on(weight.scorer_async(segment_reader, 1.0))?;
+        while scorer.doc() != crate::TERMINATED {
+            num_docs += 1;
+            scorer.advance();
+        }
+    }
+    assert_eq!(num_docs, 2);
+    Ok(())
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use common::{BinarySerializable, CountingWriter, HasLen, VInt};
+
+use crate::core::META_FILEPATH;
+use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::{
+    Directory, DirectoryLock, FileHandle, FileSlice, Lock, WatchCallback, WatchHandle, WritePtr,
+    INDEX_WRITER_LOCK,
+};
+use crate::error::DataCorruption;
+use crate::{Index, TantivyError};
+
+/// Magic number written at the very end of a bundle.
+const BUNDLE_MAGIC_NUMBER: u32 = 1_651_469_582;
+/// Version of the bundle format.
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+/// `offset table start (u64) | version (u32) | magic number (u32)`
+const BUNDLE_FOOTER_NUM_BYTES: usize = 16;
+
+/// Writes files one after the other, followed by their offset table.
+struct BundleWriter<W: Write> {
+    wrt: CountingWriter<W>,
+    file_offsets: Vec<(PathBuf, Range<u64>)>,
+}
+
+impl<W: Write> BundleWriter<W> {
+    fn new(wrt: W) -> BundleWriter<W> {
+        BundleWriter {
+            wrt: CountingWriter::wrap(wrt),
+            file_offsets: Vec::new(),
+        }
+    }
+
+    /// Copies the file chunk by chunk, so that it is never entirely loaded in memory.
+    fn add_file(&mut self, path: &Path, file: &FileSlice) -> io::Result<()> {
+        let start = self.wrt.written_bytes();
+        for chunk in file.stream_file_chunks() {
+            self.wrt.write_all(chunk?.as_slice())?;
+        }
+        let end = self.wrt.written_bytes();
+        self.file_offsets.push((path.to_path_buf(), start..end));
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        let offset_table_start = self.wrt.written_bytes();
+        VInt(self.file_offsets.len() as u64).serialize(&mut self.wrt)?;
+        for (path, range) in &self.file_offsets {
+            path.to_string_lossy()
+                .to_string()
+                .serialize(&mut self.wrt)?;
+            VInt(range.start).serialize(&mut self.wrt)?;
+            VInt(range.end - range.start).serialize(&mut self.wrt)?;
+        }
+        offset_table_start.serialize(&mut self.wrt)?;
+        BUNDLE_FORMAT_VERSION.serialize(&mut self.wrt)?;
+        BUNDLE_MAGIC_NUMBER.serialize(&mut self.wrt)?;
+        self.wrt.flush()?;
+        Ok(self.wrt.finish())
+    }
+}
+
+/// A read-only [`Directory`] over a bundle, i.e. a whole index packed into
+/// a single file.
+///
+/// A bundle is the concatenation of the index files (`meta.json` and the files
+/// of all of the segments), followed by an offset table locating each file.
+/// It is created with [`BundleDirectory::pack_index`] and makes it possible to
+/// distribute a finished index atomically, as a single file.
+///
+/// All of the files are served as slices of the single [`FileSlice`] the
+/// bundle is opened from. Any write operation fails.
+#[derive(Clone, Debug)]
+pub struct BundleDirectory {
+    file_offsets: Arc<HashMap<PathBuf, Range<usize>>>,
+    data: FileSlice,
+}
+
+impl BundleDirectory {
+    /// Packs the files of the searchable segments of an index, along with its
+    /// `meta.json`, into a bundle.
+    ///
+    /// The index should not be modified while it is being packed.
+    pub fn pack_index<W: Write>(index: &Index, wrt: W) -> crate::Result<W> {
+        // Files are copied along with their footer.
+        let directory = index.directory().underlying();
+        let mut bundle_writer = BundleWriter::new(wrt);
+        let meta_data = directory.atomic_read(&META_FILEPATH)?;
+        bundle_writer.add_file(&META_FILEPATH, &FileSlice::from(meta_data))?;
+        for segment_meta in index.searchable_segment_metas()? {
+            let mut files: Vec<PathBuf> = segment_meta.list_files().into_iter().collect();
+            files.sort();
+            for file in files {
+                if !directory.exists(&file)? {
+                    continue;
+                }
+                let file_slice = directory.open_read(&file)?;
+                bundle_writer.add_file(&file, &file_slice)?;
+            }
+        }
+        Ok(bundle_writer.finish()?)
+    }
+
+    /// Opens a bundle created with [`BundleDirectory::pack_index`].
+    ///
+    /// Returns a [`TantivyError::DataCorruption`] error if the file is not a valid bundle.
+    pub fn open(file: FileSlice) -> crate::Result<BundleDirectory> {
+        let corrupted = |comment: String| TantivyError::from(DataCorruption::comment_only(comment));
+        let num_bytes = file.len();
+        if num_bytes < BUNDLE_FOOTER_NUM_BYTES {
+            return Err(corrupted(format!(
+                "File is too small to be a bundle ({num_bytes} bytes)"
+            )));
+        }
+        let (body, footer) = file.split_from_end(BUNDLE_FOOTER_NUM_BYTES);
+        let footer_bytes = footer.read_bytes()?;
+        let mut footer_data = footer_bytes.as_slice();
+        let offset_table_start = u64::deserialize(&mut footer_data)?;
+        let version = u32::deserialize(&mut footer_data)?;
+        let magic_number = u32::deserialize(&mut footer_data)?;
+        if magic_number != BUNDLE_MAGIC_NUMBER {
+            return Err(corrupted(
+                "Bundle magic number mismatch. The file is not a bundle or is corrupted"
+                    .to_string(),
+            ));
+        }
+        if version != BUNDLE_FORMAT_VERSION {
+            return Err(corrupted(format!(
+                "Unsupported bundle format version {version}"
+            )));
+        }
+        let offset_table_start = usize::try_from(offset_table_start)
+            .ok()
+            .filter(|offset_table_start| *offset_table_start <= body.len())
+            .ok_or_else(|| corrupted("Bundle offset table start is out of bounds".to_string()))?;
+        let (data, offset_table) = body.split(offset_table_start);
+        let offset_table_bytes = offset_table.read_bytes()?;
+        let mut offset_table_data = offset_table_bytes.as_slice();
+        let num_files = VInt::deserialize(&mut offset_table_data)?.0;
+        // Each entry takes at least 3 bytes: the capacity is bounded by the size of the table.
+        let mut file_offsets =
+            HashMap::with_capacity((num_files as usize).min(offset_table_data.len() / 3));
+        for _ in 0..num_files {
+            let path = String::deserialize(&mut offset_table_data)?;
+            let start = VInt::deserialize(&mut offset_table_data)?.0;
+            let len = VInt::deserialize(&mut offset_table_data)?.0;
+            let range = start
+                .checked_add(len)
+                .and_then(|end| Some(usize::try_from(start).ok()?..usize::try_from(end).ok()?))
+                .filter(|range| range.end <= data.len())
+                .ok_or_else(|| corrupted(format!("Bundled file '{path}' is out of bounds")))?;
+            file_offsets.insert(PathBuf::from(path), range);
+        }
+        Ok(BundleDirectory {
+            file_offsets: Arc::new(file_offsets),
+            data,
+        })
+    }
+
+    /// Returns the paths of the files in the bundle.
+    pub fn list_files(&self) -> Vec<&Path> {
+        self.file_offsets.keys().map(PathBuf::as_path).collect()
+    }
+
+    fn read_only_error() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "BundleDirectory is read-only.",
+        )
+    }
+}
+
+impl Directory for BundleDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>, OpenReadError> {
+        Ok(Arc::new(self.open_read(path)?))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<FileSlice, OpenReadError> {
+        let range = self
+            .file_offsets
+            .get(path)
+            .ok_or_else(|| OpenReadError::FileDoesNotExist(path.to_path_buf()))?;
+        Ok(self.data.slice(range.clone()))
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        Err(DeleteError::IoError {
+            io_error: Arc::new(Self::read_only_error()),
+            filepath: path.to_path_buf(),
+        })
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        Ok(self.file_offsets.contains_key(path))
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        Err(OpenWriteError::wrap_io_error(
+            Self::read_only_error(),
+            path.to_path_buf(),
+        ))
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        let file_slice = self.open_read(path)?;
+        let bytes = file_slice
+            .read_bytes()
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(bytes.as_slice().to_vec())
+    }
+
+    fn atomic_write(&self, _path: &Path, _data: &[u8]) -> io::Result<()> {
+        Err(Self::read_only_error())
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The bundle is immutable, so no lock is required to read it. Acquiring the
+    /// index writer lock fails however, as the bundle cannot be written to.
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        if lock.filepath == INDEX_WRITER_LOCK.filepath {
+            return Err(LockError::wrap_io_error(Self::read_only_error()));
+        }
+        Ok(DirectoryLock::from(Box::new(())))
+    }
+
+    /// The bundle is immutable: the callback will never be called.
+    fn watch(&self, _watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        Ok(WatchHandle::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use common::{BinarySerializable, VInt};
+
+    use super::{BundleDirectory, BUNDLE_FORMAT_VERSION, BUNDLE_MAGIC_NUMBER};
+    use crate::collector::Count;
+    use crate::directory::{Directory, FileSlice};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, STORED, TEXT};
+    use crate::{Index, IndexWriter, TantivyDocument, TantivyError, Term};
+
+    #[test]
+    fn test_bundle_directory() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "hello happy tax payer"))?;
+        index_writer.add_document(doc!(text => "goodbye"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "hello"))?;
+        index_writer.delete_term(Term::from_field_text(text, "goodbye"));
+        index_writer.commit()?;
+
+        let bundle = BundleDirectory::pack_index(&index, Vec::new())?;
+        let bundle_directory = BundleDirectory::open(FileSlice::from(bundle))?;
+        assert!(bundle_directory.exists(Path::new("meta.json"))?);
+
+        let bundled_index = Index::open(bundle_directory.clone())?;
+        let searcher = bundled_index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        assert_eq!(searcher.num_docs(), 2);
+        let query = TermQuery::new(
+            Term::from_field_text(text, "hello"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&query, &Count)?, 2);
+        let doc: TantivyDocument = searcher.doc(crate::DocAddress::new(0, 0))?;
+        assert!(doc.get_first(text).is_some());
+
+        assert!(bundle_directory
+            .atomic_write(Path::new("meta.json"), b"")
+            .is_err());
+        assert!(bundle_directory.open_write(Path::new("new.txt")).is_err());
+        assert!(bundle_directory.delete(Path::new("meta.json")).is_err());
+        assert!(bundled_index.writer_for_tests::<TantivyDocument>().is_err());
+        Ok(())
+    }
+
+    /// Builds a bundle with the given data, offset table entries and offset table start.
+    fn bundle_bytes(data: &[u8], entries: &[(&str, u64, u64)], offset_table_start: u64) -> Vec<u8> {
+        let mut bytes = data.to_vec();
+        VInt(entries.len() as u64).serialize(&mut bytes).unwrap();
+        for (path, start, len) in entries {
+            path.to_string().serialize(&mut bytes).unwrap();
+            VInt(*start).serialize(&mut bytes).unwrap();
+            VInt(*len).serialize(&mut bytes).unwrap();
+        }
+        offset_table_start.serialize(&mut bytes).unwrap();
+        BUNDLE_FORMAT_VERSION.serialize(&mut bytes).unwrap();
+        BUNDLE_MAGIC_NUMBER.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn is_data_corruption(bundle: Vec<u8>) -> bool {
+        matches!(
+            BundleDirectory::open(FileSlice::from(bundle)),
+            Err(TantivyError::DataCorruption(_))
+        )
+    }
+
+    #[test]
+    fn test_bundle_directory_corrupted() {
+        assert!(is_data_corruption(vec![0u8; 8]));
+        assert!(is_data_corruption(vec![0u8; 64]));
+
+        let bundle_directory =
+            BundleDirectory::open(FileSlice::from(bundle_bytes(b"abc", &[("a", 1, 2)], 3)))
+                .unwrap();
+        assert_eq!(bundle_directory.atomic_read(Path::new("a")).unwrap(), b"bc");
+
+        assert!(is_data_corruption(bundle_bytes(
+            b"abc",
+            &[("a", 1, 2)],
+            u64::MAX
+        )));
+        assert!(is_data_corruption(bundle_bytes(b"abc", &[("a", 1, 2)], 100)));
+        assert!(is_data_corruption(bundle_bytes(b"abc", &[("a", 2, 2)], 3)));
+        assert!(is_data_corruption(bundle_bytes(
+            b"abc",
+            &[("a", u64::MAX, 2)],
+            3
+        )));
+        assert!(is_data_corruption(bundle_bytes(
+            b"abc",
+            &[("a", 1, u64::MAX)],
+            3
+        )));
+        // The number of files is not trusted either.
+        let mut bundle = bundle_bytes(b"abc", &[], 3);
+        bundle[3] = 0xff;
+        assert!(BundleDirectory::open(FileSlice::from(bundle)).is_err());
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::{fmt, io};
+
+use async_trait::async_trait;
+use common::HasLen;
+use lru::LruCache;
+
+use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::{
+    Directory, DirectoryLock, FileHandle, Lock, OwnedBytes, WatchCallback, WatchHandle, WritePtr,
+};
+use crate::store::CacheStats;
+
+/// Extensions of the segment files (field norms and term dictionary) pinned in memory
+/// when they are small enough.
+const PINNED_EXTENSIONS: [&str; 2] = ["fieldnorm", "term"];
+
+/// Default upper bound on the size of a file to be pinned in memory.
+const DEFAULT_PINNED_FILE_MAX_NUM_BYTES: usize = 1_000_000;
+
+/// Reads that end at the end of a file and are shorter than this are
+/// considered footer reads and pinned in memory.
+const PINNED_FOOTER_MAX_NUM_BYTES: usize = 16_384;
+
+type CacheKey = (Arc<Path>, usize);
+
+struct CachedRange {
+    end: usize,
+    bytes: OwnedBytes,
+}
+
+#[derive(Default)]
+struct CacheCounters {
+    cache_hits: usize,
+    cache_misses: usize,
+}
+
+/// LRU cache of byte ranges, bounded by the total number of bytes it holds.
+///
+/// A read is a hit if it is fully contained in the cached range with the
+/// largest start offset lower or equal to its start.
+struct ByteRangeCache {
+    ranges: LruCache<CacheKey, CachedRange>,
+    // `(path, start) -> end`, used to find the range containing a given read.
+    index: BTreeMap<CacheKey, usize>,
+    // Footers are kept outside of the LRU: `path -> (start, bytes)`.
+    footers: HashMap<Arc<Path>, (usize, OwnedBytes)>,
+    num_bytes: usize,
+    capacity: usize,
+    counters: CacheCounters,
+}
+
+impl ByteRangeCache {
+    fn with_capacity(capacity: usize) -> ByteRangeCache {
+        ByteRangeCache {
+            ranges: LruCache::unbounded(),
+            index: BTreeMap::new(),
+            footers: HashMap::new(),
+            num_bytes: 0,
+            capacity,
+            counters: CacheCounters::default(),
+        }
+    }
+
+    fn get(&mut self, path: &Arc<Path>, range: &Range<usize>) -> Option<OwnedBytes> {
+        let bytes_opt = self.get_footer(path, range).or_else(|| {
+            let (key, end) = self
+                .index
+                .range(..=(path.clone(), range.start))
+                .next_back()
+                .filter(|((range_path, _), &end)| range_path == path && end >= range.end)
+                .map(|(key, &end)| (key.clone(), end))?;
+            debug_assert!(end >= range.end);
+            let cached_range = self.ranges.get(&key)?;
+            let offset = key.1;
+            Some(
+                cached_range
+                    .bytes
+                    .slice(range.start - offset..range.end - offset),
+            )
+        });
+        if bytes_opt.is_some() {
+            self.counters.cache_hits += 1;
+        } else {
+            self.counters.cache_misses += 1;
+        }
+        bytes_opt
+    }
+
+    fn get_footer(&self, path: &Arc<Path>, range: &Range<usize>) -> Option<OwnedBytes> {
+        let (start, bytes) = self.footers.get(path)?;
+        if range.start < *start || range.end > *start + bytes.len() {
+            return None;
+        }
+        Some(bytes.slice(range.start - start..range.end - start))
+    }
+
+    fn put_footer(&mut self, path: Arc<Path>, start: usize, bytes: OwnedBytes) {
+        match self.footers.get(&path) {
+            Some((cached_start, _)) if *cached_start <= start => {}
+            _ => {
+                self.footers.insert(path, (start, bytes));
+            }
+        }
+    }
+
+    fn put(&mut self, path: Arc<Path>, range: Range<usize>, bytes: OwnedBytes) {
+        let num_bytes = bytes.len();
+        if num_bytes > self.capacity {
+            return;
+        }
+        let key = (path, range.start);
+        if let Some(&end) = self.index.get(&key) {
+            if end >= range.end {
+                return;
+            }
+            self.remove(&key);
+        }
+        while self.num_bytes + num_bytes > self.capacity {
+            let Some((evicted_key, evicted)) = self.ranges.pop_lru() else {
+                break;
+            };
+            self.index.remove(&evicted_key);
+            self.num_bytes -= evicted.bytes.len();
+        }
+        self.index.insert(key.clone(), range.end);
+        self.ranges.put(
+            key,
+            CachedRange {
+                end: range.end,
+                bytes,
+            },
+        );
+        self.num_bytes += num_bytes;
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        self.index.remove(key);
+        if let Some(cached_range) = self.ranges.pop(key) {
+            debug_assert_eq!(cached_range.end - key.1, cached_range.bytes.len());
+            self.num_bytes -= cached_range.bytes.len();
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path) {
+        let path: Arc<Path> = Arc::from(path);
+        let keys: Vec<CacheKey> = self
+            .index
+            .range((path.clone(), 0)..=(path.clone(), usize::MAX))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            self.remove(key);
+        }
+        self.footers.remove(&path);
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            num_entries: self.ranges.len() + self.footers.len(),
+            cache_hits: self.counters.cache_hits,
+            cache_misses: self.counters.cache_misses,
+        }
+    }
+}
+
+/// File handle reading through the byte range cache of a [`CachingDirectory`].
+struct CachingFileHandle {
+    path: Arc<Path>,
+    underlying: Arc<dyn FileHandle>,
+    cache: Arc<Mutex<ByteRangeCache>>,
+}
+
+impl fmt::Debug for CachingFileHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CachingFileHandle({:?})", self.path)
+    }
+}
+
+impl CachingFileHandle {
+    fn get_from_cache(&self, range: &Range<usize>) -> Option<OwnedBytes> {
+        self.cache.lock().unwrap().get(&self.path, range)
+    }
+
+    fn put_in_cache(&self, range: Range<usize>, bytes: OwnedBytes) {
+        let mut cache = self.cache.lock().unwrap();
+        if range.end == self.len() && range.len() <= PINNED_FOOTER_MAX_NUM_BYTES {
+            cache.put_footer(self.path.clone(), range.start, bytes);
+        } else {
+            cache.put(self.path.clone(), range, bytes);
+        }
+    }
+}
+
+impl HasLen for CachingFileHandle {
+    fn len(&self) -> usize {
+        self.underlying.len()
+    }
+}
+
+#[async_trait]
+impl FileHandle for CachingFileHandle {
+    fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        if let Some(bytes) = self.get_from_cache(&range) {
+            return Ok(bytes);
+        }
+        let bytes = self.underlying.read_bytes(range.clone())?;
+        self.put_in_cache(range, bytes.clone());
+        Ok(bytes)
+    }
+
+    async fn read_bytes_async(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        if let Some(bytes) = self.get_from_cache(&range) {
+            return Ok(bytes);
+        }
+        let bytes = self.underlying.read_bytes_async(range.clone()).await?;
+        self.put_in_cache(range, bytes.clone());
+        Ok(bytes)
+    }
+}
+
+/// A read-through cache over another [`Directory`].
+///
+/// The byte ranges read through the [`FileSlice`](crate::directory::FileSlice)s
+/// of the directory are kept in an LRU cache, bounded by a memory budget.
+/// This is mostly useful to wrap a directory backed by a slow storage.
+///
+/// In addition, some small hot files are pinned in memory, outside of the memory
+/// budget:
+/// - field norms and term dictionary files, when their size does not exceed
+///   [`CachingDirectory::set_pinned_file_max_num_bytes`],
+/// - footers, i.e. small reads ending at the end of a file.
+///
+/// Writes are forwarded to the underlying directory.
+pub struct CachingDirectory<D: Directory> {
+    underlying: Arc<D>,
+    cache: Arc<Mutex<ByteRangeCache>>,
+    pinned_files: Arc<RwLock<HashMap<PathBuf, OwnedBytes>>>,
+    pinned_file_max_num_bytes: usize,
+}
+
+impl<D: Directory> Clone for CachingDirectory<D> {
+    fn clone(&self) -> Self {
+        CachingDirectory {
+            underlying: self.underlying.clone(),
+            cache: self.cache.clone(),
+            pinned_files: self.pinned_files.clone(),
+            pinned_file_max_num_bytes: self.pinned_file_max_num_bytes,
+        }
+    }
+}
+
+impl<D: Directory> fmt::Debug for CachingDirectory<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CachingDirectory({:?})", self.underlying)
+    }
+}
+
+impl<D: Directory> CachingDirectory<D> {
+    /// Wraps a directory with a byte range cache of `capacity_in_bytes`.
+    pub fn new(underlying: D, capacity_in_bytes: usize) -> CachingDirectory<D> {
+        CachingDirectory {
+            underlying: Arc::new(underlying),
+            cache: Arc::new(Mutex::new(ByteRangeCache::with_capacity(capacity_in_bytes))),
+            pinned_files: Default::default(),
+            pinned_file_max_num_bytes: DEFAULT_PINNED_FILE_MAX_NUM_BYTES,
+        }
+    }
+
+    /// Sets the maximum size of the field norms and term dictionary files
+    /// to be pinned in memory. Setting it to 0 disables file pinning.
+    #[must_use]
+    pub fn set_pinned_file_max_num_bytes(mut self, num_bytes: usize) -> CachingDirectory<D> {
+        self.pinned_file_max_num_bytes = num_bytes;
+        self
+    }
+
+    /// Returns the underlying directory.
+    pub fn underlying(&self) -> &D {
+        &self.underlying
+    }
+
+    /// Hit/miss statistics of the byte range cache.
+    ///
+    /// Reads served by pinned files are not accounted for.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats()
+    }
+
+    /// Number of bytes held by the byte range cache.
+    pub fn cache_num_bytes(&self) -> usize {
+        self.cache.lock().unwrap().num_bytes
+    }
+
+    /// Number of bytes held by the pinned files.
+    pub fn pinned_num_bytes(&self) -> usize {
+        self.pinned_files
+            .read()
+            .unwrap()
+            .values()
+            .map(|bytes| bytes.len())
+            .sum()
+    }
+
+    fn should_pin(&self, path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| PINNED_EXTENSIONS.iter().any(|pinned| extension == *pinned))
+    }
+
+    fn invalidate(&self, path: &Path) {
+        self.pinned_files.write().unwrap().remove(path);
+        self.cache.lock().unwrap().remove_file(path);
+    }
+}
+
+impl<D: Directory> Directory for CachingDirectory<D> {
+    fn get_file_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>, OpenReadError> {
+        if let Some(bytes) = self.pinned_files.read().unwrap().get(path) {
+            return Ok(Arc::new(bytes.clone()));
+        }
+        let file_handle = self.underlying.get_file_handle(path)?;
+        if self.should_pin(path) && file_handle.len() <= self.pinned_file_max_num_bytes {
+            let bytes = file_handle
+                .read_bytes(0..file_handle.len())
+                .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+            self.pinned_files
+                .write()
+                .unwrap()
+                .insert(path.to_path_buf(), bytes.clone());
+            return Ok(Arc::new(bytes));
+        }
+        Ok(Arc::new(CachingFileHandle {
+            path: Arc::from(path),
+            underlying: file_handle,
+            cache: self.cache.clone(),
+        }))
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        self.invalidate(path);
+        self.underlying.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.underlying.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        self.invalidate(path);
+        self.underlying.open_write(path)
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        self.underlying.atomic_read(path)
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.invalidate(path);
+        self.underlying.atomic_write(path, data)
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.underlying.sync_directory()
+    }
+
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        self.underlying.acquire_lock(lock)
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.underlying.watch(watch_callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::Path;
+
+    use super::CachingDirectory;
+    use crate::collector::Count;
+    use crate::directory::{Directory, RamDirectory, TerminatingWrite};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, STORED, TEXT};
+    use crate::{Index, IndexWriter, Term};
+
+    fn write_file(directory: &dyn Directory, path: &Path, len: usize) {
+        let mut wrt = directory.open_write(path).unwrap();
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        wrt.write_all(&data).unwrap();
+        wrt.terminate().unwrap();
+    }
+
+    #[test]
+    fn test_caching_directory_byte_ranges() {
+        let path = Path::new("data.bin");
+        let directory = CachingDirectory::new(RamDirectory::create(), 100);
+        write_file(&directory, path, 1_000);
+        let file = directory.open_read(path).unwrap();
+
+        assert_eq!(file.read_bytes_slice(10..60).unwrap().as_slice()[0], 10);
+        assert_eq!(directory.cache_stats().cache_misses, 1);
+        // Contained in the cached range.
+        assert_eq!(file.read_bytes_slice(20..30).unwrap().as_slice()[0], 20);
+        assert_eq!(directory.cache_stats().cache_hits, 1);
+        assert_eq!(directory.cache_num_bytes(), 50);
+
+        // Evicts the least recently used range.
+        file.read_bytes_slice(100..160).unwrap();
+        assert_eq!(directory.cache_num_bytes(), 60);
+        file.read_bytes_slice(20..30).unwrap();
+        assert_eq!(directory.cache_stats().cache_misses, 3);
+
+        // Larger than the cache capacity.
+        file.read_bytes_slice(0..500).unwrap();
+        assert!(directory.cache_num_bytes() <= 100);
+
+        // Footers are pinned outside of the LRU.
+        file.read_bytes_slice(990..1_000).unwrap();
+        let cache_num_bytes = directory.cache_num_bytes();
+        assert_eq!(
+            file.read_bytes_slice(995..1_000).unwrap().as_slice()[0],
+            227
+        );
+        assert_eq!(directory.cache_num_bytes(), cache_num_bytes);
+        assert_eq!(directory.cache_stats().cache_hits, 2);
+    }
+
+    #[test]
+    fn test_caching_directory_invalidate_on_delete() {
+        let path = Path::new("data.bin");
+        let directory = CachingDirectory::new(RamDirectory::create(), 100);
+        write_file(&directory, path, 100);
+        directory
+            .open_read(path)
+            .unwrap()
+            .read_bytes_slice(0..10)
+            .unwrap();
+        assert_eq!(directory.cache_num_bytes(), 10);
+        directory.delete(path).unwrap();
+        assert_eq!(directory.cache_num_bytes(), 0);
+        assert!(directory.open_read(path).is_err());
+    }
+
+    #[test]
+    fn test_caching_directory_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let directory = CachingDirectory::new(RamDirectory::create(), 10_000_000);
+        let index = Index::create(directory.clone(), schema, Default::default())?;
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "hello happy tax payer"))?;
+        index_writer.add_document(doc!(text => "hello"))?;
+        index_writer.commit()?;
+
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "hello"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&query, &Count)?, 2);
+        assert!(directory.pinned_num_bytes() > 0);
+        let misses = directory.cache_stats().cache_misses;
+        assert_eq!(searcher.search(&query, &Count)?, 2);
+        assert_eq!(directory.cache_stats().cache_misses, misses);
+        Ok(())
+    }
+}
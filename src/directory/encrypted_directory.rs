@@ -0,0 +1,701 @@
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, mem};
+
+use async_trait::async_trait;
+use chacha20poly1305::aead::{AeadCore, AeadInPlace, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+use common::{AntiCallToken, HasLen, TerminatingWrite};
+
+use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::{
+    Directory, DirectoryLock, FileHandle, Lock, OwnedBytes, WatchCallback, WatchHandle, WritePtr,
+};
+
+/// Magic number at the beginning of every encrypted file.
+const ENCRYPTED_FILE_MAGIC_NUMBER: u32 = 1_937_205_858;
+
+/// `magic number (u32) | key id (u32) | chunk size (u32) | nonce prefix (8 bytes)`
+const HEADER_NUM_BYTES: usize = 20;
+
+const TAG_NUM_BYTES: usize = 16;
+
+/// Default number of plaintext bytes per chunk.
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// An encryption key for [`EncryptedDirectory`].
+pub type EncryptionKey = [u8; 32];
+
+/// Supplies the keys used by an [`EncryptedDirectory`].
+///
+/// Each encrypted file records the id of the key it was encrypted with,
+/// so that keys can be rotated: new files are encrypted with the current
+/// key while older files remain readable as long as their key is returned
+/// by [`KeyProvider::key`].
+pub trait KeyProvider: Send + Sync + 'static {
+    /// Returns the id and the value of the key to encrypt new files with.
+    fn current_key(&self) -> io::Result<(u32, EncryptionKey)>;
+
+    /// Returns the key associated with the given key id.
+    fn key(&self, key_id: u32) -> io::Result<EncryptionKey>;
+}
+
+/// A [`KeyProvider`] with a single key, whose id is 0.
+pub struct StaticKeyProvider(EncryptionKey);
+
+impl StaticKeyProvider {
+    /// Creates a key provider always returning the given key.
+    pub fn new(key: EncryptionKey) -> StaticKeyProvider {
+        StaticKeyProvider(key)
+    }
+}
+
+impl KeyProvider for StaticKeyProvider {
+    fn current_key(&self) -> io::Result<(u32, EncryptionKey)> {
+        Ok((0, self.0))
+    }
+
+    fn key(&self, key_id: u32) -> io::Result<EncryptionKey> {
+        if key_id != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown encryption key id {key_id}."),
+            ));
+        }
+        Ok(self.0)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Header {
+    key_id: u32,
+    chunk_size: u32,
+    nonce_prefix: [u8; 8],
+}
+
+impl Header {
+    fn to_bytes(self) -> [u8; HEADER_NUM_BYTES] {
+        let mut bytes = [0u8; HEADER_NUM_BYTES];
+        bytes[0..4].copy_from_slice(&ENCRYPTED_FILE_MAGIC_NUMBER.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.key_id.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.chunk_size.to_le_bytes());
+        bytes[12..20].copy_from_slice(&self.nonce_prefix);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> io::Result<Header> {
+        if bytes.len() < HEADER_NUM_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File is too small to be encrypted.",
+            ));
+        }
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        if read_u32(0) != ENCRYPTED_FILE_MAGIC_NUMBER {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Encrypted file magic number mismatch. The file is not encrypted or is corrupted.",
+            ));
+        }
+        let chunk_size = read_u32(8);
+        if chunk_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid encryption chunk size.",
+            ));
+        }
+        let mut nonce_prefix = [0u8; 8];
+        nonce_prefix.copy_from_slice(&bytes[12..20]);
+        Ok(Header {
+            key_id: read_u32(4),
+            chunk_size,
+            nonce_prefix,
+        })
+    }
+}
+
+/// Encrypts and decrypts the chunks of a single file.
+///
+/// Each chunk is sealed with ChaCha20-Poly1305. The nonce is made of the random
+/// nonce prefix of the file followed by the chunk ordinal. The header, the chunk
+/// ordinal and whether the chunk is the last one are authenticated, which makes
+/// it impossible to reorder or truncate chunks without being detected.
+struct ChunkCipher {
+    cipher: ChaCha20Poly1305,
+    header: Header,
+}
+
+impl ChunkCipher {
+    fn new(key: &EncryptionKey, header: Header) -> ChunkCipher {
+        ChunkCipher {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            header,
+        }
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.header.chunk_size as usize
+    }
+
+    fn nonce_and_aad(&self, chunk_ord: u32, is_last: bool) -> (Nonce, [u8; HEADER_NUM_BYTES + 5]) {
+        let mut nonce = Nonce::default();
+        nonce[..8].copy_from_slice(&self.header.nonce_prefix);
+        nonce[8..].copy_from_slice(&chunk_ord.to_le_bytes());
+        let mut aad = [0u8; HEADER_NUM_BYTES + 5];
+        aad[..HEADER_NUM_BYTES].copy_from_slice(&self.header.to_bytes());
+        aad[HEADER_NUM_BYTES..HEADER_NUM_BYTES + 4].copy_from_slice(&chunk_ord.to_le_bytes());
+        aad[HEADER_NUM_BYTES + 4] = is_last as u8;
+        (nonce, aad)
+    }
+
+    /// Encrypts `chunk` in place and appends its tag.
+    fn seal(&self, chunk_ord: u32, is_last: bool, chunk: &mut Vec<u8>) -> io::Result<()> {
+        let (nonce, aad) = self.nonce_and_aad(chunk_ord, is_last);
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(&nonce, &aad, chunk)
+            .map_err(|_| io::Error::other("Failed to encrypt chunk."))?;
+        chunk.extend_from_slice(&tag);
+        Ok(())
+    }
+
+    /// Decrypts a sealed chunk (ciphertext followed by its tag) in place.
+    fn open(&self, chunk_ord: u32, is_last: bool, sealed_chunk: &mut [u8]) -> io::Result<usize> {
+        if sealed_chunk.len() < TAG_NUM_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Encrypted chunk is truncated.",
+            ));
+        }
+        let plaintext_len = sealed_chunk.len() - TAG_NUM_BYTES;
+        let (chunk, tag) = sealed_chunk.split_at_mut(plaintext_len);
+        let (nonce, aad) = self.nonce_and_aad(chunk_ord, is_last);
+        self.cipher
+            .decrypt_in_place_detached(&nonce, &aad, chunk, Tag::from_slice(tag))
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Failed to decrypt chunk. The key is wrong or the file is corrupted.",
+                )
+            })?;
+        Ok(plaintext_len)
+    }
+}
+
+/// Layout of an encrypted file, computed from its length.
+struct EncryptedFileLayout {
+    sealed_chunk_size: usize,
+    num_chunks: usize,
+    plaintext_len: usize,
+}
+
+impl EncryptedFileLayout {
+    fn new(chunk_size: usize, encrypted_len: usize) -> io::Result<EncryptedFileLayout> {
+        let sealed_chunk_size = chunk_size + TAG_NUM_BYTES;
+        let body_len = encrypted_len.saturating_sub(HEADER_NUM_BYTES);
+        // There is always at least one (possibly empty) chunk.
+        let num_chunks = body_len.div_ceil(sealed_chunk_size);
+        let last_sealed_chunk_len =
+            body_len.saturating_sub((num_chunks.max(1) - 1) * sealed_chunk_size);
+        if num_chunks == 0 || last_sealed_chunk_len < TAG_NUM_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Encrypted file is truncated.",
+            ));
+        }
+        Ok(EncryptedFileLayout {
+            sealed_chunk_size,
+            num_chunks,
+            plaintext_len: body_len - num_chunks * TAG_NUM_BYTES,
+        })
+    }
+
+    fn sealed_chunk_range(&self, chunk_ord: usize, encrypted_len: usize) -> Range<usize> {
+        let start = HEADER_NUM_BYTES + chunk_ord * self.sealed_chunk_size;
+        start..(start + self.sealed_chunk_size).min(encrypted_len)
+    }
+}
+
+/// Decrypts the sealed chunks `chunks` read from the file, and returns the plaintext
+/// bytes for `range`.
+fn decrypt_range(
+    cipher: &ChunkCipher,
+    layout: &EncryptedFileLayout,
+    chunks: Range<usize>,
+    sealed_chunks: &[u8],
+    range: Range<usize>,
+) -> io::Result<OwnedBytes> {
+    let mut plaintext = Vec::with_capacity(chunks.len() * cipher.chunk_size());
+    for (chunk_ord, sealed_chunk) in chunks
+        .clone()
+        .zip(sealed_chunks.chunks(layout.sealed_chunk_size))
+    {
+        let mut buffer = sealed_chunk.to_vec();
+        let is_last = chunk_ord + 1 == layout.num_chunks;
+        let plaintext_len = cipher.open(chunk_ord as u32, is_last, &mut buffer)?;
+        plaintext.extend_from_slice(&buffer[..plaintext_len]);
+    }
+    let offset = chunks.start * cipher.chunk_size();
+    Ok(OwnedBytes::new(plaintext).slice(range.start - offset..range.end - offset))
+}
+
+/// File handle decrypting the chunks touched by each read.
+struct EncryptedFileHandle {
+    underlying: Arc<dyn FileHandle>,
+    cipher: ChunkCipher,
+    layout: EncryptedFileLayout,
+}
+
+impl fmt::Debug for EncryptedFileHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EncryptedFileHandle({:?})", self.underlying)
+    }
+}
+
+impl EncryptedFileHandle {
+    fn open(
+        underlying: Arc<dyn FileHandle>,
+        key_provider: &dyn KeyProvider,
+    ) -> io::Result<EncryptedFileHandle> {
+        if underlying.len() < HEADER_NUM_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File is too small to be encrypted.",
+            ));
+        }
+        let header_bytes = underlying.read_bytes(0..HEADER_NUM_BYTES)?;
+        let header = Header::from_bytes(header_bytes.as_slice())?;
+        let key = key_provider.key(header.key_id)?;
+        let layout = EncryptedFileLayout::new(header.chunk_size as usize, underlying.len())?;
+        Ok(EncryptedFileHandle {
+            underlying,
+            cipher: ChunkCipher::new(&key, header),
+            layout,
+        })
+    }
+
+    /// Returns the range of chunks and the range of encrypted bytes to read
+    /// to serve `range`.
+    fn chunks_to_read(&self, range: &Range<usize>) -> (Range<usize>, Range<usize>) {
+        let chunk_size = self.cipher.chunk_size();
+        let first_chunk = (range.start / chunk_size).min(self.layout.num_chunks - 1);
+        let last_chunk = if range.is_empty() {
+            first_chunk
+        } else {
+            ((range.end - 1) / chunk_size).min(self.layout.num_chunks - 1)
+        };
+        let encrypted_len = self.underlying.len();
+        let start = self
+            .layout
+            .sealed_chunk_range(first_chunk, encrypted_len)
+            .start;
+        let end = self
+            .layout
+            .sealed_chunk_range(last_chunk, encrypted_len)
+            .end;
+        (first_chunk..last_chunk + 1, start..end)
+    }
+
+    fn check_range(&self, range: &Range<usize>) -> io::Result<()> {
+        if range.start > range.end || range.end > self.layout.plaintext_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Range {range:?} is out of bounds (file length: {}).",
+                    self.layout.plaintext_len
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl HasLen for EncryptedFileHandle {
+    fn len(&self) -> usize {
+        self.layout.plaintext_len
+    }
+}
+
+#[async_trait]
+impl FileHandle for EncryptedFileHandle {
+    fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        self.check_range(&range)?;
+        let (chunks, encrypted_range) = self.chunks_to_read(&range);
+        let sealed_chunks = self.underlying.read_bytes(encrypted_range)?;
+        decrypt_range(
+            &self.cipher,
+            &self.layout,
+            chunks,
+            sealed_chunks.as_slice(),
+            range,
+        )
+    }
+
+    async fn read_bytes_async(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        self.check_range(&range)?;
+        let (chunks, encrypted_range) = self.chunks_to_read(&range);
+        let sealed_chunks = self.underlying.read_bytes_async(encrypted_range).await?;
+        decrypt_range(
+            &self.cipher,
+            &self.layout,
+            chunks,
+            sealed_chunks.as_slice(),
+            range,
+        )
+    }
+}
+
+/// Writer encrypting the data written to it chunk by chunk.
+struct EncryptedWriter<W: Write> {
+    underlying: W,
+    cipher: ChunkCipher,
+    buffer: Vec<u8>,
+    chunk_ord: u32,
+}
+
+impl<W: Write> EncryptedWriter<W> {
+    fn new(
+        mut underlying: W,
+        key_provider: &dyn KeyProvider,
+        chunk_size: usize,
+    ) -> io::Result<Self> {
+        let (key_id, key) = key_provider.current_key()?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut nonce_prefix = [0u8; 8];
+        nonce_prefix.copy_from_slice(&nonce[..8]);
+        let header = Header {
+            key_id,
+            chunk_size: chunk_size as u32,
+            nonce_prefix,
+        };
+        underlying.write_all(&header.to_bytes())?;
+        Ok(EncryptedWriter {
+            underlying,
+            cipher: ChunkCipher::new(&key, header),
+            buffer: Vec::with_capacity(chunk_size + TAG_NUM_BYTES),
+            chunk_ord: 0,
+        })
+    }
+
+    fn write_chunk(&mut self, is_last: bool) -> io::Result<()> {
+        let mut chunk = mem::take(&mut self.buffer);
+        self.cipher.seal(self.chunk_ord, is_last, &mut chunk)?;
+        self.underlying.write_all(&chunk)?;
+        self.chunk_ord = self.chunk_ord.checked_add(1).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "File is too large to be encrypted.",
+            )
+        })?;
+        chunk.clear();
+        self.buffer = chunk;
+        Ok(())
+    }
+
+    /// Encrypts the remaining buffered data as the last chunk.
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_chunk(true)?;
+        self.underlying.flush()
+    }
+}
+
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let chunk_size = self.cipher.chunk_size();
+        // A full chunk is only written once we know it is not the last one.
+        if self.buffer.len() == chunk_size && !buf.is_empty() {
+            self.write_chunk(false)?;
+        }
+        let num_bytes = buf.len().min(chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..num_bytes]);
+        Ok(num_bytes)
+    }
+
+    /// Chunks are only written once complete, so flushing does not make
+    /// the buffered data visible.
+    fn flush(&mut self) -> io::Result<()> {
+        self.underlying.flush()
+    }
+}
+
+impl TerminatingWrite for EncryptedWriter<WritePtr> {
+    fn terminate_ref(&mut self, token: AntiCallToken) -> io::Result<()> {
+        self.finish()?;
+        self.underlying.terminate_ref(token)
+    }
+}
+
+/// A [`Directory`] encrypting, at rest, all of the files written to an
+/// underlying directory.
+///
+/// Files are encrypted with ChaCha20-Poly1305 in fixed-size chunks, so that
+/// reads only decrypt the chunks they touch. The keys are supplied by a
+/// [`KeyProvider`].
+///
+/// The directory is meant to be wrapped by the
+/// [`ManagedDirectory`](crate::directory::ManagedDirectory) of an index (e.g. via
+/// [`Index::create`](crate::Index::create)): footers and checksums are computed
+/// on the plaintext and garbage collection works as usual.
+///
+/// Decrypted data is not cached. Wrap the directory in a
+/// [`CachingDirectory`](crate::directory::CachingDirectory) to avoid decrypting
+/// the same chunks over and over.
+pub struct EncryptedDirectory<D: Directory> {
+    underlying: Arc<D>,
+    key_provider: Arc<dyn KeyProvider>,
+    chunk_size: usize,
+}
+
+impl<D: Directory> Clone for EncryptedDirectory<D> {
+    fn clone(&self) -> Self {
+        EncryptedDirectory {
+            underlying: self.underlying.clone(),
+            key_provider: self.key_provider.clone(),
+            chunk_size: self.chunk_size,
+        }
+    }
+}
+
+impl<D: Directory> fmt::Debug for EncryptedDirectory<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EncryptedDirectory({:?})", self.underlying)
+    }
+}
+
+impl<D: Directory> EncryptedDirectory<D> {
+    /// Wraps a directory, encrypting its files with the keys of `key_provider`.
+    pub fn new<K: KeyProvider>(underlying: D, key_provider: K) -> EncryptedDirectory<D> {
+        EncryptedDirectory {
+            underlying: Arc::new(underlying),
+            key_provider: Arc::new(key_provider),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Sets the number of plaintext bytes per encrypted chunk for the files
+    /// written from now on. Defaults to 64KiB.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0 or does not fit in a `u32`.
+    #[must_use]
+    pub fn set_chunk_size(mut self, chunk_size: usize) -> EncryptedDirectory<D> {
+        assert!(chunk_size > 0 && chunk_size <= u32::MAX as usize);
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Returns the underlying directory.
+    pub fn underlying(&self) -> &D {
+        &self.underlying
+    }
+}
+
+impl<D: Directory> Directory for EncryptedDirectory<D> {
+    fn get_file_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>, OpenReadError> {
+        let underlying = self.underlying.get_file_handle(path)?;
+        let file_handle = EncryptedFileHandle::open(underlying, self.key_provider.as_ref())
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(Arc::new(file_handle))
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        self.underlying.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.underlying.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        let underlying = self.underlying.open_write(path)?;
+        let writer = EncryptedWriter::new(underlying, self.key_provider.as_ref(), self.chunk_size)
+            .map_err(|io_error| OpenWriteError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(BufWriter::new(Box::new(writer)))
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        let data = self.underlying.atomic_read(path)?;
+        let file_handle: Arc<dyn FileHandle> = Arc::new(OwnedBytes::new(data));
+        let decrypt = || {
+            let file_handle = EncryptedFileHandle::open(file_handle, self.key_provider.as_ref())?;
+            file_handle.read_bytes(0..file_handle.len())
+        };
+        let bytes = decrypt()
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(bytes.as_slice().to_vec())
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut writer =
+            EncryptedWriter::new(Vec::new(), self.key_provider.as_ref(), self.chunk_size)?;
+        writer.write_all(data)?;
+        writer.finish()?;
+        self.underlying.atomic_write(path, &writer.underlying)
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.underlying.sync_directory()
+    }
+
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        self.underlying.acquire_lock(lock)
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.underlying.watch(watch_callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::Path;
+
+    use common::HasLen;
+
+    use super::{EncryptedDirectory, StaticKeyProvider};
+    use crate::collector::Count;
+    use crate::directory::{Directory, RamDirectory, TerminatingWrite};
+    use crate::indexer::NoMergePolicy;
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, Value, STORED, TEXT};
+    use crate::{Index, IndexWriter, TantivyDocument, Term};
+
+    fn write_file(directory: &dyn Directory, path: &Path, data: &[u8]) {
+        let mut wrt = directory.open_write(path).unwrap();
+        wrt.write_all(data).unwrap();
+        wrt.terminate().unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_directory_random_access() {
+        let ram_directory = RamDirectory::create();
+        let directory =
+            EncryptedDirectory::new(ram_directory.clone(), StaticKeyProvider::new([7u8; 32]))
+                .set_chunk_size(10);
+        for len in [0, 1, 9, 10, 11, 20, 95] {
+            let path_str = format!("file{len}");
+            let path = Path::new(&path_str);
+            let data: Vec<u8> = (0..len as u8).collect();
+            write_file(&directory, path, &data);
+            let encrypted = ram_directory.open_read(path).unwrap().read_bytes().unwrap();
+            assert!(!encrypted.as_slice().windows(3).any(|w| w == [4, 5, 6]));
+
+            let file = directory.open_read(path).unwrap();
+            assert_eq!(file.len(), len);
+            assert_eq!(file.read_bytes().unwrap().as_slice(), &data[..]);
+            for start in 0..len {
+                for end in start..=len {
+                    assert_eq!(
+                        file.read_bytes_slice(start..end).unwrap().as_slice(),
+                        &data[start..end]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypted_directory_wrong_key_and_truncation() {
+        let ram_directory = RamDirectory::create();
+        let path = Path::new("file");
+        let directory =
+            EncryptedDirectory::new(ram_directory.clone(), StaticKeyProvider::new([1u8; 32]))
+                .set_chunk_size(10);
+        write_file(&directory, path, &[3u8; 25]);
+        let other_key_directory =
+            EncryptedDirectory::new(ram_directory.clone(), StaticKeyProvider::new([2u8; 32]));
+        let file = other_key_directory.open_read(path).unwrap();
+        assert!(file.read_bytes().is_err());
+
+        // Drop the last chunk.
+        let encrypted = ram_directory.open_read(path).unwrap().read_bytes().unwrap();
+        let truncated = &encrypted.as_slice()[..20 + 2 * 26];
+        write_file(&ram_directory, Path::new("truncated"), truncated);
+        let file = directory.open_read(Path::new("truncated")).unwrap();
+        assert!(file.read_bytes().is_err());
+    }
+
+    #[test]
+    fn test_encrypted_directory_atomic_write() {
+        let ram_directory = RamDirectory::create();
+        let directory =
+            EncryptedDirectory::new(ram_directory.clone(), StaticKeyProvider::new([1u8; 32]));
+        let path = Path::new("meta.json");
+        directory.atomic_write(path, b"{}").unwrap();
+        assert_ne!(ram_directory.atomic_read(path).unwrap(), b"{}");
+        assert_eq!(directory.atomic_read(path).unwrap(), b"{}");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_encrypted_directory_lock() {
+        use crate::directory::error::LockError;
+        use crate::directory::{MmapDirectory, INDEX_WRITER_LOCK};
+
+        let mmap_directory = MmapDirectory::create_from_tempdir().unwrap();
+        // A lock file left behind by a crashed process does not block the index.
+        write_file(&mmap_directory, &INDEX_WRITER_LOCK.filepath, b"");
+        let directory = EncryptedDirectory::new(mmap_directory, StaticKeyProvider::new([1u8; 32]));
+        let other_directory = directory.clone();
+        let _lock = directory.acquire_lock(&INDEX_WRITER_LOCK).unwrap();
+        assert!(matches!(
+            other_directory.acquire_lock(&INDEX_WRITER_LOCK),
+            Err(LockError::LockBusy)
+        ));
+    }
+
+    #[test]
+    fn test_encrypted_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let directory =
+            EncryptedDirectory::new(RamDirectory::create(), StaticKeyProvider::new([5u8; 32]))
+                .set_chunk_size(100);
+        let index = Index::create(directory, schema, Default::default())?;
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text => "hello happy tax payer"))?;
+        index_writer.add_document(doc!(text => "goodbye"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "hello"))?;
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        index_writer.wait_merging_threads()?;
+
+        let reader = index.reader()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let query = TermQuery::new(
+            Term::from_field_text(text, "hello"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&query, &Count)?, 2);
+        let doc: TantivyDocument = searcher.doc(crate::DocAddress::new(0, 1))?;
+        assert_eq!(
+            doc.get_first(text).and_then(|v| v.as_str()),
+            Some("goodbye")
+        );
+        // The files of the merged segments have been garbage collected.
+        let segment_files = index.searchable_segment_metas()?[0].list_files();
+        assert!(index
+            .directory()
+            .list_managed_files()
+            .iter()
+            .all(|path| segment_files.contains(path) || path == Path::new("meta.json")));
+        Ok(())
+    }
+}
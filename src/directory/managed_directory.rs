@@ -256,6 +256,11 @@ impl ManagedDirectory {
         Ok(footer.crc() == crc)
     }
 
+    /// Returns the wrapped directory, whose files include their footer.
+    pub(crate) fn underlying(&self) -> &dyn Directory {
+        self.directory.as_ref()
+    }
+
     /// List all managed files
     pub fn list_managed_files(&self) -> HashSet<PathBuf> {
         let managed_paths = self
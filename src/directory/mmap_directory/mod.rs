@@ -675,9 +675,10 @@ mod tests {
             reader.reload().unwrap();
             let num_segments = reader.searcher().segment_readers().len();
             assert!(num_segments <= 4);
-            let num_components_except_deletes_and_tempstore =
-                crate::index::SegmentComponent::iterator().len() - 2;
-            let max_num_mmapped = num_components_except_deletes_and_tempstore * num_segments;
+            let num_components_except_deletes_tempstore_and_separate_store =
+                crate::index::SegmentComponent::iterator().len() - 3;
+            let max_num_mmapped =
+                num_components_except_deletes_tempstore_and_separate_store * num_segments;
             assert_eventually(|| {
                 let num_mmapped = mmap_directory.get_cache_info().mmapped.len();
                 if num_mmapped > max_num_mmapped {
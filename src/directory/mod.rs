@@ -3,6 +3,11 @@
 #[cfg(feature = "mmap")]
 mod mmap_directory;
 
+#[cfg(feature = "encryption")]
+mod encrypted_directory;
+
+mod bundle_directory;
+mod caching_directory;
 mod directory;
 mod directory_lock;
 pub mod footer;
@@ -21,9 +26,15 @@ use std::path::PathBuf;
 pub use common::file_slice::{FileHandle, FileSlice};
 pub use common::{AntiCallToken, OwnedBytes, TerminatingWrite};
 
+pub use self::bundle_directory::BundleDirectory;
+pub use self::caching_directory::CachingDirectory;
 pub(crate) use self::composite_file::{CompositeFile, CompositeWrite};
 pub use self::directory::{Directory, DirectoryClone, DirectoryLock};
 pub use self::directory_lock::{Lock, INDEX_WRITER_LOCK, META_LOCK};
+#[cfg(feature = "encryption")]
+pub use self::encrypted_directory::{
+    EncryptedDirectory, EncryptionKey, KeyProvider, StaticKeyProvider,
+};
 pub use self::ram_directory::RamDirectory;
 pub use self::watch_event_router::{WatchCallback, WatchCallbackList, WatchHandle};
 
@@ -117,6 +117,60 @@ mod ram_directory_tests {
     }
 }
 
+mod caching_directory_tests {
+    use crate::directory::{CachingDirectory, RamDirectory};
+
+    type DirectoryImpl = CachingDirectory<RamDirectory>;
+
+    fn make_directory() -> DirectoryImpl {
+        CachingDirectory::new(RamDirectory::default(), 1_000)
+    }
+
+    #[test]
+    fn test_simple() -> crate::Result<()> {
+        let directory = make_directory();
+        super::test_simple(&directory)
+    }
+
+    #[test]
+    fn test_write_create_the_file() {
+        let directory = make_directory();
+        super::test_write_create_the_file(&directory);
+    }
+
+    #[test]
+    fn test_rewrite_forbidden() -> crate::Result<()> {
+        let directory = make_directory();
+        super::test_rewrite_forbidden(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_delete() -> crate::Result<()> {
+        let directory = make_directory();
+        super::test_directory_delete(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_non_blocking() {
+        let directory = make_directory();
+        super::test_lock_non_blocking(&directory);
+    }
+
+    #[test]
+    fn test_lock_blocking() {
+        let directory = make_directory();
+        super::test_lock_blocking(&directory);
+    }
+
+    #[test]
+    fn test_watch() {
+        let directory = make_directory();
+        super::test_watch(&directory);
+    }
+}
+
 fn test_simple(directory: &dyn Directory) -> crate::Result<()> {
     let test_path: &'static Path = Path::new("some_path_for_test");
     let mut write_file = directory.open_write(test_path)?;
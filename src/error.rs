@@ -113,6 +113,9 @@ pub enum TantivyError {
     /// Index is read-only.
     #[error("Index is read-only")]
     IndexReadOnly,
+    /// The search was cancelled or exceeded its deadline.
+    #[error("Timeout: '{0}'")]
+    Timeout(String),
 }
 
 impl From<io::Error> for TantivyError {
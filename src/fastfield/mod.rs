@@ -26,6 +26,7 @@ pub use self::alive_bitset::{intersect_alive_bitsets, write_alive_bitset, AliveB
 pub use self::error::{FastFieldNotAvailableError, Result};
 pub use self::facet_reader::FacetReader;
 pub use self::readers::FastFieldReaders;
+pub use self::runtime_field::{RuntimeField, RuntimeFieldManager, RuntimeFieldType};
 pub use self::writer::FastFieldsWriter;
 use crate::schema::Type;
 use crate::DateTime;
@@ -34,6 +35,7 @@ mod alive_bitset;
 mod error;
 mod facet_reader;
 mod readers;
+mod runtime_field;
 mod writer;
 
 /// Trait for types that are allowed for fast fields:
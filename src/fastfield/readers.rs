@@ -1,6 +1,7 @@
+use std::collections::HashMap;
 use std::io;
 use std::net::Ipv6Addr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use columnar::{
     BytesColumn, Column, ColumnType, ColumnValues, ColumnarReader, DynamicColumn,
@@ -10,6 +11,7 @@ use common::ByteCount;
 
 use crate::core::json_utils::{encode_column_name, json_path_sep_to_dot};
 use crate::directory::FileSlice;
+use crate::fastfield::{RuntimeField, RuntimeFieldManager};
 use crate::schema::{Field, FieldEntry, FieldType, Schema};
 use crate::space_usage::{FieldUsage, PerFieldSpaceUsage};
 use crate::TantivyError;
@@ -22,12 +24,109 @@ use crate::TantivyError;
 pub struct FastFieldReaders {
     columnar: Arc<ColumnarReader>,
     schema: Schema,
+    runtime_fields: RuntimeFieldManager,
+    /// Columns of the runtime fields computed on this segment, with the runtime field they were
+    /// computed with.
+    runtime_column_cache: Arc<RwLock<HashMap<String, (RuntimeField, DynamicColumn)>>>,
 }
 
 impl FastFieldReaders {
     pub(crate) fn open(fast_field_file: FileSlice, schema: Schema) -> io::Result<FastFieldReaders> {
         let columnar = Arc::new(ColumnarReader::open(fast_field_file)?);
-        Ok(FastFieldReaders { columnar, schema })
+        Ok(FastFieldReaders {
+            columnar,
+            schema,
+            runtime_fields: RuntimeFieldManager::default(),
+            runtime_column_cache: Default::default(),
+        })
+    }
+
+    /// Makes the runtime fields of the given manager available as columns.
+    pub(crate) fn with_runtime_fields(mut self, runtime_fields: RuntimeFieldManager) -> Self {
+        self.runtime_fields = runtime_fields;
+        self
+    }
+
+    /// Returns the generation of the runtime fields visible to the segment.
+    ///
+    /// It changes whenever a runtime field is registered or unregistered, and is part of the
+    /// keys of the caches holding results computed from runtime fields.
+    pub(crate) fn runtime_fields_generation(&self) -> u64 {
+        self.runtime_fields.generation()
+    }
+
+    /// Returns the runtime field registered under `field_name`, if any.
+    pub(crate) fn runtime_field(&self, field_name: &str) -> Option<RuntimeField> {
+        self.runtime_fields.get(field_name)
+    }
+
+    /// Returns the column of the runtime field registered under `field_name`, if any.
+    fn runtime_column(&self, field_name: &str) -> crate::Result<Option<DynamicColumn>> {
+        let Some(runtime_field) = self.runtime_fields.get(field_name) else {
+            return Ok(None);
+        };
+        self.cached_runtime_column(field_name, &runtime_field)
+            .map(Some)
+    }
+
+    /// Returns the column of `runtime_field` on this segment, computing it on the first call.
+    ///
+    /// The column is computed again if another runtime field was registered under the same name
+    /// in the meantime.
+    fn cached_runtime_column(
+        &self,
+        field_name: &str,
+        runtime_field: &RuntimeField,
+    ) -> crate::Result<DynamicColumn> {
+        if let Some((cached_runtime_field, column)) = self
+            .runtime_column_cache
+            .read()
+            .expect("Runtime column cache lock poisoned. This should never happen.")
+            .get(field_name)
+        {
+            if cached_runtime_field.is_same(runtime_field) {
+                return Ok(column.clone());
+            }
+        }
+        // The lock is not held while computing the column, as it may request other runtime
+        // fields.
+        let column = runtime_field.compute_column(self)?;
+        self.runtime_column_cache
+            .write()
+            .expect("Runtime column cache lock poisoned. This should never happen.")
+            .insert(
+                field_name.to_string(),
+                (runtime_field.clone(), column.clone()),
+            );
+        Ok(column)
+    }
+
+    /// Returns the `u64` representation of a runtime field column, if its type is accepted by
+    /// `type_white_list_opt`.
+    fn runtime_u64_lenient_for_type(
+        &self,
+        field_name: &str,
+        runtime_field: &RuntimeField,
+        type_white_list_opt: Option<&[ColumnType]>,
+    ) -> crate::Result<Option<(Column<u64>, ColumnType)>> {
+        let column_type = runtime_field.column_type();
+        if let Some(type_white_list) = type_white_list_opt {
+            if !type_white_list.contains(&column_type) {
+                return Ok(None);
+            }
+        }
+        let runtime_column = self.cached_runtime_column(field_name, runtime_field)?;
+        let col_u64 = match runtime_column {
+            DynamicColumn::Bool(column) => column.to_u64_monotonic(),
+            DynamicColumn::I64(column) => column.to_u64_monotonic(),
+            DynamicColumn::U64(column) => column,
+            DynamicColumn::F64(column) => column.to_u64_monotonic(),
+            DynamicColumn::DateTime(column) => column.to_u64_monotonic(),
+            DynamicColumn::IpAddr(_) | DynamicColumn::Bytes(_) | DynamicColumn::Str(_) => {
+                return Ok(None);
+            }
+        };
+        Ok(Some((col_u64, column_type)))
     }
 
     fn resolve_field(&self, column_name: &str) -> crate::Result<Option<String>> {
@@ -116,6 +215,9 @@ impl FastFieldReaders {
         T: HasAssociatedColumnType,
         DynamicColumn: Into<Option<Column<T>>>,
     {
+        if let Some(runtime_column) = self.runtime_column(field_name)? {
+            return Ok(runtime_column.into());
+        }
         let Some(dynamic_column_handle) =
             self.dynamic_column_handle(field_name, T::column_type())?
         else {
@@ -277,6 +379,24 @@ impl FastFieldReaders {
         Ok(columns)
     }
 
+    /// Prefetches, through the async read path, all of the columns associated
+    /// with the given fast field name.
+    ///
+    /// Use `subpaths` to also load the columns of the sub paths of a json field.
+    pub async fn warm_fast_field(&self, field_name: &str, subpaths: bool) -> crate::Result<()> {
+        let mut column_handles = self.list_dynamic_column_handles(field_name).await?;
+        if subpaths {
+            column_handles.extend(self.list_subpath_dynamic_column_handles(field_name).await?);
+        }
+        futures_util::future::try_join_all(
+            column_handles
+                .iter()
+                .map(|column_handle| column_handle.file_slice().read_bytes_async()),
+        )
+        .await?;
+        Ok(())
+    }
+
     #[doc(hidden)]
     pub async fn list_subpath_dynamic_column_handles(
         &self,
@@ -301,6 +421,22 @@ impl FastFieldReaders {
         &self,
         type_white_list_opt: Option<&[ColumnType]>,
         field_name: &str,
+    ) -> crate::Result<Option<(Column<u64>, ColumnType)>> {
+        if let Some(runtime_field) = self.runtime_fields.get(field_name) {
+            return self.runtime_u64_lenient_for_type(
+                field_name,
+                &runtime_field,
+                type_white_list_opt,
+            );
+        }
+        self.physical_u64_lenient_for_type(type_white_list_opt, field_name)
+    }
+
+    /// Same as [`FastFieldReaders::u64_lenient_for_type`], ignoring runtime fields.
+    pub(crate) fn physical_u64_lenient_for_type(
+        &self,
+        type_white_list_opt: Option<&[ColumnType]>,
+        field_name: &str,
     ) -> crate::Result<Option<(Column<u64>, ColumnType)>> {
         let Some(resolved_field_name) = self.resolve_field(field_name)? else {
             return Ok(None);
@@ -329,6 +465,11 @@ impl FastFieldReaders {
         type_white_list_opt: Option<&[ColumnType]>,
         field_name: &str,
     ) -> crate::Result<Vec<(Column<u64>, ColumnType)>> {
+        if let Some(runtime_field) = self.runtime_fields.get(field_name) {
+            let runtime_column_opt =
+                self.runtime_u64_lenient_for_type(field_name, &runtime_field, type_white_list_opt)?;
+            return Ok(runtime_column_opt.into_iter().collect());
+        }
         let mut columns_and_types = Vec::new();
         let Some(resolved_field_name) = self.resolve_field(field_name)? else {
             return Ok(columns_and_types);
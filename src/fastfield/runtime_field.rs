@@ -0,0 +1,825 @@
+//! Runtime fields are virtual fast fields, computed at search time from the
+//! physical fast fields of a segment.
+//!
+//! A runtime field is registered on the [`Index`](crate::Index) under a name, and can then be
+//! used anywhere a fast field name is accepted: in aggregation requests,
+//! `TopDocs::order_by_fast_field` or [`ColumnRangeQuery`](crate::query::ColumnRangeQuery).
+//!
+//! A runtime field is either defined by a small arithmetic expression over fast fields
+//! (see [`RuntimeField::from_expression`]), or by a Rust closure building a
+//! [`Column`] from the segment [`FastFieldReaders`] (see [`RuntimeField::from_fn`]).
+//!
+//! The column is materialized in memory for each segment it is requested on.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use columnar::column_index::OptionalIndex;
+use columnar::column_values::VecColumn;
+use columnar::{
+    Column, ColumnIndex, ColumnType, DynamicColumn, HasAssociatedColumnType,
+    MonotonicallyMappableToU64, RowId,
+};
+
+use crate::fastfield::FastFieldReaders;
+use crate::{DateTime, TantivyError};
+
+type ComputeColumnFn = dyn Fn(&FastFieldReaders) -> crate::Result<DynamicColumn> + Send + Sync;
+
+/// The type of the values produced by a runtime field expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuntimeFieldType {
+    /// Unsigned 64-bits integer. Negative values are treated as missing.
+    U64,
+    /// Signed 64-bits integer.
+    I64,
+    /// 64-bits float.
+    F64,
+    /// Date, expressed by the expression as milliseconds since the unix epoch.
+    Date,
+}
+
+impl RuntimeFieldType {
+    fn column_type(self) -> ColumnType {
+        match self {
+            RuntimeFieldType::U64 => ColumnType::U64,
+            RuntimeFieldType::I64 => ColumnType::I64,
+            RuntimeFieldType::F64 => ColumnType::F64,
+            RuntimeFieldType::Date => ColumnType::DateTime,
+        }
+    }
+
+    /// Returns true if `val` is representable in this type.
+    fn accepts(self, val: f64) -> bool {
+        match self {
+            // `u64::MAX as f64` and `i64::MAX as f64` round up to out of range values.
+            RuntimeFieldType::U64 => (0.0..u64::MAX as f64).contains(&val),
+            RuntimeFieldType::I64 => (i64::MIN as f64..i64::MAX as f64).contains(&val),
+            RuntimeFieldType::F64 => val.is_finite(),
+            RuntimeFieldType::Date => date_from_millis(val).is_some(),
+        }
+    }
+}
+
+/// Converts milliseconds since the unix epoch to a date, if it is in the range of [`DateTime`].
+fn date_from_millis(millis: f64) -> Option<DateTime> {
+    const MAX_MILLIS: i64 = i64::MAX / 1_000_000;
+    if !(-MAX_MILLIS as f64..=MAX_MILLIS as f64).contains(&millis) {
+        return None;
+    }
+    Some(DateTime::from_timestamp_millis(millis as i64))
+}
+
+/// A virtual fast field, computed from the other fast fields of a segment.
+#[derive(Clone)]
+pub struct RuntimeField {
+    column_type: ColumnType,
+    compute: Arc<ComputeColumnFn>,
+}
+
+impl fmt::Debug for RuntimeField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeField")
+            .field("column_type", &self.column_type)
+            .finish()
+    }
+}
+
+impl RuntimeField {
+    /// Creates a runtime field from a closure building the column of a segment.
+    ///
+    /// The closure must not request the runtime field it defines.
+    pub fn from_fn<T, F>(compute: F) -> RuntimeField
+    where
+        T: HasAssociatedColumnType,
+        Column<T>: Into<DynamicColumn>,
+        F: Fn(&FastFieldReaders) -> crate::Result<Column<T>> + Send + Sync + 'static,
+    {
+        RuntimeField {
+            column_type: T::column_type(),
+            compute: Arc::new(move |fast_field_readers: &FastFieldReaders| {
+                compute(fast_field_readers).map(Into::into)
+            }),
+        }
+    }
+
+    /// Creates a runtime field from an arithmetic expression over fast fields.
+    ///
+    /// The expression supports numerical literals, the `+`, `-`, `*`, `/` and `%` operators,
+    /// parentheses, and references to numerical, boolean or date fast fields by name.
+    /// Date fields evaluate to milliseconds since the unix epoch.
+    ///
+    /// The following functions are available:
+    /// - `hour_of_day(date)`, `day_of_week(date)` (1 for Monday to 7 for Sunday),
+    ///   `month_of_year(date)` (1 to 12) and `year(date)`, all computed in UTC.
+    /// - `abs(x)`, `min(x, y)` and `max(x, y)`.
+    ///
+    /// A document gets no value if one of the referenced fields has no value for it, or if the
+    /// result is not representable in the output type.
+    ///
+    /// ```rust
+    /// use tantivy::fastfield::{RuntimeField, RuntimeFieldType};
+    ///
+    /// let total = RuntimeField::from_expression("price * quantity", RuntimeFieldType::F64);
+    /// assert!(total.is_ok());
+    /// let invalid = RuntimeField::from_expression("price *", RuntimeFieldType::F64);
+    /// assert!(invalid.is_err());
+    /// ```
+    pub fn from_expression(
+        expression: &str,
+        output_type: RuntimeFieldType,
+    ) -> crate::Result<RuntimeField> {
+        let parsed_expression = Arc::new(ExpressionParser::parse(expression)?);
+        Ok(RuntimeField {
+            column_type: output_type.column_type(),
+            compute: Arc::new(move |fast_field_readers: &FastFieldReaders| {
+                parsed_expression.compute_column(fast_field_readers, output_type)
+            }),
+        })
+    }
+
+    /// Returns the type of the column produced by this runtime field.
+    pub fn column_type(&self) -> ColumnType {
+        self.column_type
+    }
+
+    /// Returns true if both runtime fields are clones of the same runtime field.
+    pub(crate) fn is_same(&self, other: &RuntimeField) -> bool {
+        Arc::ptr_eq(&self.compute, &other.compute)
+    }
+
+    pub(crate) fn compute_column(
+        &self,
+        fast_field_readers: &FastFieldReaders,
+    ) -> crate::Result<DynamicColumn> {
+        let column = (self.compute)(fast_field_readers)?;
+        if column.column_type() != self.column_type {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Runtime field produced a column of type {:?}, expected {:?}",
+                column.column_type(),
+                self.column_type
+            )));
+        }
+        Ok(column)
+    }
+}
+
+/// The runtime field manager serves as a store for the runtime fields of an index.
+///
+/// Runtime fields registered after a reader was opened are also visible to that reader.
+#[derive(Clone, Default)]
+pub struct RuntimeFieldManager {
+    runtime_fields: Arc<RwLock<HashMap<String, RuntimeField>>>,
+    /// Incremented each time a runtime field is registered or unregistered, so that the
+    /// results cached across searches can tell they were computed with stale definitions.
+    generation: Arc<AtomicU64>,
+}
+
+impl RuntimeFieldManager {
+    /// Registers a runtime field under the given name.
+    ///
+    /// A runtime field shadows the fast field of the same name, if any.
+    pub fn register(&self, field_name: &str, runtime_field: RuntimeField) {
+        let mut runtime_fields = self
+            .runtime_fields
+            .write()
+            .expect("Acquiring the lock should never fail");
+        runtime_fields.insert(field_name.to_string(), runtime_field);
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Removes the runtime field registered under the given name.
+    pub fn unregister(&self, field_name: &str) -> Option<RuntimeField> {
+        let mut runtime_fields = self
+            .runtime_fields
+            .write()
+            .expect("Acquiring the lock should never fail");
+        let runtime_field = runtime_fields.remove(field_name);
+        self.generation.fetch_add(1, Ordering::Release);
+        runtime_field
+    }
+
+    /// Accessing a runtime field given its name.
+    pub fn get(&self, field_name: &str) -> Option<RuntimeField> {
+        self.runtime_fields
+            .read()
+            .expect("Acquiring the lock should never fail")
+            .get(field_name)
+            .cloned()
+    }
+
+    /// Returns a counter that changes each time the set of runtime fields changes.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+}
+
+/// Column types that can be referenced from an expression.
+const EXPRESSION_INPUT_TYPES: [ColumnType; 5] = [
+    ColumnType::U64,
+    ColumnType::I64,
+    ColumnType::F64,
+    ColumnType::Bool,
+    ColumnType::DateTime,
+];
+
+fn input_to_f64(val: u64, column_type: ColumnType) -> f64 {
+    match column_type {
+        ColumnType::I64 => i64::from_u64(val) as f64,
+        ColumnType::F64 => f64::from_u64(val),
+        ColumnType::DateTime => DateTime::from_u64(val).into_timestamp_millis() as f64,
+        _ => val as f64,
+    }
+}
+
+fn build_column<T: MonotonicallyMappableToU64 + Default>(
+    num_docs: RowId,
+    row_ids: &[RowId],
+    values: Vec<T>,
+) -> Column<T> {
+    let index = if row_ids.len() == num_docs as usize {
+        ColumnIndex::Full
+    } else {
+        ColumnIndex::Optional(OptionalIndex::from_row_ids(num_docs, row_ids))
+    };
+    Column {
+        index,
+        values: Arc::new(VecColumn::from(values)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Function {
+    HourOfDay,
+    DayOfWeek,
+    MonthOfYear,
+    Year,
+    Abs,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "hour_of_day" => Some(Function::HourOfDay),
+            "day_of_week" => Some(Function::DayOfWeek),
+            "month_of_year" => Some(Function::MonthOfYear),
+            "year" => Some(Function::Year),
+            "abs" => Some(Function::Abs),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Min | Function::Max => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns NaN, i.e. no value, if a date argument is out of the range of [`DateTime`].
+    fn apply(self, args: &[f64]) -> f64 {
+        let date_part = |part: fn(time::OffsetDateTime) -> f64| {
+            date_from_millis(args[0]).map_or(f64::NAN, |date| part(date.into_utc()))
+        };
+        match self {
+            Function::HourOfDay => date_part(|date| date.hour() as f64),
+            Function::DayOfWeek => date_part(|date| date.weekday().number_from_monday() as f64),
+            Function::MonthOfYear => date_part(|date| u8::from(date.month()) as f64),
+            Function::Year => date_part(|date| date.year() as f64),
+            Function::Abs => args[0].abs(),
+            Function::Min => args[0].min(args[1]),
+            Function::Max => args[0].max(args[1]),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Literal(f64),
+    /// Ordinal of the field in [`Expression::field_names`].
+    Field(usize),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, inputs: &[f64]) -> f64 {
+        match self {
+            Expr::Literal(val) => *val,
+            Expr::Field(ord) => inputs[*ord],
+            Expr::Neg(expr) => -expr.eval(inputs),
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (left.eval(inputs), right.eval(inputs));
+                match op {
+                    BinaryOp::Add => left + right,
+                    BinaryOp::Sub => left - right,
+                    BinaryOp::Mul => left * right,
+                    BinaryOp::Div => left / right,
+                    BinaryOp::Rem => left % right,
+                }
+            }
+            Expr::Call(function, args) => {
+                let args: Vec<f64> = args.iter().map(|arg| arg.eval(inputs)).collect();
+                function.apply(&args)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Expression {
+    root: Expr,
+    field_names: Vec<String>,
+}
+
+impl Expression {
+    fn compute_column(
+        &self,
+        fast_field_readers: &FastFieldReaders,
+        output_type: RuntimeFieldType,
+    ) -> crate::Result<DynamicColumn> {
+        let num_docs = fast_field_readers.columnar().num_docs();
+        let mut input_columns = Vec::with_capacity(self.field_names.len());
+        for field_name in &self.field_names {
+            if let Some(column_and_type) = fast_field_readers
+                .physical_u64_lenient_for_type(Some(&EXPRESSION_INPUT_TYPES), field_name)?
+            {
+                input_columns.push(column_and_type);
+            }
+        }
+        let mut row_ids: Vec<RowId> = Vec::new();
+        let mut values: Vec<f64> = Vec::new();
+        // If one of the fields has no column, no document can get a value.
+        if input_columns.len() == self.field_names.len() {
+            let mut inputs = vec![0.0f64; input_columns.len()];
+            for doc in 0..num_docs {
+                let has_all_inputs = input_columns.iter().zip(inputs.iter_mut()).all(
+                    |((column, column_type), input)| {
+                        if let Some(val) = column.first(doc) {
+                            *input = input_to_f64(val, *column_type);
+                            true
+                        } else {
+                            false
+                        }
+                    },
+                );
+                if !has_all_inputs {
+                    continue;
+                }
+                let val = self.root.eval(&inputs);
+                if output_type.accepts(val) {
+                    row_ids.push(doc);
+                    values.push(val);
+                }
+            }
+        }
+        let column: DynamicColumn = match output_type {
+            RuntimeFieldType::U64 => {
+                let values = values.into_iter().map(|val| val as u64).collect();
+                build_column::<u64>(num_docs, &row_ids, values).into()
+            }
+            RuntimeFieldType::I64 => {
+                let values = values.into_iter().map(|val| val as i64).collect();
+                build_column::<i64>(num_docs, &row_ids, values).into()
+            }
+            RuntimeFieldType::F64 => build_column::<f64>(num_docs, &row_ids, values).into(),
+            RuntimeFieldType::Date => {
+                // The values were checked to be in the range of `DateTime`.
+                let values = values
+                    .into_iter()
+                    .map(|val| date_from_millis(val).unwrap_or_default())
+                    .collect();
+                build_column::<DateTime>(num_docs, &row_ids, values).into()
+            }
+        };
+        Ok(column)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(BinaryOp),
+    OpenParen,
+    CloseParen,
+    Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '0'..='9' | '.' => {
+                let mut end = start;
+                while let Some(&(pos, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = pos + c.len_utf8();
+                    chars.next();
+                }
+                let literal = &expression[start..end];
+                let val = literal
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid number `{literal}`"))?;
+                tokens.push(Token::Number(val));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some(&(pos, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    end = pos + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Ident(expression[start..end].to_string()));
+                continue;
+            }
+            '+' => Token::Op(BinaryOp::Add),
+            '-' => Token::Op(BinaryOp::Sub),
+            '*' => Token::Op(BinaryOp::Mul),
+            '/' => Token::Op(BinaryOp::Div),
+            '%' => Token::Op(BinaryOp::Rem),
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            ',' => Token::Comma,
+            _ => return Err(format!("unexpected character `{c}` at position {start}")),
+        };
+        chars.next();
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser for runtime field expressions.
+///
+/// ```text
+/// expr    := term (('+' | '-') term)*
+/// term    := unary (('*' | '/' | '%') unary)*
+/// unary   := '-' unary | primary
+/// primary := number | ident '(' args ')' | ident | '(' expr ')'
+/// ```
+struct ExpressionParser {
+    tokens: Vec<Token>,
+    pos: usize,
+    field_names: Vec<String>,
+}
+
+impl ExpressionParser {
+    fn parse(expression: &str) -> crate::Result<Expression> {
+        let to_error = |msg: String| {
+            TantivyError::InvalidArgument(format!(
+                "Invalid runtime field expression `{expression}`: {msg}"
+            ))
+        };
+        let tokens = tokenize(expression).map_err(to_error)?;
+        let mut parser = ExpressionParser {
+            tokens,
+            pos: 0,
+            field_names: Vec::new(),
+        };
+        let root = parser.parse_expr().map_err(to_error)?;
+        if let Some(token) = parser.peek() {
+            return Err(to_error(format!("unexpected token {token:?}")));
+        }
+        Ok(Expression {
+            root,
+            field_names: parser.field_names,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {expected:?}, got {token:?}")),
+            None => Err(format!("expected {expected:?}, got end of expression")),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_term()?;
+        while let Some(&Token::Op(op @ (BinaryOp::Add | BinaryOp::Sub))) = self.peek() {
+            self.pos += 1;
+            let right = self.parse_term()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_unary()?;
+        while let Some(&Token::Op(op @ (BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem))) =
+            self.peek()
+        {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Op(BinaryOp::Sub)) {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(val)) => Ok(Expr::Literal(val)),
+            Some(Token::OpenParen) => {
+                let expr = self.parse_expr()?;
+                self.expect(Token::CloseParen)?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::OpenParen) {
+                    self.pos += 1;
+                    self.parse_call(&name)
+                } else {
+                    Ok(Expr::Field(self.field_ord(name)))
+                }
+            }
+            Some(token) => Err(format!("unexpected token {token:?}")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str) -> Result<Expr, String> {
+        let function =
+            Function::from_name(name).ok_or_else(|| format!("unknown function `{name}`"))?;
+        let mut args = Vec::new();
+        if self.peek() != Some(&Token::CloseParen) {
+            args.push(self.parse_expr()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                args.push(self.parse_expr()?);
+            }
+        }
+        self.expect(Token::CloseParen)?;
+        if args.len() != function.arity() {
+            return Err(format!(
+                "function `{name}` expects {} argument(s), got {}",
+                function.arity(),
+                args.len()
+            ));
+        }
+        Ok(Expr::Call(function, args))
+    }
+
+    fn field_ord(&mut self, name: String) -> usize {
+        if let Some(ord) = self.field_names.iter().position(|field| *field == name) {
+            return ord;
+        }
+        self.field_names.push(name);
+        self.field_names.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use columnar::{Column, ColumnType};
+    use serde_json::json;
+
+    use super::{ExpressionParser, RuntimeField, RuntimeFieldType};
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::agg_result::AggregationResults;
+    use crate::aggregation::AggregationCollector;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{AllQuery, ColumnRangeQuery};
+    use crate::schema::{Schema, FAST};
+    use crate::{DateTime, Index, IndexWriter, Order};
+
+    fn eval(expression: &str, inputs: &[f64]) -> f64 {
+        ExpressionParser::parse(expression)
+            .unwrap()
+            .root
+            .eval(inputs)
+    }
+
+    #[test]
+    fn test_runtime_field_expression_parse() {
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(eval("-2 * -(3 - 1)", &[]), 4.0);
+        assert_eq!(eval("7 % 4 / 2", &[]), 1.5);
+        assert_eq!(eval("max(a, b) - min(a, abs(b))", &[-3.0, 2.0]), 5.0);
+        // 2015-01-01T17:00:00Z, a thursday.
+        assert_eq!(eval("hour_of_day(1420131600000)", &[]), 17.0);
+        assert_eq!(eval("day_of_week(1420131600000)", &[]), 4.0);
+        assert_eq!(eval("month_of_year(1420131600000)", &[]), 1.0);
+        assert_eq!(eval("year(1420131600000)", &[]), 2015.0);
+        assert!(eval("year(9223372036854775807)", &[]).is_nan());
+        assert!(eval("hour_of_day(-9223372036854775807)", &[]).is_nan());
+        let expression = ExpressionParser::parse("a * b + a").unwrap();
+        assert_eq!(
+            expression.field_names,
+            vec!["a".to_string(), "b".to_string()]
+        );
+
+        for invalid in ["", "a +", "(a", "a b", "unknown(a)", "min(a)", "a $ b"] {
+            assert!(
+                RuntimeField::from_expression(invalid, RuntimeFieldType::F64).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    fn create_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let price = schema_builder.add_f64_field("price", FAST);
+        let quantity = schema_builder.add_u64_field("quantity", FAST);
+        let date = schema_builder.add_date_field("date", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        let hour_ms = 3_600_000;
+        index_writer.add_document(doc!(
+            price => 2.5f64,
+            quantity => 4u64,
+            date => DateTime::from_timestamp_millis(1_420_070_400_000 + 10 * hour_ms),
+        ))?;
+        index_writer.add_document(doc!(
+            price => 10.0f64,
+            quantity => 1u64,
+            date => DateTime::from_timestamp_millis(1_420_070_400_000 + 17 * hour_ms),
+        ))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(
+            price => 1.0f64,
+            quantity => 3u64,
+            date => DateTime::from_timestamp_millis(1_420_070_400_000 + 10 * hour_ms),
+        ))?;
+        // No quantity, hence no total.
+        index_writer.add_document(doc!(price => 100.0f64))?;
+        index_writer.commit()?;
+        let runtime_fields = index.runtime_fields();
+        runtime_fields.register(
+            "total",
+            RuntimeField::from_expression("price * quantity", RuntimeFieldType::F64)?,
+        );
+        runtime_fields.register(
+            "hour",
+            RuntimeField::from_expression("hour_of_day(date)", RuntimeFieldType::U64)?,
+        );
+        Ok(index)
+    }
+
+    #[test]
+    fn test_runtime_field_aggregation() -> crate::Result<()> {
+        let index = create_index()?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "total_sum": { "sum": { "field": "total" } },
+            "by_hour": { "terms": { "field": "hour" } },
+        }))
+        .unwrap();
+        let searcher = index.reader()?.searcher();
+        let collector = AggregationCollector::from_aggs(agg_req, Default::default());
+        let agg_res: AggregationResults = searcher.search(&AllQuery, &collector)?;
+        let res = serde_json::to_value(agg_res)?;
+        assert_eq!(res["total_sum"]["value"], 23.0);
+        assert_eq!(
+            res["by_hour"]["buckets"],
+            json!([{ "key": 10, "doc_count": 2 }, { "key": 17, "doc_count": 1 }])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_field_order_by_and_range_query() -> crate::Result<()> {
+        let index = create_index()?;
+        let searcher = index.reader()?.searcher();
+        let top_docs = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(4).order_by_fast_field::<f64>("total", Order::Desc),
+        )?;
+        let totals: Vec<Option<f64>> = top_docs.into_iter().map(|(total, _)| total).collect();
+        assert_eq!(totals, vec![Some(10.0), Some(10.0), Some(3.0), None]);
+
+        let range_query = ColumnRangeQuery::new("total", Bound::Included(5.0f64), Bound::Unbounded);
+        assert_eq!(searcher.search(&range_query, &Count)?, 2);
+        let range_query = ColumnRangeQuery::new("hour", Bound::Excluded(10u64), Bound::Unbounded);
+        assert_eq!(searcher.search(&range_query, &Count)?, 1);
+
+        let err = searcher
+            .search(
+                &AllQuery,
+                &TopDocs::with_limit(4).order_by_fast_field::<u64>("total", Order::Desc),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("is of type F64"), "{err}");
+        let err = searcher
+            .search(
+                &AllQuery,
+                &TopDocs::with_limit(4).order_by_fast_field::<f64>("unknown", Order::Desc),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("nor a runtime field"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_field_date_out_of_range() -> crate::Result<()> {
+        let index = create_index()?;
+        index.runtime_fields().register(
+            "far_future",
+            RuntimeField::from_expression("date * 1000000000", RuntimeFieldType::Date)?,
+        );
+        let searcher = index.reader()?.searcher();
+        let range_query = ColumnRangeQuery::new(
+            "far_future",
+            Bound::Included(DateTime::MIN),
+            Bound::Unbounded,
+        );
+        assert_eq!(searcher.search(&range_query, &Count)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_column_computed_once_per_segment() -> crate::Result<()> {
+        let index = create_index()?;
+        let num_computations = Arc::new(AtomicUsize::new(0));
+        let num_computations_clone = num_computations.clone();
+        let price = RuntimeField::from_fn(move |fast_field_readers| {
+            num_computations_clone.fetch_add(1, Ordering::Relaxed);
+            let price: Column<f64> = fast_field_readers.f64("price")?;
+            Ok(price)
+        });
+        index.runtime_fields().register("price_copy", price.clone());
+        let searcher = index.reader()?.searcher();
+        let range_query =
+            ColumnRangeQuery::new("price_copy", Bound::Included(2.0f64), Bound::Unbounded);
+        assert_eq!(searcher.search(&range_query, &Count)?, 3);
+        assert_eq!(searcher.search(&range_query, &Count)?, 3);
+        assert_eq!(num_computations.load(Ordering::Relaxed), 2);
+        index.runtime_fields().register("price_copy", price);
+        assert_eq!(searcher.search(&range_query, &Count)?, 3);
+        assert_eq!(num_computations.load(Ordering::Relaxed), 2);
+        // Registering another runtime field under the same name invalidates the cached columns.
+        index.runtime_fields().register(
+            "price_copy",
+            RuntimeField::from_expression("price * 2", RuntimeFieldType::F64)?,
+        );
+        assert_eq!(searcher.search(&range_query, &Count)?, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_field_from_fn() -> crate::Result<()> {
+        let index = create_index()?;
+        let discounted = RuntimeField::from_fn(|fast_field_readers| {
+            let price: Column<f64> = fast_field_readers.f64("price")?;
+            Ok(price)
+        });
+        assert_eq!(discounted.column_type(), ColumnType::F64);
+        index.runtime_fields().register("discounted", discounted);
+        let searcher = index.reader()?.searcher();
+        let range_query =
+            ColumnRangeQuery::new("discounted", Bound::Unbounded, Bound::Excluded(10.0f64));
+        assert_eq!(searcher.search(&range_query, &Count)?, 2);
+        // The column type of the runtime field does not match the requested one.
+        let range_query =
+            ColumnRangeQuery::new("discounted", Bound::Included(0u64), Bound::Unbounded);
+        assert_eq!(searcher.search(&range_query, &Count)?, 0);
+        Ok(())
+    }
+}
@@ -36,6 +36,14 @@ impl FieldNormReaders {
         }
     }
 
+    /// Prefetches the fieldnorms of a specific field through the async read path.
+    pub async fn warm_field(&self, field: Field) -> crate::Result<()> {
+        if let Some(file) = self.data.open_read(field) {
+            file.read_bytes_async().await?;
+        }
+        Ok(())
+    }
+
     /// Return a break down of the space usage per field.
     pub fn space_usage(&self, schema: &Schema) -> PerFieldSpaceUsage {
         self.data.space_usage(schema)
@@ -14,6 +14,7 @@ use crate::directory::error::OpenReadError;
 use crate::directory::MmapDirectory;
 use crate::directory::{Directory, ManagedDirectory, RamDirectory, INDEX_WRITER_LOCK};
 use crate::error::{DataCorruption, TantivyError};
+use crate::fastfield::RuntimeFieldManager;
 use crate::index::{IndexMeta, SegmentId, SegmentMeta, SegmentMetaInventory};
 use crate::indexer::index_writer::{
     IndexWriterOptions, MAX_NUM_THREAD, MEMORY_BUDGET_NUM_BYTES_MIN,
@@ -106,6 +107,7 @@ pub struct IndexBuilder {
     index_settings: IndexSettings,
     tokenizer_manager: TokenizerManager,
     fast_field_tokenizer_manager: TokenizerManager,
+    runtime_field_manager: RuntimeFieldManager,
 }
 impl Default for IndexBuilder {
     fn default() -> Self {
@@ -120,6 +122,7 @@ impl IndexBuilder {
             index_settings: IndexSettings::default(),
             tokenizer_manager: TokenizerManager::default(),
             fast_field_tokenizer_manager: TokenizerManager::default(),
+            runtime_field_manager: RuntimeFieldManager::default(),
         }
     }
 
@@ -149,6 +152,12 @@ impl IndexBuilder {
         self
     }
 
+    /// Set the runtime fields.
+    pub fn runtime_fields(mut self, runtime_fields: RuntimeFieldManager) -> Self {
+        self.runtime_field_manager = runtime_fields;
+        self
+    }
+
     /// Creates a new index using the [`RamDirectory`].
     ///
     /// The index will be allocated in anonymous memory.
@@ -222,6 +231,7 @@ impl IndexBuilder {
         }
         let mut index = Index::open(dir)?;
         index.set_tokenizers(self.tokenizer_manager.clone());
+        index.set_runtime_fields(self.runtime_field_manager.clone());
         if index.schema() == self.get_expect_schema()? {
             Ok(index)
         } else {
@@ -258,6 +268,7 @@ impl IndexBuilder {
         let mut index = Index::open_from_metas(directory, &metas, SegmentMetaInventory::default());
         index.set_tokenizers(self.tokenizer_manager);
         index.set_fast_field_tokenizers(self.fast_field_tokenizer_manager);
+        index.set_runtime_fields(self.runtime_field_manager);
         Ok(index)
     }
 }
@@ -271,6 +282,7 @@ pub struct Index {
     executor: Executor,
     tokenizers: TokenizerManager,
     fast_field_tokenizers: TokenizerManager,
+    runtime_fields: RuntimeFieldManager,
     inventory: SegmentMetaInventory,
     read_only: bool
 }
@@ -390,6 +402,7 @@ impl Index {
             schema,
             tokenizers: TokenizerManager::default(),
             fast_field_tokenizers: TokenizerManager::default(),
+            runtime_fields: RuntimeFieldManager::default(),
             executor: Executor::single_thread(),
             inventory,
             read_only: false,
@@ -426,6 +439,19 @@ impl Index {
         &self.fast_field_tokenizers
     }
 
+    /// Setter for the runtime field manager.
+    pub fn set_runtime_fields(&mut self, runtime_fields: RuntimeFieldManager) {
+        self.runtime_fields = runtime_fields;
+    }
+
+    /// Accessor for the runtime field manager.
+    ///
+    /// Runtime fields registered here can be used as fast fields in aggregations,
+    /// sorts and [`ColumnRangeQuery`](crate::query::ColumnRangeQuery).
+    pub fn runtime_fields(&self) -> &RuntimeFieldManager {
+        &self.runtime_fields
+    }
+
     /// Get the tokenizer associated with a specific field.
     pub fn tokenizer_for_field(&self, field: Field) -> crate::Result<TextAnalyzer> {
         let field_entry = self.schema.get_field_entry(field);
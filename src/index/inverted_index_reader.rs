@@ -1,4 +1,5 @@
 use std::io;
+use std::ops::Range;
 
 use common::json_path_writer::JSON_END_OF_PATH;
 use common::{BinarySerializable, ByteCount};
@@ -280,6 +281,44 @@ impl InvertedIndexReader {
             .unwrap_or(0u32))
     }
 
+    /// Warmup the block postings associated with the given `TermInfo`s.
+    /// This method is for an advanced usage only.
+    ///
+    /// The term infos are expected to be sorted, as returned by a term dictionary
+    /// stream. Byte ranges separated by small holes are coalesced into a single read.
+    pub async fn warm_postings_for_term_infos(
+        &self,
+        term_infos: &[TermInfo],
+        with_positions: bool,
+    ) -> io::Result<()> {
+        let postings_ranges = coalesce_ranges(
+            term_infos
+                .iter()
+                .map(|term_info| term_info.postings_range.clone()),
+        );
+        let postings = futures_util::future::try_join_all(
+            postings_ranges
+                .into_iter()
+                .map(|range| self.postings_file_slice.read_bytes_slice_async(range)),
+        );
+        if with_positions {
+            let positions_ranges = coalesce_ranges(
+                term_infos
+                    .iter()
+                    .map(|term_info| term_info.positions_range.clone()),
+            );
+            let positions = futures_util::future::try_join_all(
+                positions_ranges
+                    .into_iter()
+                    .map(|range| self.positions_file_slice.read_bytes_slice_async(range)),
+            );
+            futures_util::future::try_join(postings, positions).await?;
+        } else {
+            postings.await?;
+        }
+        Ok(())
+    }
+
     fn warmup_bytes(bytes: &[u8]) {
         let page_size = page_size::get();
         for i in (0..bytes.len()).step_by(page_size) {
@@ -309,6 +348,13 @@ impl InvertedIndexReader {
         Ok(())
     }
 
+    /// The fst term dictionary is loaded in memory when the segment is opened, so there is
+    /// nothing to fetch here.
+    #[cfg(not(feature = "quickwit"))]
+    pub(crate) async fn get_term_info_async(&self, term: &Term) -> io::Result<Option<TermInfo>> {
+        self.get_term_info(term)
+    }
+
     /// Warmup a block postings given a `Term`.
     /// This method is for an advanced usage only.
     ///
@@ -546,3 +592,20 @@ impl InvertedIndexReader {
             .unwrap_or(0u32))
     }
 }
+
+/// Byte ranges separated by a hole smaller than this are fetched with a single read.
+const COALESCE_HOLES_UNDER_BYTES: usize = 64 * 1024;
+
+fn coalesce_ranges(ranges: impl Iterator<Item = Range<usize>>) -> Vec<Range<usize>> {
+    let mut coalesced: Vec<Range<usize>> = Vec::new();
+    for range in ranges.filter(|range| !range.is_empty()) {
+        if let Some(last) = coalesced.last_mut() {
+            if range.start >= last.start && range.start <= last.end + COALESCE_HOLES_UNDER_BYTES {
+                last.end = last.end.max(range.end);
+                continue;
+            }
+        }
+        coalesced.push(range);
+    }
+    coalesced
+}
@@ -23,6 +23,10 @@ pub enum SegmentComponent {
     /// Accessing a document from the store is relatively slow, as it
     /// requires to decompress the entire block it belongs to.
     Store,
+    /// Row-oriented, compressed storage of the fields marked as
+    /// [stored separately](crate::schema::FieldEntry::set_stored_separately).
+    /// Only present if the schema has such fields.
+    SeparateStore,
     /// Temporary storage of the documents, before streamed to `Store`.
     TempStore,
     /// Bitset describing which document of the segment is alive.
@@ -33,13 +37,14 @@ pub enum SegmentComponent {
 impl SegmentComponent {
     /// Iterates through the components.
     pub fn iterator() -> slice::Iter<'static, SegmentComponent> {
-        static SEGMENT_COMPONENTS: [SegmentComponent; 8] = [
+        static SEGMENT_COMPONENTS: [SegmentComponent; 9] = [
             SegmentComponent::Postings,
             SegmentComponent::Positions,
             SegmentComponent::FastFields,
             SegmentComponent::FieldNorms,
             SegmentComponent::Terms,
             SegmentComponent::Store,
+            SegmentComponent::SeparateStore,
             SegmentComponent::TempStore,
             SegmentComponent::Delete,
         ];
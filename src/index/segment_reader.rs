@@ -12,11 +12,12 @@ use crate::fastfield::{intersect_alive_bitsets, AliveBitSet, FacetReader, FastFi
 use crate::fieldnorm::{FieldNormReader, FieldNormReaders};
 use crate::index::{InvertedIndexReader, Segment, SegmentComponent, SegmentId};
 use crate::json_utils::json_path_sep_to_dot;
+use crate::query::QueryCache;
 use crate::schema::{Field, IndexRecordOption, Schema, Type};
 use crate::space_usage::SegmentSpaceUsage;
 use crate::store::StoreReader;
 use crate::termdict::TermDictionary;
-use crate::{DocId, Opstamp};
+use crate::{DocId, Opstamp, SearchContext};
 
 /// Entry point to access all of the datastructures of the `Segment`
 ///
@@ -45,8 +46,11 @@ pub struct SegmentReader {
     fieldnorm_readers: FieldNormReaders,
 
     store_file: FileSlice,
+    separate_store_file: Option<FileSlice>,
     alive_bitset_opt: Option<AliveBitSet>,
     schema: Schema,
+    query_cache: Option<Arc<QueryCache>>,
+    search_context: SearchContext,
 }
 
 impl SegmentReader {
@@ -135,8 +139,32 @@ impl SegmentReader {
     ///
     /// `cache_num_blocks` sets the number of decompressed blocks to be cached in an LRU.
     /// The size of blocks is configurable, this should be reflexted in the
+    ///
+    /// If some fields are [stored separately](crate::schema::FieldEntry::set_stored_separately),
+    /// the returned reader also reads them from the separate doc store.
     pub fn get_store_reader(&self, cache_num_blocks: usize) -> io::Result<StoreReader> {
-        StoreReader::open(self.store_file.clone(), cache_num_blocks)
+        let store_reader = StoreReader::open(self.store_file.clone(), cache_num_blocks)?;
+        if let Some(separate_store_reader) = self.get_separate_store_reader(cache_num_blocks)? {
+            Ok(store_reader.with_separate_store(
+                separate_store_reader,
+                self.schema.separately_stored_fields(),
+            ))
+        } else {
+            Ok(store_reader)
+        }
+    }
+
+    /// Accessor to the [`StoreReader`](crate::store::StoreReader) of the separate doc store.
+    ///
+    /// Returns `None` if no field of the schema is stored separately.
+    pub(crate) fn get_separate_store_reader(
+        &self,
+        cache_num_blocks: usize,
+    ) -> io::Result<Option<StoreReader>> {
+        self.separate_store_file
+            .clone()
+            .map(|separate_store_file| StoreReader::open(separate_store_file, cache_num_blocks))
+            .transpose()
     }
 
     /// Open a new segment for reading.
@@ -153,6 +181,11 @@ impl SegmentReader {
         let termdict_composite = CompositeFile::open(&termdict_file)?;
 
         let store_file = segment.open_read(SegmentComponent::Store)?;
+        let separate_store_file = if segment.schema().has_separate_store() {
+            Some(segment.open_read(SegmentComponent::SeparateStore)?)
+        } else {
+            None
+        };
 
         crate::fail_point!("SegmentReader::open#middle");
 
@@ -170,7 +203,8 @@ impl SegmentReader {
         let schema = segment.schema();
 
         let fast_fields_data = segment.open_read(SegmentComponent::FastFields)?;
-        let fast_fields_readers = FastFieldReaders::open(fast_fields_data, schema.clone())?;
+        let fast_fields_readers = FastFieldReaders::open(fast_fields_data, schema.clone())?
+            .with_runtime_fields(segment.index().runtime_fields().clone());
         let fieldnorm_data = segment.open_read(SegmentComponent::FieldNorms)?;
         let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
 
@@ -201,9 +235,12 @@ impl SegmentReader {
             segment_id: segment.id(),
             delete_opstamp: segment.meta().delete_opstamp(),
             store_file,
+            separate_store_file,
             alive_bitset_opt,
             positions_composite,
             schema,
+            query_cache: None,
+            search_context: SearchContext::default(),
         })
     }
 
@@ -429,6 +466,32 @@ impl SegmentReader {
         self.delete_opstamp
     }
 
+    /// Returns the [`QueryCache`] of the [`IndexReader`](crate::IndexReader) that opened this
+    /// segment reader, if any.
+    pub fn query_cache(&self) -> Option<&QueryCache> {
+        self.query_cache.as_deref()
+    }
+
+    pub(crate) fn set_query_cache(&mut self, query_cache: Option<Arc<QueryCache>>) {
+        self.query_cache = query_cache;
+    }
+
+    /// Returns the [`SearchContext`] of the search this segment reader is used for.
+    ///
+    /// Outside of [`Searcher::search_with_context`](crate::Searcher::search_with_context), the
+    /// context has no deadline and cannot be cancelled.
+    pub fn search_context(&self) -> &SearchContext {
+        &self.search_context
+    }
+
+    /// Returns a copy of the segment reader, for a search with the given context.
+    pub(crate) fn with_search_context(&self, search_context: SearchContext) -> SegmentReader {
+        SegmentReader {
+            search_context,
+            ..self.clone()
+        }
+    }
+
     /// Returns the bitset representing the alive `DocId`s.
     pub fn alive_bitset(&self) -> Option<&AliveBitSet> {
         self.alive_bitset_opt.as_ref()
@@ -460,7 +523,9 @@ impl SegmentReader {
             self.positions_composite.space_usage(self.schema()),
             self.fast_fields_readers.space_usage()?,
             self.fieldnorm_readers.space_usage(self.schema()),
-            self.get_store_reader(0)?.space_usage(),
+            StoreReader::open(self.store_file.clone(), 0)?.space_usage(),
+            self.get_separate_store_reader(0)?
+                .map(|store_reader| store_reader.space_usage()),
             self.alive_bitset_opt
                 .as_ref()
                 .map(AliveBitSet::space_usage)
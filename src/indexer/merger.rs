@@ -17,7 +17,7 @@ use crate::indexer::doc_id_mapping::{MappingType, SegmentDocIdMapping};
 use crate::indexer::SegmentSerializer;
 use crate::postings::{InvertedIndexSerializer, Postings, SegmentPostings};
 use crate::schema::{value_type_to_column_type, Field, FieldType, Schema};
-use crate::store::StoreWriter;
+use crate::store::{StoreReader, StoreWriter};
 use crate::termdict::{TermMerger, TermOrdinal};
 use crate::{DocAddress, DocId, InvertedIndexReader};
 
@@ -492,30 +492,55 @@ impl IndexMerger {
 
         for reader in &self.readers {
             let store_reader = reader.get_store_reader(1)?;
-            if reader.has_deletes()
-                    // If there is not enough data in the store, we avoid stacking in order to
-                    // avoid creating many small blocks in the doc store. Once we have 5 full blocks,
-                    // we start stacking. In the worst case 2/7 of the blocks would be very small.
-                    // [segment 1 - {1 doc}][segment 2 - {fullblock * 5}{1doc}]
-                    // => 5 * full blocks, 2 * 1 document blocks
-                    //
-                    // In a more realistic scenario the segments are of the same size, so 1/6 of
-                    // the doc stores would be on average half full, given total randomness (which
-                    // is not the case here, but not sure how it behaves exactly).
-                    //
-                    // https://github.com/quickwit-oss/tantivy/issues/1053
-                    //
-                    // take 7 in order to not walk over all checkpoints.
-                    || store_reader.block_checkpoints().take(7).count() < 6
-                    || store_reader.decompressor() != store_writer.compressor().into()
-            {
-                for doc_bytes_res in store_reader.iter_raw(reader.alive_bitset()) {
-                    let doc_bytes = doc_bytes_res?;
-                    store_writer.store_bytes(&doc_bytes)?;
-                }
-            } else {
-                store_writer.stack(store_reader)?;
+            Self::write_store_reader(reader, store_reader, store_writer)?;
+        }
+        Ok(())
+    }
+
+    fn write_separately_stored_fields(&self, store_writer: &mut StoreWriter) -> crate::Result<()> {
+        debug_time!("write-separately-stored-fields");
+        debug!("write-separately-stored-fields");
+
+        for reader in &self.readers {
+            let store_reader = reader.get_separate_store_reader(1)?.ok_or_else(|| {
+                crate::TantivyError::InternalError(format!(
+                    "Segment {} has no separate doc store",
+                    reader.segment_id().uuid_string()
+                ))
+            })?;
+            Self::write_store_reader(reader, store_reader, store_writer)?;
+        }
+        Ok(())
+    }
+
+    fn write_store_reader(
+        reader: &SegmentReader,
+        store_reader: StoreReader,
+        store_writer: &mut StoreWriter,
+    ) -> crate::Result<()> {
+        if reader.has_deletes()
+                // If there is not enough data in the store, we avoid stacking in order to
+                // avoid creating many small blocks in the doc store. Once we have 5 full blocks,
+                // we start stacking. In the worst case 2/7 of the blocks would be very small.
+                // [segment 1 - {1 doc}][segment 2 - {fullblock * 5}{1doc}]
+                // => 5 * full blocks, 2 * 1 document blocks
+                //
+                // In a more realistic scenario the segments are of the same size, so 1/6 of
+                // the doc stores would be on average half full, given total randomness (which
+                // is not the case here, but not sure how it behaves exactly).
+                //
+                // https://github.com/quickwit-oss/tantivy/issues/1053
+                //
+                // take 7 in order to not walk over all checkpoints.
+                || store_reader.block_checkpoints().take(7).count() < 6
+                || store_reader.decompressor() != store_writer.compressor().into()
+        {
+            for doc_bytes_res in store_reader.iter_raw(reader.alive_bitset()) {
+                let doc_bytes = doc_bytes_res?;
+                store_writer.store_bytes(&doc_bytes)?;
             }
+        } else {
+            store_writer.stack(store_reader)?;
         }
         Ok(())
     }
@@ -544,6 +569,9 @@ impl IndexMerger {
 
         debug!("write-storagefields");
         self.write_storable_fields(serializer.get_store_writer())?;
+        if let Some(separate_store_writer) = serializer.get_separate_store_writer() {
+            self.write_separately_stored_fields(separate_store_writer)?;
+        }
         debug!("write-fastfields");
         self.write_fast_fields(serializer.get_fast_field_write(), doc_id_mapping)?;
 
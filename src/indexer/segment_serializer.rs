@@ -11,6 +11,7 @@ use crate::store::StoreWriter;
 pub struct SegmentSerializer {
     segment: Segment,
     pub(crate) store_writer: StoreWriter,
+    separate_store_writer: Option<StoreWriter>,
     fast_field_write: WritePtr,
     fieldnorms_serializer: Option<FieldNormsSerializer>,
     postings_serializer: InvertedIndexSerializer,
@@ -30,6 +31,19 @@ impl SegmentSerializer {
             )?
         };
 
+        let separate_store_writer = if segment.schema().has_separate_store() {
+            let store_write = segment.open_write(SegmentComponent::SeparateStore)?;
+            let store_writer = StoreWriter::new(
+                store_write,
+                settings.docstore_compression,
+                settings.docstore_blocksize,
+                settings.docstore_compress_dedicated_thread,
+            )?;
+            Some(store_writer.for_separate_store())
+        } else {
+            None
+        };
+
         let fast_field_write = segment.open_write(SegmentComponent::FastFields)?;
 
         let fieldnorms_write = segment.open_write(SegmentComponent::FieldNorms)?;
@@ -39,6 +53,7 @@ impl SegmentSerializer {
         Ok(SegmentSerializer {
             segment,
             store_writer,
+            separate_store_writer,
             fast_field_write,
             fieldnorms_serializer: Some(fieldnorms_serializer),
             postings_serializer,
@@ -48,6 +63,11 @@ impl SegmentSerializer {
     /// The memory used (inclusive childs)
     pub fn mem_usage(&self) -> usize {
         self.store_writer.mem_usage()
+            + self
+                .separate_store_writer
+                .as_ref()
+                .map(StoreWriter::mem_usage)
+                .unwrap_or(0)
     }
 
     pub fn segment(&self) -> &Segment {
@@ -76,6 +96,13 @@ impl SegmentSerializer {
         &mut self.store_writer
     }
 
+    /// Accessor to the `StoreWriter` of the separate doc store.
+    ///
+    /// Returns `None` if no field of the schema is stored separately.
+    pub fn get_separate_store_writer(&mut self) -> Option<&mut StoreWriter> {
+        self.separate_store_writer.as_mut()
+    }
+
     /// Finalize the segment serialization.
     pub fn close(mut self) -> crate::Result<()> {
         if let Some(fieldnorms_serializer) = self.extract_fieldnorms_serializer() {
@@ -84,6 +111,9 @@ impl SegmentSerializer {
         self.fast_field_write.terminate()?;
         self.postings_serializer.close()?;
         self.store_writer.close()?;
+        if let Some(separate_store_writer) = self.separate_store_writer {
+            separate_store_writer.close()?;
+        }
         Ok(())
     }
 }
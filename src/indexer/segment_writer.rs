@@ -356,6 +356,9 @@ impl SegmentWriter {
         self.index_document(&document)?;
         let doc_writer = self.segment_serializer.get_store_writer();
         doc_writer.store(&document, &self.schema)?;
+        if let Some(separate_doc_writer) = self.segment_serializer.get_separate_store_writer() {
+            separate_doc_writer.store(&document, &self.schema)?;
+        }
         self.max_doc += 1;
         Ok(())
     }
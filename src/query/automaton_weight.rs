@@ -6,16 +6,18 @@ use std::io;
 use std::sync::Arc;
 
 use common::BitSet;
+use futures_util::future::BoxFuture;
 use tantivy_fst::Automaton;
 
 use super::fuzzy_query::StartsWithAutomaton;
 use super::phrase_prefix_query::prefix_end;
 use super::BitSetDocSet;
 use crate::index::SegmentReader;
-use crate::query::{ConstScorer, Explanation, Scorer, Weight};
+use crate::query::matches::{dictionary_term, positions_in_doc};
+use crate::query::{ConstScorer, Explanation, Matches, Scorer, Weight};
 use crate::schema::{Field, IndexRecordOption};
 use crate::termdict::{TermDictionary, TermWithStateStreamer};
-use crate::{DocId, Score, TantivyError};
+use crate::{DocId, Score, TantivyError, Term};
 
 /// A weight struct for Fuzzy Term and Regex Queries
 pub struct AutomatonWeight<A> {
@@ -96,6 +98,43 @@ where
         Ok(term_infos)
     }
 
+    /// Returns the term infos the automaton expands to, up to `max_expansions`.
+    fn expanded_term_infos(&self, term_dict: &TermDictionary) -> io::Result<Vec<TermInfo>> {
+        let mut term_stream = self.automaton_stream(term_dict)?;
+        let max_expansions = self
+            .max_expansions
+            .map_or(usize::MAX, |limit| limit as usize);
+        let mut term_infos = Vec::new();
+        while term_infos.len() < max_expansions && term_stream.advance() {
+            term_infos.push(term_stream.value().clone());
+        }
+        Ok(term_infos)
+    }
+
+    /// Returns the terms the automaton expands to that appear in `doc`, up to
+    /// `max_expansions`, with their positions in `doc`.
+    pub(crate) fn match_terms_in_doc(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> crate::Result<Vec<(Term, Vec<u32>)>> {
+        let inverted_index = reader.inverted_index(self.field)?;
+        let mut term_stream = self.automaton_stream(inverted_index.terms())?;
+        let max_expansions = self
+            .max_expansions
+            .map_or(usize::MAX, |limit| limit as usize);
+        let mut num_expansions = 0;
+        let mut terms = Vec::new();
+        while num_expansions < max_expansions && term_stream.advance() {
+            num_expansions += 1;
+            if let Some(positions) = positions_in_doc(&inverted_index, term_stream.value(), doc)? {
+                let term = dictionary_term(reader, self.field, term_stream.key());
+                terms.push((term, positions));
+            }
+        }
+        Ok(terms)
+    }
+
     fn process_term(
         &self,
         term_stream: &mut TermWithStateStreamer<'_, &A>,
@@ -127,8 +166,8 @@ where
     ) -> Result<(), TantivyError> {
         if let Some(state) = term_stream.state() {
             let score = automaton_score(self.automaton.as_ref(), state);
-            let segment_postings =
-                inverted_index.read_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic)?;
+            let segment_postings = inverted_index
+                .read_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic)?;
             let scorer = ConstScorer::new(segment_postings, boost * score);
             scorers.push(scorer);
         }
@@ -146,11 +185,18 @@ where
         let term_dict = inverted_index.terms();
         let mut term_stream = self.automaton_stream(term_dict)?;
         let max_doc = reader.max_doc();
+        // The expansion of the automaton stops early if the search context asks to. The context
+        // is only checked every `SEARCH_CONTEXT_CHECK_INTERVAL` terms.
+        let search_context = reader.search_context();
+        let mut num_terms = 0u32;
         if self.fuzzy_scoring {
             let mut scorers = vec![];
             if let Some(max_expansion) = self.max_expansions {
                 let mut counter: u32 = 0;
-                while counter < max_expansion && term_stream.advance() {
+                while counter < max_expansion
+                    && !search_context.should_stop_every(&mut num_terms)
+                    && term_stream.advance()
+                {
                     self.process_term_fuzzy_scoring(
                         &mut term_stream,
                         &inverted_index,
@@ -160,7 +206,7 @@ where
                     counter += 1;
                 }
             } else {
-                while term_stream.advance() {
+                while !search_context.should_stop_every(&mut num_terms) && term_stream.advance() {
                     self.process_term_fuzzy_scoring(
                         &mut term_stream,
                         &inverted_index,
@@ -176,12 +222,15 @@ where
             let mut doc_bitset = BitSet::with_max_value(max_doc);
             if let Some(max_expansion) = self.max_expansions {
                 let mut counter: u32 = 0;
-                while counter < max_expansion && term_stream.advance() {
+                while counter < max_expansion
+                    && !search_context.should_stop_every(&mut num_terms)
+                    && term_stream.advance()
+                {
                     self.process_term(&mut term_stream, &inverted_index, &mut doc_bitset)?;
                     counter += 1;
                 }
             } else {
-                while term_stream.advance() {
+                while !search_context.should_stop_every(&mut num_terms) && term_stream.advance() {
                     self.process_term(&mut term_stream, &inverted_index, &mut doc_bitset)?;
                 }
             }
@@ -191,6 +240,17 @@ where
         }
     }
 
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            let inverted_index = reader.inverted_index(self.field)?;
+            let term_infos = self.expanded_term_infos(inverted_index.terms())?;
+            inverted_index
+                .warm_postings_for_term_infos(&term_infos, false)
+                .await?;
+            Ok(())
+        })
+    }
+
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
         let mut scorer = self.scorer(reader, 1.0)?;
         if scorer.seek(doc) == doc {
@@ -205,6 +265,18 @@ where
             ))
         }
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let terms = self.match_terms_in_doc(reader, doc)?;
+        if terms.is_empty() {
+            return Ok(None);
+        }
+        let mut matches = Matches::default();
+        for (term, positions) in &terms {
+            matches.add_term_positions(term, positions);
+        }
+        Ok(Some(matches))
+    }
 }
 
 fn automaton_score<A>(automaton: &A, state: &A::State) -> f32
@@ -1,3 +1,6 @@
+use std::borrow::Borrow;
+use std::sync::Arc;
+
 use common::{BitSet, TinySet};
 
 use crate::docset::{DocSet, TERMINATED};
@@ -5,6 +8,8 @@ use crate::DocId;
 
 /// A `BitSetDocSet` makes it possible to iterate through a bitset as if it was a `DocSet`.
 ///
+/// The bitset is either owned, or shared through an `Arc<BitSet>`, e.g. with a cache.
+///
 /// # Implementation detail
 ///
 /// Skipping is relatively fast here as we can directly point to the
@@ -12,26 +17,20 @@ use crate::DocId;
 ///
 /// TODO: Consider implementing a `BitTreeSet` in order to advance faster
 /// when the bitset is sparse
-pub struct BitSetDocSet {
-    docs: BitSet,
+pub struct BitSetDocSet<B: Borrow<BitSet> = BitSet> {
+    docs: B,
     cursor_bucket: u32, //< index associated with the current tiny bitset
     cursor_tinybitset: TinySet,
     doc: u32,
 }
 
-impl BitSetDocSet {
-    fn go_to_bucket(&mut self, bucket_addr: u32) {
-        self.cursor_bucket = bucket_addr;
-        self.cursor_tinybitset = self.docs.tinyset(bucket_addr);
-    }
-}
-
-impl From<BitSet> for BitSetDocSet {
-    fn from(docs: BitSet) -> BitSetDocSet {
-        let first_tiny_bitset = if docs.max_value() == 0 {
+impl<B: Borrow<BitSet> + Send> BitSetDocSet<B> {
+    fn new(docs: B) -> BitSetDocSet<B> {
+        let bitset = docs.borrow();
+        let first_tiny_bitset = if bitset.max_value() == 0 {
             TinySet::empty()
         } else {
-            docs.tinyset(0)
+            bitset.tinyset(0)
         };
         let mut docset = BitSetDocSet {
             docs,
@@ -42,16 +41,37 @@ impl From<BitSet> for BitSetDocSet {
         docset.advance();
         docset
     }
+
+    fn go_to_bucket(&mut self, bucket_addr: u32) {
+        self.cursor_bucket = bucket_addr;
+        self.cursor_tinybitset = self.docs.borrow().tinyset(bucket_addr);
+    }
+}
+
+impl From<BitSet> for BitSetDocSet {
+    fn from(docs: BitSet) -> BitSetDocSet {
+        BitSetDocSet::new(docs)
+    }
+}
+
+impl From<Arc<BitSet>> for BitSetDocSet<Arc<BitSet>> {
+    fn from(docs: Arc<BitSet>) -> BitSetDocSet<Arc<BitSet>> {
+        BitSetDocSet::new(docs)
+    }
 }
 
-impl DocSet for BitSetDocSet {
+impl<B: Borrow<BitSet> + Send> DocSet for BitSetDocSet<B> {
     #[inline]
     fn advance(&mut self) -> DocId {
         if let Some(lower) = self.cursor_tinybitset.pop_lowest() {
             self.doc = (self.cursor_bucket * 64u32) | lower;
             return self.doc;
         }
-        if let Some(cursor_bucket) = self.docs.first_non_empty_bucket(self.cursor_bucket + 1) {
+        if let Some(cursor_bucket) = self
+            .docs
+            .borrow()
+            .first_non_empty_bucket(self.cursor_bucket + 1)
+        {
             self.go_to_bucket(cursor_bucket);
             let lower = self.cursor_tinybitset.pop_lowest().unwrap();
             self.doc = (cursor_bucket * 64u32) | lower;
@@ -63,7 +83,7 @@ impl DocSet for BitSetDocSet {
     }
 
     fn seek(&mut self, target: DocId) -> DocId {
-        if target >= self.docs.max_value() {
+        if target >= self.docs.borrow().max_value() {
             self.doc = TERMINATED;
             return TERMINATED;
         }
@@ -89,13 +109,14 @@ impl DocSet for BitSetDocSet {
 
     /// Returns the number of values set in the underlying bitset.
     fn size_hint(&self) -> u32 {
-        self.docs.len() as u32
+        self.docs.borrow().len() as u32
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeSet;
+    use std::sync::Arc;
 
     use common::BitSet;
 
@@ -142,6 +163,21 @@ mod tests {
         assert_eq!(empty.advance(), TERMINATED)
     }
 
+    #[test]
+    fn test_shared_bitset() {
+        let mut bitset = BitSet::with_max_value(1000);
+        bitset.insert(3);
+        bitset.insert(700);
+        let bitset = Arc::new(bitset);
+        for _ in 0..2 {
+            let mut docset = BitSetDocSet::from(bitset.clone());
+            assert_eq!(docset.size_hint(), 2);
+            assert_eq!(docset.doc(), 3);
+            assert_eq!(docset.seek(500), 700);
+            assert_eq!(docset.advance(), TERMINATED);
+        }
+    }
+
     #[test]
     fn test_seek_terminated() {
         let bitset = BitSet::with_max_value(1000);
@@ -22,6 +22,18 @@ pub trait Bm25StatisticsProvider {
 
     /// The number of documents containing the given term.
     fn doc_freq(&self, term: &Term) -> crate::Result<u64>;
+
+    /// The number of documents containing at least one of the given terms.
+    ///
+    /// The default implementation returns the largest [`doc_freq`](Self::doc_freq) of the terms,
+    /// a lower bound of the exact count that does not require reading any postings.
+    fn union_doc_freq(&self, terms: &[Term]) -> crate::Result<u64> {
+        let mut union_doc_freq = 0u64;
+        for term in terms {
+            union_doc_freq = union_doc_freq.max(self.doc_freq(term)?);
+        }
+        Ok(union_doc_freq)
+    }
 }
 
 impl Bm25StatisticsProvider for Searcher {
@@ -55,15 +67,15 @@ pub(crate) fn idf(doc_freq: u64, doc_count: u64) -> Score {
     (1.0 + x).ln()
 }
 
-fn cached_tf_component(fieldnorm: u32, average_fieldnorm: Score) -> Score {
-    K1 * (1.0 - B + B * fieldnorm as Score / average_fieldnorm)
+fn cached_tf_component(fieldnorm: Score, average_fieldnorm: Score) -> Score {
+    K1 * (1.0 - B + B * fieldnorm / average_fieldnorm)
 }
 
 fn compute_tf_cache(average_fieldnorm: Score) -> Arc<[Score; 256]> {
     let mut cache: [Score; 256] = [0.0; 256];
     for (fieldnorm_id, cache_mut) in cache.iter_mut().enumerate() {
         let fieldnorm = FieldNormReader::id_to_fieldnorm(fieldnorm_id as u8);
-        *cache_mut = cached_tf_component(fieldnorm, average_fieldnorm);
+        *cache_mut = cached_tf_component(fieldnorm as Score, average_fieldnorm);
     }
     Arc::new(cache)
 }
@@ -185,6 +197,23 @@ impl Bm25Weight {
         self.score(255u8, 2_013_265_944)
     }
 
+    /// Compute the BM25 score of a document, given a field length and a term frequency that are
+    /// not necessarily integers.
+    ///
+    /// This is used to score the virtual field of a
+    /// [`CombinedFieldsQuery`](crate::query::CombinedFieldsQuery), whose length and term
+    /// frequency are weighted sums over several fields.
+    #[inline]
+    pub(crate) fn score_weighted(&self, length: Score, term_freq: Score) -> Score {
+        let norm = cached_tf_component(length, self.average_fieldnorm);
+        self.weight * term_freq / (term_freq + norm)
+    }
+
+    /// Returns an upper bound of the scores computed by [`Bm25Weight::score_weighted`].
+    pub(crate) fn weighted_max_score(&self) -> Score {
+        self.weight
+    }
+
     #[inline]
     pub(crate) fn tf_factor(&self, fieldnorm_id: u8, term_freq: u32) -> Score {
         let term_freq = term_freq as Score;
@@ -194,12 +223,33 @@ impl Bm25Weight {
 
     /// Produce an [Explanation] of a BM25 score.
     pub fn explain(&self, fieldnorm_id: u8, term_freq: u32) -> Explanation {
+        self.explain_with_norm(
+            self.score(fieldnorm_id, term_freq),
+            self.cache[fieldnorm_id as usize],
+            FieldNormReader::id_to_fieldnorm(fieldnorm_id) as Score,
+            term_freq as Score,
+        )
+    }
+
+    /// Produce an [Explanation] of a score computed by [`Bm25Weight::score_weighted`].
+    pub(crate) fn explain_weighted(&self, length: Score, term_freq: Score) -> Explanation {
+        self.explain_with_norm(
+            self.score_weighted(length, term_freq),
+            cached_tf_component(length, self.average_fieldnorm),
+            length,
+            term_freq,
+        )
+    }
+
+    fn explain_with_norm(
+        &self,
+        score: Score,
+        norm: Score,
+        length: Score,
+        term_freq: Score,
+    ) -> Explanation {
         // The explain format is directly copied from Lucene's.
         // (So, Kudos to Lucene)
-        let score = self.score(fieldnorm_id, term_freq);
-
-        let norm = self.cache[fieldnorm_id as usize];
-        let term_freq = term_freq as Score;
         let right_factor = term_freq / (term_freq + norm);
 
         let mut tf_explanation = Explanation::new(
@@ -210,10 +260,7 @@ impl Bm25Weight {
         tf_explanation.add_const("freq, occurrences of term within document", term_freq);
         tf_explanation.add_const("k1, term saturation parameter", K1);
         tf_explanation.add_const("b, length normalization parameter", B);
-        tf_explanation.add_const(
-            "dl, length of field",
-            FieldNormReader::id_to_fieldnorm(fieldnorm_id) as Score,
-        );
+        tf_explanation.add_const("dl, length of field", length);
         tf_explanation.add_const("avgdl, average length of field", self.average_fieldnorm);
 
         let mut explanation = Explanation::new("TermQuery, product of...", score);
@@ -1,8 +1,23 @@
 use std::ops::{Deref, DerefMut};
 
-use crate::query::term_query::TermScorer;
 use crate::query::Scorer;
-use crate::{DocId, DocSet, Score, TERMINATED};
+use crate::{DocId, Score, TERMINATED};
+
+/// A scorer exposing upper bounds of its scores, over all documents and over blocks of
+/// documents, which lets [`block_wand`] skip the documents that cannot reach the threshold.
+pub(crate) trait BlockMaxScorer: Scorer {
+    /// Returns an upper bound of the score of all documents.
+    fn max_score(&self) -> Score;
+
+    /// Positions the block cursor on the block containing `target_doc`, without loading it.
+    fn seek_block(&mut self, target_doc: DocId);
+
+    /// Returns an upper bound of the score of the documents of the current block.
+    fn block_max_score(&mut self) -> Score;
+
+    /// Returns the last document of the current block.
+    fn last_doc_in_block(&self) -> DocId;
+}
 
 /// Takes a term_scorers sorted by their current doc() and a threshold and returns
 /// Returns (pivot_len, pivot_ord) defined as follows:
@@ -13,8 +28,8 @@ use crate::{DocId, DocSet, Score, TERMINATED};
 /// We always have `before_pivot_len` < `pivot_len`.
 ///
 /// `None` is returned if we establish that no document can exceed the threshold.
-fn find_pivot_doc(
-    term_scorers: &[TermScorerWithMaxScore],
+fn find_pivot_doc<S: BlockMaxScorer>(
+    term_scorers: &[ScorerWithMaxScore<S>],
     threshold: Score,
 ) -> Option<(usize, usize, DocId)> {
     let mut max_score = 0.0;
@@ -46,8 +61,8 @@ fn find_pivot_doc(
 /// the next doc candidate defined by the min of `last_doc_in_block + 1` for
 /// scorer in scorers[..pivot_len] and `scorer.doc()` for scorer in scorers[pivot_len..].
 /// Note: before and after calling this method, scorers need to be sorted by their `.doc()`.
-fn block_max_was_too_low_advance_one_scorer(
-    scorers: &mut [TermScorerWithMaxScore],
+fn block_max_was_too_low_advance_one_scorer<S: BlockMaxScorer>(
+    scorers: &mut [ScorerWithMaxScore<S>],
     pivot_len: usize,
 ) {
     debug_assert!(is_sorted(scorers.iter().map(|scorer| scorer.doc())));
@@ -82,7 +97,7 @@ fn block_max_was_too_low_advance_one_scorer(
 // Given a list of term_scorers and a `ord` and assuming that `term_scorers[ord]` is sorted
 // except term_scorers[ord] that might be in advance compared to its ranks,
 // bubble up term_scorers[ord] in order to restore the ordering.
-fn restore_ordering(term_scorers: &mut [TermScorerWithMaxScore], ord: usize) {
+fn restore_ordering<S: BlockMaxScorer>(term_scorers: &mut [ScorerWithMaxScore<S>], ord: usize) {
     let doc = term_scorers[ord].doc();
     for i in ord + 1..term_scorers.len() {
         if term_scorers[i].doc() >= doc {
@@ -98,8 +113,8 @@ fn restore_ordering(term_scorers: &mut [TermScorerWithMaxScore], ord: usize) {
 // If this fails (ie: one of the term_scorer does not contain `pivot_doc` and seek goes past the
 // pivot), reorder the term_scorers to ensure the list is still sorted and returns `false`.
 // If a term_scorer reach TERMINATED in the process return false remove the term_scorer and return.
-fn align_scorers(
-    term_scorers: &mut Vec<TermScorerWithMaxScore>,
+fn align_scorers<S: BlockMaxScorer>(
+    term_scorers: &mut Vec<ScorerWithMaxScore<S>>,
     pivot_doc: DocId,
     before_pivot_len: usize,
 ) -> bool {
@@ -126,7 +141,10 @@ fn align_scorers(
 // Assumes terms_scorers[..pivot_len] are positioned on the same doc (pivot_doc).
 // Advance term_scorers[..pivot_len] and out of these removes the terminated scores.
 // Restores the ordering of term_scorers.
-fn advance_all_scorers_on_pivot(term_scorers: &mut Vec<TermScorerWithMaxScore>, pivot_len: usize) {
+fn advance_all_scorers_on_pivot<S: BlockMaxScorer>(
+    term_scorers: &mut Vec<ScorerWithMaxScore<S>>,
+    pivot_len: usize,
+) {
     for term_scorer in &mut term_scorers[..pivot_len] {
         term_scorer.advance();
     }
@@ -145,15 +163,13 @@ fn advance_all_scorers_on_pivot(term_scorers: &mut Vec<TermScorerWithMaxScore>,
 /// Implements the WAND (Weak AND) algorithm for dynamic pruning
 /// described in the paper "Faster Top-k Document Retrieval Using Block-Max Indexes".
 /// Link: <http://engineering.nyu.edu/~suel/papers/bmw.pdf>
-pub fn block_wand(
-    mut scorers: Vec<TermScorer>,
+pub fn block_wand<S: BlockMaxScorer>(
+    mut scorers: Vec<S>,
     mut threshold: Score,
     callback: &mut dyn FnMut(u32, Score) -> Score,
 ) {
-    let mut scorers: Vec<TermScorerWithMaxScore> = scorers
-        .iter_mut()
-        .map(TermScorerWithMaxScore::from)
-        .collect();
+    let mut scorers: Vec<ScorerWithMaxScore<S>> =
+        scorers.iter_mut().map(ScorerWithMaxScore::from).collect();
     scorers.sort_by_key(|scorer| scorer.doc());
     // At this point we need to ensure that the scorers are sorted!
     debug_assert!(is_sorted(scorers.iter().map(|scorer| scorer.doc())));
@@ -219,8 +235,8 @@ pub fn block_wand(
 ///   - While the block max score is under the `threshold`, go to the next block.
 ///   - On a block, advance until the end and execute `callback` when the doc score is greater or
 ///     equal to the `threshold`.
-pub fn block_wand_single_scorer(
-    mut scorer: TermScorer,
+pub fn block_wand_single_scorer<S: BlockMaxScorer>(
+    mut scorer: S,
     mut threshold: Score,
     callback: &mut dyn FnMut(u32, Score) -> Score,
 ) {
@@ -260,27 +276,27 @@ pub fn block_wand_single_scorer(
     }
 }
 
-struct TermScorerWithMaxScore<'a> {
-    scorer: &'a mut TermScorer,
+struct ScorerWithMaxScore<'a, S> {
+    scorer: &'a mut S,
     max_score: Score,
 }
 
-impl<'a> From<&'a mut TermScorer> for TermScorerWithMaxScore<'a> {
-    fn from(scorer: &'a mut TermScorer) -> Self {
+impl<'a, S: BlockMaxScorer> From<&'a mut S> for ScorerWithMaxScore<'a, S> {
+    fn from(scorer: &'a mut S) -> Self {
         let max_score = scorer.max_score();
-        TermScorerWithMaxScore { scorer, max_score }
+        ScorerWithMaxScore { scorer, max_score }
     }
 }
 
-impl Deref for TermScorerWithMaxScore<'_> {
-    type Target = TermScorer;
+impl<S> Deref for ScorerWithMaxScore<'_, S> {
+    type Target = S;
 
     fn deref(&self) -> &Self::Target {
         self.scorer
     }
 }
 
-impl DerefMut for TermScorerWithMaxScore<'_> {
+impl<S> DerefMut for ScorerWithMaxScore<'_, S> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.scorer
     }
@@ -1,6 +1,14 @@
+use std::collections::BTreeMap;
+
 use super::boolean_weight::BooleanWeight;
-use crate::query::{EnableScoring, Occur, Query, SumCombiner, TermQuery, Weight};
-use crate::schema::{IndexRecordOption, Term};
+use crate::query::fingerprint::FingerprintHasher;
+use crate::query::query_cache::CachingWeight;
+use crate::query::{
+    AllQuery, ConstScoreQuery, EmptyQuery, EnableScoring, Occur, Query, QueryProfiler, SumCombiner,
+    TermQuery, TermSetQuery, Weight,
+};
+use crate::schema::{Field, IndexRecordOption, Term};
+use crate::Searcher;
 
 /// The boolean query returns a set of documents
 /// that matches the Boolean combination of constituent subqueries.
@@ -155,10 +163,91 @@ impl From<Vec<(Occur, Box<dyn Query>)>> for BooleanQuery {
 
 impl Query for BooleanQuery {
     fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        self.weight_with(enable_scoring, |subquery| subquery.weight(enable_scoring))
+    }
+
+    fn weight_with_profiler(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        profiler: &QueryProfiler,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let subquery_profiler = QueryProfiler::default();
+        let weight = self.weight_with(enable_scoring, |subquery| {
+            subquery.weight_with_profiler(enable_scoring, &subquery_profiler)
+        })?;
+        Ok(profiler.instrument(self, weight, subquery_profiler))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for (_occur, subquery) in &self.subqueries {
+            subquery.query_terms(visitor);
+        }
+    }
+
+    fn rewrite(&self, searcher: &Searcher) -> crate::Result<Box<dyn Query>> {
+        self.rewrite_with_scoring(searcher, true)
+    }
+
+    fn fingerprint(&self) -> u128 {
+        // Sorting the clauses makes the fingerprint independent of their order.
+        let mut clause_fingerprints: Vec<(u8, u128)> = self
+            .subqueries
+            .iter()
+            .map(|(occur, subquery)| (occur_code(*occur), subquery.fingerprint()))
+            .collect();
+        clause_fingerprints.sort_unstable();
+        let mut hasher = FingerprintHasher::new("BooleanQuery");
+        hasher.write_u64(self.minimum_number_should_match as u64);
+        hasher.write_u64(clause_fingerprints.len() as u64);
+        for (occur_code, clause_fingerprint) in clause_fingerprints {
+            hasher.write(&[occur_code]);
+            hasher.write_u128(clause_fingerprint);
+        }
+        hasher.finish()
+    }
+}
+
+fn occur_code(occur: Occur) -> u8 {
+    match occur {
+        Occur::Must => 0,
+        Occur::Should => 1,
+        Occur::MustNot => 2,
+    }
+}
+
+/// Rewrites a subquery, telling nested boolean queries whether their score is used.
+fn rewrite_subquery(
+    subquery: &dyn Query,
+    searcher: &Searcher,
+    scoring_enabled: bool,
+) -> crate::Result<Box<dyn Query>> {
+    if let Some(boolean_query) = subquery.downcast_ref::<BooleanQuery>() {
+        boolean_query.rewrite_with_scoring(searcher, scoring_enabled)
+    } else {
+        subquery.rewrite(searcher)
+    }
+}
+
+impl BooleanQuery {
+    /// Builds the weight of the query, using `subquery_weight` to build the weights of the
+    /// subqueries.
+    fn weight_with(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        mut subquery_weight: impl FnMut(&dyn Query) -> crate::Result<Box<dyn Weight>>,
+    ) -> crate::Result<Box<dyn Weight>> {
         let sub_weights = self
             .subqueries
             .iter()
-            .map(|(occur, subquery)| Ok((*occur, subquery.weight(enable_scoring)?)))
+            .map(|(occur, subquery)| {
+                let mut sub_weight = subquery_weight(subquery.as_ref())?;
+                // Term queries are as cheap to evaluate as a cached bitset.
+                let is_filter = *occur == Occur::MustNot || !enable_scoring.is_scoring_enabled();
+                if is_filter && !subquery.is::<TermQuery>() {
+                    sub_weight = CachingWeight::wrap(subquery.as_ref(), sub_weight);
+                }
+                Ok((*occur, sub_weight))
+            })
             .collect::<crate::Result<_>>()?;
         Ok(Box::new(BooleanWeight::with_minimum_number_should_match(
             sub_weights,
@@ -168,14 +257,6 @@ impl Query for BooleanQuery {
         )))
     }
 
-    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
-        for (_occur, subquery) in &self.subqueries {
-            subquery.query_terms(visitor);
-        }
-    }
-}
-
-impl BooleanQuery {
     /// Creates a new boolean query.
     pub fn new(subqueries: Vec<(Occur, Box<dyn Query>)>) -> BooleanQuery {
         // If the bool query includes at least one should clause
@@ -257,6 +338,168 @@ impl BooleanQuery {
     pub fn clauses(&self) -> &[(Occur, Box<dyn Query>)] {
         &self.subqueries[..]
     }
+
+    /// Returns true if the query is a plain conjunction, i.e. it has no `Should` clause and at
+    /// least one `Must` clause.
+    ///
+    /// A query made only of `MustNot` clauses matches no document, so it is not a conjunction.
+    fn is_conjunction(&self) -> bool {
+        self.minimum_number_should_match == 0
+            && self
+                .subqueries
+                .iter()
+                .all(|(occur, _)| *occur != Occur::Should)
+            && self
+                .subqueries
+                .iter()
+                .any(|(occur, _)| *occur == Occur::Must)
+    }
+
+    /// Returns true if the query is a plain disjunction, i.e. it only has `Should` clauses and
+    /// any of them is enough to match.
+    fn is_disjunction(&self) -> bool {
+        self.minimum_number_should_match <= 1
+            && self
+                .subqueries
+                .iter()
+                .all(|(occur, _)| *occur == Occur::Should)
+    }
+
+    /// Rewrites the query, knowing whether its score is used.
+    ///
+    /// When scoring is disabled, clauses can be rewritten into queries that match the same
+    /// documents with a different score.
+    pub(crate) fn rewrite_with_scoring(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Query>> {
+        let minimum_number_should_match = self.minimum_number_should_match;
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::with_capacity(self.subqueries.len());
+        for (occur, subquery) in &self.subqueries {
+            let occur = *occur;
+            let subquery_scoring_enabled = scoring_enabled && occur != Occur::MustNot;
+            let subquery = rewrite_subquery(subquery.as_ref(), searcher, subquery_scoring_enabled)?;
+            if let Some(nested_query) = subquery.downcast_ref::<BooleanQuery>() {
+                let flattened_occur = match occur {
+                    Occur::Must if nested_query.is_conjunction() => None,
+                    Occur::Should
+                        if minimum_number_should_match <= 1 && nested_query.is_disjunction() =>
+                    {
+                        Some(Occur::Should)
+                    }
+                    Occur::MustNot if nested_query.is_disjunction() => Some(Occur::MustNot),
+                    _ => {
+                        clauses.push((occur, subquery));
+                        continue;
+                    }
+                };
+                for (nested_occur, nested_subquery) in nested_query.clauses() {
+                    clauses.push((
+                        flattened_occur.unwrap_or(*nested_occur),
+                        nested_subquery.box_clone(),
+                    ));
+                }
+                continue;
+            }
+            clauses.push((occur, subquery));
+        }
+
+        // Remove the clauses that do not change the matching documents.
+        let mut rewritten_clauses = Vec::with_capacity(clauses.len());
+        for (occur, subquery) in clauses {
+            let is_empty = subquery.is::<EmptyQuery>();
+            match occur {
+                Occur::Must if is_empty => return Ok(Box::new(EmptyQuery)),
+                Occur::MustNot if subquery.is::<AllQuery>() => return Ok(Box::new(EmptyQuery)),
+                Occur::Should | Occur::MustNot if is_empty => {}
+                _ => rewritten_clauses.push((occur, subquery)),
+            }
+        }
+        let num_should = rewritten_clauses
+            .iter()
+            .filter(|(occur, _)| *occur == Occur::Should)
+            .count();
+        if num_should < minimum_number_should_match {
+            return Ok(Box::new(EmptyQuery));
+        }
+        if !scoring_enabled {
+            // `AllQuery` only contributes to the score if another clause restricts the documents.
+            let is_restricted = rewritten_clauses
+                .iter()
+                .any(|(occur, subquery)| *occur == Occur::Must && !subquery.is::<AllQuery>())
+                || (num_should > 0 && minimum_number_should_match > 0);
+            if is_restricted {
+                rewritten_clauses.retain(|(occur, subquery)| {
+                    *occur != Occur::Must || !subquery.is::<AllQuery>()
+                });
+            }
+        }
+
+        // Term queries on a same field are merged into a term set query, when their score is
+        // not needed.
+        let merge_should = !scoring_enabled && minimum_number_should_match <= 1;
+        let mut terms_to_merge: BTreeMap<(u8, Field), Vec<Term>> = BTreeMap::new();
+        for (occur, subquery) in &rewritten_clauses {
+            if *occur == Occur::Must || (*occur == Occur::Should && !merge_should) {
+                continue;
+            }
+            if let Some(term_query) = subquery.downcast_ref::<TermQuery>() {
+                let term = term_query.term();
+                if searcher.schema().get_field_entry(term.field()).is_indexed() {
+                    terms_to_merge
+                        .entry((occur_code(*occur), term.field()))
+                        .or_default()
+                        .push(term.clone());
+                }
+            }
+        }
+        terms_to_merge.retain(|_, terms| terms.len() > 1);
+        if !terms_to_merge.is_empty() {
+            rewritten_clauses.retain(|(occur, subquery)| {
+                let Some(term_query) = subquery.downcast_ref::<TermQuery>() else {
+                    return true;
+                };
+                !terms_to_merge.contains_key(&(occur_code(*occur), term_query.term().field()))
+            });
+            for ((code, _field), terms) in terms_to_merge {
+                let occur = if code == occur_code(Occur::Should) {
+                    Occur::Should
+                } else {
+                    Occur::MustNot
+                };
+                rewritten_clauses.push((occur, Box::new(TermSetQuery::new(terms))));
+            }
+        }
+
+        // The score of excluded subtrees is never used.
+        if scoring_enabled {
+            for (occur, subquery) in &mut rewritten_clauses {
+                if *occur == Occur::MustNot && subquery.is::<BooleanQuery>() {
+                    let excluded_query = std::mem::replace(subquery, Box::new(EmptyQuery));
+                    *subquery = Box::new(ConstScoreQuery::new(excluded_query, 0.0));
+                }
+            }
+        }
+
+        let num_clauses = rewritten_clauses.len();
+        match rewritten_clauses.pop() {
+            None => Ok(Box::new(EmptyQuery)),
+            Some((Occur::Must, subquery)) if num_clauses == 1 => Ok(subquery),
+            Some((Occur::Should, subquery))
+                if num_clauses == 1 && minimum_number_should_match <= 1 =>
+            {
+                Ok(subquery)
+            }
+            Some(clause) => {
+                rewritten_clauses.push(clause);
+                Ok(Box::new(BooleanQuery::with_minimum_required_clauses(
+                    rewritten_clauses,
+                    minimum_number_should_match,
+                )))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -264,8 +507,11 @@ mod tests {
     use std::collections::HashSet;
 
     use super::BooleanQuery;
-    use crate::collector::{Count, DocSetCollector};
-    use crate::query::{Query, QueryClone, QueryParser, TermQuery};
+    use crate::collector::{Count, DocSetCollector, TopDocs};
+    use crate::query::{
+        AllQuery, ConstScoreQuery, EmptyQuery, Occur, Query, QueryClone, QueryParser, TermQuery,
+        TermSetQuery,
+    };
     use crate::schema::{Field, IndexRecordOption, Schema, TEXT};
     use crate::{DocAddress, DocId, Index, Term};
 
@@ -447,4 +693,140 @@ mod tests {
         ));
         Ok(())
     }
+
+    #[test]
+    fn test_rewrite() -> crate::Result<()> {
+        let index = create_test_index()?;
+        let searcher = index.reader()?.searcher();
+        let text = index.schema().get_field("text").unwrap();
+        let term = |text_value: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text, text_value),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let check_same_results = |query: &dyn Query| -> crate::Result<Box<dyn Query>> {
+            let rewritten_query = query.rewrite(&searcher)?;
+            let top_docs = TopDocs::with_limit(10).order_by_score();
+            assert_eq!(
+                searcher.search(query, &top_docs)?,
+                searcher.search(&rewritten_query, &top_docs)?
+            );
+            Ok(rewritten_query)
+        };
+
+        // Nested conjunctions are flattened.
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term("a")),
+            (
+                Occur::Must,
+                Box::new(BooleanQuery::intersection(vec![term("b"), term("c")])),
+            ),
+        ]);
+        let rewritten_query = check_same_results(&query)?;
+        let rewritten_query = rewritten_query.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(rewritten_query.clauses().len(), 3);
+
+        // A nested query made only of exclusions matches nothing, and is not flattened.
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term("a")),
+            (
+                Occur::Must,
+                Box::new(BooleanQuery::new(vec![(Occur::MustNot, term("b"))])),
+            ),
+        ]);
+        check_same_results(&query)?;
+        assert_eq!(searcher.search(&query, &Count)?, 0);
+
+        // Clauses without effect are removed.
+        let query = BooleanQuery::new(vec![
+            (Occur::Should, term("a")),
+            (Occur::Should, Box::new(EmptyQuery)),
+        ]);
+        assert!(check_same_results(&query)?.is::<TermQuery>());
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term("a")),
+            (Occur::Must, Box::new(EmptyQuery)),
+        ]);
+        assert!(check_same_results(&query)?.is::<EmptyQuery>());
+
+        // Excluded terms are merged.
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term("a")),
+            (Occur::MustNot, term("b")),
+            (
+                Occur::MustNot,
+                Box::new(BooleanQuery::intersection(vec![term("c"), term("d")])),
+            ),
+            (Occur::MustNot, term("e")),
+        ]);
+        let rewritten_query = check_same_results(&query)?;
+        let rewritten_query = rewritten_query.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(rewritten_query.clauses().len(), 3);
+        assert!(rewritten_query.clauses()[1].1.is::<ConstScoreQuery>());
+        assert!(rewritten_query.clauses()[2].1.is::<TermSetQuery>());
+
+        // Filters are rewritten without caring about the score.
+        let query = ConstScoreQuery::new(
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, Box::new(AllQuery)),
+                (
+                    Occur::Must,
+                    Box::new(BooleanQuery::union(vec![term("b"), term("d")])),
+                ),
+            ])),
+            1.0,
+        );
+        let rewritten_query = query.rewrite(&searcher)?;
+        assert_eq!(
+            searcher.search(&query, &DocSetCollector)?,
+            searcher.search(&rewritten_query, &DocSetCollector)?
+        );
+        assert_eq!(
+            format!("{rewritten_query:?}"),
+            format!(
+                "Const(score=1, query={:?})",
+                TermSetQuery::new(vec![
+                    Term::from_field_text(text, "b"),
+                    Term::from_field_text(text, "d"),
+                ])
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let text = Field::from_field_id(0);
+        let term = |text_value: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text, text_value),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let query_ab =
+            BooleanQuery::new(vec![(Occur::Must, term("a")), (Occur::Should, term("b"))]);
+        let query_ba =
+            BooleanQuery::new(vec![(Occur::Should, term("b")), (Occur::Must, term("a"))]);
+        assert_eq!(query_ab.fingerprint(), query_ba.fingerprint());
+        assert_eq!(query_ab.fingerprint(), query_ab.box_clone().fingerprint());
+        let query_ac =
+            BooleanQuery::new(vec![(Occur::Must, term("a")), (Occur::Should, term("c"))]);
+        assert_ne!(query_ab.fingerprint(), query_ac.fingerprint());
+        let query_ab_inverted =
+            BooleanQuery::new(vec![(Occur::Should, term("a")), (Occur::Must, term("b"))]);
+        assert_ne!(query_ab.fingerprint(), query_ab_inverted.fingerprint());
+        assert_eq!(
+            TermSetQuery::new(vec![
+                Term::from_field_text(text, "a"),
+                Term::from_field_u64(Field::from_field_id(1), 1),
+            ])
+            .fingerprint(),
+            TermSetQuery::new(vec![
+                Term::from_field_u64(Field::from_field_id(1), 1),
+                Term::from_field_text(text, "a"),
+            ])
+            .fingerprint()
+        );
+    }
 }
@@ -1,16 +1,22 @@
 use std::collections::HashMap;
 
+use futures_util::future::{try_join_all, BoxFuture};
+
 use crate::docset::COLLECT_BLOCK_BUFFER_LEN;
 use crate::index::SegmentReader;
 use crate::postings::FreqReadingOption;
 use crate::query::disjunction::Disjunction;
 use crate::query::explanation::does_not_match;
+use crate::query::matches::doc_matches;
 use crate::query::score_combiner::{DoNothingCombiner, ScoreCombiner};
 use crate::query::term_query::TermScorer;
-use crate::query::weight::{for_each_docset_buffered, for_each_pruning_scorer, for_each_scorer};
+use crate::query::weight::{
+    for_each_docset_buffered, for_each_pruning_scorer, for_each_scorer,
+    pruning_callback_with_context,
+};
 use crate::query::{
-    intersect_scorers, AllScorer, BufferedUnionScorer, EmptyScorer, Exclude, Explanation, Occur,
-    RequiredOptionalScorer, Scorer, Weight,
+    intersect_scorers, AllScorer, BufferedUnionScorer, EmptyScorer, Exclude, Explanation, Matches,
+    Occur, RequiredOptionalScorer, Scorer, Weight,
 };
 use crate::{DocId, Score};
 
@@ -418,6 +424,13 @@ fn remove_and_count_all_and_empty_scorers(
 }
 
 impl<TScoreCombiner: ScoreCombiner + Sync> Weight for BooleanWeight<TScoreCombiner> {
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            try_join_all(self.weights.iter().map(|(_, weight)| weight.warmup(reader))).await?;
+            Ok(())
+        })
+    }
+
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
         let num_docs = reader.num_docs();
         if self.weights.is_empty() {
@@ -462,6 +475,21 @@ impl<TScoreCombiner: ScoreCombiner + Sync> Weight for BooleanWeight<TScoreCombin
         Ok(explanation)
     }
 
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        if !doc_matches(self, reader, doc)? {
+            return Ok(None);
+        }
+        let mut matches = Matches::default();
+        for (occur, subweight) in &self.weights {
+            if is_include_occur(*occur) {
+                if let Some(child_matches) = subweight.matches(reader, doc)? {
+                    matches.merge(child_matches);
+                }
+            }
+        }
+        Ok(Some(matches))
+    }
+
     fn for_each(
         &self,
         reader: &SegmentReader,
@@ -475,10 +503,10 @@ impl<TScoreCombiner: ScoreCombiner + Sync> Weight for BooleanWeight<TScoreCombin
                     &self.score_combiner_fn,
                     reader.num_docs(),
                 );
-                for_each_scorer(&mut union_scorer, callback);
+                for_each_scorer(&mut union_scorer, reader.search_context(), callback);
             }
             SpecializedScorer::Other(mut scorer) => {
-                for_each_scorer(scorer.as_mut(), callback);
+                for_each_scorer(scorer.as_mut(), reader.search_context(), callback);
             }
         }
         Ok(())
@@ -499,10 +527,20 @@ impl<TScoreCombiner: ScoreCombiner + Sync> Weight for BooleanWeight<TScoreCombin
                     &self.score_combiner_fn,
                     reader.num_docs(),
                 );
-                for_each_docset_buffered(&mut union_scorer, &mut buffer, callback);
+                for_each_docset_buffered(
+                    &mut union_scorer,
+                    &mut buffer,
+                    reader.search_context(),
+                    callback,
+                );
             }
             SpecializedScorer::Other(mut scorer) => {
-                for_each_docset_buffered(scorer.as_mut(), &mut buffer, callback);
+                for_each_docset_buffered(
+                    scorer.as_mut(),
+                    &mut buffer,
+                    reader.search_context(),
+                    callback,
+                );
             }
         }
         Ok(())
@@ -527,10 +565,16 @@ impl<TScoreCombiner: ScoreCombiner + Sync> Weight for BooleanWeight<TScoreCombin
         let scorer = self.complex_scorer(reader, 1.0, &self.score_combiner_fn)?;
         match scorer {
             SpecializedScorer::TermUnion(term_scorers) => {
-                super::block_wand(term_scorers, threshold, callback);
+                let mut callback = pruning_callback_with_context(reader.search_context(), callback);
+                super::block_wand(term_scorers, threshold, &mut callback);
             }
             SpecializedScorer::Other(mut scorer) => {
-                for_each_pruning_scorer(scorer.as_mut(), threshold, callback);
+                for_each_pruning_scorer(
+                    scorer.as_mut(),
+                    threshold,
+                    reader.search_context(),
+                    callback,
+                );
             }
         }
         Ok(())
@@ -1,9 +1,15 @@
 use std::fmt;
 
+use futures_util::future::BoxFuture;
+
 use crate::docset::COLLECT_BLOCK_BUFFER_LEN;
 use crate::fastfield::AliveBitSet;
-use crate::query::{EnableScoring, Explanation, Query, Scorer, Weight};
-use crate::{DocId, DocSet, Score, SegmentReader, Term};
+use crate::query::fingerprint::FingerprintHasher;
+use crate::query::{
+    ConstScoreQuery, EmptyQuery, EnableScoring, Explanation, Matches, Query, QueryProfiler, Scorer,
+    Weight,
+};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
 
 /// `BoostQuery` is a wrapper over a query used to boost its score.
 ///
@@ -20,6 +26,23 @@ impl BoostQuery {
     pub fn new(query: Box<dyn Query>, boost: Score) -> BoostQuery {
         BoostQuery { query, boost }
     }
+
+    /// Returns the boosted query.
+    pub fn query(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+
+    fn boost_weight(
+        &self,
+        weight_without_boost: Box<dyn Weight>,
+        enable_scoring: EnableScoring<'_>,
+    ) -> Box<dyn Weight> {
+        if enable_scoring.is_scoring_enabled() {
+            Box::new(BoostWeight::new(weight_without_boost, self.boost))
+        } else {
+            weight_without_boost
+        }
+    }
 }
 
 impl Clone for BoostQuery {
@@ -40,17 +63,43 @@ impl fmt::Debug for BoostQuery {
 impl Query for BoostQuery {
     fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
         let weight_without_boost = self.query.weight(enable_scoring)?;
-        let boosted_weight = if enable_scoring.is_scoring_enabled() {
-            Box::new(BoostWeight::new(weight_without_boost, self.boost))
-        } else {
-            weight_without_boost
-        };
-        Ok(boosted_weight)
+        Ok(self.boost_weight(weight_without_boost, enable_scoring))
+    }
+
+    fn weight_with_profiler(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        profiler: &QueryProfiler,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let inner_profiler = QueryProfiler::default();
+        let weight_without_boost = self
+            .query
+            .weight_with_profiler(enable_scoring, &inner_profiler)?;
+        let weight = self.boost_weight(weight_without_boost, enable_scoring);
+        Ok(profiler.instrument(self, weight, inner_profiler))
     }
 
     fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
         self.query.query_terms(visitor)
     }
+
+    fn rewrite(&self, searcher: &Searcher) -> crate::Result<Box<dyn Query>> {
+        let query = self.query.rewrite(searcher)?;
+        if self.boost == 1.0 || query.is::<EmptyQuery>() {
+            return Ok(query);
+        }
+        if self.boost == 0.0 {
+            return ConstScoreQuery::new(query, 0.0).rewrite(searcher);
+        }
+        Ok(Box::new(BoostQuery::new(query, self.boost)))
+    }
+
+    fn fingerprint(&self) -> u128 {
+        let mut hasher = FingerprintHasher::new("BoostQuery");
+        hasher.write(&self.boost.to_le_bytes());
+        hasher.write_u128(self.query.fingerprint());
+        hasher.finish()
+    }
 }
 
 /// Weight associated to the BoostQuery.
@@ -71,6 +120,10 @@ impl Weight for BoostWeight {
         self.weight.scorer(reader, boost * self.boost)
     }
 
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        self.weight.warmup(reader)
+    }
+
     fn explain(&self, reader: &SegmentReader, doc: u32) -> crate::Result<Explanation> {
         let underlying_explanation = self.weight.explain(reader, doc)?;
         let score = underlying_explanation.value() * self.boost;
@@ -80,6 +133,10 @@ impl Weight for BoostWeight {
         Ok(explanation)
     }
 
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
+
     fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
         self.weight.count(reader)
     }
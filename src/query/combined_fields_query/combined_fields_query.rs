@@ -0,0 +1,234 @@
+use super::combined_fields_weight::{CombinedFieldsWeight, CombinedTerm};
+use crate::docset::DocSet;
+use crate::query::bm25::Bm25Weight;
+use crate::query::{Bm25StatisticsProvider, EnableScoring, Explanation, Query, Weight};
+use crate::schema::{Field, FieldType, IndexRecordOption};
+use crate::{Score, Searcher, TantivyError, Term, TERMINATED};
+
+/// `CombinedFieldsQuery` searches terms in several text fields, scoring them as if the fields
+/// were a single virtual field (BM25F).
+///
+/// A disjunction of per-field term queries sums the BM25 scores of each field, which over-rewards
+/// a term matching in several fields and ignores that some fields are much longer than others.
+/// Instead, for each term, the `CombinedFieldsQuery` computes:
+/// - the term frequency of the virtual field, as the weighted sum of the term frequencies in the
+///   fields,
+/// - the length of the virtual field, as the weighted sum of the fieldnorms of the fields,
+/// - the document frequency, as the number of documents containing the term in any of the fields.
+///
+/// By default, the document frequency is estimated by the [`Bm25StatisticsProvider`] as the
+/// largest document frequency of the term across the fields. The exact count requires walking the
+/// postings of the term in every field and segment, and can be requested with
+/// [`CombinedFieldsQuery::set_exact_doc_freq`].
+///
+/// The score of a document is the sum of the BM25 scores of the terms on the virtual field.
+///
+/// ```rust
+/// use tantivy::collector::TopDocs;
+/// use tantivy::query::CombinedFieldsQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, DocAddress, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let body = schema_builder.add_text_field("body", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+///
+/// let mut index_writer = index.writer_with_num_threads(1, 20_000_000)?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib", body => "A diary"))?;
+/// index_writer.add_document(doc!(title => "A Dairy Cow", body => "Milk"))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query = CombinedFieldsQuery::new(
+///     vec![(title, 2.0), (body, 1.0)],
+///     vec!["diary".to_string(), "cow".to_string()],
+/// );
+/// let top_docs = searcher.search(&query, &TopDocs::with_limit(2).order_by_score())?;
+/// assert_eq!(top_docs.len(), 2);
+/// assert_eq!(top_docs[0].1, DocAddress::new(0, 0));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CombinedFieldsQuery {
+    fields: Vec<(Field, Score)>,
+    // `field_terms[term_ord][field_ord]` is the term `term_ord` in the field `field_ord`.
+    field_terms: Vec<Vec<Term>>,
+    exact_doc_freq: bool,
+}
+
+impl CombinedFieldsQuery {
+    /// Creates a query searching `terms` in `fields`.
+    ///
+    /// `fields` are text fields, associated with their weight. `terms` are the tokens to search,
+    /// already processed by the tokenizer of the fields.
+    pub fn new(fields: Vec<(Field, Score)>, terms: Vec<String>) -> CombinedFieldsQuery {
+        let field_terms = terms
+            .iter()
+            .map(|text| {
+                fields
+                    .iter()
+                    .map(|&(field, _)| Term::from_field_text(field, text))
+                    .collect()
+            })
+            .collect();
+        CombinedFieldsQuery {
+            fields,
+            field_terms,
+            exact_doc_freq: false,
+        }
+    }
+
+    /// Sets whether the document frequency of each term is computed exactly, as the number of
+    /// documents containing the term in any of the fields.
+    ///
+    /// Computing it exactly reads the whole postings of the term in every field when the query is
+    /// created, so it is disabled by default and the statistics provider estimates it instead.
+    pub fn set_exact_doc_freq(&mut self, exact_doc_freq: bool) {
+        self.exact_doc_freq = exact_doc_freq;
+    }
+
+    /// Returns the index record option used to read the postings of each field.
+    fn index_record_options(
+        &self,
+        enable_scoring: &EnableScoring<'_>,
+    ) -> crate::Result<Vec<IndexRecordOption>> {
+        let schema = enable_scoring.schema();
+        let mut index_record_options = Vec::with_capacity(self.fields.len());
+        for &(field, weight) in &self.fields {
+            let field_entry = schema.get_field_entry(field);
+            let FieldType::Str(text_options) = field_entry.field_type() else {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is not a text field.",
+                    field_entry.name()
+                )));
+            };
+            let Some(text_indexing) = text_options.get_indexing_options() else {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is not indexed.",
+                    field_entry.name()
+                )));
+            };
+            if !(weight.is_finite() && weight > 0.0) {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "The weight of field {:?} should be positive, got {weight}.",
+                    field_entry.name()
+                )));
+            }
+            let index_record_option =
+                if enable_scoring.is_scoring_enabled() && text_indexing.index_option().has_freq() {
+                    IndexRecordOption::WithFreqs
+                } else {
+                    IndexRecordOption::Basic
+                };
+            index_record_options.push(index_record_option);
+        }
+        Ok(index_record_options)
+    }
+
+    fn similarity_weights(
+        &self,
+        searcher: &Searcher,
+        statistics_provider: &dyn Bm25StatisticsProvider,
+    ) -> crate::Result<Vec<Bm25Weight>> {
+        let total_num_docs = statistics_provider.total_num_docs()?;
+        let mut total_length: Score = 0.0;
+        for &(field, weight) in &self.fields {
+            total_length += weight * statistics_provider.total_num_tokens(field)? as Score;
+        }
+        let average_length = total_length / total_num_docs as Score;
+        self.field_terms
+            .iter()
+            .map(|terms| {
+                let doc_freq = if self.exact_doc_freq {
+                    union_doc_freq(searcher, terms)?
+                } else {
+                    statistics_provider.union_doc_freq(terms)?
+                };
+                let doc_freq = doc_freq.min(total_num_docs);
+                Ok(Bm25Weight::for_one_term(
+                    doc_freq,
+                    total_num_docs,
+                    average_length,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Returns the number of documents containing at least one of `terms`.
+fn union_doc_freq(searcher: &Searcher, terms: &[Term]) -> crate::Result<u64> {
+    let mut doc_freq = 0u64;
+    for segment_reader in searcher.segment_readers() {
+        let mut postings_list = Vec::with_capacity(terms.len());
+        for term in terms {
+            let inverted_index = segment_reader.inverted_index(term.field())?;
+            if let Some(postings) = inverted_index.read_postings(term, IndexRecordOption::Basic)? {
+                postings_list.push(postings);
+            }
+        }
+        if let [postings] = &postings_list[..] {
+            doc_freq += u64::from(postings.doc_freq());
+            continue;
+        }
+        loop {
+            let doc = postings_list
+                .iter()
+                .map(|postings| postings.doc())
+                .min()
+                .unwrap_or(TERMINATED);
+            if doc == TERMINATED {
+                break;
+            }
+            doc_freq += 1;
+            for postings in &mut postings_list {
+                if postings.doc() == doc {
+                    postings.advance();
+                }
+            }
+        }
+    }
+    Ok(doc_freq)
+}
+
+impl Query for CombinedFieldsQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let index_record_options = self.index_record_options(&enable_scoring)?;
+        let similarity_weights = match enable_scoring {
+            EnableScoring::Enabled {
+                searcher,
+                statistics_provider,
+            } => self.similarity_weights(searcher, statistics_provider)?,
+            EnableScoring::Disabled { .. } => {
+                vec![
+                    Bm25Weight::new(Explanation::new("<no score>", 1.0f32), 1.0f32);
+                    self.field_terms.len()
+                ]
+            }
+        };
+        let terms = self
+            .field_terms
+            .iter()
+            .cloned()
+            .zip(similarity_weights)
+            .map(|(field_terms, similarity_weight)| CombinedTerm {
+                field_terms,
+                similarity_weight,
+            })
+            .collect();
+        Ok(Box::new(CombinedFieldsWeight::new(
+            self.fields.clone(),
+            terms,
+            index_record_options,
+            enable_scoring.is_scoring_enabled(),
+        )))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for term in self.field_terms.iter().flatten() {
+            visitor(term, false);
+        }
+    }
+}
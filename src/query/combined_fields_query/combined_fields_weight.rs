@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use super::combined_term_scorer::{CombinedTermScorer, WeightedFieldNorm};
+use crate::docset::DocSet;
+use crate::fieldnorm::FieldNormReader;
+use crate::index::SegmentReader;
+use crate::query::bm25::Bm25Weight;
+use crate::query::boolean_query::{block_wand, block_wand_single_scorer};
+use crate::query::explanation::does_not_match;
+use crate::query::matches::term_positions_in_doc;
+use crate::query::score_combiner::{DoNothingCombiner, SumCombiner};
+use crate::query::weight::pruning_callback_with_context;
+use crate::query::{BufferedUnionScorer, EmptyScorer, Explanation, Matches, Scorer, Weight};
+use crate::schema::{Field, IndexRecordOption};
+use crate::{DocId, Score, Term, TERMINATED};
+
+/// A term of a [`CombinedFieldsWeight`], declined for each of the fields.
+pub(crate) struct CombinedTerm {
+    pub(crate) field_terms: Vec<Term>,
+    pub(crate) similarity_weight: Bm25Weight,
+}
+
+/// Weight of a [`CombinedFieldsQuery`](super::CombinedFieldsQuery).
+pub struct CombinedFieldsWeight {
+    fields: Vec<(Field, Score)>,
+    terms: Vec<CombinedTerm>,
+    index_record_options: Vec<IndexRecordOption>,
+    scoring_enabled: bool,
+}
+
+impl CombinedFieldsWeight {
+    pub(crate) fn new(
+        fields: Vec<(Field, Score)>,
+        terms: Vec<CombinedTerm>,
+        index_record_options: Vec<IndexRecordOption>,
+        scoring_enabled: bool,
+    ) -> CombinedFieldsWeight {
+        CombinedFieldsWeight {
+            fields,
+            terms,
+            index_record_options,
+            scoring_enabled,
+        }
+    }
+
+    fn fieldnorms(&self, reader: &SegmentReader) -> crate::Result<Arc<[WeightedFieldNorm]>> {
+        if !self.scoring_enabled {
+            return Ok(Arc::new([]));
+        }
+        let mut fieldnorms = Vec::with_capacity(self.fields.len());
+        for &(field, weight) in &self.fields {
+            let fieldnorm_reader = reader
+                .fieldnorms_readers()
+                .get_field(field)?
+                .unwrap_or_else(|| FieldNormReader::constant(reader.max_doc(), 1));
+            fieldnorms.push(WeightedFieldNorm {
+                fieldnorm_reader,
+                weight,
+            });
+        }
+        Ok(fieldnorms.into())
+    }
+
+    fn term_scorer(
+        &self,
+        combined_term: &CombinedTerm,
+        reader: &SegmentReader,
+        fieldnorms: Arc<[WeightedFieldNorm]>,
+        boost: Score,
+    ) -> crate::Result<CombinedTermScorer> {
+        let mut postings = Vec::with_capacity(self.fields.len());
+        for ((term, &(field, weight)), &index_record_option) in combined_term
+            .field_terms
+            .iter()
+            .zip(&self.fields)
+            .zip(&self.index_record_options)
+        {
+            let inverted_index = reader.inverted_index(field)?;
+            if let Some(segment_postings) =
+                inverted_index.read_postings(term, index_record_option)?
+            {
+                postings.push((segment_postings, weight));
+            }
+        }
+        Ok(CombinedTermScorer::new(
+            postings,
+            fieldnorms,
+            combined_term.similarity_weight.boost_by(boost),
+        ))
+    }
+
+    /// Returns the scorers of the terms matching at least one document of the segment.
+    fn term_scorers(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> crate::Result<Vec<CombinedTermScorer>> {
+        let fieldnorms = self.fieldnorms(reader)?;
+        let mut term_scorers = Vec::with_capacity(self.terms.len());
+        for combined_term in &self.terms {
+            let term_scorer = self.term_scorer(combined_term, reader, fieldnorms.clone(), boost)?;
+            if term_scorer.doc() != TERMINATED {
+                term_scorers.push(term_scorer);
+            }
+        }
+        Ok(term_scorers)
+    }
+}
+
+impl Weight for CombinedFieldsWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let mut term_scorers = self.term_scorers(reader, boost)?;
+        match term_scorers.len() {
+            0 => Ok(Box::new(EmptyScorer)),
+            1 => Ok(Box::new(term_scorers.pop().unwrap())),
+            _ if self.scoring_enabled => Ok(Box::new(BufferedUnionScorer::build(
+                term_scorers,
+                SumCombiner::default,
+                reader.max_doc(),
+            ))),
+            _ => Ok(Box::new(BufferedUnionScorer::build(
+                term_scorers,
+                DoNothingCombiner::default,
+                reader.max_doc(),
+            ))),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let fieldnorms = self.fieldnorms(reader)?;
+        let mut term_explanations = Vec::new();
+        for combined_term in &self.terms {
+            let mut term_scorer =
+                self.term_scorer(combined_term, reader, fieldnorms.clone(), 1.0)?;
+            if term_scorer.doc() <= doc && term_scorer.seek(doc) == doc {
+                let mut term_explanation = term_scorer.explain();
+                term_explanation.add_context(format!("Terms={:?}", combined_term.field_terms));
+                term_explanations.push(term_explanation);
+            }
+        }
+        if term_explanations.is_empty() {
+            return Err(does_not_match(doc));
+        }
+        let score = term_explanations.iter().map(Explanation::value).sum();
+        let mut explanation = Explanation::new("CombinedFieldsQuery, sum of ...", score);
+        for term_explanation in term_explanations {
+            explanation.add_detail(term_explanation);
+        }
+        Ok(explanation)
+    }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let mut matches_opt: Option<Matches> = None;
+        for combined_term in &self.terms {
+            for term in &combined_term.field_terms {
+                if let Some(positions) = term_positions_in_doc(reader, term, doc)? {
+                    matches_opt
+                        .get_or_insert_with(Matches::default)
+                        .add_term_positions(term, &positions);
+                }
+            }
+        }
+        Ok(matches_opt)
+    }
+
+    fn for_each_pruning(
+        &self,
+        threshold: Score,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId, Score) -> Score,
+    ) -> crate::Result<()> {
+        let mut term_scorers = self.term_scorers(reader, 1.0)?;
+        let mut callback = pruning_callback_with_context(reader.search_context(), callback);
+        if term_scorers.len() == 1 {
+            block_wand_single_scorer(term_scorers.pop().unwrap(), threshold, &mut callback);
+        } else {
+            block_wand(term_scorers, threshold, &mut callback);
+        }
+        Ok(())
+    }
+}
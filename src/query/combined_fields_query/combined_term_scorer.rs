@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use crate::docset::DocSet;
+use crate::fieldnorm::FieldNormReader;
+use crate::postings::{Postings, SegmentPostings};
+use crate::query::bm25::Bm25Weight;
+use crate::query::boolean_query::BlockMaxScorer;
+use crate::query::{Explanation, Scorer};
+use crate::{DocId, Score, TERMINATED};
+
+/// Fieldnorm reader of one of the fields of the virtual field, along with the weight of the field.
+#[derive(Clone)]
+pub(crate) struct WeightedFieldNorm {
+    pub(crate) fieldnorm_reader: FieldNormReader,
+    pub(crate) weight: Score,
+}
+
+struct WeightedPostings {
+    postings: SegmentPostings,
+    weight: Score,
+}
+
+/// Scores a term over the virtual field made of several weighted fields, using BM25F.
+///
+/// The term frequency and the length of the virtual field are the weighted sums of the term
+/// frequencies and of the lengths of the fields.
+pub struct CombinedTermScorer {
+    postings: Vec<WeightedPostings>,
+    fieldnorms: Arc<[WeightedFieldNorm]>,
+    similarity_weight: Bm25Weight,
+    doc: DocId,
+}
+
+impl CombinedTermScorer {
+    pub(crate) fn new(
+        postings: Vec<(SegmentPostings, Score)>,
+        fieldnorms: Arc<[WeightedFieldNorm]>,
+        similarity_weight: Bm25Weight,
+    ) -> CombinedTermScorer {
+        let postings: Vec<WeightedPostings> = postings
+            .into_iter()
+            .map(|(postings, weight)| WeightedPostings { postings, weight })
+            .collect();
+        let doc = min_doc(&postings);
+        CombinedTermScorer {
+            postings,
+            fieldnorms,
+            similarity_weight,
+            doc,
+        }
+    }
+
+    /// Returns the weighted sum of the term frequencies in the fields.
+    pub fn term_freq(&self) -> Score {
+        self.postings
+            .iter()
+            .filter(|weighted_postings| weighted_postings.postings.doc() == self.doc)
+            .map(|weighted_postings| {
+                weighted_postings.weight * weighted_postings.postings.term_freq() as Score
+            })
+            .sum()
+    }
+
+    /// Returns the weighted sum of the lengths of the fields.
+    pub fn length(&self) -> Score {
+        self.fieldnorms
+            .iter()
+            .map(|weighted_fieldnorm| {
+                weighted_fieldnorm.weight
+                    * weighted_fieldnorm.fieldnorm_reader.fieldnorm(self.doc) as Score
+            })
+            .sum()
+    }
+
+    /// Explains the score of the current document.
+    pub fn explain(&self) -> Explanation {
+        self.similarity_weight
+            .explain_weighted(self.length(), self.term_freq())
+    }
+}
+
+fn min_doc(postings: &[WeightedPostings]) -> DocId {
+    postings
+        .iter()
+        .map(|weighted_postings| weighted_postings.postings.doc())
+        .min()
+        .unwrap_or(TERMINATED)
+}
+
+impl DocSet for CombinedTermScorer {
+    fn advance(&mut self) -> DocId {
+        let doc = self.doc;
+        for weighted_postings in &mut self.postings {
+            if weighted_postings.postings.doc() == doc {
+                weighted_postings.postings.advance();
+            }
+        }
+        self.doc = min_doc(&self.postings);
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        for weighted_postings in &mut self.postings {
+            if weighted_postings.postings.doc() < target {
+                weighted_postings.postings.seek(target);
+            }
+        }
+        self.doc = min_doc(&self.postings);
+        self.doc
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings
+            .iter()
+            .map(|weighted_postings| weighted_postings.postings.size_hint())
+            .sum()
+    }
+}
+
+impl Scorer for CombinedTermScorer {
+    fn score(&mut self) -> Score {
+        self.similarity_weight
+            .score_weighted(self.length(), self.term_freq())
+    }
+}
+
+/// The block max scores of the postings are computed for the BM25 of their own field, and do not
+/// bound the BM25F score. A `CombinedTermScorer` is therefore seen as a single block, bounded by
+/// its maximum score: block-WAND only skips the documents whose terms cannot reach the threshold
+/// together.
+impl BlockMaxScorer for CombinedTermScorer {
+    fn max_score(&self) -> Score {
+        self.similarity_weight.weighted_max_score()
+    }
+
+    fn seek_block(&mut self, _target_doc: DocId) {}
+
+    fn block_max_score(&mut self) -> Score {
+        self.similarity_weight.weighted_max_score()
+    }
+
+    fn last_doc_in_block(&self) -> DocId {
+        TERMINATED
+    }
+}
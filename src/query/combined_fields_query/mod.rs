@@ -0,0 +1,174 @@
+mod combined_fields_query;
+mod combined_fields_weight;
+mod combined_term_scorer;
+
+pub use self::combined_fields_query::CombinedFieldsQuery;
+
+#[cfg(test)]
+mod tests {
+    use super::CombinedFieldsQuery;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::bm25::Bm25Weight;
+    use crate::query::{Bm25StatisticsProvider, EnableScoring, Query};
+    use crate::schema::{Field, Schema, INDEXED, TEXT};
+    use crate::{
+        assert_nearly_equals, DocAddress, Index, IndexWriter, Score, Searcher, TantivyError, Term,
+    };
+
+    #[test]
+    fn test_combined_fields_query_bm25f() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "a c", body => "a"))?;
+        index_writer.add_document(doc!(title => "a a", body => "b"))?;
+        index_writer.add_document(doc!(title => "b", body => "c"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let scores = |title_weight: Score| -> crate::Result<Vec<(Score, DocAddress)>> {
+            let mut query = CombinedFieldsQuery::new(
+                vec![(title, title_weight), (body, 1.0)],
+                vec!["a".to_string()],
+            );
+            query.set_exact_doc_freq(true);
+            let mut top_docs =
+                searcher.search(&query, &TopDocs::with_limit(10).order_by_score())?;
+            top_docs.sort_by_key(|(_, doc_address)| *doc_address);
+            Ok(top_docs)
+        };
+
+        // Both documents have the same weighted term frequency and length.
+        let top_docs = scores(1.0)?;
+        assert_eq!(top_docs.len(), 2);
+        // The term is in 2 of the 3 documents, the average length is (5 + 3) / 3.
+        let bm25_weight = Bm25Weight::for_one_term(2, 3, 8.0 / 3.0);
+        assert_nearly_equals!(top_docs[0].0, bm25_weight.score_weighted(3.0, 2.0));
+        assert_nearly_equals!(top_docs[1].0, bm25_weight.score_weighted(3.0, 2.0));
+
+        let top_docs = scores(2.0)?;
+        let bm25_weight = Bm25Weight::for_one_term(2, 3, 13.0 / 3.0);
+        assert_nearly_equals!(top_docs[0].0, bm25_weight.score_weighted(5.0, 3.0));
+        assert_nearly_equals!(top_docs[1].0, bm25_weight.score_weighted(5.0, 4.0));
+        assert!(top_docs[1].0 > top_docs[0].0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_combined_fields_query_doc_freq() -> crate::Result<()> {
+        struct SearcherStatistics<'a>(&'a Searcher);
+
+        impl Bm25StatisticsProvider for SearcherStatistics<'_> {
+            fn total_num_tokens(&self, field: Field) -> crate::Result<u64> {
+                Bm25StatisticsProvider::total_num_tokens(self.0, field)
+            }
+
+            fn total_num_docs(&self) -> crate::Result<u64> {
+                Bm25StatisticsProvider::total_num_docs(self.0)
+            }
+
+            fn doc_freq(&self, term: &Term) -> crate::Result<u64> {
+                self.0.doc_freq(term)
+            }
+        }
+
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "a c", body => "a"))?;
+        index_writer.add_document(doc!(title => "a a", body => "b"))?;
+        index_writer.add_document(doc!(title => "b", body => "c"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let statistics_provider = SearcherStatistics(&searcher);
+        let mut query =
+            CombinedFieldsQuery::new(vec![(title, 1.0), (body, 1.0)], vec!["c".to_string()]);
+        let top_docs = TopDocs::with_limit(10).order_by_score();
+
+        // The term is in one document per field, the estimate is the largest of the two.
+        let estimated =
+            searcher.search_with_statistics_provider(&query, &top_docs, &statistics_provider)?;
+        let bm25_weight = Bm25Weight::for_one_term(1, 3, 8.0 / 3.0);
+        assert_eq!(estimated.len(), 2);
+        assert_nearly_equals!(estimated[0].0, bm25_weight.score_weighted(2.0, 1.0));
+        assert_nearly_equals!(estimated[1].0, bm25_weight.score_weighted(3.0, 1.0));
+
+        // Counted exactly, the term is in 2 of the 3 documents.
+        query.set_exact_doc_freq(true);
+        let exact =
+            searcher.search_with_statistics_provider(&query, &top_docs, &statistics_provider)?;
+        let bm25_weight = Bm25Weight::for_one_term(2, 3, 8.0 / 3.0);
+        assert_eq!(exact.len(), 2);
+        assert_nearly_equals!(exact[0].0, bm25_weight.score_weighted(2.0, 1.0));
+        assert_nearly_equals!(exact[1].0, bm25_weight.score_weighted(3.0, 1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_combined_fields_query_block_wand() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        let words = ["a", "b", "c", "d", "e"];
+        for i in 0..1_000usize {
+            let title_text: Vec<&str> = (0..1 + i % 3).map(|j| words[(i + j) % 4]).collect();
+            let body_text: Vec<&str> = (0..1 + i % 7).map(|j| words[(i * 7 + j * 3) % 5]).collect();
+            index_writer
+                .add_document(doc!(title => title_text.join(" "), body => body_text.join(" ")))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = CombinedFieldsQuery::new(
+            vec![(title, 3.0), (body, 1.0)],
+            vec!["a".to_string(), "d".to_string(), "e".to_string()],
+        );
+
+        let weight = query.weight(EnableScoring::enabled_from_searcher(&searcher))?;
+        let mut all_docs: Vec<(Score, DocAddress)> = Vec::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            weight.for_each(segment_reader, &mut |doc, score| {
+                all_docs.push((score, DocAddress::new(segment_ord as u32, doc)));
+            })?;
+        }
+        assert_eq!(all_docs.len(), searcher.search(&query, &Count)?);
+        all_docs.sort_by(|left, right| right.0.total_cmp(&left.0).then(left.1.cmp(&right.1)));
+
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(10).order_by_score())?;
+        assert_eq!(top_docs.len(), 10);
+        for ((score, _), (expected_score, _)) in top_docs.iter().zip(&all_docs) {
+            assert_nearly_equals!(*score, *expected_score);
+        }
+        for (score, doc_address) in &top_docs {
+            let explanation = query.explain(&searcher, *doc_address)?;
+            assert_nearly_equals!(explanation.value(), *score);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_combined_fields_query_errors() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let num = schema_builder.add_u64_field("num", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let searcher = index.reader()?.searcher();
+        let enable_scoring = EnableScoring::enabled_from_searcher(&searcher);
+
+        let query = CombinedFieldsQuery::new(vec![(title, 1.0), (num, 1.0)], vec!["a".into()]);
+        assert!(matches!(
+            query.weight(enable_scoring),
+            Err(TantivyError::SchemaError(_))
+        ));
+        let query = CombinedFieldsQuery::new(vec![(title, 0.0)], vec!["a".into()]);
+        assert!(matches!(
+            query.weight(enable_scoring),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+}
@@ -1,8 +1,15 @@
 use std::fmt;
 
+use futures_util::future::BoxFuture;
+
 use crate::docset::COLLECT_BLOCK_BUFFER_LEN;
-use crate::query::{EnableScoring, Explanation, Query, Scorer, Weight};
-use crate::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
+use crate::query::fingerprint::FingerprintHasher;
+use crate::query::query_cache::CachingWeight;
+use crate::query::{
+    BooleanQuery, EmptyQuery, EnableScoring, Explanation, Matches, Query, QueryProfiler, Scorer,
+    Weight,
+};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader, TantivyError, Term};
 
 /// `ConstScoreQuery` is a wrapper over a query to provide a constant score.
 /// It can avoid unnecessary score computation on the wrapped query.
@@ -19,6 +26,26 @@ impl ConstScoreQuery {
     pub fn new(query: Box<dyn Query>, score: Score) -> ConstScoreQuery {
         ConstScoreQuery { query, score }
     }
+
+    /// Returns the wrapped query.
+    pub fn query(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+}
+
+impl ConstScoreQuery {
+    fn weight_from_inner(
+        &self,
+        inner_weight: Box<dyn Weight>,
+        enable_scoring: EnableScoring<'_>,
+    ) -> Box<dyn Weight> {
+        let inner_weight = CachingWeight::wrap(self.query.as_ref(), inner_weight);
+        if enable_scoring.is_scoring_enabled() {
+            Box::new(ConstWeight::new(inner_weight, self.score))
+        } else {
+            inner_weight
+        }
+    }
 }
 
 impl Clone for ConstScoreQuery {
@@ -39,16 +66,49 @@ impl fmt::Debug for ConstScoreQuery {
 impl Query for ConstScoreQuery {
     fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
         let inner_weight = self.query.weight(enable_scoring)?;
-        Ok(if enable_scoring.is_scoring_enabled() {
-            Box::new(ConstWeight::new(inner_weight, self.score))
-        } else {
-            inner_weight
-        })
+        Ok(self.weight_from_inner(inner_weight, enable_scoring))
+    }
+
+    fn weight_with_profiler(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        profiler: &QueryProfiler,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let inner_profiler = QueryProfiler::default();
+        let inner_weight = self
+            .query
+            .weight_with_profiler(enable_scoring, &inner_profiler)?;
+        let weight = self.weight_from_inner(inner_weight, enable_scoring);
+        Ok(profiler.instrument(self, weight, inner_profiler))
     }
 
     fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
         self.query.query_terms(visitor);
     }
+
+    fn rewrite(&self, searcher: &Searcher) -> crate::Result<Box<dyn Query>> {
+        // The score of the wrapped query is never used.
+        let query = if let Some(boolean_query) = self.query.downcast_ref::<BooleanQuery>() {
+            boolean_query.rewrite_with_scoring(searcher, false)?
+        } else {
+            self.query.rewrite(searcher)?
+        };
+        if query.is::<EmptyQuery>() {
+            return Ok(query);
+        }
+        let query = match query.downcast::<ConstScoreQuery>() {
+            Ok(const_score_query) => const_score_query.query,
+            Err(query) => query,
+        };
+        Ok(Box::new(ConstScoreQuery::new(query, self.score)))
+    }
+
+    fn fingerprint(&self) -> u128 {
+        let mut hasher = FingerprintHasher::new("ConstScoreQuery");
+        hasher.write(&self.score.to_le_bytes());
+        hasher.write_u128(self.query.fingerprint());
+        hasher.finish()
+    }
 }
 
 struct ConstWeight {
@@ -68,6 +128,10 @@ impl Weight for ConstWeight {
         Ok(Box::new(ConstScorer::new(inner_scorer, boost * self.score)))
     }
 
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        self.weight.warmup(reader)
+    }
+
     fn explain(&self, reader: &SegmentReader, doc: u32) -> crate::Result<Explanation> {
         let mut scorer = self.scorer(reader, 1.0)?;
         if scorer.seek(doc) != doc {
@@ -81,6 +145,10 @@ impl Weight for ConstWeight {
         Ok(explanation)
     }
 
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
+
     fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
         self.weight.count(reader)
     }
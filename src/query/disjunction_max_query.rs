@@ -1,4 +1,6 @@
-use crate::query::{BooleanWeight, DisjunctionMaxCombiner, EnableScoring, Occur, Query, Weight};
+use crate::query::{
+    BooleanWeight, DisjunctionMaxCombiner, EnableScoring, Occur, Query, QueryProfiler, Weight,
+};
 use crate::{Score, Term};
 
 /// The disjunction max query returns documents matching one or more wrapped queries,
@@ -91,10 +93,40 @@ impl Clone for DisjunctionMaxQuery {
 
 impl Query for DisjunctionMaxQuery {
     fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        self.weight_with(enable_scoring, |disjunct| disjunct.weight(enable_scoring))
+    }
+
+    fn weight_with_profiler(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        profiler: &QueryProfiler,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let disjunct_profiler = QueryProfiler::default();
+        let weight = self.weight_with(enable_scoring, |disjunct| {
+            disjunct.weight_with_profiler(enable_scoring, &disjunct_profiler)
+        })?;
+        Ok(profiler.instrument(self, weight, disjunct_profiler))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for disjunct in &self.disjuncts {
+            disjunct.query_terms(visitor);
+        }
+    }
+}
+
+impl DisjunctionMaxQuery {
+    /// Builds the weight of the query, using `disjunct_weight` to build the weights of the
+    /// disjuncts.
+    fn weight_with(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        mut disjunct_weight: impl FnMut(&dyn Query) -> crate::Result<Box<dyn Weight>>,
+    ) -> crate::Result<Box<dyn Weight>> {
         let disjuncts = self
             .disjuncts
             .iter()
-            .map(|disjunct| Ok((Occur::Should, disjunct.weight(enable_scoring)?)))
+            .map(|disjunct| Ok((Occur::Should, disjunct_weight(disjunct.as_ref())?)))
             .collect::<crate::Result<_>>()?;
         let tie_breaker = self.tie_breaker;
         Ok(Box::new(BooleanWeight::new(
@@ -104,14 +136,6 @@ impl Query for DisjunctionMaxQuery {
         )))
     }
 
-    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
-        for disjunct in &self.disjuncts {
-            disjunct.query_terms(visitor);
-        }
-    }
-}
-
-impl DisjunctionMaxQuery {
     /// Creates a new `DisjunctionMaxQuery` with tie breaker.
     pub fn with_tie_breaker(
         disjuncts: Vec<Box<dyn Query>>,
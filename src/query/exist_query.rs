@@ -2,6 +2,7 @@ use core::fmt::Debug;
 
 use columnar::{ColumnIndex, DynamicColumn};
 use common::BitSet;
+use futures_util::future::BoxFuture;
 
 use super::{ConstScorer, EmptyScorer};
 use crate::docset::{DocSet, TERMINATED};
@@ -9,7 +10,8 @@ use crate::index::SegmentReader;
 use crate::query::all_query::AllScorer;
 use crate::query::boost_query::BoostScorer;
 use crate::query::explanation::does_not_match;
-use crate::query::{BitSetDocSet, EnableScoring, Explanation, Query, Scorer, Weight};
+use crate::query::matches::doc_matches;
+use crate::query::{BitSetDocSet, EnableScoring, Explanation, Matches, Query, Scorer, Weight};
 use crate::schema::Type;
 use crate::{DocId, Score, TantivyError};
 
@@ -98,6 +100,15 @@ pub struct ExistsWeight {
 }
 
 impl Weight for ExistsWeight {
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        let subpaths = self.field_type == Type::Json && self.json_subpaths;
+        Box::pin(
+            reader
+                .fast_fields()
+                .warm_fast_field(&self.field_name, subpaths),
+        )
+    }
+
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
         let fast_field_reader = reader.fast_fields();
         let mut column_handles = fast_field_reader.dynamic_column_handles(&self.field_name)?;
@@ -172,6 +183,16 @@ impl Weight for ExistsWeight {
         }
         Ok(Explanation::new("ExistsQuery", 1.0))
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        if !doc_matches(self, reader, doc)? {
+            return Ok(None);
+        }
+        Ok(Some(match reader.schema().find_field(&self.field_name) {
+            Some((field, _)) => Matches::with_field(field),
+            None => Matches::default(),
+        }))
+    }
 }
 
 pub(crate) struct ExistsDocSet {
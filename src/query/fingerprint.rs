@@ -0,0 +1,72 @@
+//! Stable hashing of queries.
+//!
+//! Query fingerprints are used as cache keys, so they rely on FNV-1a rather than on the
+//! randomly seeded hasher of the standard library.
+
+const FNV_128_OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_128_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// 128-bit FNV-1a hasher, used to compute [`Query::fingerprint`](crate::query::Query).
+pub(crate) struct FingerprintHasher {
+    state: u128,
+}
+
+impl FingerprintHasher {
+    /// Creates a hasher for a query of the given kind.
+    pub fn new(query_kind: &str) -> FingerprintHasher {
+        let mut hasher = FingerprintHasher {
+            state: FNV_128_OFFSET_BASIS,
+        };
+        hasher.write_str(query_kind);
+        hasher
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= byte as u128;
+            self.state = self.state.wrapping_mul(FNV_128_PRIME);
+        }
+    }
+
+    /// Writes a length-prefixed string, so that consecutive strings cannot collide.
+    pub fn write_str(&mut self, text: &str) {
+        self.write_u64(text.len() as u64);
+        self.write(text.as_bytes());
+    }
+
+    pub fn write_u64(&mut self, val: u64) {
+        self.write(&val.to_le_bytes());
+    }
+
+    pub fn write_u128(&mut self, val: u128) {
+        self.write(&val.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u128 {
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FingerprintHasher;
+
+    #[test]
+    fn test_fingerprint_hasher_is_stable() {
+        let mut hasher = FingerprintHasher::new("");
+        hasher.write(b"");
+        // FNV-1a of the 8 zero bytes of the length prefix.
+        let mut expected = super::FNV_128_OFFSET_BASIS;
+        for _ in 0..8 {
+            expected = expected.wrapping_mul(super::FNV_128_PRIME);
+        }
+        assert_eq!(hasher.finish(), expected);
+        let fingerprint = |kind: &str, text: &str| {
+            let mut hasher = FingerprintHasher::new(kind);
+            hasher.write_str(text);
+            hasher.finish()
+        };
+        assert_eq!(fingerprint("a", "bc"), fingerprint("a", "bc"));
+        assert_ne!(fingerprint("a", "bc"), fingerprint("ab", "c"));
+    }
+}
@@ -205,6 +205,33 @@ impl Automaton for DfaWrapper {
     }
 }
 
+/// Returns the (lazily built and shared) Levenshtein automaton builder for the given distance.
+pub(crate) fn levenshtein_automaton_builder(
+    distance: u8,
+    transposition_cost_one: bool,
+) -> crate::Result<&'static LevenshteinAutomatonBuilder> {
+    static AUTOMATON_BUILDER: [[OnceCell<LevenshteinAutomatonBuilder>; 2]; 4] = [
+        [OnceCell::new(), OnceCell::new()],
+        [OnceCell::new(), OnceCell::new()],
+        [OnceCell::new(), OnceCell::new()],
+        [OnceCell::new(), OnceCell::new()],
+    ];
+
+    let automaton_builder = AUTOMATON_BUILDER
+        .get(distance as usize)
+        .ok_or_else(|| {
+            InvalidArgument(format!(
+                "Levenshtein distance of {} is not allowed. Choose a value less than {}",
+                distance,
+                AUTOMATON_BUILDER.len()
+            ))
+        })?
+        .get(transposition_cost_one as usize)
+        .unwrap()
+        .get_or_init(|| LevenshteinAutomatonBuilder::new(distance, transposition_cost_one));
+    Ok(automaton_builder)
+}
+
 /// A Fuzzy Query matches all of the documents
 /// containing a specific term that is within
 /// Levenshtein distance
@@ -310,27 +337,8 @@ impl FuzzyTermQuery {
     }
 
     fn specialized_weight(&self) -> crate::Result<AutomatonWeight<DfaWrapper>> {
-        static AUTOMATON_BUILDER: [[OnceCell<LevenshteinAutomatonBuilder>; 2]; 4] = [
-            [OnceCell::new(), OnceCell::new()],
-            [OnceCell::new(), OnceCell::new()],
-            [OnceCell::new(), OnceCell::new()],
-            [OnceCell::new(), OnceCell::new()],
-        ];
-
-        let automaton_builder = AUTOMATON_BUILDER
-            .get(self.distance as usize)
-            .ok_or_else(|| {
-                InvalidArgument(format!(
-                    "Levenshtein distance of {} is not allowed. Choose a value less than {}",
-                    self.distance,
-                    AUTOMATON_BUILDER.len()
-                ))
-            })?
-            .get(self.transposition_cost_one as usize)
-            .unwrap()
-            .get_or_init(|| {
-                LevenshteinAutomatonBuilder::new(self.distance, self.transposition_cost_one)
-            });
+        let automaton_builder =
+            levenshtein_automaton_builder(self.distance, self.transposition_cost_one)?;
 
         let term_value = self.term.value();
 
@@ -391,27 +399,8 @@ impl FuzzyTermQuery {
             >,
         >,
     > {
-        static AUTOMATON_BUILDER: [[OnceCell<LevenshteinAutomatonBuilder>; 2]; 4] = [
-            [OnceCell::new(), OnceCell::new()],
-            [OnceCell::new(), OnceCell::new()],
-            [OnceCell::new(), OnceCell::new()],
-            [OnceCell::new(), OnceCell::new()],
-        ];
-
-        let automaton_builder = AUTOMATON_BUILDER
-            .get(self.distance as usize)
-            .ok_or_else(|| {
-                InvalidArgument(format!(
-                    "Levenshtein distance of {} is not allowed. Choose a value less than {}",
-                    self.distance,
-                    AUTOMATON_BUILDER.len()
-                ))
-            })?
-            .get(self.transposition_cost_one as usize)
-            .unwrap()
-            .get_or_init(|| {
-                LevenshteinAutomatonBuilder::new(self.distance, self.transposition_cost_one)
-            });
+        let automaton_builder =
+            levenshtein_automaton_builder(self.distance, self.transposition_cost_one)?;
 
         let term_value = self.term.value();
 
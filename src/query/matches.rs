@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::index::SegmentReader;
+use crate::postings::{Postings, TermInfo};
+use crate::query::Weight;
+use crate::schema::{Field, IndexRecordOption};
+use crate::{DocId, DocSet, InvertedIndexReader, Term};
+
+/// A span of token positions of a field, matched by a query.
+///
+/// The interval of a term query covers a single position, while the interval of a phrase
+/// query spans from its first to its last term.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MatchInterval {
+    start: u32,
+    end: u32,
+    terms: Vec<(u32, Term)>,
+}
+
+impl MatchInterval {
+    /// Creates an interval covering the given `(position, term)` pairs.
+    pub(crate) fn new(mut terms: Vec<(u32, Term)>) -> MatchInterval {
+        debug_assert!(!terms.is_empty());
+        terms.sort();
+        let start = terms.first().map(|(position, _)| *position).unwrap_or(0);
+        let end = terms.last().map(|(position, _)| *position).unwrap_or(0);
+        MatchInterval { start, end, terms }
+    }
+
+    /// Position of the first token of the interval.
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// Position of the last token of the interval (inclusive).
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    /// The terms of the interval, with their positions, sorted by position.
+    pub fn terms(&self) -> &[(u32, Term)] {
+        &self.terms
+    }
+}
+
+/// Describes where a query matches a given document.
+///
+/// `Matches` is returned by [`Weight::matches`] and [`Query::matches`](crate::query::Query::matches).
+/// For each field involved in the match, it lists the matched [`MatchInterval`]s.
+///
+/// A field may have matched without positional information, for instance for range
+/// and exists queries, or for fields indexed without positions. Such a field is listed by
+/// [`Matches::fields`] but has no interval.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Matches {
+    fields: BTreeMap<Field, Vec<MatchInterval>>,
+}
+
+impl Matches {
+    /// Creates a match on `field`, without any positional information.
+    pub(crate) fn with_field(field: Field) -> Matches {
+        let mut matches = Matches::default();
+        matches.add_field(field);
+        matches
+    }
+
+    /// Returns the fields that matched, in increasing order.
+    pub fn fields(&self) -> impl Iterator<Item = Field> + '_ {
+        self.fields.keys().copied()
+    }
+
+    /// Returns the intervals matched within `field`, sorted by position.
+    ///
+    /// Returns `None` if the field did not match, and an empty slice if the field matched
+    /// without positional information.
+    pub fn intervals(&self, field: Field) -> Option<&[MatchInterval]> {
+        self.fields.get(&field).map(Vec::as_slice)
+    }
+
+    /// Returns true if the query does not tell where the document matched,
+    /// as for an [`AllQuery`](crate::query::AllQuery).
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub(crate) fn add_field(&mut self, field: Field) {
+        self.fields.entry(field).or_default();
+    }
+
+    pub(crate) fn add_interval(&mut self, field: Field, interval: MatchInterval) {
+        let intervals = self.fields.entry(field).or_default();
+        if let Err(pos) = intervals.binary_search(&interval) {
+            intervals.insert(pos, interval);
+        }
+    }
+
+    /// Records the positions of `term` within its field.
+    pub(crate) fn add_term_positions(&mut self, term: &Term, positions: &[u32]) {
+        self.add_field(term.field());
+        for &position in positions {
+            self.add_interval(
+                term.field(),
+                MatchInterval::new(vec![(position, term.clone())]),
+            );
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: Matches) {
+        for (field, intervals) in other.fields {
+            self.add_field(field);
+            for interval in intervals {
+                self.add_interval(field, interval);
+            }
+        }
+    }
+}
+
+/// Returns true if `weight` matches `doc`.
+pub(crate) fn doc_matches<W: Weight + ?Sized>(
+    weight: &W,
+    reader: &SegmentReader,
+    doc: DocId,
+) -> crate::Result<bool> {
+    let mut scorer = weight.scorer(reader, 1.0)?;
+    Ok(scorer.doc() <= doc && scorer.seek(doc) == doc)
+}
+
+/// Returns the positions of the term associated with `term_info` within `doc`, or `None` if
+/// the term does not appear in `doc`.
+///
+/// The positions are empty if the field does not record positions.
+pub(crate) fn positions_in_doc(
+    inverted_index: &InvertedIndexReader,
+    term_info: &TermInfo,
+    doc: DocId,
+) -> io::Result<Option<Vec<u32>>> {
+    let mut postings = inverted_index
+        .read_postings_from_terminfo(term_info, IndexRecordOption::WithFreqsAndPositions)?;
+    if postings.doc() > doc || postings.seek(doc) != doc {
+        return Ok(None);
+    }
+    let mut positions = Vec::new();
+    postings.positions(&mut positions);
+    Ok(Some(positions))
+}
+
+/// Returns the positions of `term` within `doc`. See [`positions_in_doc`].
+pub(crate) fn term_positions_in_doc(
+    reader: &SegmentReader,
+    term: &Term,
+    doc: DocId,
+) -> crate::Result<Option<Vec<u32>>> {
+    let inverted_index = reader.inverted_index(term.field())?;
+    let Some(term_info) = inverted_index.get_term_info(term)? else {
+        return Ok(None);
+    };
+    Ok(positions_in_doc(&inverted_index, &term_info, doc)?)
+}
+
+/// Rebuilds the term of `field` stored under `key` in the term dictionary.
+pub(crate) fn dictionary_term(reader: &SegmentReader, field: Field, key: &[u8]) -> Term {
+    let typ = reader
+        .schema()
+        .get_field_entry(field)
+        .field_type()
+        .value_type();
+    let mut term = Term::with_type_and_field(typ, field);
+    term.append_bytes(key);
+    term
+}
+
+/// The alternative terms expected at a given offset of a phrase, with their positions in the
+/// document.
+pub(crate) type PhraseSlot = (usize, Vec<(Term, Vec<u32>)>);
+
+/// Returns the matches of a phrase on `field`, given the positions of its slots in the document.
+///
+/// Each occurrence of the first slot starts a candidate interval, which is kept if every other
+/// slot has a term within `slop` positions of where the phrase expects it.
+/// The field is recorded even if no interval is found, e.g. if it does not record positions.
+pub(crate) fn phrase_matches(field: Field, slots: &[PhraseSlot], slop: u32) -> Matches {
+    let mut matches = Matches::with_field(field);
+    let Some(((first_offset, first_terms), other_slots)) = slots.split_first() else {
+        return matches;
+    };
+    for (first_term, first_positions) in first_terms {
+        'candidates: for &first_position in first_positions {
+            let mut terms = vec![(first_position, first_term.clone())];
+            for (offset, slot_terms) in other_slots {
+                let expected = first_position as i64 + *offset as i64 - *first_offset as i64;
+                let closest = slot_terms
+                    .iter()
+                    .flat_map(|(term, positions)| positions.iter().map(move |&pos| (pos, term)))
+                    .filter(|(pos, _)| terms.iter().all(|(used, _)| used != pos))
+                    .map(|(pos, term)| ((pos as i64 - expected).unsigned_abs(), pos, term))
+                    .filter(|&(distance, _, _)| distance <= slop as u64)
+                    .min_by_key(|&(distance, pos, _)| (distance, pos));
+                let Some((_, position, term)) = closest else {
+                    continue 'candidates;
+                };
+                terms.push((position, term.clone()));
+            }
+            matches.add_interval(field, MatchInterval::new(terms));
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use super::{MatchInterval, Matches};
+    use crate::query::{
+        BooleanQuery, ExistsQuery, FuzzyTermQuery, InvertedIndexRangeQuery, Occur, PhraseQuery,
+        Query, RangeQuery, RegexQuery, TermQuery,
+    };
+    use crate::schema::{Field, IndexRecordOption, Schema, FAST, INDEXED, STRING, TEXT};
+    use crate::{DocAddress, Index, IndexWriter, Searcher, Term};
+
+    fn create_searcher() -> crate::Result<(Searcher, Field, Field, Field)> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let tag = schema_builder.add_text_field("tag", STRING);
+        let year = schema_builder.add_u64_field("year", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            title => "the old man and the sea",
+            tag => "novel",
+            year => 1952u64,
+        ))?;
+        index_writer.add_document(doc!(title => "a sea of troubles"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        Ok((searcher, title, tag, year))
+    }
+
+    fn text(field: Field, text: &str) -> Term {
+        Term::from_field_text(field, text)
+    }
+
+    fn positions(matches: &Matches, field: Field) -> Vec<(u32, u32)> {
+        matches
+            .intervals(field)
+            .unwrap()
+            .iter()
+            .map(|interval| (interval.start(), interval.end()))
+            .collect()
+    }
+
+    #[test]
+    fn test_term_query_matches() -> crate::Result<()> {
+        let (searcher, title, _, _) = create_searcher()?;
+        let query = TermQuery::new(text(title, "the"), IndexRecordOption::WithFreqsAndPositions);
+        let matches = query.matches(&searcher, DocAddress::new(0, 0))?.unwrap();
+        assert_eq!(matches.fields().collect::<Vec<_>>(), vec![title]);
+        assert_eq!(positions(&matches, title), vec![(0, 0), (4, 4)]);
+        assert_eq!(
+            matches.intervals(title).unwrap()[0].terms(),
+            &[(0, text(title, "the"))]
+        );
+        assert!(query.matches(&searcher, DocAddress::new(0, 1))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_phrase_query_matches() -> crate::Result<()> {
+        let (searcher, title, _, _) = create_searcher()?;
+        let query = PhraseQuery::new(vec![text(title, "the"), text(title, "sea")]);
+        let matches = query.matches(&searcher, DocAddress::new(0, 0))?.unwrap();
+        assert_eq!(positions(&matches, title), vec![(4, 5)]);
+        assert!(query.matches(&searcher, DocAddress::new(0, 1))?.is_none());
+
+        let mut query = PhraseQuery::new(vec![text(title, "old"), text(title, "sea")]);
+        query.set_slop(3);
+        let matches = query.matches(&searcher, DocAddress::new(0, 0))?.unwrap();
+        assert_eq!(positions(&matches, title), vec![(1, 5)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_term_query_matches() -> crate::Result<()> {
+        let (searcher, title, _, _) = create_searcher()?;
+        let fuzzy = FuzzyTermQuery::new(text(title, "see"), 1, true);
+        let matches = fuzzy.matches(&searcher, DocAddress::new(0, 1))?.unwrap();
+        assert_eq!(positions(&matches, title), vec![(1, 1)]);
+        assert_eq!(
+            matches.intervals(title).unwrap()[0],
+            MatchInterval::new(vec![(1, text(title, "sea"))])
+        );
+
+        let regex = RegexQuery::from_pattern("t.*", title)?;
+        let matches = regex.matches(&searcher, DocAddress::new(0, 1))?.unwrap();
+        assert_eq!(positions(&matches, title), vec![(3, 3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_level_matches() -> crate::Result<()> {
+        let (searcher, _, tag, year) = create_searcher()?;
+        let range = RangeQuery::new(
+            Bound::Included(Term::from_field_u64(year, 1900)),
+            Bound::Excluded(Term::from_field_u64(year, 2000)),
+            None,
+            None,
+            None,
+        );
+        let matches = range.matches(&searcher, DocAddress::new(0, 0))?.unwrap();
+        assert_eq!(matches.intervals(year), Some(&[][..]));
+        assert!(range.matches(&searcher, DocAddress::new(0, 1))?.is_none());
+
+        let range = InvertedIndexRangeQuery::new(
+            Bound::Included(text(tag, "a")),
+            Bound::Included(text(tag, "z")),
+        );
+        let matches = range.matches(&searcher, DocAddress::new(0, 0))?.unwrap();
+        assert_eq!(matches.fields().collect::<Vec<_>>(), vec![tag]);
+
+        let exists = ExistsQuery::new("year".to_string(), false);
+        let matches = exists.matches(&searcher, DocAddress::new(0, 0))?.unwrap();
+        assert_eq!(matches.fields().collect::<Vec<_>>(), vec![year]);
+        assert!(exists.matches(&searcher, DocAddress::new(0, 1))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_boolean_query_matches() -> crate::Result<()> {
+        let (searcher, title, tag, _) = create_searcher()?;
+        let term_query = |term: Term| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                term,
+                IndexRecordOption::WithFreqsAndPositions,
+            ))
+        };
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term_query(text(title, "sea"))),
+            (Occur::Should, term_query(text(tag, "novel"))),
+            (Occur::Should, term_query(text(title, "troubles"))),
+            (Occur::MustNot, term_query(text(title, "whale"))),
+        ]);
+        let matches = query.matches(&searcher, DocAddress::new(0, 0))?.unwrap();
+        assert_eq!(matches.fields().collect::<Vec<_>>(), vec![title, tag]);
+        assert_eq!(positions(&matches, title), vec![(5, 5)]);
+        let matches = query.matches(&searcher, DocAddress::new(0, 1))?.unwrap();
+        assert_eq!(positions(&matches, title), vec![(1, 1), (3, 3)]);
+
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term_query(text(title, "sea"))),
+            (Occur::MustNot, term_query(text(title, "troubles"))),
+        ]);
+        assert!(query.matches(&searcher, DocAddress::new(0, 1))?.is_none());
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@ mod bitset;
 mod bm25;
 mod boolean_query;
 mod boost_query;
+mod combined_fields_query;
 mod const_score_query;
 mod disjunction;
 mod disjunction_max_query;
@@ -11,12 +12,17 @@ mod empty_query;
 mod exclude;
 mod exist_query;
 mod explanation;
+mod fingerprint;
 mod fuzzy_query;
 mod intersection;
+mod matches;
 mod more_like_this;
+mod percolator;
 mod phrase_prefix_query;
 mod phrase_query;
+mod profile;
 mod query;
+mod query_cache;
 mod query_parser;
 mod range_query;
 mod regex_query;
@@ -24,6 +30,7 @@ mod reqopt_scorer;
 mod scorer;
 mod set_query;
 mod size_hint;
+mod suggester;
 mod term_query;
 mod union;
 mod weight;
@@ -40,6 +47,7 @@ pub use self::bitset::BitSetDocSet;
 pub use self::bm25::{Bm25StatisticsProvider, Bm25Weight};
 pub use self::boolean_query::{BooleanQuery, BooleanWeight};
 pub use self::boost_query::{BoostQuery, BoostWeight};
+pub use self::combined_fields_query::CombinedFieldsQuery;
 pub use self::const_score_query::{ConstScoreQuery, ConstScorer};
 pub use self::disjunction_max_query::DisjunctionMaxQuery;
 pub use self::empty_query::{EmptyQuery, EmptyScorer, EmptyWeight};
@@ -50,12 +58,18 @@ pub use self::explanation::Explanation;
 pub(crate) use self::fuzzy_query::DfaWrapper;
 pub use self::fuzzy_query::FuzzyTermQuery;
 pub use self::intersection::{intersect_scorers, Intersection};
+pub use self::matches::{MatchInterval, Matches};
 pub use self::more_like_this::{MoreLikeThisQuery, MoreLikeThisQueryBuilder};
+pub use self::percolator::Percolator;
 pub use self::phrase_prefix_query::PhrasePrefixQuery;
 pub use self::phrase_query::regex_phrase_query::{wildcard_query_to_regex_str, RegexPhraseQuery};
 pub use self::phrase_query::PhraseQuery;
 pub use self::phrase_query::SparsePhraSeQuery;
+pub use self::profile::{QueryProfile, QueryProfiler, SegmentProfile};
 pub use self::query::{EnableScoring, Query, QueryClone};
+pub use self::query_cache::{
+    QueryCache, DEFAULT_QUERY_CACHE_BUDGET_NUM_BYTES, DEFAULT_QUERY_CACHE_MIN_FREQUENCY,
+};
 pub use self::query_parser::{QueryParser, QueryParserError};
 pub use self::range_query::*;
 pub use self::regex_query::RegexQuery;
@@ -63,6 +77,7 @@ pub use self::reqopt_scorer::RequiredOptionalScorer;
 pub use self::score_combiner::{DisjunctionMaxCombiner, ScoreCombiner, SumCombiner, DoNothingCombiner};
 pub use self::scorer::Scorer;
 pub use self::set_query::TermSetQuery;
+pub use self::suggester::{Suggester, Suggestion};
 pub use self::term_query::TermQuery;
 pub use self::term_query::TermFilterQuery;
 pub use self::union::BufferedUnionScorer;
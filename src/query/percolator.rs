@@ -0,0 +1,478 @@
+//! Reverse search: finding the registered queries matching a document.
+//!
+//! The [`Percolator`] keeps the registered queries in an index of its own. Each query is stored
+//! in its serialized form, along with the terms extracted from it for candidate preselection:
+//! a document can only match a query if it contains at least one of these terms.
+//!
+//! Percolating a batch of documents indexes them into a temporary in-RAM segment, looks up the
+//! queries sharing a term with the batch, and runs these candidate queries against the segment.
+
+use std::collections::HashMap;
+
+use crate::collector::DocSetCollector;
+use crate::directory::{Directory, RamDirectory};
+use crate::indexer::index_writer::MEMORY_BUDGET_NUM_BYTES_MIN;
+use crate::query::{
+    BooleanQuery, BoostQuery, ConstScoreQuery, EnableScoring, Occur, PhrasePrefixQuery,
+    PhraseQuery, Query, QueryParser, TermQuery, TermSetQuery,
+};
+use crate::schema::document::{Document, Value};
+use crate::schema::{Field, IndexRecordOption, Schema, FAST, INDEXED, STORED};
+use crate::tokenizer::TokenizerManager;
+use crate::{
+    Index, IndexReader, IndexWriter, ReloadPolicy, Searcher, TantivyDocument, TantivyError, Term,
+};
+
+const ID_FIELD: &str = "id";
+const QUERY_FIELD: &str = "query";
+const TERMS_FIELD: &str = "terms";
+const ALWAYS_CANDIDATE_FIELD: &str = "always_candidate";
+
+/// Memory budget of the temporary segment the percolated documents are indexed into.
+const BATCH_MEMORY_BUDGET: usize = MEMORY_BUDGET_NUM_BYTES_MIN;
+
+struct QueriesSchema {
+    schema: Schema,
+    id: Field,
+    query: Field,
+    terms: Field,
+    always_candidate: Field,
+}
+
+impl QueriesSchema {
+    fn new() -> QueriesSchema {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_u64_field(ID_FIELD, INDEXED | FAST);
+        let query = schema_builder.add_text_field(QUERY_FIELD, STORED);
+        let terms = schema_builder.add_bytes_field(TERMS_FIELD, INDEXED);
+        let always_candidate = schema_builder.add_bool_field(ALWAYS_CANDIDATE_FIELD, INDEXED);
+        QueriesSchema {
+            schema: schema_builder.build(),
+            id,
+            query,
+            terms,
+            always_candidate,
+        }
+    }
+}
+
+/// A `Percolator` finds, for each incoming document, the registered queries matching it.
+///
+/// Queries are registered in the syntax of the [`QueryParser`] given at creation, and
+/// identified by a `u64` id. As with an [`IndexWriter`], registrations only become visible
+/// after a call to [`Percolator::commit`].
+///
+/// ```rust
+/// use tantivy::query::{Percolator, QueryParser};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+///
+/// let query_parser = QueryParser::for_index(&index, vec![title]);
+/// let mut percolator = Percolator::create_in_ram(&index, query_parser)?;
+/// percolator.register(1, "diary")?;
+/// percolator.register(2, "\"young girl\"")?;
+/// percolator.register(3, "cow AND -dairy")?;
+/// percolator.commit()?;
+///
+/// let matching_queries = percolator.percolate_batch(vec![
+///     doc!(title => "The Diary of a Young Girl"),
+///     doc!(title => "A Dairy Cow"),
+/// ])?;
+/// assert_eq!(matching_queries, vec![vec![1, 2], vec![]]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Percolator {
+    schema: Schema,
+    tokenizers: TokenizerManager,
+    fast_field_tokenizers: TokenizerManager,
+    query_parser: QueryParser,
+    queries_schema: QueriesSchema,
+    index_writer: IndexWriter,
+    index_reader: IndexReader,
+    queries: HashMap<u64, Box<dyn Query>>,
+    pending_queries: HashMap<u64, Option<Box<dyn Query>>>,
+}
+
+impl Percolator {
+    /// Creates a percolator for the documents of `index`, keeping its queries in RAM.
+    pub fn create_in_ram(index: &Index, query_parser: QueryParser) -> crate::Result<Percolator> {
+        Percolator::open_or_create(index, query_parser, RamDirectory::create())
+    }
+
+    /// Opens the percolator keeping its queries in `directory`, or creates it if the directory
+    /// is empty.
+    ///
+    /// The queries registered in a previous session are parsed again with `query_parser`.
+    pub fn open_or_create<T: Into<Box<dyn Directory>>>(
+        index: &Index,
+        query_parser: QueryParser,
+        directory: T,
+    ) -> crate::Result<Percolator> {
+        let queries_schema = QueriesSchema::new();
+        let queries_index = Index::open_or_create(directory, queries_schema.schema.clone())?;
+        let index_writer = queries_index.writer_with_num_threads(1, MEMORY_BUDGET_NUM_BYTES_MIN)?;
+        let index_reader = queries_index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let mut percolator = Percolator {
+            schema: index.schema(),
+            tokenizers: index.tokenizers().clone(),
+            fast_field_tokenizers: index.fast_field_tokenizer().clone(),
+            query_parser,
+            queries_schema,
+            index_writer,
+            index_reader,
+            queries: HashMap::new(),
+            pending_queries: HashMap::new(),
+        };
+        percolator.load_queries()?;
+        Ok(percolator)
+    }
+
+    /// Parses the queries stored in the index of the percolator.
+    fn load_queries(&mut self) -> crate::Result<()> {
+        let searcher = self.index_reader.searcher();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let ids = segment_reader.fast_fields().u64(ID_FIELD)?;
+            let store_reader = segment_reader.get_store_reader(1)?;
+            for doc in segment_reader.doc_ids_alive() {
+                let id = ids.first(doc).ok_or_else(|| {
+                    TantivyError::InternalError(format!(
+                        "percolator query without id in segment {segment_ord}"
+                    ))
+                })?;
+                let stored_query: TantivyDocument = store_reader.get(doc)?;
+                let query_str = stored_query
+                    .get_first(self.queries_schema.query)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default();
+                let query = self.query_parser.parse_query(query_str)?;
+                self.queries.insert(id, query);
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers `query` under `id`, replacing the query previously registered under this id.
+    ///
+    /// Returns an error if the query cannot be parsed.
+    pub fn register(&mut self, id: u64, query: &str) -> crate::Result<()> {
+        let parsed_query = self.query_parser.parse_query(query)?;
+        let queries_schema = &self.queries_schema;
+        let mut query_doc = TantivyDocument::default();
+        query_doc.add_u64(queries_schema.id, id);
+        query_doc.add_text(queries_schema.query, query);
+        match extract_candidate_terms(parsed_query.as_ref()) {
+            Some(terms) => {
+                for term in terms {
+                    query_doc.add_bytes(queries_schema.terms, &serialize_term(&term));
+                }
+            }
+            None => query_doc.add_bool(queries_schema.always_candidate, true),
+        }
+        self.index_writer
+            .delete_term(Term::from_field_u64(queries_schema.id, id));
+        self.index_writer.add_document(query_doc)?;
+        self.pending_queries.insert(id, Some(parsed_query));
+        Ok(())
+    }
+
+    /// Removes the query registered under `id`, if any.
+    pub fn unregister(&mut self, id: u64) {
+        self.index_writer
+            .delete_term(Term::from_field_u64(self.queries_schema.id, id));
+        self.pending_queries.insert(id, None);
+    }
+
+    /// Makes the registrations since the last commit visible to [`Percolator::percolate`].
+    pub fn commit(&mut self) -> crate::Result<()> {
+        self.index_writer.commit()?;
+        self.index_reader.reload()?;
+        for (id, query_opt) in self.pending_queries.drain() {
+            match query_opt {
+                Some(query) => self.queries.insert(id, query),
+                None => self.queries.remove(&id),
+            };
+        }
+        Ok(())
+    }
+
+    /// Returns the number of committed queries.
+    pub fn num_queries(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns the sorted ids of the queries matching `doc`.
+    pub fn percolate<D: Document>(&self, doc: D) -> crate::Result<Vec<u64>> {
+        Ok(self
+            .percolate_batch(std::iter::once(doc))?
+            .pop()
+            .unwrap_or_default())
+    }
+
+    /// Returns, for each document of `docs`, the sorted ids of the queries matching it.
+    pub fn percolate_batch<D: Document>(
+        &self,
+        docs: impl IntoIterator<Item = D>,
+    ) -> crate::Result<Vec<Vec<u64>>> {
+        let mut batch_writer = Index::builder()
+            .schema(self.schema.clone())
+            .tokenizers(self.tokenizers.clone())
+            .fast_field_tokenizers(self.fast_field_tokenizers.clone())
+            .single_segment_index_writer(RamDirectory::create(), BATCH_MEMORY_BUDGET)?;
+        let mut num_docs = 0;
+        for doc in docs {
+            batch_writer.add_document(doc)?;
+            num_docs += 1;
+        }
+        let mut matching_queries = vec![Vec::new(); num_docs];
+        if num_docs == 0 {
+            return Ok(matching_queries);
+        }
+        let batch_searcher = batch_writer.finalize()?.reader()?.searcher();
+        let batch_segment_reader = batch_searcher.segment_reader(0);
+        let enable_scoring = EnableScoring::disabled_from_searcher(&batch_searcher);
+        for id in self.candidate_query_ids(&batch_searcher)? {
+            let Some(query) = self.queries.get(&id) else {
+                // The query was registered by a more recent commit than the reader of the
+                // percolator, which is only reloaded on commit.
+                continue;
+            };
+            let weight = query.weight(enable_scoring)?;
+            weight.for_each_no_score(batch_segment_reader, &mut |docs| {
+                for &doc in docs {
+                    matching_queries[doc as usize].push(id);
+                }
+            })?;
+        }
+        for ids in &mut matching_queries {
+            ids.sort_unstable();
+        }
+        Ok(matching_queries)
+    }
+
+    /// Returns the ids of the queries sharing a term with the documents of the batch, and of
+    /// the queries no term could be extracted from.
+    fn candidate_query_ids(&self, batch_searcher: &Searcher) -> crate::Result<Vec<u64>> {
+        let batch_segment_reader = batch_searcher.segment_reader(0);
+        let mut batch_terms = Vec::new();
+        for (field, field_entry) in self.schema.fields() {
+            if !field_entry.is_indexed() {
+                continue;
+            }
+            let inverted_index = batch_segment_reader.inverted_index(field)?;
+            let mut term_stream = inverted_index.terms().stream()?;
+            while term_stream.advance() {
+                let mut serialized_term = field.field_id().to_be_bytes().to_vec();
+                serialized_term.extend_from_slice(term_stream.key());
+                batch_terms.push(Term::from_field_bytes(
+                    self.queries_schema.terms,
+                    &serialized_term,
+                ));
+            }
+        }
+        let candidates_query = BooleanQuery::union(vec![
+            Box::new(TermSetQuery::new(batch_terms)),
+            Box::new(TermQuery::new(
+                Term::from_field_bool(self.queries_schema.always_candidate, true),
+                IndexRecordOption::Basic,
+            )),
+        ]);
+        let searcher = self.index_reader.searcher();
+        let mut ids = Vec::new();
+        for doc_address in searcher.search(&candidates_query, &DocSetCollector)? {
+            let segment_reader = searcher.segment_reader(doc_address.segment_ord);
+            let id_column = segment_reader.fast_fields().u64(ID_FIELD)?;
+            ids.extend(id_column.first(doc_address.doc_id));
+        }
+        Ok(ids)
+    }
+}
+
+/// Serializes a term as its field id followed by its key in the term dictionary.
+fn serialize_term(term: &Term) -> Vec<u8> {
+    let mut serialized_term = term.field().field_id().to_be_bytes().to_vec();
+    serialized_term.extend_from_slice(term.serialized_value_bytes());
+    serialized_term
+}
+
+/// Returns terms such that every document matching `query` contains at least one of them,
+/// or `None` if no such terms can be extracted from the query.
+fn extract_candidate_terms(query: &dyn Query) -> Option<Vec<Term>> {
+    if let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() {
+        return extract_boolean_candidate_terms(boolean_query);
+    }
+    if let Some(boost_query) = query.downcast_ref::<BoostQuery>() {
+        return extract_candidate_terms(boost_query.query());
+    }
+    if let Some(const_score_query) = query.downcast_ref::<ConstScoreQuery>() {
+        return extract_candidate_terms(const_score_query.query());
+    }
+    // The documents matched by other queries, e.g. range or fuzzy queries, do not necessarily
+    // contain the terms of the query.
+    let matches_only_docs_with_terms = query.is::<TermQuery>()
+        || query.is::<TermSetQuery>()
+        || query.is::<PhraseQuery>()
+        || query.is::<PhrasePrefixQuery>();
+    if !matches_only_docs_with_terms {
+        return None;
+    }
+    let mut terms = Vec::new();
+    query.query_terms(&mut |term, _| terms.push(term.clone()));
+    if terms.is_empty() {
+        return None;
+    }
+    Some(terms)
+}
+
+fn extract_boolean_candidate_terms(boolean_query: &BooleanQuery) -> Option<Vec<Term>> {
+    let mut has_must = false;
+    let mut has_should = false;
+    // Any of the must clauses is required: we keep the one with the fewest terms.
+    let mut must_terms: Option<Vec<Term>> = None;
+    // Without must clauses, one of the should clauses is required.
+    let mut should_terms: Option<Vec<Term>> = Some(Vec::new());
+    for (occur, subquery) in boolean_query.clauses() {
+        match occur {
+            Occur::Must => {
+                has_must = true;
+                if let Some(terms) = extract_candidate_terms(subquery.as_ref()) {
+                    if must_terms
+                        .as_ref()
+                        .is_none_or(|must_terms| terms.len() < must_terms.len())
+                    {
+                        must_terms = Some(terms);
+                    }
+                }
+            }
+            Occur::Should => {
+                has_should = true;
+                should_terms = should_terms
+                    .zip(extract_candidate_terms(subquery.as_ref()))
+                    .map(|(mut terms, subquery_terms)| {
+                        terms.extend(subquery_terms);
+                        terms
+                    });
+            }
+            Occur::MustNot => {}
+        }
+    }
+    if has_must {
+        must_terms
+    } else if has_should {
+        should_terms
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_candidate_terms, Percolator};
+    use crate::directory::RamDirectory;
+    use crate::query::QueryParser;
+    use crate::schema::{Schema, INDEXED, TEXT};
+    use crate::Index;
+
+    fn test_index() -> Index {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("title", TEXT);
+        schema_builder.add_u64_field("price", INDEXED);
+        Index::create_in_ram(schema_builder.build())
+    }
+
+    #[test]
+    fn test_extract_candidate_terms() {
+        let index = test_index();
+        let title = index.schema().get_field("title").unwrap();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let candidate_terms = |query_str: &str| -> Option<Vec<String>> {
+            let query = query_parser.parse_query(query_str).unwrap();
+            extract_candidate_terms(query.as_ref()).map(|terms| {
+                let mut terms: Vec<String> = terms
+                    .iter()
+                    .map(|term| term.value().as_str().unwrap_or("?").to_string())
+                    .collect();
+                terms.sort();
+                terms
+            })
+        };
+        let terms = |terms: &[&str]| Some(terms.iter().map(|term| term.to_string()).collect());
+        assert_eq!(candidate_terms("a"), terms(&["a"]));
+        assert_eq!(candidate_terms("a^2 OR \"b c\""), terms(&["a", "b", "c"]));
+        assert_eq!(candidate_terms("+(a b c) +d -e"), terms(&["d"]));
+        assert_eq!(
+            candidate_terms("+price:[1 TO 5] +(a b)"),
+            terms(&["a", "b"])
+        );
+        assert_eq!(candidate_terms("a OR price:[1 TO 5]"), None);
+        assert_eq!(candidate_terms("+price:[1 TO 5] a"), None);
+        assert_eq!(candidate_terms("* -a"), None);
+        assert_eq!(candidate_terms("*"), None);
+    }
+
+    #[test]
+    fn test_percolator() -> crate::Result<()> {
+        let index = test_index();
+        let schema = index.schema();
+        let title = schema.get_field("title").unwrap();
+        let price = schema.get_field("price").unwrap();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let directory = RamDirectory::create();
+        let mut percolator =
+            Percolator::open_or_create(&index, query_parser.clone(), directory.clone())?;
+        percolator.register(1, "diary")?;
+        percolator.register(2, "\"young girl\"")?;
+        percolator.register(3, "cow AND -dairy")?;
+        percolator.register(4, "price:[10 TO 20]")?;
+        percolator.register(5, "* -wind")?;
+        assert!(percolator.register(6, "title:(").is_err());
+        assert!(percolator.percolate(doc!(title => "diary"))?.is_empty());
+        percolator.commit()?;
+        assert_eq!(percolator.num_queries(), 5);
+
+        let batch = || {
+            vec![
+                doc!(title => "The Diary of a Young Girl", price => 15u64),
+                doc!(title => "A Dairy Cow", price => 30u64),
+                doc!(title => "The Name of the Wind"),
+                doc!(title => "A young cow"),
+            ]
+        };
+        assert_eq!(
+            percolator.percolate_batch(batch())?,
+            vec![vec![1, 2, 4, 5], vec![5], vec![], vec![3, 5]]
+        );
+        assert!(percolator
+            .percolate_batch(Vec::<crate::TantivyDocument>::new())?
+            .is_empty());
+
+        percolator.unregister(5);
+        percolator.register(3, "cow")?;
+        // Not committed yet.
+        assert_eq!(percolator.percolate(doc!(title => "cow"))?, vec![3, 5]);
+        percolator.commit()?;
+        assert_eq!(
+            percolator.percolate_batch(batch())?,
+            vec![vec![1, 2, 4], vec![3], vec![], vec![3]]
+        );
+        assert_eq!(percolator.percolate(doc!(title => "cow"))?, vec![3]);
+
+        // The queries are persisted in the directory of the percolator.
+        drop(percolator);
+        let percolator = Percolator::open_or_create(&index, query_parser, directory)?;
+        assert_eq!(percolator.num_queries(), 4);
+        assert_eq!(
+            percolator.percolate_batch(batch())?,
+            vec![vec![1, 2, 4], vec![3], vec![], vec![3]]
+        );
+        Ok(())
+    }
+}
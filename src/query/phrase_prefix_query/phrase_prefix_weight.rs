@@ -1,10 +1,14 @@
+use futures_util::future::BoxFuture;
+use futures_util::TryFutureExt;
+
 use super::{prefix_end, PhrasePrefixScorer};
 use crate::fieldnorm::FieldNormReader;
 use crate::index::SegmentReader;
 use crate::postings::SegmentPostings;
 use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
-use crate::query::{EmptyScorer, Explanation, Scorer, Weight};
+use crate::query::matches::{phrase_matches, positions_in_doc, term_positions_in_doc, PhraseSlot};
+use crate::query::{EmptyScorer, Explanation, Matches, Scorer, Weight};
 use crate::schema::{IndexRecordOption, Term};
 use crate::{DocId, DocSet, Score};
 
@@ -42,6 +46,44 @@ impl PhrasePrefixWeight {
         Ok(FieldNormReader::constant(reader.max_doc(), 1))
     }
 
+    async fn warm_phrase_terms(&self, reader: &SegmentReader) -> crate::Result<()> {
+        let field = self.phrase_terms[0].1.field();
+        let inverted_index = reader.inverted_index(field)?;
+        let postings = futures_util::future::try_join_all(
+            self.phrase_terms
+                .iter()
+                .map(|(_, term)| inverted_index.warm_postings(term, true)),
+        );
+        if self.similarity_weight_opt.is_some() {
+            let fieldnorms = reader.fieldnorms_readers().warm_field(field);
+            futures_util::future::try_join(postings.map_err(From::from), fieldnorms).await?;
+        } else {
+            postings.await?;
+        }
+        Ok(())
+    }
+
+    /// Prefetches the postings of the terms the prefix expands to.
+    async fn warm_prefix_terms(&self, reader: &SegmentReader) -> crate::Result<()> {
+        let inv_index = reader.inverted_index(self.prefix.1.field())?;
+        let mut stream = inv_index
+            .terms()
+            .range()
+            .ge(self.prefix.1.serialized_value_bytes());
+        if let Some(end) = prefix_end(self.prefix.1.serialized_value_bytes()) {
+            stream = stream.lt(&end);
+        }
+        let mut stream = stream.into_stream()?;
+        let mut term_infos = Vec::with_capacity(self.max_expansions as usize);
+        while stream.advance() && (term_infos.len() as u32) < self.max_expansions {
+            term_infos.push(stream.value().clone());
+        }
+        inv_index
+            .warm_postings_for_term_infos(&term_infos, true)
+            .await?;
+        Ok(())
+    }
+
     pub(crate) fn phrase_scorer(
         &self,
         reader: &SegmentReader,
@@ -122,6 +164,17 @@ impl Weight for PhrasePrefixWeight {
         }
     }
 
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            futures_util::future::try_join(
+                self.warm_phrase_terms(reader),
+                self.warm_prefix_terms(reader),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
         let scorer_opt = self.phrase_scorer(reader, 1.0)?;
         if scorer_opt.is_none() {
@@ -140,6 +193,43 @@ impl Weight for PhrasePrefixWeight {
         }
         Ok(explanation)
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let Some(mut scorer) = self.phrase_scorer(reader, 1.0)? else {
+            return Ok(None);
+        };
+        if scorer.doc() > doc || scorer.seek(doc) != doc {
+            return Ok(None);
+        }
+        let mut slots: Vec<PhraseSlot> = Vec::with_capacity(self.phrase_terms.len() + 1);
+        for (offset, term) in &self.phrase_terms {
+            let positions = term_positions_in_doc(reader, term, doc)?.unwrap_or_default();
+            slots.push((*offset, vec![(term.clone(), positions)]));
+        }
+        let inv_index = reader.inverted_index(self.prefix.1.field())?;
+        let mut stream = inv_index
+            .terms()
+            .range()
+            .ge(self.prefix.1.serialized_value_bytes());
+        if let Some(end) = prefix_end(self.prefix.1.serialized_value_bytes()) {
+            stream = stream.lt(&end);
+        }
+        let mut stream = stream.into_stream()?;
+        let mut num_expansions = 0;
+        let mut suffixes = Vec::new();
+        while stream.advance() && num_expansions < self.max_expansions {
+            num_expansions += 1;
+            if let Some(positions) = positions_in_doc(&inv_index, stream.value(), doc)? {
+                let mut suffix = self.prefix.1.clone();
+                suffix.clear_with_type(suffix.typ());
+                suffix.append_bytes(stream.key());
+                suffixes.push((suffix, positions));
+            }
+        }
+        slots.push((self.prefix.0, suffixes));
+        let field = self.phrase_terms[0].1.field();
+        Ok(Some(phrase_matches(field, &slots, 0)))
+    }
 }
 
 #[cfg(test)]
@@ -1,10 +1,14 @@
+use futures_util::future::BoxFuture;
+use futures_util::TryFutureExt;
+
 use super::PhraseScorer;
 use crate::fieldnorm::FieldNormReader;
 use crate::index::SegmentReader;
 use crate::postings::SegmentPostings;
 use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
-use crate::query::{EmptyScorer, Explanation, Scorer, Weight};
+use crate::query::matches::{phrase_matches, term_positions_in_doc};
+use crate::query::{EmptyScorer, Explanation, Matches, Scorer, Weight};
 use crate::schema::{IndexRecordOption, Term};
 use crate::{DocId, DocSet, Score};
 
@@ -39,6 +43,23 @@ impl PhraseWeight {
         Ok(FieldNormReader::constant(reader.max_doc(), 1))
     }
 
+    async fn warm_phrase_terms(&self, reader: &SegmentReader) -> crate::Result<()> {
+        let field = self.phrase_terms[0].1.field();
+        let inverted_index = reader.inverted_index(field)?;
+        let postings = futures_util::future::try_join_all(
+            self.phrase_terms
+                .iter()
+                .map(|(_, term)| inverted_index.warm_postings(term, true)),
+        );
+        if self.similarity_weight_opt.is_some() {
+            let fieldnorms = reader.fieldnorms_readers().warm_field(field);
+            futures_util::future::try_join(postings.map_err(From::from), fieldnorms).await?;
+        } else {
+            postings.await?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn phrase_scorer(
         &self,
         reader: &SegmentReader,
@@ -82,6 +103,10 @@ impl Weight for PhraseWeight {
         }
     }
 
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(self.warm_phrase_terms(reader))
+    }
+
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
         let scorer_opt = self.phrase_scorer(reader, 1.0)?;
         if scorer_opt.is_none() {
@@ -100,6 +125,22 @@ impl Weight for PhraseWeight {
         }
         Ok(explanation)
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let Some(mut scorer) = self.phrase_scorer(reader, 1.0)? else {
+            return Ok(None);
+        };
+        if scorer.doc() > doc || scorer.seek(doc) != doc {
+            return Ok(None);
+        }
+        let mut slots = Vec::with_capacity(self.phrase_terms.len());
+        for (offset, term) in &self.phrase_terms {
+            let positions = term_positions_in_doc(reader, term, doc)?.unwrap_or_default();
+            slots.push((*offset, vec![(term.clone(), positions)]));
+        }
+        let field = self.phrase_terms[0].1.field();
+        Ok(Some(phrase_matches(field, &slots, self.slop)))
+    }
 }
 
 #[cfg(test)]
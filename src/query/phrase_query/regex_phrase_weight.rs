@@ -9,8 +9,11 @@ use crate::index::SegmentReader;
 use crate::postings::{LoadedPostings, Postings, SegmentPostings, TermInfo};
 use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
+use crate::query::matches::{phrase_matches, PhraseSlot};
 use crate::query::union::{BitSetPostingUnion, SimpleUnion};
-use crate::query::{AutomatonWeight, BitSetDocSet, EmptyScorer, Explanation, Scorer, Weight};
+use crate::query::{
+    AutomatonWeight, BitSetDocSet, EmptyScorer, Explanation, Matches, Scorer, Weight,
+};
 use crate::schema::{Field, IndexRecordOption};
 use crate::{DocId, DocSet, InvertedIndexReader, Score};
 
@@ -295,6 +298,24 @@ impl Weight for RegexPhraseWeight {
         }
         Ok(explanation)
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let Some(mut scorer) = self.phrase_scorer(reader, 1.0)? else {
+            return Ok(None);
+        };
+        if scorer.doc() > doc || scorer.seek(doc) != doc {
+            return Ok(None);
+        }
+        let mut slots: Vec<PhraseSlot> = Vec::with_capacity(self.phrase_terms.len());
+        for (offset, term) in &self.phrase_terms {
+            let regex = Regex::new(term)
+                .map_err(|e| crate::TantivyError::InvalidArgument(format!("Invalid regex: {e}")))?;
+            let automaton: AutomatonWeight<Regex> =
+                AutomatonWeight::new(self.field, Arc::new(regex), None, false);
+            slots.push((*offset, automaton.match_terms_in_doc(reader, doc)?));
+        }
+        Ok(Some(phrase_matches(self.field, &slots, self.slop)))
+    }
 }
 
 #[cfg(test)]
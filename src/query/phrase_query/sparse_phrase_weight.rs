@@ -4,7 +4,8 @@ use crate::index::SegmentReader;
 use crate::postings::SegmentPostings;
 use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
-use crate::query::{EmptyScorer, Explanation, Scorer, Weight};
+use crate::query::matches::term_positions_in_doc;
+use crate::query::{EmptyScorer, Explanation, Matches, Scorer, Weight};
 use crate::schema::{IndexRecordOption, Term};
 use crate::{DocId, DocSet, Score};
 
@@ -105,4 +106,20 @@ impl Weight for SparsePhraSeWeight {
         }
         Ok(explanation)
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let Some(mut scorer) = self.sparse_phrase_scorer(reader, 1.0)? else {
+            return Ok(None);
+        };
+        if scorer.doc() > doc || scorer.seek(doc) != doc {
+            return Ok(None);
+        }
+        let mut matches = Matches::default();
+        for (_, term) in &self.phrase_terms {
+            if let Some(positions) = term_positions_in_doc(reader, term, doc)? {
+                matches.add_term_positions(term, &positions);
+            }
+        }
+        Ok(Some(matches))
+    }
 }
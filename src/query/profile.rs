@@ -0,0 +1,558 @@
+//! Profiling of the execution of a query.
+//!
+//! [`Searcher::search_with_profile`](crate::Searcher::search_with_profile) instruments the
+//! [`Weight`] of each clause of the query, and records, for each segment, the time spent
+//! building its [`Scorer`] and iterating over it, along with the number of calls to the
+//! [`DocSet`] and [`Scorer`] methods.
+//!
+//! When a clause is collected by a pruning collector such as
+//! [`TopDocs`](crate::collector::TopDocs), the term scorers of its subclauses are left
+//! uninstrumented, so that block-WAND runs as it would in a regular search. Only the time spent
+//! building them is recorded. The pruning statistics of the clause then tell how many of its
+//! matching documents were actually passed to the collector.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+
+use crate::docset::DocSet;
+use crate::index::SegmentId;
+use crate::query::term_query::TermScorer;
+use crate::query::{Explanation, Matches, Query, Scorer, Weight};
+use crate::{DocId, Score, SegmentReader, TERMINATED};
+
+/// Execution statistics of a clause on a segment.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct SegmentProfile {
+    /// Id of the segment.
+    pub segment_id: String,
+    /// Time spent building the scorers, in nanoseconds.
+    pub build_scorer_nanos: u64,
+    /// Time spent iterating over the matching documents and scoring them, in nanoseconds.
+    pub collect_nanos: u64,
+    /// Number of calls to `advance`.
+    pub num_advance: u64,
+    /// Number of calls to `seek`.
+    pub num_seek: u64,
+    /// Number of calls to `score`.
+    pub num_score: u64,
+    /// Number of matching documents visited.
+    pub num_docs_matched: u64,
+    /// Number of documents matching the clause, when it was collected by a pruning collector.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_pruning_candidates: Option<u64>,
+    /// Number of documents passed to the pruning collector.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_pruning_collected: Option<u64>,
+}
+
+impl SegmentProfile {
+    /// Returns the ratio of matching documents that were skipped by block-WAND, or by the
+    /// score threshold of a pruning collector such as [`TopDocs`](crate::collector::TopDocs).
+    ///
+    /// Returns `None` if the clause was not collected by a pruning collector.
+    pub fn pruning_rate(&self) -> Option<f64> {
+        let num_candidates = self.num_pruning_candidates?;
+        let num_collected = self.num_pruning_collected?;
+        if num_candidates == 0 {
+            return Some(0.0);
+        }
+        Some(1.0 - (num_collected.min(num_candidates) as f64 / num_candidates as f64))
+    }
+}
+
+/// Profile of a clause of a query, organized as a tree that mirrors the query.
+///
+/// `.to_pretty_json()` returns a representation of the tree similar to the profile API of
+/// Elasticsearch.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueryProfile {
+    /// Type of the query, e.g. `BooleanQuery`.
+    pub query_type: String,
+    /// Debug representation of the query.
+    pub description: String,
+    /// Total time spent in this clause and its children, in nanoseconds.
+    pub time_nanos: u64,
+    /// Statistics of the clause on each segment, sorted by segment id.
+    pub segments: Vec<SegmentProfile>,
+    /// Profiles of the subqueries.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<QueryProfile>,
+}
+
+impl QueryProfile {
+    /// Returns an indented json representation of the profile.
+    pub fn to_pretty_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+struct ProfileNode {
+    query_type: String,
+    description: String,
+    segments: Mutex<BTreeMap<SegmentId, SegmentProfile>>,
+    children: Vec<Arc<ProfileNode>>,
+}
+
+impl ProfileNode {
+    fn record(&self, segment_id: SegmentId, update: impl FnOnce(&mut SegmentProfile)) {
+        if PROFILING_STATE.with(|state| state.get()) == ProfilingState::Paused {
+            return;
+        }
+        let mut segments = self.segments.lock().unwrap();
+        let segment_profile = segments
+            .entry(segment_id)
+            .or_insert_with(|| SegmentProfile {
+                segment_id: segment_id.uuid_string(),
+                ..Default::default()
+            });
+        update(segment_profile);
+    }
+
+    fn to_profile(&self) -> QueryProfile {
+        let segments: Vec<SegmentProfile> =
+            self.segments.lock().unwrap().values().cloned().collect();
+        let time_nanos = segments
+            .iter()
+            .map(|segment| segment.build_scorer_nanos + segment.collect_nanos)
+            .sum();
+        QueryProfile {
+            query_type: self.query_type.clone(),
+            description: self.description.clone(),
+            time_nanos,
+            segments,
+            children: self
+                .children
+                .iter()
+                .map(|child| child.to_profile())
+                .collect(),
+        }
+    }
+}
+
+/// Collects the profiled weights of the clauses of a query.
+///
+/// See [`Query::weight_with_profiler`].
+#[derive(Default)]
+pub struct QueryProfiler {
+    nodes: Mutex<Vec<Arc<ProfileNode>>>,
+}
+
+impl QueryProfiler {
+    /// Instruments the weight of `query`.
+    ///
+    /// `children` is the profiler that was passed to the subqueries of `query`, if any.
+    pub fn instrument<Q: Query + ?Sized>(
+        &self,
+        query: &Q,
+        weight: Box<dyn Weight>,
+        children: QueryProfiler,
+    ) -> Box<dyn Weight> {
+        let node = Arc::new(ProfileNode {
+            query_type: query_type_name::<Q>().to_string(),
+            description: format!("{query:?}"),
+            segments: Mutex::default(),
+            children: children.nodes.into_inner().unwrap(),
+        });
+        self.nodes.lock().unwrap().push(node.clone());
+        Box::new(ProfileWeight { weight, node })
+    }
+
+    /// Returns the profile of the query instrumented with this profiler.
+    pub(crate) fn into_profile(self) -> Option<QueryProfile> {
+        let nodes = self.nodes.into_inner().unwrap();
+        nodes.first().map(|node| node.to_profile())
+    }
+}
+
+/// Returns the name of the type, without its module path and generic parameters.
+fn query_type_name<Q: ?Sized>() -> &'static str {
+    let type_name = std::any::type_name::<Q>();
+    let type_name = type_name.split('<').next().unwrap_or(type_name);
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ProfilingState {
+    /// Every scorer is instrumented.
+    Recording,
+    /// A clause is being collected by a pruning collector: term scorers are not instrumented,
+    /// so that their parent can run block-WAND on them.
+    Pruning,
+    /// Nothing is recorded, e.g. while counting the candidates of a pruning collector.
+    Paused,
+}
+
+thread_local! {
+    static PROFILING_STATE: Cell<ProfilingState> = const { Cell::new(ProfilingState::Recording) };
+}
+
+/// Runs `f` with the profiling state of the current thread set to `state`.
+fn with_profiling_state<T>(state: ProfilingState, f: impl FnOnce() -> T) -> T {
+    let previous_state = PROFILING_STATE.with(|cell| cell.replace(state));
+    // Restores the previous state even if `f` panics.
+    struct RestoreState(ProfilingState);
+    impl Drop for RestoreState {
+        fn drop(&mut self) {
+            PROFILING_STATE.with(|cell| cell.set(self.0));
+        }
+    }
+    let _restore_state = RestoreState(previous_state);
+    f()
+}
+
+fn elapsed_nanos(start: Instant) -> u64 {
+    duration_nanos(start.elapsed())
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_nanos() as u64
+}
+
+struct ProfileWeight {
+    weight: Box<dyn Weight>,
+    node: Arc<ProfileNode>,
+}
+
+impl Weight for ProfileWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let start = Instant::now();
+        let scorer = self.weight.scorer(reader, boost)?;
+        let build_scorer_nanos = elapsed_nanos(start);
+        self.node.record(reader.segment_id(), |segment_profile| {
+            segment_profile.build_scorer_nanos += build_scorer_nanos;
+        });
+        let is_left_uninstrumented = match PROFILING_STATE.with(|state| state.get()) {
+            ProfilingState::Recording => false,
+            ProfilingState::Pruning => scorer.is::<TermScorer>(),
+            ProfilingState::Paused => true,
+        };
+        if is_left_uninstrumented {
+            return Ok(scorer);
+        }
+        Ok(Box::new(ProfileScorer::new(
+            scorer,
+            self.node.clone(),
+            reader.segment_id(),
+        )))
+    }
+
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        self.weight.warmup(reader)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        self.weight.explain(reader, doc)
+    }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        let start = Instant::now();
+        let count = self.weight.count(reader)?;
+        let collect_nanos = elapsed_nanos(start);
+        self.node.record(reader.segment_id(), |segment_profile| {
+            segment_profile.collect_nanos += collect_nanos;
+            segment_profile.num_docs_matched += count as u64;
+        });
+        Ok(count)
+    }
+
+    fn for_each(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId, Score),
+    ) -> crate::Result<()> {
+        let mut num_docs = 0u64;
+        let start = Instant::now();
+        self.weight.for_each(reader, &mut |doc, score| {
+            num_docs += 1;
+            callback(doc, score);
+        })?;
+        let collect_nanos = elapsed_nanos(start);
+        self.node.record(reader.segment_id(), |segment_profile| {
+            segment_profile.collect_nanos += collect_nanos;
+            segment_profile.num_docs_matched += num_docs;
+        });
+        Ok(())
+    }
+
+    fn for_each_no_score(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(&[DocId]),
+    ) -> crate::Result<()> {
+        let mut num_docs = 0u64;
+        let start = Instant::now();
+        self.weight.for_each_no_score(reader, &mut |docs| {
+            num_docs += docs.len() as u64;
+            callback(docs);
+        })?;
+        let collect_nanos = elapsed_nanos(start);
+        self.node.record(reader.segment_id(), |segment_profile| {
+            segment_profile.collect_nanos += collect_nanos;
+            segment_profile.num_docs_matched += num_docs;
+        });
+        Ok(())
+    }
+
+    fn for_each_pruning(
+        &self,
+        threshold: Score,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId, Score) -> Score,
+    ) -> crate::Result<()> {
+        if PROFILING_STATE.with(|state| state.get()) == ProfilingState::Paused {
+            return self.weight.for_each_pruning(threshold, reader, callback);
+        }
+        let mut num_collected = 0u64;
+        let start = Instant::now();
+        with_profiling_state(ProfilingState::Pruning, || {
+            self.weight
+                .for_each_pruning(threshold, reader, &mut |doc, score| {
+                    num_collected += 1;
+                    callback(doc, score)
+                })
+        })?;
+        let collect_nanos = elapsed_nanos(start);
+        // The exact number of matching documents is computed outside of the timed section.
+        let num_candidates =
+            with_profiling_state(ProfilingState::Paused, || self.weight.count(reader))? as u64;
+        self.node.record(reader.segment_id(), |segment_profile| {
+            segment_profile.collect_nanos += collect_nanos;
+            segment_profile.num_docs_matched += num_collected;
+            *segment_profile.num_pruning_candidates.get_or_insert(0) += num_candidates;
+            *segment_profile.num_pruning_collected.get_or_insert(0) += num_collected;
+        });
+        Ok(())
+    }
+}
+
+/// Counts the calls to a scorer, and flushes them to its profile node when dropped.
+struct ProfileScorer {
+    scorer: Box<dyn Scorer>,
+    node: Arc<ProfileNode>,
+    segment_id: SegmentId,
+    stats: SegmentProfile,
+    elapsed: Duration,
+    // The document the scorer is initially positioned on is only counted once the scorer is
+    // used, so that scorers built just to get their size hint are not counted.
+    is_used: bool,
+}
+
+impl ProfileScorer {
+    fn new(scorer: Box<dyn Scorer>, node: Arc<ProfileNode>, segment_id: SegmentId) -> Self {
+        ProfileScorer {
+            scorer,
+            node,
+            segment_id,
+            stats: SegmentProfile::default(),
+            elapsed: Duration::ZERO,
+            is_used: false,
+        }
+    }
+
+    fn mark_used(&mut self) {
+        if !self.is_used {
+            self.is_used = true;
+            if self.scorer.doc() != TERMINATED {
+                self.stats.num_docs_matched += 1;
+            }
+        }
+    }
+
+    #[inline]
+    fn timed<T>(&mut self, f: impl FnOnce(&mut Box<dyn Scorer>) -> T) -> T {
+        let start = Instant::now();
+        let res = f(&mut self.scorer);
+        self.elapsed += start.elapsed();
+        res
+    }
+
+    fn on_doc_changed(&mut self, previous_doc: DocId, doc: DocId) {
+        if doc != previous_doc && doc != TERMINATED {
+            self.stats.num_docs_matched += 1;
+        }
+    }
+}
+
+impl DocSet for ProfileScorer {
+    fn advance(&mut self) -> DocId {
+        self.mark_used();
+        self.stats.num_advance += 1;
+        let previous_doc = self.scorer.doc();
+        let doc = self.timed(|scorer| scorer.advance());
+        self.on_doc_changed(previous_doc, doc);
+        doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.mark_used();
+        self.stats.num_seek += 1;
+        let previous_doc = self.scorer.doc();
+        let doc = self.timed(|scorer| scorer.seek(target));
+        self.on_doc_changed(previous_doc, doc);
+        doc
+    }
+
+    fn seek_into_the_danger_zone(&mut self, target: DocId) -> bool {
+        self.mark_used();
+        self.stats.num_seek += 1;
+        let previous_doc = self.scorer.doc();
+        let is_match = self.timed(|scorer| scorer.seek_into_the_danger_zone(target));
+        if is_match {
+            self.on_doc_changed(previous_doc, target);
+        }
+        is_match
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+
+    fn cost(&self) -> u64 {
+        self.scorer.cost()
+    }
+}
+
+impl Scorer for ProfileScorer {
+    fn score(&mut self) -> Score {
+        self.mark_used();
+        self.stats.num_score += 1;
+        self.timed(|scorer| scorer.score())
+    }
+}
+
+impl Drop for ProfileScorer {
+    fn drop(&mut self) {
+        let stats = &self.stats;
+        let collect_nanos = duration_nanos(self.elapsed);
+        self.node.record(self.segment_id, |segment_profile| {
+            segment_profile.collect_nanos += collect_nanos;
+            segment_profile.num_advance += stats.num_advance;
+            segment_profile.num_seek += stats.num_seek;
+            segment_profile.num_score += stats.num_score;
+            segment_profile.num_docs_matched += stats.num_docs_matched;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::query_type_name;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{BooleanQuery, ColumnRangeQuery, Occur, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{Index, IndexWriter, Term};
+
+    #[test]
+    fn test_query_type_name() {
+        assert_eq!(query_type_name::<BooleanQuery>(), "BooleanQuery");
+        assert_eq!(
+            query_type_name::<ColumnRangeQuery<u64>>(),
+            "ColumnRangeQuery"
+        );
+    }
+
+    #[test]
+    fn test_search_with_profile() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        index_writer.add_document(doc!(text => "a c"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text => "b c"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let term_query = |text_value: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text, text_value),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term_query("a")),
+            (Occur::Should, term_query("b")),
+        ]);
+        let (top_docs, profile) =
+            searcher.search_with_profile(&query, &TopDocs::with_limit(10).order_by_score())?;
+        assert_eq!(
+            top_docs,
+            searcher.search(&query, &TopDocs::with_limit(10).order_by_score())?
+        );
+        assert_eq!(profile.query_type, "BooleanQuery");
+        assert_eq!(profile.segments.len(), 2);
+        assert_eq!(profile.children.len(), 2);
+        assert_eq!(profile.children[0].query_type, "TermQuery");
+
+        let json: serde_json::Value = serde_json::from_str(&profile.to_pretty_json())?;
+        assert_eq!(
+            json["children"][1]["description"],
+            format!("{:?}", term_query("b"))
+        );
+
+        assert!(profile.segments.iter().all(|segment| {
+            segment.num_pruning_candidates.is_some() && segment.pruning_rate().is_some()
+        }));
+
+        let (count, profile) = searcher.search_with_profile(&query, &Count)?;
+        assert_eq!(count, 2);
+        assert!(profile
+            .children
+            .iter()
+            .all(|child| child.segments.len() == 2));
+        let num_docs_matched: u64 = profile.children[0]
+            .segments
+            .iter()
+            .map(|segment| segment.num_docs_matched)
+            .sum();
+        assert_eq!(num_docs_matched, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_pruning_rate() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b"))?;
+        for _ in 0..10_000 {
+            index_writer.add_document(doc!(text => "a c"))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let query = BooleanQuery::new_multiterms_query(
+            ["a", "b"]
+                .into_iter()
+                .map(|text_value| Term::from_field_text(text, text_value))
+                .collect(),
+        );
+        let collector = TopDocs::with_limit(1).order_by_score();
+        let (top_docs, profile) = searcher.search_with_profile(&query, &collector)?;
+        assert_eq!(top_docs, searcher.search(&query, &collector)?);
+        let segment_profile = &profile.segments[0];
+        assert_eq!(segment_profile.num_pruning_candidates, Some(10_001));
+        assert!(segment_profile.pruning_rate().unwrap() > 0.9);
+        // The term scorers are handed to block-WAND as is.
+        assert!(profile
+            .children
+            .iter()
+            .all(|child| child.segments[0].num_advance == 0));
+        Ok(())
+    }
+}
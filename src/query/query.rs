@@ -3,9 +3,11 @@ use std::fmt;
 use downcast_rs::impl_downcast;
 
 use super::bm25::Bm25StatisticsProvider;
+use super::fingerprint::FingerprintHasher;
+use super::profile::QueryProfiler;
 use super::Weight;
 use crate::core::searcher::Searcher;
-use crate::query::Explanation;
+use crate::query::{Explanation, Matches};
 use crate::schema::Schema;
 use crate::{DocAddress, Term};
 
@@ -141,6 +143,19 @@ pub trait Query: QueryClone + Send + Sync + downcast_rs::Downcast + fmt::Debug {
         weight.explain(reader, doc_address.doc_id)
     }
 
+    /// Returns where the query matches the document, or `None` if it does not match.
+    ///
+    /// See [`Weight::matches`].
+    fn matches(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
+    ) -> crate::Result<Option<Matches>> {
+        let weight = self.weight(EnableScoring::disabled_from_searcher(searcher))?;
+        let reader = searcher.segment_reader(doc_address.segment_ord);
+        weight.matches(reader, doc_address.doc_id)
+    }
+
     /// Returns the number of documents matching the query.
     fn count(&self, searcher: &Searcher) -> crate::Result<usize> {
         let weight = self.weight(EnableScoring::disabled_from_searcher(searcher))?;
@@ -160,6 +175,44 @@ pub trait Query: QueryClone + Send + Sync + downcast_rs::Downcast + fmt::Debug {
     /// Note that there can be multiple instances of any given term
     /// in a query and deduplication must be handled by the visitor.
     fn query_terms<'a>(&'a self, _visitor: &mut dyn FnMut(&'a Term, bool)) {}
+
+    /// Returns the weight of the query, instrumented to record its execution statistics in
+    /// `profiler`.
+    ///
+    /// Queries made of subqueries override this method, so that the weight of each of their
+    /// subqueries is instrumented too. See [`Searcher::search_with_profile`].
+    fn weight_with_profiler(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        profiler: &QueryProfiler,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let weight = self.weight(enable_scoring)?;
+        Ok(profiler.instrument(self, weight, QueryProfiler::default()))
+    }
+
+    /// Rewrites the query into a simpler equivalent query.
+    ///
+    /// Rewriting flattens nested boolean queries, removes the clauses that have no effect,
+    /// and turns the subtrees that do not contribute to the score into constant score queries.
+    /// The rewritten query matches the same documents as the original query.
+    ///
+    /// The default implementation returns a copy of the query.
+    fn rewrite(&self, _searcher: &Searcher) -> crate::Result<Box<dyn Query>> {
+        Ok(self.box_clone())
+    }
+
+    /// Returns a 128-bit hash of the query, for use as a cache key.
+    ///
+    /// Two identical queries have the same fingerprint, and it does not depend on the order of
+    /// the clauses of a boolean query. The fingerprint is stable across processes for a given
+    /// version of tantivy. It is usually computed on the [rewritten](Query::rewrite) query.
+    ///
+    /// The default implementation hashes the `Debug` representation of the query.
+    fn fingerprint(&self) -> u128 {
+        let mut hasher = FingerprintHasher::new("");
+        hasher.write_str(&format!("{self:?}"));
+        hasher.finish()
+    }
 }
 
 /// Implements `box_clone`.
@@ -188,6 +241,22 @@ impl Query for Box<dyn Query> {
     fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
         self.as_ref().query_terms(visitor);
     }
+
+    fn weight_with_profiler(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+        profiler: &QueryProfiler,
+    ) -> crate::Result<Box<dyn Weight>> {
+        self.as_ref().weight_with_profiler(enable_scoring, profiler)
+    }
+
+    fn rewrite(&self, searcher: &Searcher) -> crate::Result<Box<dyn Query>> {
+        self.as_ref().rewrite(searcher)
+    }
+
+    fn fingerprint(&self) -> u128 {
+        self.as_ref().fingerprint()
+    }
 }
 
 impl QueryClone for Box<dyn Query> {
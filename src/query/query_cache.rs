@@ -0,0 +1,457 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use common::BitSet;
+use futures_util::future::BoxFuture;
+use lru::LruCache;
+
+use crate::index::SegmentId;
+use crate::query::{BitSetDocSet, ConstScorer, Explanation, Matches, Query, Scorer, Weight};
+use crate::store::CacheStats;
+use crate::{DocId, Opstamp, Score, Searcher, SearcherGeneration, SegmentReader, Warmer};
+
+/// Default memory budget of a [`QueryCache`]: 64MB.
+pub const DEFAULT_QUERY_CACHE_BUDGET_NUM_BYTES: usize = 64_000_000;
+
+/// Default number of uses of a filter on a segment before it gets cached.
+pub const DEFAULT_QUERY_CACHE_MIN_FREQUENCY: u32 = 2;
+
+/// Number of recently used filters for which the usage count is tracked.
+const USAGE_HISTORY_CAPACITY: usize = 1_024;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct QueryCacheKey {
+    segment_id: SegmentId,
+    delete_opstamp: Option<Opstamp>,
+    query_fingerprint: u128,
+    /// Runtime fields may be redefined under the same name.
+    runtime_fields_generation: u64,
+}
+
+fn bitset_num_bytes(bitset: &BitSet) -> usize {
+    (bitset.max_value() as usize).div_ceil(64) * 8
+}
+
+struct QueryCacheInner {
+    entries: LruCache<QueryCacheKey, Arc<BitSet>>,
+    usage_history: LruCache<QueryCacheKey, u32>,
+    num_bytes: usize,
+}
+
+/// Per-segment cache of the documents matching non-scoring queries.
+///
+/// Segments are immutable, so the set of documents matching a filter such as
+/// `tenant_id:42 AND status:active` on a segment only changes with its deletes.
+/// Once attached to an [`IndexReader`](crate::IndexReader) via
+/// [`IndexReaderBuilder::query_cache`](crate::IndexReaderBuilder::query_cache), the cache
+/// is used transparently for the non-scoring clauses of a
+/// [`BooleanQuery`](crate::query::BooleanQuery) and for the query wrapped in a
+/// [`ConstScoreQuery`](crate::query::ConstScoreQuery).
+///
+/// A filter is only cached on a segment after it was used `min_frequency` times on it.
+/// The least recently used entries are evicted once the memory budget is exceeded, and the
+/// entries of the segments that are not part of any live searcher generation are dropped.
+/// Registering or unregistering a runtime field invalidates all of the entries.
+pub struct QueryCache {
+    inner: Mutex<QueryCacheInner>,
+    budget_num_bytes: usize,
+    min_frequency: u32,
+    cache_hits: AtomicUsize,
+    cache_misses: AtomicUsize,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        QueryCache::new(
+            DEFAULT_QUERY_CACHE_BUDGET_NUM_BYTES,
+            DEFAULT_QUERY_CACHE_MIN_FREQUENCY,
+        )
+    }
+}
+
+impl QueryCache {
+    /// Creates a query cache with the given memory budget, caching the filters used at least
+    /// `min_frequency` times on a segment.
+    pub fn new(budget_num_bytes: usize, min_frequency: u32) -> QueryCache {
+        QueryCache {
+            inner: Mutex::new(QueryCacheInner {
+                entries: LruCache::unbounded(),
+                usage_history: LruCache::new(NonZeroUsize::new(USAGE_HISTORY_CAPACITY).unwrap()),
+                num_bytes: 0,
+            }),
+            budget_num_bytes,
+            min_frequency,
+            cache_hits: AtomicUsize::new(0),
+            cache_misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of entries, hits and misses of the cache.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            num_entries: self.inner.lock().unwrap().entries.len(),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the memory used by the cached bitsets.
+    pub fn num_bytes(&self) -> usize {
+        self.inner.lock().unwrap().num_bytes
+    }
+
+    /// Removes all of the entries of the cache.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.usage_history.clear();
+        inner.num_bytes = 0;
+    }
+
+    /// Returns the cached bitset, or computes it with `compute_bitset` if the filter was used
+    /// frequently enough on the segment.
+    ///
+    /// Returns `None` if the filter should not be cached, or if `compute_bitset` returns `None`.
+    fn get_or_compute(
+        &self,
+        key: QueryCacheKey,
+        compute_bitset: impl FnOnce() -> crate::Result<Option<BitSet>>,
+    ) -> crate::Result<Option<Arc<BitSet>>> {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(bitset) = inner.entries.get(&key) {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(Some(bitset.clone()));
+            }
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+            let usage_count = inner.usage_history.get_or_insert_mut(key.clone(), || 0);
+            *usage_count += 1;
+            if *usage_count < self.min_frequency {
+                return Ok(None);
+            }
+        }
+        let Some(bitset) = compute_bitset()? else {
+            return Ok(None);
+        };
+        let num_bytes = bitset_num_bytes(&bitset);
+        if num_bytes > self.budget_num_bytes {
+            return Ok(None);
+        }
+        let bitset = Arc::new(bitset);
+        let mut inner = self.inner.lock().unwrap();
+        inner.usage_history.pop(&key);
+        if let Some(previous_bitset) = inner.entries.put(key, bitset.clone()) {
+            inner.num_bytes -= bitset_num_bytes(&previous_bitset);
+        }
+        inner.num_bytes += num_bytes;
+        while inner.num_bytes > self.budget_num_bytes {
+            let Some((_, evicted_bitset)) = inner.entries.pop_lru() else {
+                break;
+            };
+            inner.num_bytes -= bitset_num_bytes(&evicted_bitset);
+        }
+        Ok(Some(bitset))
+    }
+}
+
+impl Warmer for QueryCache {
+    fn warm(&self, _searcher: &Searcher) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn garbage_collect(&self, live_generations: &[&SearcherGeneration]) {
+        let is_live = |key: &QueryCacheKey| {
+            live_generations.iter().any(|generation| {
+                generation.segments().get(&key.segment_id) == Some(&key.delete_opstamp)
+            })
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let dead_keys: Vec<QueryCacheKey> = inner
+            .entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| !is_live(key))
+            .cloned()
+            .collect();
+        for key in dead_keys {
+            if let Some(evicted_bitset) = inner.entries.pop(&key) {
+                inner.num_bytes -= bitset_num_bytes(&evicted_bitset);
+            }
+        }
+        let dead_keys: Vec<QueryCacheKey> = inner
+            .usage_history
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| !is_live(key))
+            .cloned()
+            .collect();
+        for key in dead_keys {
+            inner.usage_history.pop(&key);
+        }
+    }
+}
+
+/// Wraps the weight of a non-scoring query, to serve its documents from the [`QueryCache`] of
+/// the segment reader, if any.
+pub(crate) struct CachingWeight {
+    weight: Box<dyn Weight>,
+    query_fingerprint: u128,
+}
+
+impl CachingWeight {
+    /// Wraps the weight of `query`, unless caching it would not help.
+    pub(crate) fn wrap(query: &dyn Query, weight: Box<dyn Weight>) -> Box<dyn Weight> {
+        if query.is::<crate::query::AllQuery>() || query.is::<crate::query::EmptyQuery>() {
+            return weight;
+        }
+        Box::new(CachingWeight {
+            weight,
+            query_fingerprint: query.fingerprint(),
+        })
+    }
+
+    fn cached_bitset(&self, reader: &SegmentReader) -> crate::Result<Option<Arc<BitSet>>> {
+        let Some(query_cache) = reader.query_cache() else {
+            return Ok(None);
+        };
+        let key = QueryCacheKey {
+            segment_id: reader.segment_id(),
+            delete_opstamp: reader.delete_opstamp(),
+            query_fingerprint: self.query_fingerprint,
+            runtime_fields_generation: reader.fast_fields().runtime_fields_generation(),
+        };
+        query_cache.get_or_compute(key, || {
+            let mut bitset = BitSet::with_max_value(reader.max_doc());
+            self.weight.for_each_no_score(reader, &mut |docs| {
+                for &doc in docs {
+                    bitset.insert(doc);
+                }
+            })?;
+            // The bitset of an interrupted search is incomplete.
+            if reader.search_context().timed_out() {
+                return Ok(None);
+            }
+            Ok(Some(bitset))
+        })
+    }
+}
+
+impl Weight for CachingWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if let Some(bitset) = self.cached_bitset(reader)? {
+            let docset = BitSetDocSet::from(bitset);
+            return Ok(Box::new(ConstScorer::new(docset, boost)));
+        }
+        self.weight.scorer(reader, boost)
+    }
+
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        self.weight.warmup(reader)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        self.weight.explain(reader, doc)
+    }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+    use std::sync::Arc;
+
+    use super::{QueryCache, DEFAULT_QUERY_CACHE_BUDGET_NUM_BYTES};
+    use crate::collector::Count;
+    use crate::fastfield::{RuntimeField, RuntimeFieldType};
+    use crate::indexer::NoMergePolicy;
+    use crate::query::{BooleanQuery, ColumnRangeQuery, ConstScoreQuery, Occur, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, STRING};
+    use crate::{Index, IndexWriter, ReloadPolicy, Term, Warmer};
+
+    #[test]
+    fn test_query_cache() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tenant = schema_builder.add_text_field("tenant", STRING);
+        let status = schema_builder.add_text_field("status", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query_cache = Arc::new(QueryCache::default());
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .query_cache(query_cache.clone())
+            .try_into()?;
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(tenant => "42", status => "active"))?;
+        index_writer.add_document(doc!(tenant => "42", status => "closed"))?;
+        index_writer.add_document(doc!(tenant => "43", status => "active"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+
+        let term_query = |field, text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let filter = ConstScoreQuery::new(
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, term_query(tenant, "42")),
+                (Occur::Must, term_query(status, "active")),
+            ])),
+            1.0,
+        );
+        assert_eq!(reader.searcher().search(&filter, &Count)?, 1);
+        assert_eq!(query_cache.stats().num_entries, 0);
+        assert_eq!(reader.searcher().search(&filter, &Count)?, 1);
+        assert_eq!(query_cache.stats().num_entries, 1);
+        assert_eq!(reader.searcher().search(&filter, &Count)?, 1);
+        assert_eq!(query_cache.stats().cache_hits, 1);
+        assert!(query_cache.num_bytes() > 0);
+
+        // Non-scoring clauses are cached too, except for term queries.
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term_query(status, "active")),
+            (
+                Occur::MustNot,
+                Box::new(BooleanQuery::new(vec![
+                    (Occur::Should, term_query(tenant, "43")),
+                    (Occur::Should, term_query(tenant, "44")),
+                ])),
+            ),
+        ]);
+        for _ in 0..3 {
+            assert_eq!(reader.searcher().search(&query, &Count)?, 1);
+        }
+        assert_eq!(query_cache.stats().num_entries, 2);
+
+        // Deletes change the key of the segment.
+        index_writer.delete_term(Term::from_field_text(status, "closed"));
+        index_writer.add_document(doc!(tenant => "42", status => "active"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(reader.searcher().search(&filter, &Count)?, 2);
+        assert_eq!(reader.searcher().search(&filter, &Count)?, 2);
+
+        query_cache.clear();
+        assert_eq!(query_cache.num_bytes(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache_budget() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tenant = schema_builder.add_text_field("tenant", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for i in 0..1_000u64 {
+            index_writer.add_document(doc!(tenant => (i % 10).to_string()))?;
+        }
+        index_writer.commit()?;
+        // Enough room for two bitsets of 1000 docs.
+        let query_cache = Arc::new(QueryCache::new(300, 1));
+        let reader = index
+            .reader_builder()
+            .query_cache(query_cache.clone())
+            .try_into()?;
+        let searcher = reader.searcher();
+        for i in 0..10u64 {
+            let filter = ConstScoreQuery::new(
+                Box::new(TermQuery::new(
+                    Term::from_field_text(tenant, &i.to_string()),
+                    IndexRecordOption::Basic,
+                )),
+                1.0,
+            );
+            assert_eq!(searcher.search(&filter, &Count)?, 100);
+        }
+        assert_eq!(query_cache.stats().num_entries, 2);
+        assert!(query_cache.num_bytes() <= 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache_runtime_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let price = schema_builder.add_u64_field("price", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for price_value in [1u64, 2, 3] {
+            index_writer.add_document(doc!(price => price_value))?;
+        }
+        index_writer.commit()?;
+        let query_cache = Arc::new(QueryCache::new(DEFAULT_QUERY_CACHE_BUDGET_NUM_BYTES, 1));
+        let reader = index
+            .reader_builder()
+            .query_cache(query_cache.clone())
+            .try_into()?;
+        let searcher = reader.searcher();
+        let filter = ConstScoreQuery::new(
+            Box::new(ColumnRangeQuery::new(
+                "doubled",
+                Bound::Included(3u64),
+                Bound::Unbounded,
+            )),
+            1.0,
+        );
+
+        index.runtime_fields().register(
+            "doubled",
+            RuntimeField::from_expression("price * 2", RuntimeFieldType::U64)?,
+        );
+        assert_eq!(searcher.search(&filter, &Count)?, 2);
+        assert_eq!(searcher.search(&filter, &Count)?, 2);
+        assert_eq!(query_cache.stats().cache_hits, 1);
+        // The bitset computed with the previous definition is not reused.
+        index.runtime_fields().register(
+            "doubled",
+            RuntimeField::from_expression("price * 3", RuntimeFieldType::U64)?,
+        );
+        assert_eq!(searcher.search(&filter, &Count)?, 3);
+        assert_eq!(query_cache.stats().cache_hits, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache_garbage_collect() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tenant = schema_builder.add_text_field("tenant", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query_cache = Arc::new(QueryCache::new(DEFAULT_QUERY_CACHE_BUDGET_NUM_BYTES, 1));
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .query_cache(query_cache.clone())
+            .try_into()?;
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(tenant => "42"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(tenant => "42"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        let filter = ConstScoreQuery::new(
+            Box::new(TermQuery::new(
+                Term::from_field_text(tenant, "42"),
+                IndexRecordOption::Basic,
+            )),
+            1.0,
+        );
+        assert_eq!(reader.searcher().search(&filter, &Count)?, 2);
+        assert_eq!(query_cache.stats().num_entries, 2);
+
+        let segment_ids = index.searchable_segment_ids()?;
+        index_writer.merge(&segment_ids).wait()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.search(&filter, &Count)?, 2);
+        assert_eq!(query_cache.stats().num_entries, 3);
+        // The entries of the merged segments are dropped.
+        query_cache.garbage_collect(&[searcher.generation()]);
+        assert_eq!(query_cache.stats().num_entries, 1);
+        Ok(())
+    }
+}
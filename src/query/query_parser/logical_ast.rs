@@ -4,6 +4,7 @@ use std::sync::Arc;
 
 use tantivy_fst::Regex;
 
+use super::minimum_should_match::MinimumShouldMatch;
 use crate::query::Occur;
 use crate::schema::{Field, Term};
 use crate::Score;
@@ -34,6 +35,7 @@ pub enum LogicalAst {
     Clause(Vec<(Occur, LogicalAst)>),
     Leaf(Box<LogicalLiteral>),
     Boost(Box<LogicalAst>, Score),
+    MinimumShouldMatch(Box<LogicalAst>, MinimumShouldMatch),
 }
 
 impl LogicalAst {
@@ -70,6 +72,20 @@ impl LogicalAst {
 
                 LogicalAst::Clause(new_clauses)
             }
+            // The clauses are not pulled up, as that would change the number of should clauses
+            // the minimum applies to.
+            LogicalAst::MinimumShouldMatch(ast, minimum_should_match) => {
+                let ast = match *ast {
+                    LogicalAst::Clause(clauses) => LogicalAst::Clause(
+                        clauses
+                            .into_iter()
+                            .map(|(occur, sub_ast)| (occur, sub_ast.simplify()))
+                            .collect(),
+                    ),
+                    ast => ast.simplify(),
+                };
+                LogicalAst::MinimumShouldMatch(Box::new(ast), minimum_should_match)
+            }
             LogicalAst::Leaf(_) | LogicalAst::Boost(_, _) => self,
         }
     }
@@ -100,6 +116,9 @@ impl fmt::Debug for LogicalAst {
                 Ok(())
             }
             LogicalAst::Boost(ref ast, boost) => write!(formatter, "{ast:?}^{boost}"),
+            LogicalAst::MinimumShouldMatch(ref ast, ref minimum_should_match) => {
+                write!(formatter, "{ast:?}@{minimum_should_match}")
+            }
             LogicalAst::Leaf(ref literal) => write!(formatter, "{literal:?}"),
         }
     }
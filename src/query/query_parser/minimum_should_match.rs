@@ -0,0 +1,185 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::QueryParserError;
+
+/// How many of the optional clauses of a boolean query are required to match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Requirement {
+    /// A number of clauses, or of clauses allowed to be missing if negative.
+    Count(i64),
+    /// A percentage of the clauses, or of the clauses allowed to be missing if negative.
+    ///
+    /// Always within `-100..=100`.
+    Percentage(i64),
+}
+
+impl Requirement {
+    fn num_required(self, num_clauses: usize) -> usize {
+        let num_clauses = num_clauses as i64;
+        let num_required = match self {
+            Requirement::Count(count) if count < 0 => num_clauses + count,
+            Requirement::Count(count) => count,
+            Requirement::Percentage(percentage) if percentage < 0 => {
+                num_clauses - num_clauses * -percentage / 100
+            }
+            Requirement::Percentage(percentage) => num_clauses * percentage / 100,
+        };
+        num_required.clamp(0, num_clauses) as usize
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = ();
+
+    fn from_str(requirement: &str) -> Result<Requirement, ()> {
+        if let Some(percentage) = requirement.strip_suffix('%') {
+            let percentage: i64 = percentage.parse().map_err(|_| ())?;
+            if !(-100..=100).contains(&percentage) {
+                return Err(());
+            }
+            Ok(Requirement::Percentage(percentage))
+        } else {
+            Ok(Requirement::Count(requirement.parse().map_err(|_| ())?))
+        }
+    }
+}
+
+/// Minimum should match specification, using the syntax of Solr's `mm` parameter.
+///
+/// - `3`: at least 3 optional clauses must match.
+/// - `-1`: all optional clauses but one must match.
+/// - `75%`: at least 75% of the optional clauses, rounded down, must match.
+/// - `-25%`: up to 25% of the optional clauses, rounded down, may be missing.
+/// - `3<90%`: if there are at most 3 optional clauses, all of them must match, otherwise 90% of
+///   them must match.
+/// - `2<-25% 9<-3`: conditions can be chained, and the condition with the greatest bound lower
+///   than the number of optional clauses applies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinimumShouldMatch {
+    spec: String,
+    unconditional: Option<Requirement>,
+    // Sorted by increasing bound.
+    conditions: Vec<(usize, Requirement)>,
+}
+
+impl MinimumShouldMatch {
+    /// Returns the number of optional clauses required to match, out of `num_clauses`.
+    pub fn num_required(&self, num_clauses: usize) -> usize {
+        let condition = self
+            .conditions
+            .iter()
+            .rev()
+            .find(|(bound, _)| *bound < num_clauses)
+            .map(|(_, requirement)| *requirement);
+        match (condition, self.unconditional) {
+            (Some(requirement), _) | (None, Some(requirement)) => {
+                requirement.num_required(num_clauses)
+            }
+            // Below the lowest bound of a conditional spec, all clauses are required.
+            (None, None) => num_clauses,
+        }
+    }
+}
+
+impl FromStr for MinimumShouldMatch {
+    type Err = QueryParserError;
+
+    fn from_str(spec: &str) -> Result<MinimumShouldMatch, QueryParserError> {
+        let invalid = || QueryParserError::InvalidMinimumShouldMatch(spec.to_string());
+        let mut unconditional = None;
+        let mut conditions = Vec::new();
+        for part in spec.split_whitespace() {
+            if let Some((bound, requirement)) = part.split_once('<') {
+                if unconditional.is_some() {
+                    return Err(invalid());
+                }
+                let bound: usize = bound.parse().map_err(|_| invalid())?;
+                let requirement: Requirement = requirement.parse().map_err(|_| invalid())?;
+                conditions.push((bound, requirement));
+            } else if unconditional.is_none() && conditions.is_empty() {
+                unconditional = Some(part.parse().map_err(|_| invalid())?);
+            } else {
+                return Err(invalid());
+            }
+        }
+        if unconditional.is_none() && conditions.is_empty() {
+            return Err(invalid());
+        }
+        conditions.sort_by_key(|(bound, _)| *bound);
+        Ok(MinimumShouldMatch {
+            spec: spec.to_string(),
+            unconditional,
+            conditions,
+        })
+    }
+}
+
+impl fmt::Display for MinimumShouldMatch {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MinimumShouldMatch;
+    use crate::query::QueryParserError;
+
+    fn num_required(spec: &str, num_clauses: usize) -> usize {
+        spec.parse::<MinimumShouldMatch>()
+            .unwrap()
+            .num_required(num_clauses)
+    }
+
+    #[test]
+    fn test_minimum_should_match_simple() {
+        assert_eq!(num_required("3", 5), 3);
+        assert_eq!(num_required("3", 2), 2);
+        assert_eq!(num_required("-1", 5), 4);
+        assert_eq!(num_required("-7", 5), 0);
+        assert_eq!(num_required("75%", 4), 3);
+        assert_eq!(num_required("75%", 3), 2);
+        assert_eq!(num_required("-25%", 3), 3);
+        assert_eq!(num_required("-25%", 4), 3);
+        assert_eq!(num_required("100%", 6), 6);
+    }
+
+    #[test]
+    fn test_minimum_should_match_conditional() {
+        assert_eq!(num_required("3<90%", 3), 3);
+        assert_eq!(num_required("3<90%", 10), 9);
+        assert_eq!(num_required("2<-25% 9<-3", 1), 1);
+        assert_eq!(num_required("2<-25% 9<-3", 2), 2);
+        assert_eq!(num_required("2<-25% 9<-3", 4), 3);
+        assert_eq!(num_required("9<-3 2<-25%", 8), 6);
+        assert_eq!(num_required("2<-25% 9<-3", 12), 9);
+    }
+
+    #[test]
+    fn test_minimum_should_match_invalid() {
+        for spec in [
+            "",
+            "abc",
+            "3<",
+            "<3",
+            "75%%",
+            "2 3",
+            "3<90% 2",
+            "2 3<90%",
+            "1.5",
+            "101%",
+            "-101%",
+            "9223372036854775807%",
+            "-9223372036854775808%",
+            "3<9223372036854775807%",
+        ] {
+            assert_eq!(
+                spec.parse::<MinimumShouldMatch>(),
+                Err(QueryParserError::InvalidMinimumShouldMatch(
+                    spec.to_string()
+                ))
+            );
+        }
+    }
+}
@@ -1,3 +1,4 @@
+mod minimum_should_match;
 mod query_parser;
 
 pub mod logical_ast;
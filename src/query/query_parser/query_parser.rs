@@ -12,6 +12,7 @@ use rustc_hash::FxHashMap;
 use tantivy_fst::Regex;
 
 use super::logical_ast::*;
+use super::minimum_should_match::MinimumShouldMatch;
 use crate::index::Index;
 use crate::json_utils::convert_to_fast_value_and_append_to_json_term;
 use crate::query::range_query::{is_type_valid_for_fastfield_range_query, RangeQuery};
@@ -104,6 +105,9 @@ pub enum QueryParserError {
     /// The format for the ip field is invalid.
     #[error("The ip field is malformed: {0}")]
     IpFormatError(#[from] AddrParseError),
+    /// The minimum should match specification is invalid.
+    #[error("Invalid minimum should match: '{0}'")]
+    InvalidMinimumShouldMatch(String),
 }
 
 /// Recursively remove empty clause from the AST
@@ -124,6 +128,8 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
                 Some(LogicalAst::Clause(trimmed_children))
             }
         }
+        LogicalAst::MinimumShouldMatch(child, minimum_should_match) => trim_ast(*child)
+            .map(|child| LogicalAst::MinimumShouldMatch(Box::new(child), minimum_should_match)),
         _ => Some(logical_ast),
     }
 }
@@ -200,6 +206,11 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 /// Phrase terms also support the `*` prefix operator which switches the phrase's matching
 /// to consider all documents which contain the last term as a prefix, e.g. `"big bad wo"*` will
 /// match `"big bad wolf"`.
+///
+/// A group can require a minimum number of its optional clauses to match by appending
+/// `@` and a minimum should match specification: `(sea whale ship harpoon)@3`, or `(...)@75%`.
+/// A default specification for all the groups of a query can be set via the
+/// [`QueryParser::set_minimum_should_match`] method.
 #[derive(Clone)]
 pub struct QueryParser {
     schema: Schema,
@@ -209,6 +220,7 @@ pub struct QueryParser {
     boost: FxHashMap<Field, Score>,
     fuzzy: FxHashMap<Field, Fuzzy>,
     regexes_allowed: bool,
+    minimum_should_match: Option<MinimumShouldMatch>,
 }
 
 #[derive(Clone)]
@@ -222,6 +234,7 @@ fn all_negative(ast: &LogicalAst) -> bool {
     match ast {
         LogicalAst::Leaf(_) => false,
         LogicalAst::Boost(ref child_ast, _) => all_negative(child_ast),
+        LogicalAst::MinimumShouldMatch(ref child_ast, _) => all_negative(child_ast),
         LogicalAst::Clause(children) => children
             .iter()
             .all(|(ref occur, child)| (*occur == Occur::MustNot) || all_negative(child)),
@@ -233,6 +246,7 @@ fn make_non_negative(ast: &mut LogicalAst) {
     match ast {
         LogicalAst::Leaf(_) => (),
         LogicalAst::Boost(ref mut child_ast, _) => make_non_negative(child_ast),
+        LogicalAst::MinimumShouldMatch(ref mut child_ast, _) => make_non_negative(child_ast),
         LogicalAst::Clause(children) => children.push((Occur::Should, LogicalLiteral::All.into())),
     }
 }
@@ -264,6 +278,7 @@ impl QueryParser {
             boost: Default::default(),
             fuzzy: Default::default(),
             regexes_allowed: false,
+            minimum_should_match: None,
         }
     }
 
@@ -329,6 +344,22 @@ impl QueryParser {
         self.regexes_allowed = true;
     }
 
+    /// Sets the minimum number of optional clauses that must match, for every disjunction of
+    /// the query, e.g. `happy tax payer` or `title:(happy tax payer)`.
+    ///
+    /// The specification follows the syntax of Solr's `mm` parameter: `2` or `75%` require
+    /// respectively at least 2 or 75% of the optional clauses to match, while `-1` or `-25%`
+    /// allow 1 or 25% of them to be missing. Conditional specifications such as
+    /// `2<-25% 9<-3` require all clauses up to 2 clauses, allow 25% to be missing up to 9 clauses,
+    /// and 3 above.
+    ///
+    /// A specification given in the query for a group, as in `(happy tax payer)@2`, takes
+    /// precedence.
+    pub fn set_minimum_should_match(&mut self, spec: &str) -> Result<(), QueryParserError> {
+        self.minimum_should_match = Some(spec.parse()?);
+        Ok(())
+    }
+
     /// Parse a query
     ///
     /// Note that `parse_query` returns an error if the input
@@ -668,7 +699,35 @@ impl QueryParser {
                     logical_sub_queries.push((occur, sub_ast));
                     errors.append(&mut sub_errors);
                 }
-                (LogicalAst::Clause(logical_sub_queries), errors)
+                let has_should = logical_sub_queries
+                    .iter()
+                    .any(|(occur, _)| *occur == Occur::Should);
+                let ast = LogicalAst::Clause(logical_sub_queries);
+                match &self.minimum_should_match {
+                    Some(minimum_should_match) if has_should => (
+                        LogicalAst::MinimumShouldMatch(Box::new(ast), minimum_should_match.clone()),
+                        errors,
+                    ),
+                    _ => (ast, errors),
+                }
+            }
+            UserInputAst::MinimumShouldMatch(ast, spec) => {
+                let (ast, mut errors) = self.compute_logical_ast_with_occur_lenient(*ast);
+                // The specification of the query overrides the one of the query parser.
+                let ast = match ast {
+                    LogicalAst::MinimumShouldMatch(ast, _) => *ast,
+                    ast => ast,
+                };
+                match spec.parse() {
+                    Ok(minimum_should_match) => (
+                        LogicalAst::MinimumShouldMatch(Box::new(ast), minimum_should_match),
+                        errors,
+                    ),
+                    Err(err) => {
+                        errors.push(err);
+                        (ast, errors)
+                    }
+                }
             }
             UserInputAst::Boost(ast, boost) => {
                 let (ast, errors) = self.compute_logical_ast_with_occur_lenient(*ast);
@@ -1054,19 +1113,45 @@ fn generate_literals_for_json_object(
     Ok(logical_literals)
 }
 
+fn convert_clause_to_query(
+    fuzzy: &FxHashMap<Field, Fuzzy>,
+    clause: Vec<(Occur, LogicalAst)>,
+    minimum_should_match: Option<&MinimumShouldMatch>,
+) -> Box<dyn Query> {
+    let num_should = clause
+        .iter()
+        .filter(|(occur, _)| *occur == Occur::Should)
+        .count();
+    let occur_subqueries = clause
+        .into_iter()
+        .map(|(occur, subquery)| (occur, convert_to_query(fuzzy, subquery)))
+        .collect::<Vec<_>>();
+    assert!(
+        !occur_subqueries.is_empty(),
+        "Should not be empty after trimming"
+    );
+    let mut boolean_query = BooleanQuery::new(occur_subqueries);
+    if let Some(minimum_should_match) = minimum_should_match {
+        // The specification can only make the query stricter.
+        let num_required = minimum_should_match
+            .num_required(num_should)
+            .max(boolean_query.get_minimum_number_should_match());
+        boolean_query.set_minimum_number_should_match(num_required);
+    }
+    Box::new(boolean_query)
+}
+
 fn convert_to_query(fuzzy: &FxHashMap<Field, Fuzzy>, logical_ast: LogicalAst) -> Box<dyn Query> {
     match trim_ast(logical_ast) {
         Some(LogicalAst::Clause(trimmed_clause)) => {
-            let occur_subqueries = trimmed_clause
-                .into_iter()
-                .map(|(occur, subquery)| (occur, convert_to_query(fuzzy, subquery)))
-                .collect::<Vec<_>>();
-            assert!(
-                !occur_subqueries.is_empty(),
-                "Should not be empty after trimming"
-            );
-            Box::new(BooleanQuery::new(occur_subqueries))
+            convert_clause_to_query(fuzzy, trimmed_clause, None)
         }
+        Some(LogicalAst::MinimumShouldMatch(ast, minimum_should_match)) => match *ast {
+            LogicalAst::Clause(trimmed_clause) => {
+                convert_clause_to_query(fuzzy, trimmed_clause, Some(&minimum_should_match))
+            }
+            ast => convert_to_query(fuzzy, ast),
+        },
         Some(LogicalAst::Leaf(trimmed_logical_literal)) => {
             convert_literal_to_query(fuzzy, *trimmed_logical_literal)
         }
@@ -1085,7 +1170,7 @@ mod test {
 
     use super::super::logical_ast::*;
     use super::{QueryParser, QueryParserError};
-    use crate::query::Query;
+    use crate::query::{BooleanQuery, Query};
     use crate::schema::{
         FacetOptions, Field, IndexRecordOption, Schema, Term, TextFieldIndexing, TextOptions, FAST,
         INDEXED, STORED, STRING, TEXT,
@@ -1229,6 +1314,77 @@ mod test {
         );
     }
 
+    fn minimum_should_match(query: &dyn Query) -> usize {
+        query
+            .downcast_ref::<BooleanQuery>()
+            .unwrap()
+            .get_minimum_number_should_match()
+    }
+
+    #[test]
+    pub fn test_parse_query_with_minimum_should_match() {
+        let query_parser = make_query_parser_with_default_fields(&["title"]);
+        let query = query_parser.parse_query("(a b c d)@3").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 3);
+        let query = query_parser.parse_query("(a b c d)@-25%").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 3);
+        // The specification cannot make the query less strict.
+        let query = query_parser.parse_query("(a b c)@0").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 1);
+        assert_eq!(
+            query_parser.parse_query("(a b)@x%").unwrap_err(),
+            QueryParserError::SyntaxError("(a b)@x%".to_string())
+        );
+        assert_eq!(
+            query_parser.parse_query("(a b)@3<").unwrap_err(),
+            QueryParserError::InvalidMinimumShouldMatch("3<".to_string())
+        );
+        for spec in ["9223372036854775807%", "-9223372036854775808%", "150%"] {
+            assert_eq!(
+                query_parser
+                    .parse_query(&format!("(x b)@{spec}"))
+                    .unwrap_err(),
+                QueryParserError::InvalidMinimumShouldMatch(spec.to_string())
+            );
+        }
+        test_parse_query_to_logical_ast_helper(
+            "(a b)@2",
+            r#"((Term(field=0, type=Str, "a") Term(field=1, type=Str, "a")) (Term(field=0, type=Str, "b") Term(field=1, type=Str, "b")))@2"#,
+            false,
+        );
+    }
+
+    #[test]
+    pub fn test_query_parser_minimum_should_match() {
+        let mut query_parser = make_query_parser_with_default_fields(&["title"]);
+        assert_eq!(
+            query_parser.set_minimum_should_match("75%%"),
+            Err(QueryParserError::InvalidMinimumShouldMatch(
+                "75%%".to_string()
+            ))
+        );
+        query_parser
+            .set_minimum_should_match("2<-25% 9<-3")
+            .unwrap();
+        let query = query_parser.parse_query("a b").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 2);
+        let query = query_parser.parse_query("a b c d e f g h").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 6);
+        let query = query_parser.parse_query("a b c d e f g h i j k l").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 9);
+        // The specification of the query takes precedence.
+        let query = query_parser.parse_query("(a b c d)@1").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 1);
+        // Each disjunction gets its own minimum.
+        let query = query_parser.parse_query("+x +(a b c d)").unwrap();
+        assert_eq!(minimum_should_match(query.as_ref()), 0);
+        let boolean_query = query.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(
+            minimum_should_match(boolean_query.clauses()[1].1.as_ref()),
+            3
+        );
+    }
+
     #[test]
     pub fn test_parse_query_range_with_boost() {
         let query = make_query_parser().parse_query("title:[A TO B]").unwrap();
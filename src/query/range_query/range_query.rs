@@ -3,12 +3,16 @@ use std::ops::Bound;
 
 use common::bounds::{map_bound, BoundsRange};
 use common::BitSet;
+use futures_util::future::BoxFuture;
 
 use super::range_query_fastfield::FastFieldRangeWeight;
 use crate::index::SegmentReader;
 use crate::query::explanation::does_not_match;
+use crate::query::matches::{dictionary_term, positions_in_doc};
 use crate::query::range_query::is_type_valid_for_fastfield_range_query;
-use crate::query::{BitSetDocSet, ConstScorer, EnableScoring, Explanation, Query, Scorer, Weight};
+use crate::query::{
+    BitSetDocSet, ConstScorer, EnableScoring, Explanation, Matches, Query, Scorer, Weight,
+};
 use crate::schema::{Field, IndexRecordOption, Term, Type};
 use crate::termdict::{TermDictionary, TermStreamer};
 use crate::{DocId, Score};
@@ -230,6 +234,22 @@ impl InvertedIndexRangeWeight {
 }
 
 impl Weight for InvertedIndexRangeWeight {
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            let inverted_index = reader.inverted_index(self.field)?;
+            let mut term_range = self.term_range(inverted_index.terms())?;
+            let limit = self.limit.map_or(usize::MAX, |limit| limit as usize);
+            let mut term_infos = Vec::new();
+            while term_infos.len() < limit && term_range.advance() {
+                term_infos.push(term_range.value().clone());
+            }
+            inverted_index
+                .warm_postings_for_term_infos(&term_infos, false)
+                .await?;
+            Ok(())
+        })
+    }
+
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
         let max_doc = reader.max_doc();
         let mut doc_bitset = BitSet::with_max_value(max_doc);
@@ -270,6 +290,24 @@ impl Weight for InvertedIndexRangeWeight {
         }
         Ok(Explanation::new("RangeQuery", 1.0))
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let inverted_index = reader.inverted_index(self.field)?;
+        let mut term_range = self.term_range(inverted_index.terms())?;
+        let limit = self.limit.unwrap_or(u64::MAX);
+        let mut processed_count = 0;
+        let mut matches_opt: Option<Matches> = None;
+        while processed_count < limit && term_range.advance() {
+            processed_count += 1;
+            if let Some(positions) = positions_in_doc(&inverted_index, term_range.value(), doc)? {
+                let term = dictionary_term(reader, self.field, term_range.key());
+                matches_opt
+                    .get_or_insert_with(Matches::default)
+                    .add_term_positions(&term, &positions);
+            }
+        }
+        Ok(matches_opt)
+    }
 }
 
 #[cfg(test)]
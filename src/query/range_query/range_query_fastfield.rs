@@ -11,12 +11,15 @@ use columnar::{
     NumericalType, StrColumn,
 };
 use common::bounds::{BoundsRange, TransformBound};
+use futures_util::future::BoxFuture;
 
 use super::fast_field_range_doc_set::{FastFieldRangeScorer, RangeDocSet};
+use crate::fastfield::FastValue;
+use crate::query::matches::doc_matches;
 use crate::query::{
-    AllScorer, ConstScorer, EmptyScorer, EnableScoring, Explanation, Query, Scorer, Weight,
+    AllScorer, ConstScorer, EmptyScorer, EnableScoring, Explanation, Matches, Query, Scorer, Weight,
 };
-use crate::schema::{Type, ValueBytes};
+use crate::schema::{value_type_to_column_type, Type, ValueBytes};
 use crate::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
 
 #[derive(Clone, Debug)]
@@ -83,6 +86,19 @@ impl FastFieldRangeWeight {
 }
 
 impl Weight for FastFieldRangeWeight {
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            let Some(term) = self.bounds.get_inner() else {
+                return Ok(());
+            };
+            let field_name = term.get_full_path(reader.schema());
+            reader
+                .fast_fields()
+                .warm_fast_field(&field_name, false)
+                .await
+        })
+    }
+
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
         // Check if both bounds are Bound::Unbounded
         if self.bounds.is_unbounded() {
@@ -288,6 +304,91 @@ impl Weight for FastFieldRangeWeight {
 
         Ok(explanation)
     }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        if !doc_matches(self, reader, doc)? {
+            return Ok(None);
+        }
+        Ok(Some(match self.bounds.get_inner() {
+            Some(term) => Matches::with_field(term.field()),
+            None => Matches::default(),
+        }))
+    }
+}
+
+/// `ColumnRangeQuery` matches the documents whose value in a fast field column falls within
+/// a range.
+///
+/// Unlike [`FastFieldRangeQuery`], the column is designated by its name, which means it
+/// also accepts [runtime fields](crate::fastfield::RuntimeField). The column needs to
+/// be of the type of the bounds.
+#[derive(Clone, Debug)]
+pub struct ColumnRangeQuery<T: FastValue> {
+    column_name: String,
+    bounds: BoundsRange<T>,
+}
+
+impl<T: FastValue> ColumnRangeQuery<T> {
+    /// Create new `ColumnRangeQuery`
+    pub fn new(column_name: impl ToString, lower_bound: Bound<T>, upper_bound: Bound<T>) -> Self {
+        ColumnRangeQuery {
+            column_name: column_name.to_string(),
+            bounds: BoundsRange::new(lower_bound, upper_bound),
+        }
+    }
+}
+
+impl<T: FastValue> Query for ColumnRangeQuery<T> {
+    fn weight(&self, _enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        Ok(Box::new(ColumnRangeWeight {
+            column_name: self.column_name.clone(),
+            bounds: self.bounds.map_bound(|val| val.to_u64()),
+            column_type_opt: value_type_to_column_type(T::to_type()),
+        }))
+    }
+}
+
+/// `ColumnRangeWeight` is the [`Weight`] of a [`ColumnRangeQuery`].
+struct ColumnRangeWeight {
+    column_name: String,
+    bounds: BoundsRange<u64>,
+    column_type_opt: Option<ColumnType>,
+}
+
+impl Weight for ColumnRangeWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if self.bounds.is_unbounded() {
+            return Ok(Box::new(AllScorer::new(reader.max_doc())));
+        }
+        let type_white_list = self.column_type_opt.as_ref().map(std::slice::from_ref);
+        let Some((column, _col_type)) = reader
+            .fast_fields()
+            .u64_lenient_for_type(type_white_list, &self.column_name)?
+        else {
+            return Ok(Box::new(EmptyScorer));
+        };
+        search_on_u64_ff(column, boost, self.bounds.clone(), None, None, None)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+        Ok(Explanation::new("Const", scorer.score()))
+    }
+
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        if !doc_matches(self, reader, doc)? {
+            return Ok(None);
+        }
+        Ok(Some(match reader.schema().find_field(&self.column_name) {
+            Some((field, _)) => Matches::with_field(field),
+            None => Matches::default(),
+        }))
+    }
 }
 
 /// On numerical fields the column type may not match the user provided one.
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use tantivy_fst::raw::CompiledAddr;
 use tantivy_fst::{Automaton, Map};
 
+use crate::query::fingerprint::FingerprintHasher;
 use crate::query::score_combiner::DoNothingCombiner;
 use crate::query::{AutomatonWeight, BooleanWeight, EnableScoring, Occur, Query, Weight};
 use crate::schema::{Field, Schema};
@@ -80,6 +81,25 @@ impl Query for TermSetQuery {
             }
         }
     }
+
+    fn fingerprint(&self) -> u128 {
+        // The debug representation depends on the iteration order of the map.
+        let mut fields: Vec<&Field> = self.terms_map.keys().collect();
+        fields.sort_unstable();
+        let mut hasher = FingerprintHasher::new("TermSetQuery");
+        for field in fields {
+            let terms = &self.terms_map[field];
+            hasher.write_u64(field.field_id() as u64);
+            hasher.write_u64(terms.len() as u64);
+            for term in terms {
+                let value_bytes = term.serialized_value_bytes();
+                hasher.write(&[term.typ().to_code()]);
+                hasher.write_u64(value_bytes.len() as u64);
+                hasher.write(value_bytes);
+            }
+        }
+        hasher.finish()
+    }
 }
 
 /// DFA Wrapper type
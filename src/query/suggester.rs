@@ -0,0 +1,506 @@
+//! Spelling suggestions drawn from the term dictionary.
+//!
+//! The [`Suggester`] streams the term dictionary of a text field through a Levenshtein automaton
+//! to find the terms close to a possibly misspelled one. Phrase correction picks, for each token
+//! of the phrase, the candidate that best fits its neighbours, using the positions index to count
+//! how often two candidates appear at the same distance as the tokens they replace.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::fuzzy_query::{levenshtein_automaton_builder, DfaWrapper};
+use crate::docset::{DocSet, TERMINATED};
+use crate::postings::Postings;
+use crate::schema::{Field, FieldType, IndexRecordOption, Term};
+use crate::{Searcher, TantivyError};
+
+/// Number of spelling candidates considered for each token of a phrase.
+const NUM_PHRASE_CANDIDATES: usize = 5;
+
+/// Score penalty for each edit made to a token of a phrase.
+const EDIT_PENALTY: f64 = 2.0;
+
+/// Weight of the bigram co-occurrence relative to the document frequency of the terms.
+const BIGRAM_WEIGHT: f64 = 2.0;
+
+/// Number of documents after which bigram co-occurrences stop being counted. The score grows
+/// logarithmically with the count, so going further barely changes the ranking while reading
+/// the whole postings lists of frequent terms.
+const MAX_BIGRAM_DOCS: u64 = 1_000;
+
+/// A term of the dictionary suggested as a correction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    term: String,
+    distance: u8,
+    doc_freq: u64,
+}
+
+impl Suggestion {
+    /// The suggested term.
+    pub fn term(&self) -> &str {
+        &self.term
+    }
+
+    /// Edit distance between the suggested term and the input text.
+    pub fn distance(&self) -> u8 {
+        self.distance
+    }
+
+    /// Number of documents containing the suggested term.
+    pub fn doc_freq(&self) -> u64 {
+        self.doc_freq
+    }
+}
+
+/// A `Suggester` proposes "did you mean" corrections from the terms of an index.
+///
+/// ```rust
+/// use tantivy::query::Suggester;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, IndexWriter};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer: IndexWriter = index.writer(15_000_000)?;
+/// index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let suggester = Suggester::new(&searcher);
+/// let suggestions = suggester.suggest(title, "dairy", 2, 3)?;
+/// assert_eq!(suggestions[0].term(), "diary");
+/// assert_eq!(
+///     suggester.suggest_phrase(title, "yung girl", 2)?.as_deref(),
+///     Some("young girl")
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct Suggester {
+    searcher: Searcher,
+    transposition_cost_one: bool,
+}
+
+impl Suggester {
+    /// Creates a `Suggester` over the terms visible to the given searcher.
+    ///
+    /// By default, swapping two adjacent characters counts as a single edit.
+    pub fn new(searcher: &Searcher) -> Suggester {
+        Suggester {
+            searcher: searcher.clone(),
+            transposition_cost_one: true,
+        }
+    }
+
+    /// Sets whether swapping two adjacent characters counts as one edit or as two.
+    pub fn set_transposition_cost_one(&mut self, transposition_cost_one: bool) {
+        self.transposition_cost_one = transposition_cost_one;
+    }
+
+    /// Returns up to `size` terms of `field` within `max_edits` edits of `text`.
+    ///
+    /// `text` is matched against the dictionary as is: it is expected to be a single token, as
+    /// produced by the tokenizer of the field. The term itself is never suggested.
+    ///
+    /// Suggestions are ranked by increasing edit distance, then by decreasing document
+    /// frequency.
+    pub fn suggest(
+        &self,
+        field: Field,
+        text: &str,
+        max_edits: u8,
+        size: usize,
+    ) -> crate::Result<Vec<Suggestion>> {
+        let mut suggestions = self.candidates(field, text, max_edits)?;
+        suggestions.retain(|suggestion| suggestion.term != text);
+        suggestions.truncate(size);
+        Ok(suggestions)
+    }
+
+    /// Returns a corrected version of the phrase `text`, if one fits the index better.
+    ///
+    /// The phrase is tokenized with the tokenizer of `field`. Each token may be replaced by a
+    /// term within `max_edits` edits, and the combination of terms maximizing the document
+    /// frequency of the terms and the number of documents in which consecutive terms appear at
+    /// the same distance as in the phrase is retained. The latter requires `field` to be indexed
+    /// with positions; otherwise tokens are corrected independently.
+    ///
+    /// The corrected phrase is made of the tokens joined by spaces. `None` is returned if the
+    /// phrase needs no correction.
+    pub fn suggest_phrase(
+        &self,
+        field: Field,
+        text: &str,
+        max_edits: u8,
+    ) -> crate::Result<Option<String>> {
+        let mut tokenizer = self.searcher.index().tokenizer_for_field(field)?;
+        let mut token_stream = tokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        let mut positions = Vec::new();
+        while token_stream.advance() {
+            let token = token_stream.token();
+            tokens.push(token.text.clone());
+            positions.push(token.position as u32);
+        }
+        // Tokens removed by the tokenizer, such as stop words, leave gaps between positions.
+        let position_gaps: Vec<u32> = positions
+            .windows(2)
+            .map(|window| window[1].saturating_sub(window[0]))
+            .collect();
+
+        let mut candidates_per_token = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            let mut candidates = self.candidates(field, token, max_edits)?;
+            candidates.truncate(NUM_PHRASE_CANDIDATES);
+            // The token as typed stays a candidate, even when it is absent from the index.
+            if !candidates.iter().any(|candidate| &candidate.term == token) {
+                candidates.push(Suggestion {
+                    term: token.clone(),
+                    distance: 0,
+                    doc_freq: 0,
+                });
+            }
+            candidates_per_token.push(candidates);
+        }
+
+        let corrected_terms = self.best_sequence(field, &candidates_per_token, &position_gaps)?;
+        if corrected_terms == tokens {
+            return Ok(None);
+        }
+        Ok(Some(corrected_terms.join(" ")))
+    }
+
+    /// Returns all of the terms of `field` within `max_edits` edits of `text`, ranked.
+    fn candidates(
+        &self,
+        field: Field,
+        text: &str,
+        max_edits: u8,
+    ) -> crate::Result<Vec<Suggestion>> {
+        let field_entry = self.searcher.schema().get_field_entry(field);
+        if !matches!(field_entry.field_type(), FieldType::Str(_)) {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Spelling suggestions require a text field. {:?} is not one.",
+                field_entry.name()
+            )));
+        }
+        let automaton_builder =
+            levenshtein_automaton_builder(max_edits, self.transposition_cost_one)?;
+        let automaton = DfaWrapper(automaton_builder.build_dfa(text));
+
+        let mut candidates: HashMap<String, Suggestion> = HashMap::new();
+        for segment_reader in self.searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut term_stream = inverted_index
+                .terms()
+                .search_with_state(&automaton)
+                .into_stream()?;
+            while term_stream.advance() {
+                let Ok(term) = std::str::from_utf8(term_stream.key()) else {
+                    continue;
+                };
+                let doc_freq = term_stream.value().doc_freq as u64;
+                if let Some(candidate) = candidates.get_mut(term) {
+                    candidate.doc_freq += doc_freq;
+                    continue;
+                }
+                let distance = term_stream
+                    .state()
+                    .map_or(max_edits, |state| automaton.0.distance(*state).to_u8());
+                candidates.insert(
+                    term.to_string(),
+                    Suggestion {
+                        term: term.to_string(),
+                        distance,
+                        doc_freq,
+                    },
+                );
+            }
+        }
+
+        let mut candidates: Vec<Suggestion> = candidates.into_values().collect();
+        candidates.sort_by(|left, right| {
+            left.distance
+                .cmp(&right.distance)
+                .then(right.doc_freq.cmp(&left.doc_freq))
+                .then_with(|| left.term.cmp(&right.term))
+        });
+        Ok(candidates)
+    }
+
+    /// Picks one candidate per token, maximizing the score of the whole sequence.
+    ///
+    /// `position_gaps[i]` is the difference between the positions of tokens `i + 1` and `i`.
+    fn best_sequence(
+        &self,
+        field: Field,
+        candidates_per_token: &[Vec<Suggestion>],
+        position_gaps: &[u32],
+    ) -> crate::Result<Vec<String>> {
+        let Some((first_candidates, next_candidates)) = candidates_per_token.split_first() else {
+            return Ok(Vec::new());
+        };
+        // For each candidate of the current token, the best score of a sequence ending with it,
+        // along with the index of the preceding candidate in that sequence.
+        let mut scores: Vec<f64> = first_candidates.iter().map(unigram_score).collect();
+        let mut backpointers: Vec<Vec<usize>> = Vec::with_capacity(next_candidates.len());
+        let mut previous_candidates = first_candidates;
+        for (candidates, &position_gap) in next_candidates.iter().zip(position_gaps) {
+            let mut next_scores = Vec::with_capacity(candidates.len());
+            let mut next_backpointers = Vec::with_capacity(candidates.len());
+            for candidate in candidates {
+                let mut best: Option<(usize, f64)> = None;
+                for (previous_ord, previous) in previous_candidates.iter().enumerate() {
+                    let num_bigram_docs =
+                        self.num_bigram_docs(field, &previous.term, &candidate.term, position_gap)?;
+                    let score =
+                        scores[previous_ord] + BIGRAM_WEIGHT * (1.0 + num_bigram_docs as f64).ln();
+                    if best.is_none_or(|(_, best_score)| score > best_score) {
+                        best = Some((previous_ord, score));
+                    }
+                }
+                let (previous_ord, score) = best.unwrap_or((0, 0.0));
+                next_scores.push(score + unigram_score(candidate));
+                next_backpointers.push(previous_ord);
+            }
+            scores = next_scores;
+            backpointers.push(next_backpointers);
+            previous_candidates = candidates;
+        }
+
+        let mut ord = scores
+            .iter()
+            .enumerate()
+            .max_by(|(_, left), (_, right)| left.partial_cmp(right).unwrap_or(Ordering::Equal))
+            .map(|(ord, _)| ord)
+            .unwrap_or(0);
+        let mut terms = vec![candidates_per_token[candidates_per_token.len() - 1][ord]
+            .term
+            .clone()];
+        for (token_ord, token_backpointers) in backpointers.iter().enumerate().rev() {
+            ord = token_backpointers[ord];
+            terms.push(candidates_per_token[token_ord][ord].term.clone());
+        }
+        terms.reverse();
+        Ok(terms)
+    }
+
+    /// Counts the documents in which `right` appears `position_gap` positions after `left`, up
+    /// to [`MAX_BIGRAM_DOCS`].
+    fn num_bigram_docs(
+        &self,
+        field: Field,
+        left: &str,
+        right: &str,
+        position_gap: u32,
+    ) -> crate::Result<u64> {
+        let left_term = Term::from_field_text(field, left);
+        let right_term = Term::from_field_text(field, right);
+        let mut num_docs = 0;
+        let mut left_positions = Vec::new();
+        let mut right_positions = Vec::new();
+        for segment_reader in self.searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let Some(mut left_postings) = inverted_index
+                .read_postings(&left_term, IndexRecordOption::WithFreqsAndPositions)?
+            else {
+                continue;
+            };
+            let Some(mut right_postings) = inverted_index
+                .read_postings(&right_term, IndexRecordOption::WithFreqsAndPositions)?
+            else {
+                continue;
+            };
+            let mut doc = left_postings.doc();
+            while doc != TERMINATED {
+                let mut right_doc = right_postings.doc();
+                if right_doc < doc {
+                    right_doc = right_postings.seek(doc);
+                }
+                if right_doc != doc {
+                    doc = left_postings.seek(right_doc);
+                    continue;
+                }
+                left_postings.positions(&mut left_positions);
+                right_postings.positions(&mut right_positions);
+                if left_positions.iter().any(|position| {
+                    right_positions
+                        .binary_search(&(position + position_gap))
+                        .is_ok()
+                }) {
+                    num_docs += 1;
+                    if num_docs == MAX_BIGRAM_DOCS {
+                        return Ok(num_docs);
+                    }
+                }
+                doc = left_postings.advance();
+            }
+        }
+        Ok(num_docs)
+    }
+}
+
+fn unigram_score(candidate: &Suggestion) -> f64 {
+    (1.0 + candidate.doc_freq as f64).ln() - EDIT_PENALTY * candidate.distance as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Suggester;
+    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions, INDEXED, TEXT};
+    use crate::tokenizer::{LowerCaser, SimpleTokenizer, StopWordFilter, TextAnalyzer};
+    use crate::{Index, IndexWriter};
+
+    fn create_index() -> crate::Result<(Index, crate::schema::Field)> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "the diary of a young girl"))?;
+        index_writer.add_document(doc!(title => "young and restless"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(title => "the diary of muadib"))?;
+        index_writer.add_document(doc!(title => "a dairy cow"))?;
+        index_writer.add_document(doc!(title => "the young ones"))?;
+        index_writer.add_document(doc!(title => "yang and yin"))?;
+        index_writer.commit()?;
+        Ok((index, title))
+    }
+
+    #[test]
+    fn test_suggest_ranks_by_distance_then_doc_freq() -> crate::Result<()> {
+        let (index, title) = create_index()?;
+        let searcher = index.reader()?.searcher();
+        let suggester = Suggester::new(&searcher);
+
+        let suggestions = suggester.suggest(title, "yung", 1, 10)?;
+        let terms: Vec<(&str, u8, u64)> = suggestions
+            .iter()
+            .map(|suggestion| {
+                (
+                    suggestion.term(),
+                    suggestion.distance(),
+                    suggestion.doc_freq(),
+                )
+            })
+            .collect();
+        // "young" appears in 3 documents spread over both segments.
+        assert_eq!(terms, vec![("young", 1, 3), ("yang", 1, 1)]);
+
+        let suggestions = suggester.suggest(title, "dairi", 2, 10)?;
+        let terms: Vec<&str> = suggestions
+            .iter()
+            .map(|suggestion| suggestion.term())
+            .collect();
+        assert_eq!(terms, vec!["dairy", "diary"]);
+
+        let suggestions = suggester.suggest(title, "yung", 2, 1)?;
+        let terms: Vec<&str> = suggestions
+            .iter()
+            .map(|suggestion| suggestion.term())
+            .collect();
+        assert_eq!(terms, vec!["young"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest_excludes_input_term() -> crate::Result<()> {
+        let (index, title) = create_index()?;
+        let searcher = index.reader()?.searcher();
+        let suggester = Suggester::new(&searcher);
+        let suggestions = suggester.suggest(title, "diary", 1, 10)?;
+        let terms: Vec<&str> = suggestions
+            .iter()
+            .map(|suggestion| suggestion.term())
+            .collect();
+        assert_eq!(terms, vec!["dairy"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest_transposition_cost() -> crate::Result<()> {
+        let (index, title) = create_index()?;
+        let searcher = index.reader()?.searcher();
+        let mut suggester = Suggester::new(&searcher);
+        assert_eq!(suggester.suggest(title, "cwo", 1, 10)?[0].term(), "cow");
+        suggester.set_transposition_cost_one(false);
+        assert!(suggester.suggest(title, "cwo", 1, 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest_invalid_arguments() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let id = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let searcher = index.reader()?.searcher();
+        let suggester = Suggester::new(&searcher);
+        assert!(suggester.suggest(title, "diary", 4, 10).is_err());
+        assert!(suggester.suggest(id, "diary", 1, 10).is_err());
+        assert!(suggester.suggest(title, "diary", 1, 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest_phrase_uses_bigrams() -> crate::Result<()> {
+        let (index, title) = create_index()?;
+        let searcher = index.reader()?.searcher();
+        let suggester = Suggester::new(&searcher);
+        // "dairy" is a term of the index, but "diary" is the one followed by "of".
+        assert_eq!(
+            suggester.suggest_phrase(title, "Dairy of", 2)?.as_deref(),
+            Some("diary of")
+        );
+        assert_eq!(
+            suggester.suggest_phrase(title, "yung girl", 2)?.as_deref(),
+            Some("young girl")
+        );
+        assert_eq!(
+            suggester.suggest_phrase(title, "dairy cwo", 2)?.as_deref(),
+            Some("dairy cow")
+        );
+        assert_eq!(suggester.suggest_phrase(title, "young girl", 2)?, None);
+        assert_eq!(suggester.suggest_phrase(title, "", 2)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest_phrase_position_gaps() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("no_of")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let title = schema_builder.add_text_field("title", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "no_of",
+            TextAnalyzer::builder(SimpleTokenizer::default())
+                .filter(LowerCaser)
+                .filter(StopWordFilter::remove(vec!["of".to_string()]))
+                .build(),
+        );
+        let mut index_writer: IndexWriter = index.writer_for_tests()?;
+        for _ in 0..3 {
+            index_writer.add_document(doc!(title => "the diary of muadib"))?;
+        }
+        index_writer.add_document(doc!(title => "a dairy cow"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let suggester = Suggester::new(&searcher);
+        // "of" is removed, so "muadib" comes two positions after "diary".
+        assert_eq!(
+            suggester
+                .suggest_phrase(title, "dairy of muadib", 2)?
+                .as_deref(),
+            Some("diary muadib")
+        );
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use crate::docset::DocSet;
 use crate::fieldnorm::FieldNormReader;
 use crate::postings::{FreqReadingOption, Postings, SegmentPostings};
 use crate::query::bm25::Bm25Weight;
+use crate::query::boolean_query::BlockMaxScorer;
 use crate::query::{Explanation, Scorer};
 use crate::{DocId, Score};
 
@@ -127,6 +128,24 @@ impl Scorer for TermScorer {
     }
 }
 
+impl BlockMaxScorer for TermScorer {
+    fn max_score(&self) -> Score {
+        TermScorer::max_score(self)
+    }
+
+    fn seek_block(&mut self, target_doc: DocId) {
+        TermScorer::seek_block(self, target_doc);
+    }
+
+    fn block_max_score(&mut self) -> Score {
+        TermScorer::block_max_score(self)
+    }
+
+    fn last_doc_in_block(&self) -> DocId {
+        TermScorer::last_doc_in_block(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use proptest::prelude::*;
@@ -1,3 +1,6 @@
+use futures_util::future::BoxFuture;
+use futures_util::TryFutureExt;
+
 use super::term_scorer::TermScorer;
 use crate::docset::{DocSet, COLLECT_BLOCK_BUFFER_LEN};
 use crate::fieldnorm::FieldNormReader;
@@ -5,8 +8,11 @@ use crate::index::SegmentReader;
 use crate::postings::SegmentPostings;
 use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
-use crate::query::weight::{for_each_docset_buffered, for_each_scorer};
-use crate::query::{AllScorer, AllWeight, EmptyScorer, Explanation, Scorer, Weight};
+use crate::query::matches::term_positions_in_doc;
+use crate::query::weight::{
+    for_each_docset_buffered, for_each_scorer, pruning_callback_with_context,
+};
+use crate::query::{AllScorer, AllWeight, EmptyScorer, Explanation, Matches, Scorer, Weight};
 use crate::schema::IndexRecordOption;
 use crate::{DocId, Score, TantivyError, Term};
 
@@ -38,6 +44,22 @@ impl Weight for TermWeight {
         Ok(self.specialized_scorer(reader, boost)?.into_boxed_scorer())
     }
 
+    fn warmup<'a>(&'a self, reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            let field = self.term.field();
+            let inverted_index = reader.inverted_index(field)?;
+            let with_positions = self.index_record_option.has_positions();
+            let postings = inverted_index.warm_postings(&self.term, with_positions);
+            if self.scoring_enabled {
+                let fieldnorms = reader.fieldnorms_readers().warm_field(field);
+                futures_util::future::try_join(postings.map_err(From::from), fieldnorms).await?;
+            } else {
+                postings.await?;
+            }
+            Ok(())
+        })
+    }
+
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
         match self.specialized_scorer(reader, 1.0)? {
             TermOrEmptyOrAllScorer::TermScorer(mut term_scorer) => {
@@ -53,6 +75,15 @@ impl Weight for TermWeight {
         }
     }
 
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        let Some(positions) = term_positions_in_doc(reader, &self.term, doc)? else {
+            return Ok(None);
+        };
+        let mut matches = Matches::default();
+        matches.add_term_positions(&self.term, &positions);
+        Ok(Some(matches))
+    }
+
     fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
         if let Some(alive_bitset) = reader.alive_bitset() {
             Ok(self.scorer(reader, 1.0)?.count(alive_bitset))
@@ -73,11 +104,11 @@ impl Weight for TermWeight {
     ) -> crate::Result<()> {
         match self.specialized_scorer(reader, 1.0)? {
             TermOrEmptyOrAllScorer::TermScorer(mut term_scorer) => {
-                for_each_scorer(&mut *term_scorer, callback);
+                for_each_scorer(&mut *term_scorer, reader.search_context(), callback);
             }
             TermOrEmptyOrAllScorer::Empty => {}
             TermOrEmptyOrAllScorer::AllMatch(mut all_scorer) => {
-                for_each_scorer(&mut all_scorer, callback);
+                for_each_scorer(&mut all_scorer, reader.search_context(), callback);
             }
         }
         Ok(())
@@ -93,12 +124,22 @@ impl Weight for TermWeight {
         match self.specialized_scorer(reader, 1.0)? {
             TermOrEmptyOrAllScorer::TermScorer(mut term_scorer) => {
                 let mut buffer = [0u32; COLLECT_BLOCK_BUFFER_LEN];
-                for_each_docset_buffered(&mut term_scorer, &mut buffer, callback);
+                for_each_docset_buffered(
+                    &mut term_scorer,
+                    &mut buffer,
+                    reader.search_context(),
+                    callback,
+                );
             }
             TermOrEmptyOrAllScorer::Empty => {}
             TermOrEmptyOrAllScorer::AllMatch(mut all_scorer) => {
                 let mut buffer = [0u32; COLLECT_BLOCK_BUFFER_LEN];
-                for_each_docset_buffered(&mut all_scorer, &mut buffer, callback);
+                for_each_docset_buffered(
+                    &mut all_scorer,
+                    &mut buffer,
+                    reader.search_context(),
+                    callback,
+                );
             }
         };
 
@@ -124,10 +165,11 @@ impl Weight for TermWeight {
         let specialized_scorer = self.specialized_scorer(reader, 1.0)?;
         match specialized_scorer {
             TermOrEmptyOrAllScorer::TermScorer(term_scorer) => {
+                let mut callback = pruning_callback_with_context(reader.search_context(), callback);
                 crate::query::boolean_query::block_wand_single_scorer(
                     *term_scorer,
                     threshold,
-                    callback,
+                    &mut callback,
                 );
             }
             TermOrEmptyOrAllScorer::Empty => {}
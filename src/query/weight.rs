@@ -1,36 +1,81 @@
+use futures_util::future::BoxFuture;
+
 use super::Scorer;
 use crate::docset::COLLECT_BLOCK_BUFFER_LEN;
 use crate::index::SegmentReader;
-use crate::query::Explanation;
-use crate::{DocId, DocSet, Score, TERMINATED};
+use crate::query::matches::doc_matches;
+use crate::query::{Explanation, Matches};
+use crate::{DocId, DocSet, Score, SearchContext, TERMINATED};
 
 /// Iterates through all of the documents and scores matched by the DocSet
 /// `DocSet`.
+///
+/// Stops early if the search context asks to.
 pub(crate) fn for_each_scorer<TScorer: Scorer + ?Sized>(
     scorer: &mut TScorer,
+    search_context: &SearchContext,
     callback: &mut dyn FnMut(DocId, Score),
 ) {
+    let mut num_docs = 0u32;
     let mut doc = scorer.doc();
     while doc != TERMINATED {
         callback(doc, scorer.score());
+        if search_context.should_stop_every(&mut num_docs) {
+            return;
+        }
         doc = scorer.advance();
     }
 }
 
 /// Iterates through all of the documents matched by the DocSet
 /// `DocSet`.
+///
+/// Stops early if the search context asks to.
 #[inline]
 pub(crate) fn for_each_docset_buffered<T: DocSet + ?Sized>(
     docset: &mut T,
     buffer: &mut [DocId; COLLECT_BLOCK_BUFFER_LEN],
+    search_context: &SearchContext,
     mut callback: impl FnMut(&[DocId]),
 ) {
+    // The context is checked once per buffer.
+    let mut num_buffers = 0u32;
+    let check_interval =
+        crate::core::SEARCH_CONTEXT_CHECK_INTERVAL / COLLECT_BLOCK_BUFFER_LEN as u32;
     loop {
         let num_items = docset.fill_buffer(buffer);
         callback(&buffer[..num_items]);
         if num_items != buffer.len() {
             break;
         }
+        num_buffers += 1;
+        if num_buffers % check_interval == 0 && search_context.should_stop() {
+            break;
+        }
+    }
+}
+
+/// Wraps the callback of a pruning loop, so that the loop stops early if the search context
+/// asks to.
+///
+/// Once stopped, the callback returns an infinite threshold, so that no other document can be
+/// collected and block-WAND skips all of the remaining blocks.
+pub(crate) fn pruning_callback_with_context<'a>(
+    search_context: &'a SearchContext,
+    callback: &'a mut dyn FnMut(DocId, Score) -> Score,
+) -> impl FnMut(DocId, Score) -> Score + 'a {
+    let mut num_docs = 0u32;
+    let mut is_stopped = false;
+    move |doc, score| {
+        if is_stopped {
+            return Score::INFINITY;
+        }
+        let threshold = callback(doc, score);
+        if search_context.should_stop_every(&mut num_docs) {
+            is_stopped = true;
+            return Score::INFINITY;
+        }
+        threshold
     }
 }
 
@@ -47,14 +92,19 @@ pub(crate) fn for_each_docset_buffered<T: DocSet + ?Sized>(
 pub(crate) fn for_each_pruning_scorer<TScorer: Scorer + ?Sized>(
     scorer: &mut TScorer,
     mut threshold: Score,
+    search_context: &SearchContext,
     callback: &mut dyn FnMut(DocId, Score) -> Score,
 ) {
+    let mut num_docs = 0u32;
     let mut doc = scorer.doc();
     while doc != TERMINATED {
         let score = scorer.score();
         if score > threshold {
             threshold = callback(doc, score);
         }
+        if search_context.should_stop_every(&mut num_docs) {
+            return;
+        }
         doc = scorer.advance();
     }
 }
@@ -71,9 +121,49 @@ pub trait Weight: Send + Sync + 'static {
     /// See [`Query`](crate::query::Query).
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>>;
 
+    /// Prefetches, through the async read path of the underlying
+    /// [`FileSlice`](crate::directory::FileSlice)s, the byte ranges the scorer
+    /// for the given segment will need.
+    ///
+    /// Once the returned future has resolved, [`Weight::scorer`] is expected
+    /// to be served from the directory's cache rather than from blocking reads.
+    /// The default implementation does not prefetch anything.
+    fn warmup<'a>(&'a self, _reader: &'a SegmentReader) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Async version of [`Weight::scorer`].
+    ///
+    /// The required data is first prefetched via [`Weight::warmup`].
+    fn scorer_async<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+        boost: Score,
+    ) -> BoxFuture<'a, crate::Result<Box<dyn Scorer>>> {
+        Box::pin(async move {
+            self.warmup(reader).await?;
+            self.scorer(reader, boost)
+        })
+    }
+
     /// Returns an [`Explanation`] for the given document.
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation>;
 
+    /// Returns where the given document is matched, or `None` if it does not match.
+    ///
+    /// The [`Matches`] list, for each field involved in the match, the intervals of token
+    /// positions that made the document match. This is typically used for hit highlighting.
+    ///
+    /// The default implementation only checks that the document matches, without telling
+    /// where.
+    fn matches(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Option<Matches>> {
+        if doc_matches(self, reader, doc)? {
+            Ok(Some(Matches::default()))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Returns the number documents within the given [`SegmentReader`].
     fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
         let mut scorer = self.scorer(reader, 1.0)?;
@@ -92,7 +182,7 @@ pub trait Weight: Send + Sync + 'static {
         callback: &mut dyn FnMut(DocId, Score),
     ) -> crate::Result<()> {
         let mut scorer = self.scorer(reader, 1.0)?;
-        for_each_scorer(scorer.as_mut(), callback);
+        for_each_scorer(scorer.as_mut(), reader.search_context(), callback);
         Ok(())
     }
 
@@ -106,7 +196,7 @@ pub trait Weight: Send + Sync + 'static {
         let mut docset = self.scorer(reader, 1.0)?;
 
         let mut buffer = [0u32; COLLECT_BLOCK_BUFFER_LEN];
-        for_each_docset_buffered(&mut docset, &mut buffer, callback);
+        for_each_docset_buffered(&mut docset, &mut buffer, reader.search_context(), callback);
         Ok(())
     }
 
@@ -127,7 +217,12 @@ pub trait Weight: Send + Sync + 'static {
         callback: &mut dyn FnMut(DocId, Score) -> Score,
     ) -> crate::Result<()> {
         let mut scorer = self.scorer(reader, 1.0)?;
-        for_each_pruning_scorer(scorer.as_mut(), threshold, callback);
+        for_each_pruning_scorer(
+            scorer.as_mut(),
+            threshold,
+            reader.search_context(),
+            callback,
+        );
         Ok(())
     }
 }
@@ -9,6 +9,7 @@ pub use warming::Warmer;
 use self::warming::WarmingState;
 use crate::core::searcher::{SearcherGeneration, SearcherInner};
 use crate::directory::{Directory, WatchCallback, WatchHandle, META_LOCK};
+use crate::query::QueryCache;
 use crate::store::DOCSTORE_CACHE_CAPACITY;
 use crate::{Index, Inventory, Searcher, SegmentReader, TrackedObject};
 
@@ -37,6 +38,7 @@ pub enum ReloadPolicy {
 /// - [`Warmer`] implementations
 /// - number of warming threads, for parallelizing warming work
 /// - The cache size of the underlying doc store readers.
+/// - A [`QueryCache`] for the filters of the queries.
 #[derive(Clone)]
 pub struct IndexReaderBuilder {
     reload_policy: ReloadPolicy,
@@ -44,6 +46,7 @@ pub struct IndexReaderBuilder {
     warmers: Vec<Weak<dyn Warmer>>,
     num_warming_threads: usize,
     doc_store_cache_num_blocks: usize,
+    query_cache: Option<Arc<QueryCache>>,
 }
 
 impl IndexReaderBuilder {
@@ -55,6 +58,7 @@ impl IndexReaderBuilder {
             warmers: Vec::new(),
             num_warming_threads: 1,
             doc_store_cache_num_blocks: DOCSTORE_CACHE_CAPACITY,
+            query_cache: None,
         }
     }
 
@@ -65,13 +69,20 @@ impl IndexReaderBuilder {
     /// of time and it may return an error.
     pub fn try_into(self) -> crate::Result<IndexReader> {
         let searcher_generation_inventory = Inventory::default();
+        let mut warmers = self.warmers;
+        // The query cache drops the entries of the segments that are not searched anymore.
+        if let Some(query_cache) = &self.query_cache {
+            let query_cache: Arc<dyn Warmer> = query_cache.clone();
+            warmers.push(Arc::downgrade(&query_cache));
+        }
         let warming_state = WarmingState::new(
             self.num_warming_threads,
-            self.warmers,
+            warmers,
             searcher_generation_inventory.clone(),
         )?;
         let inner_reader = InnerIndexReader::new(
             self.doc_store_cache_num_blocks,
+            self.query_cache,
             self.index,
             warming_state,
             searcher_generation_inventory,
@@ -123,6 +134,16 @@ impl IndexReaderBuilder {
         self
     }
 
+    /// Sets the [`QueryCache`] used by the searchers of the reader.
+    ///
+    /// The same cache can be shared by several readers. It is registered as a [`Warmer`] of the
+    /// reader, so that the entries of merged or deleted segments are garbage collected.
+    #[must_use]
+    pub fn query_cache(mut self, query_cache: Arc<QueryCache>) -> IndexReaderBuilder {
+        self.query_cache = Some(query_cache);
+        self
+    }
+
     /// Set the [`Warmer`]s that are invoked when reloading searchable segments.
     #[must_use]
     pub fn warmers(mut self, warmers: Vec<Weak<dyn Warmer>>) -> IndexReaderBuilder {
@@ -151,6 +172,7 @@ impl TryInto<IndexReader> for IndexReaderBuilder {
 
 struct InnerIndexReader {
     doc_store_cache_num_blocks: usize,
+    query_cache: Option<Arc<QueryCache>>,
     index: Index,
     warming_state: WarmingState,
     searcher: arc_swap::ArcSwap<SearcherInner>,
@@ -161,6 +183,7 @@ struct InnerIndexReader {
 impl InnerIndexReader {
     fn new(
         doc_store_cache_num_blocks: usize,
+        query_cache: Option<Arc<QueryCache>>,
         index: Index,
         warming_state: WarmingState,
         // The searcher_generation_inventory is not used as source, but as target to track the
@@ -172,12 +195,14 @@ impl InnerIndexReader {
         let searcher = Self::create_searcher(
             &index,
             doc_store_cache_num_blocks,
+            query_cache.as_ref(),
             &warming_state,
             &searcher_generation_counter,
             &searcher_generation_inventory,
         )?;
         Ok(InnerIndexReader {
             doc_store_cache_num_blocks,
+            query_cache,
             index,
             warming_state,
             searcher: ArcSwap::from(searcher),
@@ -189,7 +214,10 @@ impl InnerIndexReader {
     ///
     /// This function acquires a lock to prevent GC from removing files
     /// as we are opening our index.
-    fn open_segment_readers(index: &Index) -> crate::Result<Vec<SegmentReader>> {
+    fn open_segment_readers(
+        index: &Index,
+        query_cache: Option<&Arc<QueryCache>>,
+    ) -> crate::Result<Vec<SegmentReader>> {
         // Prevents segment files from getting deleted while we are in the process of opening them
         let _meta_lock = match index.is_read_only() {
             true => None,
@@ -199,7 +227,11 @@ impl InnerIndexReader {
         let searchable_segments = index.searchable_segments()?;
         let segment_readers = searchable_segments
             .iter()
-            .map(SegmentReader::open)
+            .map(|segment| {
+                let mut segment_reader = SegmentReader::open(segment)?;
+                segment_reader.set_query_cache(query_cache.cloned());
+                Ok(segment_reader)
+            })
             .collect::<crate::Result<_>>()?;
         Ok(segment_readers)
     }
@@ -218,11 +250,12 @@ impl InnerIndexReader {
     fn create_searcher(
         index: &Index,
         doc_store_cache_num_blocks: usize,
+        query_cache: Option<&Arc<QueryCache>>,
         warming_state: &WarmingState,
         searcher_generation_counter: &Arc<AtomicU64>,
         searcher_generation_inventory: &Inventory<SearcherGeneration>,
     ) -> crate::Result<Arc<SearcherInner>> {
-        let segment_readers = Self::open_segment_readers(index)?;
+        let segment_readers = Self::open_segment_readers(index, query_cache)?;
         let searcher_generation = Self::track_segment_readers_in_inventory(
             &segment_readers,
             searcher_generation_counter,
@@ -246,6 +279,7 @@ impl InnerIndexReader {
         let searcher = Self::create_searcher(
             &self.index,
             self.doc_store_cache_num_blocks,
+            self.query_cache.as_ref(),
             &self.warming_state,
             &self.searcher_generation_counter,
             &self.searcher_generation_inventory,
@@ -302,4 +336,9 @@ impl IndexReader {
     pub fn searcher(&self) -> Searcher {
         self.inner.searcher()
     }
+
+    /// Returns the [`QueryCache`] of the reader, if any.
+    pub fn query_cache(&self) -> Option<&Arc<QueryCache>> {
+        self.inner.query_cache.as_ref()
+    }
 }
@@ -297,6 +297,7 @@ pub struct BinaryDocumentDeserializer<'de, R> {
     position: usize,
     doc_store_version: DocStoreVersion,
     reader: &'de mut R,
+    projection: Option<&'de [Field]>,
 }
 
 impl<'de, R> BinaryDocumentDeserializer<'de, R>
@@ -314,14 +315,29 @@ where R: Read
             position: 0,
             doc_store_version,
             reader,
+            projection: None,
         })
     }
 
+    /// Restricts the deserializer to the given fields.
+    ///
+    /// The values of the other fields are skipped without being allocated.
+    pub(crate) fn with_projection(mut self, fields: &'de [Field]) -> Self {
+        self.projection = Some(fields);
+        self
+    }
+
     /// Returns true if the deserializer has deserialized all the entries
     /// within the document.
     fn is_complete(&self) -> bool {
         self.position >= self.length
     }
+
+    fn is_projected(&self, field: Field) -> bool {
+        self.projection
+            .map(|fields| fields.contains(&field))
+            .unwrap_or(true)
+    }
 }
 
 impl<'de, R> DocumentDeserializer<'de> for BinaryDocumentDeserializer<'de, R>
@@ -333,18 +349,61 @@ where R: Read
     }
 
     fn next_field<V: ValueDeserialize>(&mut self) -> Result<Option<(Field, V)>, DeserializeError> {
-        if self.is_complete() {
-            return Ok(None);
+        while !self.is_complete() {
+            let field = Field::deserialize(self.reader).map_err(DeserializeError::from)?;
+            let is_projected = self.is_projected(field);
+            let deserializer =
+                BinaryValueDeserializer::from_reader(self.reader, self.doc_store_version)?;
+
+            self.position += 1;
+
+            if !is_projected {
+                deserializer.skip()?;
+                continue;
+            }
+            let value = V::deserialize(deserializer)?;
+            return Ok(Some((field, value)));
         }
+        Ok(None)
+    }
+}
 
-        let field = Field::deserialize(self.reader).map_err(DeserializeError::from)?;
-        let deserializer =
-            BinaryValueDeserializer::from_reader(self.reader, self.doc_store_version)?;
-        let value = V::deserialize(deserializer)?;
+/// A document deserializer reading the fields of a document split over
+/// several doc stores, one part after the other.
+///
+/// This is used when some fields are
+/// [stored separately](crate::schema::FieldEntry::set_stored_separately).
+pub(crate) struct ChainedDocumentDeserializer<'de, R> {
+    parts: Vec<BinaryDocumentDeserializer<'de, R>>,
+}
 
-        self.position += 1;
+impl<'de, R> ChainedDocumentDeserializer<'de, R>
+where R: Read
+{
+    /// Creates a new deserializer reading the given parts in order.
+    pub(crate) fn new(mut parts: Vec<BinaryDocumentDeserializer<'de, R>>) -> Self {
+        // We pop parts from the end of the vector.
+        parts.reverse();
+        Self { parts }
+    }
+}
+
+impl<'de, R> DocumentDeserializer<'de> for ChainedDocumentDeserializer<'de, R>
+where R: Read
+{
+    #[inline]
+    fn size_hint(&self) -> usize {
+        self.parts.iter().map(|part| part.size_hint()).sum()
+    }
 
-        Ok(Some((field, value)))
+    fn next_field<V: ValueDeserialize>(&mut self) -> Result<Option<(Field, V)>, DeserializeError> {
+        while let Some(part) = self.parts.last_mut() {
+            if let Some(field_value) = part.next_field()? {
+                return Ok(Some(field_value));
+            }
+            self.parts.pop();
+        }
+        Ok(None)
     }
 }
 
@@ -411,6 +470,38 @@ where R: Read
         })
     }
 
+    /// Skips the value without deserializing it.
+    fn skip(self) -> Result<(), DeserializeError> {
+        match self.value_type {
+            ValueType::Null => Ok(()),
+            ValueType::Bool => skip_bytes(self.reader, 1),
+            ValueType::U64 | ValueType::I64 | ValueType::F64 | ValueType::DateTime => {
+                skip_bytes(self.reader, 8)
+            }
+            ValueType::IpAddr => skip_bytes(self.reader, 16),
+            ValueType::String | ValueType::Facet | ValueType::Bytes | ValueType::PreTokStr => {
+                let num_bytes = VInt::deserialize(self.reader)?.val();
+                skip_bytes(self.reader, num_bytes)
+            }
+            ValueType::Array | ValueType::Object => {
+                // Objects are serialized as arrays of the form `[key, value, key, value, ...]`.
+                let num_elements = VInt::deserialize(self.reader)?.val();
+                for _ in 0..num_elements {
+                    BinaryValueDeserializer::from_reader(self.reader, self.doc_store_version)?
+                        .skip()?;
+                }
+                Ok(())
+            }
+            #[allow(deprecated)]
+            ValueType::JSONObject => {
+                let mut de = serde_json::Deserializer::from_reader(self.reader);
+                <serde::de::IgnoredAny as serde::Deserialize>::deserialize(&mut de)
+                    .map_err(|err| DeserializeError::Custom(err.to_string()))?;
+                Ok(())
+            }
+        }
+    }
+
     fn validate_type(&self, expected_type: ValueType) -> Result<(), DeserializeError> {
         if self.value_type == expected_type {
             Ok(())
@@ -574,6 +665,17 @@ where R: Read
     }
 }
 
+fn skip_bytes<R: Read>(reader: &mut R, num_bytes: u64) -> Result<(), DeserializeError> {
+    let num_skipped = io::copy(&mut reader.take(num_bytes), &mut io::sink())?;
+    if num_skipped != num_bytes {
+        return Err(DeserializeError::from(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Unexpected end of document while skipping a value",
+        )));
+    }
+    Ok(())
+}
+
 /// A deserializer for an array of values serialized with `BinarySerializable`.
 /// TODO: Improve docs
 pub struct BinaryArrayDeserializer<'de, R> {
@@ -171,11 +171,11 @@ use std::mem;
 
 use named_field_document::NamedFieldDocumentHashMap;
 
-pub(crate) use self::de::BinaryDocumentDeserializer;
 pub use self::de::{
     ArrayAccess, DeserializeError, DocumentDeserialize, DocumentDeserializer, ObjectAccess,
     ValueDeserialize, ValueDeserializer, ValueType, ValueVisitor,
 };
+pub(crate) use self::de::{BinaryDocumentDeserializer, ChainedDocumentDeserializer};
 pub use self::default_document::{
     CompactDocArrayIter, CompactDocObjectIter, CompactDocValue, DocParsingError, TantivyDocument,
 };
@@ -13,14 +13,32 @@ use crate::schema::Schema;
 pub struct BinaryDocumentSerializer<'se, W> {
     writer: &'se mut W,
     schema: &'se Schema,
+    separate_store: bool,
 }
 
 impl<'se, W> BinaryDocumentSerializer<'se, W>
 where W: Write
 {
     /// Creates a new serializer with a provided writer.
+    ///
+    /// Only the stored fields that are not
+    /// [stored separately](crate::schema::FieldEntry::set_stored_separately) are serialized.
     pub(crate) fn new(writer: &'se mut W, schema: &'se Schema) -> Self {
-        Self { writer, schema }
+        Self {
+            writer,
+            schema,
+            separate_store: false,
+        }
+    }
+
+    /// Creates a new serializer with a provided writer, that only serializes
+    /// the fields [stored separately](crate::schema::FieldEntry::set_stored_separately).
+    pub(crate) fn for_separate_store(writer: &'se mut W, schema: &'se Schema) -> Self {
+        Self {
+            writer,
+            schema,
+            separate_store: true,
+        }
     }
 
     /// Attempts to serialize a given document and write the output
@@ -29,8 +47,10 @@ where W: Write
     pub(crate) fn serialize_doc<D>(&mut self, doc: &D) -> io::Result<()>
     where D: Document {
         let stored_field_values = || {
-            doc.iter_fields_and_values()
-                .filter(|(field, _)| self.schema.get_field_entry(*field).is_stored())
+            doc.iter_fields_and_values().filter(|(field, _)| {
+                let field_entry = self.schema.get_field_entry(*field);
+                field_entry.is_stored() && field_entry.is_stored_separately() == self.separate_store
+            })
         };
         let num_field_values = stored_field_values().count();
         let mut actual_length = 0;
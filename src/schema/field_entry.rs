@@ -18,6 +18,13 @@ pub struct FieldEntry {
     name: String,
     #[serde(flatten)]
     field_type: FieldType,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    stored_separately: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !val
 }
 
 impl FieldEntry {
@@ -27,6 +34,7 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type,
+            stored_separately: false,
         }
     }
 
@@ -131,6 +139,28 @@ impl FieldEntry {
             FieldType::IpAddr(ref options) => options.is_stored(),
         }
     }
+
+    /// Returns true if the field is stored in the separate doc store.
+    ///
+    /// See [`FieldEntry::set_stored_separately`].
+    #[inline]
+    pub fn is_stored_separately(&self) -> bool {
+        self.stored_separately && self.is_stored()
+    }
+
+    /// Moves the stored values of this field to a separate doc store component.
+    ///
+    /// Large fields (e.g. the body of an article) can be grouped in that
+    /// component, so that fetching the other stored fields with
+    /// [`Searcher::doc_with_fields`](crate::Searcher::doc_with_fields) does not
+    /// require decompressing them.
+    ///
+    /// This has no effect if the field is not stored.
+    #[must_use]
+    pub fn set_stored_separately(mut self) -> FieldEntry {
+        self.stored_separately = true;
+        self
+    }
 }
 
 #[cfg(test)]
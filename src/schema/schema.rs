@@ -292,6 +292,24 @@ impl Schema {
         self.0.fields.len()
     }
 
+    /// Returns the fields stored in the separate doc store.
+    ///
+    /// See [`FieldEntry::set_stored_separately`].
+    pub(crate) fn separately_stored_fields(&self) -> Vec<Field> {
+        self.fields()
+            .filter(|(_, field_entry)| field_entry.is_stored_separately())
+            .map(|(field, _)| field)
+            .collect()
+    }
+
+    /// Returns true if at least one field is stored in the separate doc store.
+    pub(crate) fn has_separate_store(&self) -> bool {
+        self.0
+            .fields
+            .iter()
+            .any(|field_entry| field_entry.is_stored_separately())
+    }
+
     /// Return the list of all the `Field`s.
     pub fn fields(&self) -> impl Iterator<Item = (Field, &FieldEntry)> {
         self.0
@@ -72,6 +72,9 @@ pub struct SegmentSpaceUsage {
     fieldnorms: PerFieldSpaceUsage,
 
     store: StoreSpaceUsage,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    separate_store: Option<StoreSpaceUsage>,
 
     deletes: ByteCount,
 
@@ -88,6 +91,7 @@ impl SegmentSpaceUsage {
         fast_fields: PerFieldSpaceUsage,
         fieldnorms: PerFieldSpaceUsage,
         store: StoreSpaceUsage,
+        separate_store: Option<StoreSpaceUsage>,
         deletes: ByteCount,
     ) -> SegmentSpaceUsage {
         let total = termdict.total()
@@ -96,6 +100,10 @@ impl SegmentSpaceUsage {
             + fast_fields.total()
             + fieldnorms.total()
             + store.total()
+            + separate_store
+                .as_ref()
+                .map(StoreSpaceUsage::total)
+                .unwrap_or_default()
             + deletes;
         SegmentSpaceUsage {
             num_docs,
@@ -105,6 +113,7 @@ impl SegmentSpaceUsage {
             fast_fields,
             fieldnorms,
             store,
+            separate_store,
             deletes,
             total,
         }
@@ -124,6 +133,11 @@ impl SegmentSpaceUsage {
             FieldNorms => PerField(self.fieldnorms().clone()),
             Terms => PerField(self.termdict().clone()),
             SegmentComponent::Store => ComponentSpaceUsage::Store(self.store().clone()),
+            SegmentComponent::SeparateStore => {
+                ComponentSpaceUsage::Store(self.separate_store().cloned().unwrap_or_else(|| {
+                    StoreSpaceUsage::new(ByteCount::default(), ByteCount::default())
+                }))
+            }
             SegmentComponent::TempStore => ComponentSpaceUsage::Store(self.store().clone()),
             Delete => Basic(self.deletes()),
         }
@@ -164,6 +178,11 @@ impl SegmentSpaceUsage {
         &self.store
     }
 
+    /// Space usage for the fields stored in the separate doc store, if any.
+    pub fn separate_store(&self) -> Option<&StoreSpaceUsage> {
+        self.separate_store.as_ref()
+    }
+
     /// Space usage for document deletions
     pub fn deletes(&self) -> ByteCount {
         self.deletes
@@ -16,6 +16,13 @@
 //! the block a second time, but their is no real
 //! uncompressed block* cache.
 //!
+//! Fetching only some of the fields of a document is possible with
+//! [`StoreReader::get_with_fields`]: the values of the other fields are skipped
+//! without being allocated. Large fields can also be
+//! [stored separately](crate::schema::FieldEntry::set_stored_separately),
+//! in a second doc store component, so that fetching the other fields
+//! does not require decompressing them.
+//!
 //! A typical use case for the store is, once
 //! the search result page has been computed, returning
 //! the actual content of the 10 best document.
@@ -357,6 +364,103 @@ pub(crate) mod tests {
         assert_eq!(store.block_checkpoints().count(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_doc_with_fields() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let body = schema_builder.add_text_field("body", TEXT | STORED);
+        let tags = schema_builder.add_json_field("tags", STORED);
+        let count = schema_builder.add_u64_field("count", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer: IndexWriter = index.writer_for_tests()?;
+            index_writer.add_document(doc!(
+                title => "hello",
+                body => LOREM,
+                tags => serde_json::json!({"a": [1, "b", {"c": true}]}),
+                count => 3u64,
+                title => "world",
+            ))?;
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let doc_address = crate::DocAddress::new(0, 0);
+
+        let doc: TantivyDocument = searcher.doc_with_fields(doc_address, &[title, count])?;
+        let titles: Vec<&str> = doc
+            .get_all(title)
+            .map(|value| value.as_str().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["hello", "world"]);
+        assert_eq!(doc.get_first(count).unwrap().as_u64(), Some(3));
+        assert!(doc.get_first(body).is_none());
+        assert!(doc.get_first(tags).is_none());
+
+        let doc: TantivyDocument = searcher.doc_with_fields(doc_address, &[])?;
+        assert_eq!(doc.field_values().count(), 0);
+
+        let full_doc: TantivyDocument = searcher.doc(doc_address)?;
+        assert_eq!(full_doc.field_values().count(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_separate_store() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let body = schema_builder.add_field(
+            schema::FieldEntry::new_text("body".to_string(), TEXT | STORED).set_stored_separately(),
+        );
+        let schema = schema_builder.build();
+        assert!(schema.get_field_entry(body).is_stored_separately());
+        assert!(!schema.get_field_entry(title).is_stored_separately());
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer: IndexWriter = index.writer_for_tests()?;
+            for i in 0..3 {
+                index_writer.add_document(doc!(title => format!("title {i}"), body => LOREM))?;
+                index_writer.add_document(doc!(title => "deleteme"))?;
+                index_writer.commit()?;
+            }
+            index_writer.delete_term(Term::from_field_text(title, "deleteme"));
+            index_writer.commit()?;
+            let segment_ids = index.searchable_segment_ids()?;
+            index_writer.merge(&segment_ids).wait()?;
+            index_writer.wait_merging_threads()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        assert!(segment_reader.space_usage()?.separate_store().is_some());
+
+        let doc_address = crate::DocAddress::new(0, 1);
+        let doc: TantivyDocument = searcher.doc(doc_address)?;
+        let doc_title = doc.get_first(title).unwrap().as_str().unwrap();
+        assert!(doc_title.starts_with("title "));
+        assert_eq!(doc.get_first(body).unwrap().as_str(), Some(LOREM));
+
+        let doc: TantivyDocument = searcher.doc_with_fields(doc_address, &[title])?;
+        assert_eq!(doc.get_first(title).unwrap().as_str(), Some(doc_title));
+        assert!(doc.get_first(body).is_none());
+
+        let doc: TantivyDocument = searcher.doc_with_fields(doc_address, &[body])?;
+        assert!(doc.get_first(title).is_none());
+        assert_eq!(doc.get_first(body).unwrap().as_str(), Some(LOREM));
+
+        let store = segment_reader.get_store_reader(10)?;
+        let mut titles: Vec<String> = store
+            .iter::<TantivyDocument>(segment_reader.alive_bitset())
+            .map(|doc| {
+                let doc = doc.unwrap();
+                assert_eq!(doc.get_first(body).unwrap().as_str(), Some(LOREM));
+                doc.get_first(title).unwrap().as_str().unwrap().to_string()
+            })
+            .collect();
+        titles.sort();
+        assert_eq!(titles, vec!["title 0", "title 1", "title 2"]);
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
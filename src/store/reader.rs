@@ -15,7 +15,10 @@ use super::Decompressor;
 use crate::directory::FileSlice;
 use crate::error::DataCorruption;
 use crate::fastfield::AliveBitSet;
-use crate::schema::document::{BinaryDocumentDeserializer, DocumentDeserialize};
+use crate::schema::document::{
+    BinaryDocumentDeserializer, ChainedDocumentDeserializer, DocumentDeserialize,
+};
+use crate::schema::Field;
 use crate::space_usage::StoreSpaceUsage;
 use crate::store::index::Checkpoint;
 use crate::DocId;
@@ -67,6 +70,14 @@ pub struct StoreReader {
     skip_index: Arc<SkipIndex>,
     space_usage: StoreSpaceUsage,
     cache: BlockCache,
+    separate_store: Option<SeparateStore>,
+}
+
+/// The doc store holding the fields
+/// [stored separately](crate::schema::FieldEntry::set_stored_separately).
+struct SeparateStore {
+    reader: Box<StoreReader>,
+    fields: Vec<Field>,
 }
 
 /// The cache for decompressed blocks.
@@ -174,9 +185,44 @@ impl StoreReader {
             },
             skip_index: Arc::new(skip_index),
             space_usage,
+            separate_store: None,
         })
     }
 
+    /// Attaches the reader of the separate doc store, holding the values of `fields`.
+    ///
+    /// Documents fetched through [`get`](Self::get) and [`iter`](Self::iter) then contain the
+    /// fields of both stores. The raw byte accessors only cover the main store.
+    pub(crate) fn with_separate_store(
+        mut self,
+        store_reader: StoreReader,
+        fields: Vec<Field>,
+    ) -> StoreReader {
+        self.separate_store = Some(SeparateStore {
+            reader: Box::new(store_reader),
+            fields,
+        });
+        self
+    }
+
+    /// Returns whether the main store needs to be read, as well as the separate
+    /// store reader that needs to be read (if any), to fetch the `projection` fields.
+    fn stores_to_read(&self, projection: Option<&[Field]>) -> (bool, Option<&StoreReader>) {
+        let Some(separate_store) = self.separate_store.as_ref() else {
+            return (true, None);
+        };
+        let Some(fields) = projection else {
+            return (true, Some(&separate_store.reader));
+        };
+        let read_main = fields
+            .iter()
+            .any(|field| !separate_store.fields.contains(field));
+        let read_separate = fields
+            .iter()
+            .any(|field| separate_store.fields.contains(field));
+        (read_main, read_separate.then_some(&*separate_store.reader))
+    }
+
     pub(crate) fn block_checkpoints(&self) -> impl Iterator<Item = Checkpoint> + '_ {
         self.skip_index.checkpoints()
     }
@@ -187,7 +233,11 @@ impl StoreReader {
 
     /// Returns the cache hit and miss statistics of the store reader.
     pub(crate) fn cache_stats(&self) -> CacheStats {
-        self.cache.stats()
+        let mut cache_stats = self.cache.stats();
+        if let Some(separate_store) = self.separate_store.as_ref() {
+            cache_stats += separate_store.reader.cache_stats();
+        }
+        cache_stats
     }
 
     /// Get checkpoint for `DocId`. The checkpoint can be used to load a block containing the
@@ -237,12 +287,40 @@ impl StoreReader {
     /// It should not be called to score documents
     /// for instance.
     pub fn get<D: DocumentDeserialize>(&self, doc_id: DocId) -> crate::Result<D> {
-        let mut doc_bytes = self.get_document_bytes(doc_id)?;
+        self.get_projected(doc_id, None)
+    }
 
-        let deserializer =
-            BinaryDocumentDeserializer::from_reader(&mut doc_bytes, self.doc_store_version)
-                .map_err(crate::TantivyError::from)?;
-        D::deserialize(deserializer).map_err(crate::TantivyError::from)
+    /// Reads the given fields of a document.
+    ///
+    /// The values of the other fields are skipped without being allocated.
+    /// If all of the requested fields are (or none of them is)
+    /// [stored separately](crate::schema::FieldEntry::set_stored_separately),
+    /// the other doc store is not read at all.
+    pub fn get_with_fields<D: DocumentDeserialize>(
+        &self,
+        doc_id: DocId,
+        fields: &[Field],
+    ) -> crate::Result<D> {
+        self.get_projected(doc_id, Some(fields))
+    }
+
+    fn get_projected<D: DocumentDeserialize>(
+        &self,
+        doc_id: DocId,
+        projection: Option<&[Field]>,
+    ) -> crate::Result<D> {
+        let (read_main, separate_store) = self.stores_to_read(projection);
+        let mut parts = Vec::with_capacity(2);
+        if read_main {
+            parts.push((self.get_document_bytes(doc_id)?, self.doc_store_version));
+        }
+        if let Some(separate_store) = separate_store {
+            parts.push((
+                separate_store.get_document_bytes(doc_id)?,
+                separate_store.doc_store_version,
+            ));
+        }
+        deserialize_document_parts(&mut parts, projection)
     }
 
     /// Returns raw bytes of a given document.
@@ -278,13 +356,23 @@ impl StoreReader {
         &'b self,
         alive_bitset: Option<&'a AliveBitSet>,
     ) -> impl Iterator<Item = crate::Result<D>> + 'b {
-        self.iter_raw(alive_bitset).map(|doc_bytes_res| {
-            let mut doc_bytes = doc_bytes_res?;
-
-            let deserializer =
-                BinaryDocumentDeserializer::from_reader(&mut doc_bytes, self.doc_store_version)
-                    .map_err(crate::TantivyError::from)?;
-            D::deserialize(deserializer).map_err(crate::TantivyError::from)
+        let mut separate_iter = self.separate_store.as_ref().map(|separate_store| {
+            (
+                separate_store.reader.iter_raw(alive_bitset),
+                separate_store.reader.doc_store_version,
+            )
+        });
+        self.iter_raw(alive_bitset).map(move |doc_bytes_res| {
+            let mut parts = vec![(doc_bytes_res?, self.doc_store_version)];
+            if let Some((separate_iter, separate_doc_store_version)) = separate_iter.as_mut() {
+                let separate_doc_bytes = separate_iter.next().ok_or_else(|| {
+                    DataCorruption::comment_only(
+                        "the separate doc store contains less documents than the doc store",
+                    )
+                })??;
+                parts.push((separate_doc_bytes, *separate_doc_store_version));
+            }
+            deserialize_document_parts(&mut parts, None)
         })
     }
 
@@ -354,6 +442,25 @@ impl StoreReader {
     }
 }
 
+/// Deserializes a document from its serialized parts, one per doc store.
+fn deserialize_document_parts<D: DocumentDeserialize>(
+    parts: &mut [(OwnedBytes, DocStoreVersion)],
+    projection: Option<&[Field]>,
+) -> crate::Result<D> {
+    let mut deserializers = Vec::with_capacity(parts.len());
+    for (doc_bytes, doc_store_version) in parts.iter_mut() {
+        let deserializer = BinaryDocumentDeserializer::from_reader(doc_bytes, *doc_store_version)
+            .map_err(crate::TantivyError::from)?;
+        deserializers.push(if let Some(fields) = projection {
+            deserializer.with_projection(fields)
+        } else {
+            deserializer
+        });
+    }
+    D::deserialize(ChainedDocumentDeserializer::new(deserializers))
+        .map_err(crate::TantivyError::from)
+}
+
 fn block_read_index(block: &[u8], doc_pos: u32) -> crate::Result<Range<usize>> {
     let doc_pos = doc_pos as usize;
     let size_of_u32 = std::mem::size_of::<u32>();
@@ -429,12 +536,44 @@ impl StoreReader {
         doc_id: DocId,
         executor: &Executor,
     ) -> crate::Result<D> {
-        let mut doc_bytes = self.get_document_bytes_async(doc_id, executor).await?;
+        self.get_projected_async(doc_id, None, executor).await
+    }
+
+    /// Fetches the given fields of a document asynchronously.
+    /// Async version of [`get_with_fields`](Self::get_with_fields).
+    pub async fn get_with_fields_async<D: DocumentDeserialize>(
+        &self,
+        doc_id: DocId,
+        fields: &[Field],
+        executor: &Executor,
+    ) -> crate::Result<D> {
+        self.get_projected_async(doc_id, Some(fields), executor)
+            .await
+    }
 
-        let deserializer =
-            BinaryDocumentDeserializer::from_reader(&mut doc_bytes, self.doc_store_version)
-                .map_err(crate::TantivyError::from)?;
-        D::deserialize(deserializer).map_err(crate::TantivyError::from)
+    async fn get_projected_async<D: DocumentDeserialize>(
+        &self,
+        doc_id: DocId,
+        projection: Option<&[Field]>,
+        executor: &Executor,
+    ) -> crate::Result<D> {
+        let (read_main, separate_store) = self.stores_to_read(projection);
+        let mut parts = Vec::with_capacity(2);
+        if read_main {
+            parts.push((
+                self.get_document_bytes_async(doc_id, executor).await?,
+                self.doc_store_version,
+            ));
+        }
+        if let Some(separate_store) = separate_store {
+            parts.push((
+                separate_store
+                    .get_document_bytes_async(doc_id, executor)
+                    .await?,
+                separate_store.doc_store_version,
+            ));
+        }
+        deserialize_document_parts(&mut parts, projection)
     }
 }
 
@@ -24,6 +24,7 @@ pub struct StoreWriter {
     current_block: Vec<u8>,
     doc_pos: Vec<u32>,
     block_compressor: BlockCompressor,
+    separate_store: bool,
 }
 
 impl StoreWriter {
@@ -45,9 +46,18 @@ impl StoreWriter {
             doc_pos: Vec::new(),
             current_block: Vec::new(),
             block_compressor,
+            separate_store: false,
         })
     }
 
+    /// Makes the store writer serialize the fields
+    /// [stored separately](crate::schema::FieldEntry::set_stored_separately)
+    /// instead of the other stored fields.
+    pub(crate) fn for_separate_store(mut self) -> StoreWriter {
+        self.separate_store = true;
+        self
+    }
+
     pub(crate) fn compressor(&self) -> Compressor {
         self.compressor
     }
@@ -99,7 +109,11 @@ impl StoreWriter {
     pub fn store<D: Document>(&mut self, document: &D, schema: &Schema) -> io::Result<()> {
         self.doc_pos.push(self.current_block.len() as u32);
 
-        let mut serializer = BinaryDocumentSerializer::new(&mut self.current_block, schema);
+        let mut serializer = if self.separate_store {
+            BinaryDocumentSerializer::for_separate_store(&mut self.current_block, schema)
+        } else {
+            BinaryDocumentSerializer::new(&mut self.current_block, schema)
+        };
         serializer.serialize_doc(document)?;
 
         self.num_docs_in_current_block += 1;